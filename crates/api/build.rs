@@ -0,0 +1,10 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the bundled protoc so builds don't depend on a system install
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/domain_search.proto"], &["proto"])?;
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::{request_id, AppState};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Target of the summary events, for filtering them apart (`RUST_LOG`) or
+/// routing them to their own sink
+pub const TARGET: &str = "access";
+
+/// What handlers did for the request being logged
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    /// Searches run, several for bulk requests
+    pub searches: u32,
+    /// Searches answered from the cache
+    pub cache_hits: u32,
+    pub candidates: usize,
+    pub results: usize,
+    /// Time spent searching the index, cache hits excluded
+    pub search_ms: f64,
+    /// Time spent on RDAP enrichment
+    pub enrich_ms: f64,
+}
+
+tokio::task_local! {
+    static SUMMARY: RefCell<Summary>;
+}
+
+/// Add to the summary of the request being handled; a no-op outside of
+/// logged requests (gRPC, spawned tasks, `ACCESS_LOG=false`)
+pub fn note(update: impl FnOnce(&mut Summary)) {
+    let _ = SUMMARY.try_with(|summary| update(&mut summary.borrow_mut()));
+}
+
+/// Note a search answered from the cache or by the index
+pub fn note_search(cached: bool, candidates: usize, results: usize, search_time: Duration) {
+    note(|summary| {
+        summary.searches += 1;
+        summary.cache_hits += cached as u32;
+        summary.candidates += candidates;
+        summary.results += results;
+        if !cached {
+            summary.search_ms += search_time.as_secs_f64() * 1000.0;
+        }
+    });
+}
+
+/// Emit one summary event per request, with `ACCESS_LOG` set
+///
+/// Unlike the span of `TraceLayer`, the event is a single line carrying the
+/// outcome: route, a hash of the query parameters (the same for the same
+/// parameters in any order), status and what the handler reported through
+/// [`note`], with the total latency and its search and enrichment parts.
+pub async fn record(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    if !state.config.access_log {
+        return next.run(request).await;
+    }
+
+    let start = Instant::now();
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let params_hash = params_hash(request.uri().query().unwrap_or_default());
+
+    let (response, summary) = SUMMARY
+        .scope(RefCell::new(Summary::default()), async {
+            let response = next.run(request).await;
+            (response, SUMMARY.with(|summary| summary.take()))
+        })
+        .await;
+
+    tracing::info!(
+        target: TARGET,
+        request_id = request_id::current().unwrap_or_default(),
+        method = %method,
+        route,
+        params_hash,
+        status = response.status().as_u16(),
+        searches = summary.searches,
+        cache_hits = summary.cache_hits,
+        candidates = summary.candidates,
+        results = summary.results,
+        latency_ms = start.elapsed().as_secs_f64() * 1000.0,
+        search_ms = summary.search_ms,
+        enrich_ms = summary.enrich_ms,
+        "Request summary"
+    );
+    response
+}
+
+/// Hex MD5 prefix of the query string with its parameters sorted, empty
+/// for requests without parameters
+fn params_hash(query: &str) -> String {
+    let mut params: Vec<&str> = query.split('&').filter(|param| !param.is_empty()).collect();
+    if params.is_empty() {
+        return String::new();
+    }
+    params.sort_unstable();
+    let digest = md5::compute(params.join("&"));
+    format!("{:x}", digest)[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_hash_ignores_order() {
+        assert_eq!(params_hash("q=coffee&limit=10"), params_hash("limit=10&q=coffee"));
+        assert_ne!(params_hash("q=coffee"), params_hash("q=tea"));
+        assert_eq!(params_hash("q=coffee").len(), 16);
+        assert_eq!(params_hash(""), "");
+    }
+
+    #[tokio::test]
+    async fn test_notes_add_up_within_scope() {
+        let summary = SUMMARY
+            .scope(RefCell::new(Summary::default()), async {
+                note_search(true, 10, 5, Duration::from_millis(1));
+                note_search(false, 20, 10, Duration::from_millis(4));
+                note(|summary| summary.enrich_ms += 2.0);
+                SUMMARY.with(|summary| summary.take())
+            })
+            .await;
+        assert_eq!(summary.searches, 2);
+        assert_eq!(summary.cache_hits, 1);
+        assert_eq!(summary.candidates, 30);
+        assert_eq!(summary.results, 15);
+        assert_eq!(summary.search_ms, 4.0);
+        assert_eq!(summary.enrich_ms, 2.0);
+
+        // Outside a logged request, notes are dropped
+        note_search(false, 1, 1, Duration::ZERO);
+    }
+}
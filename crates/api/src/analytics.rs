@@ -0,0 +1,362 @@
+use crate::cache::Cache;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// How often query counts are added to the Redis sorted sets
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Counters for one normalized query
+#[derive(Default)]
+struct QueryCounter {
+    count: u64,
+    zero_results: u64,
+    total_ms: f64,
+    /// Counts not yet added to Redis
+    unflushed: u64,
+    unflushed_zero: u64,
+}
+
+#[derive(Default)]
+struct Totals {
+    queries: u64,
+    zero_results: u64,
+    total_ms: f64,
+}
+
+/// In-process query frequency, zero-result and latency counters
+///
+/// At most `max_queries` distinct queries are tracked; when full, the less
+/// frequent half is dropped. Totals cover every recorded query. With a Redis
+/// key, counts are also added to the sorted sets `{key}:count` and
+/// `{key}:zero` by [`QueryAnalytics::flush`], so they aggregate across
+/// instances and survive restarts; counts of dropped queries are held until
+/// then.
+pub struct QueryAnalytics {
+    queries: Mutex<HashMap<String, QueryCounter>>,
+    /// Unflushed counts and zero-result counts of evicted queries
+    evicted: Mutex<HashMap<String, (u64, u64)>>,
+    totals: Mutex<Totals>,
+    max_queries: usize,
+    redis_key: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct QueryStatsResponse {
+    /// Searches recorded since start
+    pub queries_total: u64,
+    /// Searches that returned no results
+    pub zero_result_total: u64,
+    pub avg_latency_ms: f64,
+    /// Distinct queries currently tracked in memory
+    pub tracked_queries: usize,
+    /// `memory`, or `redis` when the lists come from the persisted counts
+    pub source: &'static str,
+    /// Most frequent queries
+    pub top_queries: Vec<QueryCount>,
+    /// Most frequent queries that returned no results
+    pub zero_result_queries: Vec<QueryCount>,
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct QueryCount {
+    pub query: String,
+    pub count: u64,
+    /// Average latency on this instance, when tracked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+}
+
+impl QueryAnalytics {
+    pub fn new(max_queries: usize, redis_key: Option<String>) -> Self {
+        Self {
+            queries: Mutex::new(HashMap::new()),
+            evicted: Mutex::new(HashMap::new()),
+            totals: Mutex::new(Totals::default()),
+            max_queries: max_queries.max(1),
+            redis_key,
+        }
+    }
+
+    /// Whether counts are persisted to Redis
+    pub fn persisted(&self) -> bool {
+        self.redis_key.is_some()
+    }
+
+    /// The `count` most frequent persisted queries, empty without Redis
+    pub async fn popular(&self, cache: &Cache, count: usize) -> Vec<String> {
+        let Some(key) = &self.redis_key else {
+            return Vec::new();
+        };
+        match cache.top_scores(&format!("{}:count", key), count).await {
+            Ok(top) => top.into_iter().map(|(query, _)| query).collect(),
+            Err(e) => {
+                warn!(error = %e, "Failed to read popular queries from Redis");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Record one search
+    pub fn record(&self, query: &str, results: usize, latency_ms: f64) {
+        let query = normalize(query);
+        if query.is_empty() {
+            return;
+        }
+        let zero = results == 0;
+
+        {
+            let mut totals = self.totals.lock().unwrap();
+            totals.queries += 1;
+            totals.zero_results += zero as u64;
+            totals.total_ms += latency_ms;
+        }
+
+        let mut queries = self.queries.lock().unwrap();
+        if queries.len() >= self.max_queries && !queries.contains_key(&query) {
+            let dropped = evict(&mut queries, self.max_queries / 2);
+            if self.persisted() {
+                let mut evicted = self.evicted.lock().unwrap();
+                for (query, counter) in dropped {
+                    if counter.unflushed > 0 || counter.unflushed_zero > 0 {
+                        let pending = evicted.entry(query).or_default();
+                        pending.0 += counter.unflushed;
+                        pending.1 += counter.unflushed_zero;
+                    }
+                }
+            }
+        }
+        let counter = queries.entry(query).or_default();
+        counter.count += 1;
+        counter.total_ms += latency_ms;
+        counter.unflushed += 1;
+        if zero {
+            counter.zero_results += 1;
+            counter.unflushed_zero += 1;
+        }
+    }
+
+    /// Counters and the `limit` most frequent queries
+    pub async fn report(&self, cache: Option<&Cache>, limit: usize) -> QueryStatsResponse {
+        let (queries_total, zero_result_total, avg_latency_ms) = {
+            let totals = self.totals.lock().unwrap();
+            (totals.queries, totals.zero_results, average(totals.total_ms, totals.queries))
+        };
+
+        let persisted = match (&self.redis_key, cache) {
+            (Some(key), Some(cache)) => {
+                let top = cache.top_scores(&format!("{}:count", key), limit).await;
+                let zero = cache.top_scores(&format!("{}:zero", key), limit).await;
+                match (top, zero) {
+                    (Ok(top), Ok(zero)) => Some((top, zero)),
+                    (Err(e), _) | (_, Err(e)) => {
+                        warn!(error = %e, "Failed to read query stats from Redis");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let queries = self.queries.lock().unwrap();
+        let latency = |query: &str| {
+            queries
+                .get(query)
+                .map(|counter| average(counter.total_ms, counter.count))
+        };
+        let with_latency = |ranked: Vec<(String, u64)>| -> Vec<QueryCount> {
+            ranked
+                .into_iter()
+                .map(|(query, count)| QueryCount {
+                    avg_latency_ms: latency(&query),
+                    query,
+                    count,
+                })
+                .collect()
+        };
+
+        let (source, top_queries, zero_result_queries) = match persisted {
+            Some((top, zero)) => ("redis", with_latency(top), with_latency(zero)),
+            None => (
+                "memory",
+                with_latency(top(&queries, limit, |c| c.count)),
+                with_latency(top(&queries, limit, |c| c.zero_results)),
+            ),
+        };
+
+        QueryStatsResponse {
+            queries_total,
+            zero_result_total,
+            avg_latency_ms,
+            tracked_queries: queries.len(),
+            source,
+            top_queries,
+            zero_result_queries,
+        }
+    }
+
+    /// Add counts recorded since the last flush to the Redis sorted sets
+    ///
+    /// Counts that fail to write are dropped rather than retried.
+    pub async fn flush(&self, cache: &Cache) {
+        let Some(key) = &self.redis_key else {
+            return;
+        };
+
+        let (counts, zeros) = {
+            let mut queries = self.queries.lock().unwrap();
+            let mut counts: HashMap<String, u64> = HashMap::new();
+            let mut zeros: HashMap<String, u64> = HashMap::new();
+            for (query, (count, zero)) in self.evicted.lock().unwrap().drain() {
+                if count > 0 {
+                    counts.insert(query.clone(), count);
+                }
+                if zero > 0 {
+                    zeros.insert(query, zero);
+                }
+            }
+            for (query, counter) in queries.iter_mut() {
+                if counter.unflushed > 0 {
+                    *counts.entry(query.clone()).or_default() +=
+                        std::mem::take(&mut counter.unflushed);
+                }
+                if counter.unflushed_zero > 0 {
+                    *zeros.entry(query.clone()).or_default() +=
+                        std::mem::take(&mut counter.unflushed_zero);
+                }
+            }
+            (
+                counts.into_iter().collect::<Vec<_>>(),
+                zeros.into_iter().collect::<Vec<_>>(),
+            )
+        };
+
+        for (suffix, deltas) in [("count", counts), ("zero", zeros)] {
+            if deltas.is_empty() {
+                continue;
+            }
+            if let Err(e) = cache.incr_scores(&format!("{}:{}", key, suffix), &deltas).await {
+                warn!(error = %e, "Failed to persist query stats to Redis");
+            }
+        }
+    }
+}
+
+/// Lowercase and collapse whitespace so trivially different spellings count together
+fn normalize(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn average(total_ms: f64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total_ms / count as f64
+    }
+}
+
+/// Keep only the `keep` most frequent queries, returning the others
+///
+/// Ties are broken by query, so exactly `keep` remain even when many
+/// queries share a count.
+fn evict(queries: &mut HashMap<String, QueryCounter>, keep: usize) -> Vec<(String, QueryCounter)> {
+    if queries.len() <= keep {
+        return Vec::new();
+    }
+    let mut ranked: Vec<(String, u64)> = queries
+        .iter()
+        .map(|(query, counter)| (query.clone(), counter.count))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+        .split_off(keep)
+        .into_iter()
+        .filter_map(|(query, _)| queries.remove_entry(&query))
+        .collect()
+}
+
+/// The `limit` queries with the highest non-zero `score`, best first
+fn top(
+    queries: &HashMap<String, QueryCounter>,
+    limit: usize,
+    score: impl Fn(&QueryCounter) -> u64,
+) -> Vec<(String, u64)> {
+    let mut ranked: Vec<(String, u64)> = queries
+        .iter()
+        .map(|(query, counter)| (query.clone(), score(counter)))
+        .filter(|(_, score)| *score > 0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(limit);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_top_and_zero_result_queries() {
+        let analytics = QueryAnalytics::new(100, None);
+        analytics.record("Cloud  Hosting", 10, 4.0);
+        analytics.record("cloud hosting", 5, 2.0);
+        analytics.record("pet shop", 3, 1.0);
+        analytics.record("zzqx", 0, 1.0);
+
+        let report = analytics.report(None, 10).await;
+        assert_eq!(report.queries_total, 4);
+        assert_eq!(report.zero_result_total, 1);
+        assert_eq!(report.source, "memory");
+        assert_eq!(report.top_queries[0].query, "cloud hosting");
+        assert_eq!(report.top_queries[0].count, 2);
+        assert_eq!(report.top_queries[0].avg_latency_ms, Some(3.0));
+        assert_eq!(report.zero_result_queries.len(), 1);
+        assert_eq!(report.zero_result_queries[0].query, "zzqx");
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_frequent_queries() {
+        let analytics = QueryAnalytics::new(4, None);
+        for _ in 0..3 {
+            analytics.record("popular", 1, 1.0);
+        }
+        for query in ["a", "b", "c", "d", "e"] {
+            analytics.record(query, 1, 1.0);
+        }
+
+        let report = analytics.report(None, 10).await;
+        assert!(report.tracked_queries <= 4);
+        assert_eq!(report.top_queries[0].query, "popular");
+        assert_eq!(report.queries_total, 8);
+    }
+
+    #[tokio::test]
+    async fn test_eviction_with_equal_counts_keeps_half() {
+        let analytics = QueryAnalytics::new(4, Some("stats".to_string()));
+        for query in ["a", "b", "c", "d", "e"] {
+            analytics.record(query, 0, 1.0);
+        }
+
+        // Two of the four tied queries stay, next to the new one
+        let report = analytics.report(None, 10).await;
+        assert_eq!(report.tracked_queries, 3);
+        assert_eq!(report.top_queries[2].query, "e");
+
+        // The dropped queries' counts wait for the next flush
+        {
+            let evicted = analytics.evicted.lock().unwrap();
+            assert_eq!(evicted.len(), 2);
+            assert!(evicted.values().all(|&pending| pending == (1, 1)));
+        }
+        let cache = Cache::memory(10, Duration::from_secs(60));
+        analytics.flush(&cache).await;
+        assert!(analytics.evicted.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::error::error_response;
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use domain_core::Config;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(86400);
+
+/// An API key definition, as loaded from config or the keys file
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+
+    /// Display name used in stats and logs
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Requests per minute (defaults to `RATE_LIMIT_PER_MINUTE`)
+    #[serde(default)]
+    pub rate_limit: Option<u32>,
+
+    /// Requests per day (defaults to `DAILY_QUOTA`, 0 = unlimited)
+    #[serde(default)]
+    pub quota: Option<u64>,
+
+    /// Whether the key may call /admin endpoints
+    #[serde(default)]
+    pub admin: bool,
+}
+
+/// Fixed-window usage counters for one key
+struct KeyUsage {
+    minute_start: Instant,
+    minute_count: u32,
+    day_start: Instant,
+    day_count: u64,
+    total: u64,
+    rejected: u64,
+}
+
+impl KeyUsage {
+    fn new(now: Instant) -> Self {
+        Self {
+            minute_start: now,
+            minute_count: 0,
+            day_start: now,
+            day_count: 0,
+            total: 0,
+            rejected: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct KeyUsageStats {
+    pub name: String,
+    pub requests_total: u64,
+    pub requests_rejected: u64,
+    pub requests_this_minute: u32,
+    pub requests_today: u64,
+    pub rate_limit: u32,
+    pub quota: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Denied {
+    UnknownKey,
+    Forbidden,
+    RateLimited { retry_after: Duration },
+    QuotaExceeded { retry_after: Duration },
+}
+
+/// Registered API keys and their usage
+pub struct KeyStore {
+    keys: HashMap<String, ApiKey>,
+    usage: Mutex<HashMap<String, KeyUsage>>,
+    default_rate_limit: u32,
+    default_quota: u64,
+}
+
+impl KeyStore {
+    pub fn new(keys: Vec<ApiKey>, default_rate_limit: u32, default_quota: u64) -> Self {
+        Self {
+            keys: keys.into_iter().map(|k| (k.key.clone(), k)).collect(),
+            usage: Mutex::new(HashMap::new()),
+            default_rate_limit,
+            default_quota,
+        }
+    }
+
+    /// Build the key store from `API_KEYS` and `API_KEYS_FILE`
+    ///
+    /// Returns `None` when no keys are configured, which disables auth.
+    pub fn from_config(config: &Config) -> anyhow::Result<Option<Self>> {
+        let mut keys: Vec<ApiKey> = config
+            .api_keys
+            .iter()
+            .map(|key| ApiKey {
+                key: key.clone(),
+                name: None,
+                rate_limit: None,
+                quota: None,
+                admin: true,
+            })
+            .collect();
+
+        if let Some(path) = &config.api_keys_file {
+            keys.extend(load_keys_file(path)?);
+        }
+
+        if keys.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(
+            keys,
+            config.rate_limit_per_minute,
+            config.daily_quota,
+        )))
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check a key and record the request against its limits
+    pub fn check(&self, key: &str, admin_route: bool) -> Result<&ApiKey, Denied> {
+        self.check_at(key, admin_route, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, admin_route: bool, now: Instant) -> Result<&ApiKey, Denied> {
+        let api_key = self.keys.get(key).ok_or(Denied::UnknownKey)?;
+
+        if admin_route && !api_key.admin {
+            return Err(Denied::Forbidden);
+        }
+
+        let rate_limit = api_key.rate_limit.unwrap_or(self.default_rate_limit);
+        let quota = api_key.quota.unwrap_or(self.default_quota);
+
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage
+            .entry(key.to_string())
+            .or_insert_with(|| KeyUsage::new(now));
+
+        if now.duration_since(entry.minute_start) >= MINUTE {
+            entry.minute_start = now;
+            entry.minute_count = 0;
+        }
+        if now.duration_since(entry.day_start) >= DAY {
+            entry.day_start = now;
+            entry.day_count = 0;
+        }
+
+        if quota > 0 && entry.day_count >= quota {
+            entry.rejected += 1;
+            return Err(Denied::QuotaExceeded {
+                retry_after: DAY - now.duration_since(entry.day_start),
+            });
+        }
+        if rate_limit > 0 && entry.minute_count >= rate_limit {
+            entry.rejected += 1;
+            return Err(Denied::RateLimited {
+                retry_after: MINUTE - now.duration_since(entry.minute_start),
+            });
+        }
+
+        entry.minute_count += 1;
+        entry.day_count += 1;
+        entry.total += 1;
+
+        Ok(api_key)
+    }
+
+    /// Per-key usage counters for /stats
+    pub fn usage(&self) -> Vec<KeyUsageStats> {
+        let usage = self.usage.lock().unwrap();
+
+        let mut stats: Vec<KeyUsageStats> = self
+            .keys
+            .values()
+            .map(|api_key| {
+                let entry = usage.get(&api_key.key);
+                KeyUsageStats {
+                    name: display_name(api_key),
+                    requests_total: entry.map(|e| e.total).unwrap_or(0),
+                    requests_rejected: entry.map(|e| e.rejected).unwrap_or(0),
+                    requests_this_minute: entry.map(|e| e.minute_count).unwrap_or(0),
+                    requests_today: entry.map(|e| e.day_count).unwrap_or(0),
+                    rate_limit: api_key.rate_limit.unwrap_or(self.default_rate_limit),
+                    quota: api_key.quota.unwrap_or(self.default_quota),
+                }
+            })
+            .collect();
+
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+}
+
+/// Key name, or a masked form of the key itself
+fn display_name(api_key: &ApiKey) -> String {
+    match &api_key.name {
+        Some(name) => name.clone(),
+        None => {
+            let visible: String = api_key.key.chars().take(4).collect();
+            format!("{}…", visible)
+        }
+    }
+}
+
+/// Load key definitions from a JSON file (array of `ApiKey` objects)
+fn load_keys_file(path: &Path) -> anyhow::Result<Vec<ApiKey>> {
+    let data = std::fs::read_to_string(path)?;
+    let keys: Vec<ApiKey> = serde_json::from_str(&data)?;
+    Ok(keys)
+}
+
+/// Extract the key from `X-API-Key` or `Authorization: Bearer`
+fn extract_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(key);
+    }
+
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Middleware enforcing API keys and per-key limits
+///
+/// `/health` stays open so load balancers and container health checks work,
+/// as do the API docs so clients can be generated without a key. Without any
+/// keys configured, `/admin` endpoints are refused, since no caller could
+/// prove to be an admin.
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let admin_route = path.starts_with("/admin");
+
+    let Some(keys) = &state.auth else {
+        if admin_route {
+            return error_response(
+                StatusCode::FORBIDDEN,
+                "Admin endpoints need API_KEYS or API_KEYS_FILE",
+            );
+        }
+        return next.run(request).await;
+    };
+
+    if matches!(path, "/health" | "/openapi.json" | "/docs") {
+        return next.run(request).await;
+    }
+
+    let Some(key) = extract_key(request.headers()) else {
+        return error_response(StatusCode::UNAUTHORIZED, "Missing API key");
+    };
+
+    match keys.check(key, admin_route) {
+        Ok(_) => next.run(request).await,
+        Err(Denied::UnknownKey) => error_response(StatusCode::UNAUTHORIZED, "Invalid API key"),
+        Err(Denied::Forbidden) => {
+            error_response(StatusCode::FORBIDDEN, "API key is not allowed to use admin endpoints")
+        }
+        Err(Denied::RateLimited { retry_after }) => {
+            too_many_requests("Rate limit exceeded", retry_after)
+        }
+        Err(Denied::QuotaExceeded { retry_after }) => {
+            too_many_requests("Daily quota exceeded", retry_after)
+        }
+    }
+}
+
+fn too_many_requests(message: &str, retry_after: Duration) -> Response {
+    let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, message);
+    if let Ok(value) = HeaderValue::from_str(&retry_after.as_secs().max(1).to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn key(key: &str, rate_limit: Option<u32>, quota: Option<u64>, admin: bool) -> ApiKey {
+        ApiKey {
+            key: key.to_string(),
+            name: Some(key.to_string()),
+            rate_limit,
+            quota,
+            admin,
+        }
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        let store = KeyStore::new(vec![key("abc", None, None, false)], 10, 0);
+        assert_eq!(store.check("nope", false).unwrap_err(), Denied::UnknownKey);
+        assert!(store.check("abc", false).is_ok());
+    }
+
+    #[test]
+    fn test_admin_routes_require_admin_key() {
+        let store = KeyStore::new(
+            vec![key("user", None, None, false), key("root", None, None, true)],
+            10,
+            0,
+        );
+        assert_eq!(store.check("user", true).unwrap_err(), Denied::Forbidden);
+        assert!(store.check("root", true).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_resets_each_minute() {
+        let store = KeyStore::new(vec![key("abc", Some(2), None, false)], 10, 0);
+        let now = Instant::now();
+
+        assert!(store.check_at("abc", false, now).is_ok());
+        assert!(store.check_at("abc", false, now).is_ok());
+        assert!(matches!(
+            store.check_at("abc", false, now),
+            Err(Denied::RateLimited { .. })
+        ));
+        assert!(store.check_at("abc", false, now + MINUTE).is_ok());
+    }
+
+    #[test]
+    fn test_daily_quota() {
+        let store = KeyStore::new(vec![key("abc", None, Some(1), false)], 100, 0);
+        let now = Instant::now();
+
+        assert!(store.check_at("abc", false, now).is_ok());
+        assert!(matches!(
+            store.check_at("abc", false, now + MINUTE),
+            Err(Denied::QuotaExceeded { .. })
+        ));
+
+        let usage = store.usage();
+        assert_eq!(usage[0].requests_total, 1);
+        assert_eq!(usage[0].requests_rejected, 1);
+    }
+
+    #[test]
+    fn test_extract_key_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert_eq!(extract_key(&headers), Some("secret"));
+
+        headers.insert("x-api-key", HeaderValue::from_static("other"));
+        assert_eq!(extract_key(&headers), Some("other"));
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_refused_without_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(dir.path(), 10);
+        let app = Router::new()
+            .route("/stats", get(|| async { "ok" }))
+            .route("/admin/reload", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(state, require_api_key));
+
+        let request = Request::get("/stats").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::get("/admin/reload").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,229 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Period the failure rate is measured over
+const WINDOW: Duration = Duration::from_secs(10);
+
+/// Calls in a window before its failure rate can open the breaker, so a
+/// single failed call on a quiet instance doesn't
+const MIN_CALLS: u32 = 10;
+
+/// Whether calls go through to the guarded service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Too many calls failed; calls are skipped until the cooldown ends
+    Open,
+    /// The cooldown ended; one probe call decides whether to close again
+    HalfOpen,
+}
+
+struct Inner {
+    state: BreakerState,
+    /// Start of the current window, or when the breaker opened
+    since: Instant,
+    calls: u32,
+    failures: u32,
+    /// A half-open probe call is in flight
+    probing: bool,
+    /// Times the breaker opened
+    opened: u64,
+}
+
+/// Circuit breaker skipping a failing service
+///
+/// Once `failure_rate` of the calls in a window fail, the breaker opens and
+/// callers skip the service instead of each waiting out its timeout. After
+/// `cooldown` one probe call is let through: success closes the breaker,
+/// failure opens it for another cooldown. A failure rate of 0 never opens.
+pub struct Breaker {
+    failure_rate: f64,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl Breaker {
+    pub fn new(failure_rate: f64, cooldown: Duration) -> Self {
+        Self {
+            failure_rate,
+            cooldown,
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                since: Instant::now(),
+                calls: 0,
+                failures: 0,
+                probing: false,
+                opened: 0,
+            }),
+        }
+    }
+
+    /// Let a call through as a guard recording its outcome
+    ///
+    /// A half-open probe dropped before [`Call::record`], e.g. when the
+    /// request times out or the client disconnects, lets the next call probe
+    /// instead of leaving the breaker waiting on it forever.
+    pub fn call(&self) -> Option<Call<'_>> {
+        let probe = self.admit()?;
+        Some(Call {
+            breaker: self,
+            probe,
+            recorded: false,
+        })
+    }
+
+    /// `Some(probe)` when a call may go through, `probe` telling whether it
+    /// is the half-open probe
+    fn admit(&self) -> Option<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::Closed => Some(false),
+            BreakerState::Open if inner.since.elapsed() < self.cooldown => None,
+            BreakerState::Open | BreakerState::HalfOpen => {
+                inner.state = BreakerState::HalfOpen;
+                (!std::mem::replace(&mut inner.probing, true)).then_some(true)
+            }
+        }
+    }
+
+    /// Record the outcome of a call [`Breaker::call`] let through
+    fn record(&self, ok: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::HalfOpen {
+            inner.probing = false;
+            if ok {
+                inner.state = BreakerState::Closed;
+                inner.since = Instant::now();
+                inner.calls = 0;
+                inner.failures = 0;
+            } else {
+                Self::open(&mut inner);
+            }
+            return;
+        }
+        if inner.state == BreakerState::Open {
+            // A call let through before the breaker opened
+            return;
+        }
+
+        if inner.since.elapsed() >= WINDOW {
+            inner.since = Instant::now();
+            inner.calls = 0;
+            inner.failures = 0;
+        }
+        inner.calls += 1;
+        if !ok {
+            inner.failures += 1;
+        }
+        let rate = inner.failures as f64 / inner.calls as f64;
+        if self.failure_rate > 0.0 && inner.calls >= MIN_CALLS && rate >= self.failure_rate {
+            Self::open(&mut inner);
+        }
+    }
+
+    fn open(inner: &mut Inner) {
+        inner.state = BreakerState::Open;
+        inner.since = Instant::now();
+        inner.opened += 1;
+    }
+
+    /// Free the half-open probe of a call that never recorded its outcome
+    fn abandon_probe(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::HalfOpen {
+            inner.probing = false;
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// Times the breaker has opened since startup
+    pub fn opened(&self) -> u64 {
+        self.inner.lock().unwrap().opened
+    }
+}
+
+/// A call [`Breaker::call`] let through
+pub struct Call<'a> {
+    breaker: &'a Breaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Call<'_> {
+    /// Record the outcome of the call
+    pub fn record(mut self, ok: bool) {
+        self.recorded = true;
+        self.breaker.record(ok);
+    }
+}
+
+impl Drop for Call<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.abandon_probe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_and_probes() {
+        let breaker = Breaker::new(0.5, Duration::from_secs(30));
+        for ok in [true, true, true, true, true, false, false, false, false] {
+            breaker.call().unwrap().record(ok);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.call().unwrap().record(false);
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.call().is_none());
+
+        // After the cooldown a single probe goes through
+        breaker.inner.lock().unwrap().since -= Duration::from_secs(30);
+        let probe = breaker.call().unwrap();
+        assert!(breaker.call().is_none());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        probe.record(false);
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.opened(), 2);
+
+        breaker.inner.lock().unwrap().since -= Duration::from_secs(30);
+        breaker.call().unwrap().record(true);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.call().is_some());
+    }
+
+    #[test]
+    fn test_dropped_probe_frees_half_open() {
+        let breaker = Breaker::new(0.5, Duration::ZERO);
+        for _ in 0..MIN_CALLS {
+            breaker.call().unwrap().record(false);
+        }
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        let probe = breaker.call().unwrap();
+        assert!(breaker.call().is_none());
+        drop(probe);
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        breaker.call().unwrap().record(true);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_zero_failure_rate_never_opens() {
+        let breaker = Breaker::new(0.0, Duration::from_secs(30));
+        for _ in 0..100 {
+            breaker.call().unwrap().record(false);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
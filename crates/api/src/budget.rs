@@ -0,0 +1,119 @@
+use domain_core::Config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Period each quota covers
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// An upstream service called while answering requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Upstream {
+    /// Word splitter API, splitting single-keyword queries
+    WordSplitter,
+    /// RDAP, for `enrich=true` and `/available?confirm=true`
+    Rdap,
+    /// DNS, confirming availability when RDAP can't
+    Dns,
+}
+
+/// Upstreams whose budget ran out while answering, so the response is
+/// missing what they would have added
+pub type Degraded = BTreeSet<Upstream>;
+
+/// Calls made in the current window
+struct Window {
+    started: Instant,
+    used: u32,
+}
+
+/// Per-minute quotas of upstream calls shared by every request
+///
+/// Features calling upstreams at query time take from their service's quota
+/// and degrade once it is spent: queries are searched unsplit, enrichment
+/// covers fewer domains and availability goes unconfirmed. Responses list
+/// the upstreams they missed under `degraded`. A quota of 0 is unlimited.
+pub struct Budget {
+    quotas: [u32; 3],
+    windows: Mutex<[Window; 3]>,
+}
+
+impl Budget {
+    pub fn new(word_splitter: u32, rdap: u32, dns: u32) -> Self {
+        let window = || Window {
+            started: Instant::now(),
+            used: 0,
+        };
+        Self {
+            quotas: [word_splitter, rdap, dns],
+            windows: Mutex::new([window(), window(), window()]),
+        }
+    }
+
+    /// Quotas of `WORD_SPLITTER_BUDGET`, `RDAP_BUDGET` and `DNS_BUDGET`
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.word_splitter_budget,
+            config.rdap_budget,
+            config.dns_budget,
+        )
+    }
+
+    /// Budget without quotas
+    pub fn unlimited() -> Self {
+        Self::new(0, 0, 0)
+    }
+
+    /// Take up to `calls` from the upstream's quota, returning how many were
+    /// granted
+    pub fn take(&self, upstream: Upstream, calls: u32) -> u32 {
+        let quota = self.quotas[upstream as usize];
+        if quota == 0 {
+            return calls;
+        }
+        let mut windows = self.windows.lock().unwrap();
+        let window = &mut windows[upstream as usize];
+        if window.started.elapsed() >= WINDOW {
+            window.started = Instant::now();
+            window.used = 0;
+        }
+        let granted = calls.min(quota - window.used);
+        window.used += granted;
+        granted
+    }
+
+    /// Take one call, recording the upstream as degraded when none is left
+    pub fn try_take(&self, upstream: Upstream, degraded: &mut Degraded) -> bool {
+        let granted = self.take(upstream, 1) == 1;
+        if !granted {
+            degraded.insert(upstream);
+        }
+        granted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotas_are_per_upstream() {
+        let budget = Budget::new(2, 0, 1);
+        let mut degraded = Degraded::new();
+
+        assert_eq!(budget.take(Upstream::WordSplitter, 3), 2);
+        assert!(!budget.try_take(Upstream::WordSplitter, &mut degraded));
+        assert_eq!(budget.take(Upstream::Rdap, 1000), 1000);
+        assert!(budget.try_take(Upstream::Dns, &mut degraded));
+        assert!(!budget.try_take(Upstream::Dns, &mut degraded));
+        let degraded: Vec<Upstream> = degraded.into_iter().collect();
+        assert_eq!(degraded, vec![Upstream::WordSplitter, Upstream::Dns]);
+
+        // A new window refills the quota
+        budget.windows.lock().unwrap()[Upstream::WordSplitter as usize].started -= WINDOW;
+        assert_eq!(budget.take(Upstream::WordSplitter, 1), 1);
+    }
+}
@@ -1,10 +1,29 @@
-use redis::aio::ConnectionManager;
-use redis::AsyncCommands;
+use crate::breaker::Breaker;
+use domain_core::config::RedisMode;
+use domain_core::Config;
+use domain_search::SearchRequest;
+use redis::aio::{ConnectionLike, ConnectionManager};
+use redis::cluster::ClusterClientBuilder;
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+use redis::{AsyncCommands, Cmd, ConnectionInfo, IntoConnectionInfo, Pipeline, RedisFuture, Value};
 use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-const CACHE_TTL: u64 = 86400; // 24 hours in seconds
-const KEY_PREFIX: &str = "ds:"; // domain-search prefix
+const DEFAULT_KEY_PREFIX: &str = "ds:"; // domain-search prefix
+
+/// Envelope tag of a Redis value stored as plain JSON
+const TAG_JSON: u8 = b'J';
+
+/// Envelope tag of a Redis value stored as zstd-compressed JSON
+const TAG_ZSTD: u8 = b'Z';
+
+/// zstd's default level: most of the size win at a fraction of the CPU
+const ZSTD_LEVEL: i32 = 3;
 
 #[derive(Error, Debug)]
 pub enum CacheError {
@@ -13,97 +32,624 @@ pub enum CacheError {
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    #[error("Redis configuration error: {0}")]
+    Config(String),
+
+    #[error("Redis timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Compression error: {0}")]
+    Compression(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, CacheError>;
 
-/// Redis cache wrapper
+/// Two-tier response cache
+///
+/// An optional in-process LRU tier sits in front of an optional Redis tier.
+/// Lookups hit the local tier first and backfill it from Redis on a miss.
 #[derive(Clone)]
 pub struct Cache {
-    conn: ConnectionManager,
+    conn: Option<Redis>,
+    local: Option<LocalCache>,
+    prefix: String,
+    /// JSON at least this long is compressed in Redis (0: never)
+    compress_min_bytes: usize,
+    /// How long a Redis command may take
+    timeout: Option<Duration>,
+    breaker: Arc<Breaker>,
+}
+
+/// Connections to the Redis tier
+#[derive(Clone)]
+enum Redis {
+    /// Multiplexed connections to one server, used in turn
+    Pool {
+        conns: Arc<[ConnectionManager]>,
+        next: Arc<AtomicUsize>,
+    },
+    /// Redis Cluster, routing each key to the node holding its slot
+    Cluster(ClusterConnection),
+}
+
+impl Redis {
+    /// A connection to send commands over, the next in turn when pooled
+    fn connection(&self) -> RedisConnection {
+        match self {
+            Redis::Pool { conns, next } => {
+                let i = next.fetch_add(1, Ordering::Relaxed) % conns.len();
+                RedisConnection::Single(Box::new(conns[i].clone()))
+            }
+            Redis::Cluster(conn) => RedisConnection::Cluster(conn.clone()),
+        }
+    }
+
+    fn is_cluster(&self) -> bool {
+        matches!(self, Redis::Cluster(_))
+    }
+}
+
+/// One connection of the Redis tier, whichever its topology
+enum RedisConnection {
+    Single(Box<ConnectionManager>),
+    Cluster(ClusterConnection),
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Single(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Single(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Single(conn) => conn.get_db(),
+            RedisConnection::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+/// Serialized value plus the TTL it was stored with
+#[derive(Clone)]
+struct LocalEntry {
+    json: Arc<str>,
+    ttl: Duration,
+}
+
+/// Expires local entries after the shorter of their own TTL and the tier TTL
+struct LocalExpiry {
+    max_ttl: Duration,
+}
+
+impl moka::Expiry<String, LocalEntry> for LocalExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &String,
+        value: &LocalEntry,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(value.ttl.min(self.max_ttl))
+    }
+}
+
+/// In-process LRU tier holding serialized values
+#[derive(Clone)]
+struct LocalCache {
+    entries: moka::sync::Cache<String, LocalEntry>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl LocalCache {
+    fn new(max_entries: u64, ttl: Duration) -> Self {
+        Self {
+            entries: moka::sync::Cache::builder()
+                .max_capacity(max_entries)
+                .expire_after(LocalExpiry { max_ttl: ttl })
+                .build(),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Arc<str>> {
+        let value = self.entries.get(key).map(|e| e.json);
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    fn insert(&self, key: String, json: Arc<str>, ttl: Duration) {
+        self.entries.insert(key, LocalEntry { json, ttl });
+    }
+}
+
+/// A value as stored in Redis: an envelope tag, then the JSON, compressed
+/// when it's at least `min_bytes` long
+///
+/// The local tier keeps plain JSON; compression only saves Redis memory and
+/// transfer, where large `limit` responses dominate.
+fn encode(json: &str, min_bytes: usize) -> Result<Vec<u8>> {
+    if min_bytes == 0 || json.len() < min_bytes {
+        let mut data = Vec::with_capacity(json.len() + 1);
+        data.push(TAG_JSON);
+        data.extend_from_slice(json.as_bytes());
+        return Ok(data);
+    }
+    let mut data = vec![TAG_ZSTD];
+    zstd::stream::copy_encode(json.as_bytes(), &mut data, ZSTD_LEVEL)?;
+    Ok(data)
+}
+
+/// The JSON of a value stored by [`encode`]; values without a tag were
+/// stored as bare JSON before the envelope
+fn decode(data: Vec<u8>) -> Result<String> {
+    let json = match data.first() {
+        Some(&TAG_JSON) => data[1..].to_vec(),
+        Some(&TAG_ZSTD) => zstd::stream::decode_all(&data[1..])?,
+        _ => data,
+    };
+    String::from_utf8(json)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+}
+
+/// A comma-separated filter list as keyed: sorted without duplicates, or
+/// `any` when empty
+fn key_list(mut values: Vec<String>) -> String {
+    if values.is_empty() {
+        return "any".to_string();
+    }
+    values.sort();
+    values.dedup();
+    values.join(",")
+}
+
+/// The query keywords of a cache key, sorted when their order can't change
+/// the response
+///
+/// Compound forms join two keywords both ways and `did_you_mean` is sorted by
+/// token, so `coffee shop` and `shop coffee` answer alike. Three or more
+/// keywords join different adjacent pairs, and hacks and autocorrect spell
+/// out the query in order, so those keep it.
+fn key_query(request: &SearchRequest) -> String {
+    let mut tokens = request.tokens();
+    if tokens.len() <= 2 && !request.hacks && !request.autocorrect {
+        tokens.sort();
+    }
+    tokens.join(" ")
+}
+
+/// Digest of a value's JSON form, telling apart values that share a name;
+/// stable across restarts, as keys outlive the process in Redis
+fn fingerprint(value: &impl Serialize) -> String {
+    let json = serde_json::to_string(value).unwrap_or_default();
+    format!("{:x}", md5::compute(json))[..16].to_string()
 }
 
 impl Cache {
-    /// Create a new cache connection
-    pub async fn new(redis_url: &str) -> Result<Self> {
-        let client = redis::Client::open(redis_url)?;
-        let conn = ConnectionManager::new(client).await?;
-        Ok(Self { conn })
+    /// Connect to the Redis tier at `redis_url` as `config` describes it:
+    /// one server (`rediss://` for TLS), a cluster from its seed nodes, or
+    /// the master a set of sentinels names
+    ///
+    /// `REDIS_USERNAME` and `REDIS_PASSWORD` override the URLs' credentials.
+    /// A sentinel-managed master is resolved once; after a failover the
+    /// API needs a restart to follow it.
+    pub async fn new(redis_url: &str, config: &Config) -> Result<Self> {
+        let urls: Vec<&str> =
+            redis_url.split(',').map(str::trim).filter(|url| !url.is_empty()).collect();
+        if urls.is_empty() {
+            return Err(CacheError::Config("REDIS_URL is empty".to_string()));
+        }
+        let with_credentials = |mut info: ConnectionInfo| {
+            if let Some(username) = &config.redis_username {
+                info.redis.username = Some(username.clone());
+            }
+            if let Some(password) = &config.redis_password {
+                info.redis.password = Some(password.clone());
+            }
+            info
+        };
+
+        let conn = match config.redis_mode {
+            RedisMode::Standalone => {
+                let [url] = urls[..] else {
+                    return Err(CacheError::Config(
+                        "REDIS_URL lists several servers; set REDIS_MODE".to_string(),
+                    ));
+                };
+                let info = with_credentials(url.into_connection_info()?);
+                Self::pool(redis::Client::open(info)?, config.redis_pool_size).await?
+            }
+            RedisMode::Cluster => {
+                let nodes = urls
+                    .iter()
+                    .map(|url| url.into_connection_info().map(with_credentials))
+                    .collect::<redis::RedisResult<Vec<_>>>()?;
+                let client = ClusterClientBuilder::new(nodes).build()?;
+                Redis::Cluster(client.get_async_connection().await?)
+            }
+            RedisMode::Sentinel => {
+                let master = config.redis_sentinel_master.as_deref().ok_or_else(|| {
+                    let message = "REDIS_MODE=sentinel needs REDIS_SENTINEL_MASTER";
+                    CacheError::Config(message.to_string())
+                })?;
+                let master_info = with_credentials(urls[0].into_connection_info()?);
+                let node = SentinelNodeConnectionInfo {
+                    tls_mode: urls[0].starts_with("rediss://").then_some(redis::TlsMode::Secure),
+                    redis_connection_info: Some(master_info.redis),
+                };
+                let mut sentinel = Sentinel::build(urls)?;
+                let client = sentinel.async_master_for(master, Some(&node)).await?;
+                Self::pool(client, config.redis_pool_size).await?
+            }
+        };
+        Ok(Self {
+            conn: Some(conn),
+            local: None,
+            prefix: DEFAULT_KEY_PREFIX.to_string(),
+            compress_min_bytes: config.cache_compress_min_bytes,
+            timeout: Some(Duration::from_millis(config.redis_timeout_ms)).filter(|t| !t.is_zero()),
+            breaker: Arc::new(Breaker::new(
+                config.redis_breaker_failure_rate,
+                Duration::from_secs(config.redis_breaker_cooldown_secs),
+            )),
+        })
+    }
+
+    /// `size` multiplexed, reconnecting connections to one server
+    async fn pool(client: redis::Client, size: usize) -> Result<Redis> {
+        let mut conns = Vec::with_capacity(size.max(1));
+        for _ in 0..size.max(1) {
+            conns.push(ConnectionManager::new(client.clone()).await?);
+        }
+        Ok(Redis::Pool {
+            conns: conns.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Create a process-local cache without Redis
+    pub fn memory(max_entries: u64, ttl: Duration) -> Self {
+        Self {
+            conn: None,
+            local: Some(LocalCache::new(max_entries, ttl)),
+            prefix: DEFAULT_KEY_PREFIX.to_string(),
+            compress_min_bytes: 0,
+            timeout: None,
+            breaker: Arc::new(Breaker::new(0.0, Duration::ZERO)),
+        }
+    }
+
+    /// Namespace all keys under a custom prefix
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Add an in-process LRU tier in front of Redis
+    pub fn with_memory(mut self, max_entries: u64, ttl: Duration) -> Self {
+        self.local = Some(LocalCache::new(max_entries, ttl));
+        self
     }
 
     /// Get a cached value
+    #[tracing::instrument(name = "cache_lookup", skip_all)]
     pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
-        let full_key = format!("{}{}", KEY_PREFIX, key);
-        let mut conn = self.conn.clone();
-
-        let data: Option<String> = conn.get(&full_key).await?;
+        let full_key = format!("{}{}", self.prefix, key);
 
-        match data {
-            Some(json) => {
-                let value: T = serde_json::from_str(&json)?;
-                Ok(Some(value))
+        if let Some(local) = &self.local {
+            if let Some(json) = local.get(&full_key) {
+                return Ok(Some(serde_json::from_str(&json)?));
             }
-            None => Ok(None),
         }
+
+        let Some(conn) = &self.conn else {
+            return Ok(None);
+        };
+        let mut conn = conn.connection();
+
+        let fetched = self
+            .guarded(async {
+                let data: Option<Vec<u8>> = conn.get(&full_key).await?;
+                let ttl: i64 = match (&data, &self.local) {
+                    (Some(_), Some(_)) => conn.ttl(&full_key).await.unwrap_or(0),
+                    _ => 0,
+                };
+                Ok((data, ttl))
+            })
+            .await?;
+        // Skipped while the breaker is open: a miss
+        let Some((Some(data), ttl)) = fetched else {
+            return Ok(None);
+        };
+
+        let json = decode(data)?;
+        let value: T = serde_json::from_str(&json)?;
+        if let Some(local) = self.local.as_ref().filter(|_| ttl > 0) {
+            local.insert(full_key, json.into(), Duration::from_secs(ttl as u64));
+        }
+        Ok(Some(value))
     }
 
     /// Set a cached value with TTL
-    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
-        let full_key = format!("{}{}", KEY_PREFIX, key);
+    #[tracing::instrument(name = "cache_store", skip_all)]
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) -> Result<()> {
+        let full_key = format!("{}{}", self.prefix, key);
         let json = serde_json::to_string(value)?;
-        let mut conn = self.conn.clone();
 
-        let _: () = conn.set_ex(&full_key, json, CACHE_TTL).await?;
+        if let Some(local) = &self.local {
+            local.insert(full_key.clone(), json.as_str().into(), ttl);
+        }
+
+        if let Some(conn) = &self.conn {
+            let mut conn = conn.connection();
+            let data = encode(&json, self.compress_min_bytes)?;
+            self.guarded(async {
+                let _: () = conn.set_ex(&full_key, data, ttl.as_secs()).await?;
+                Ok(())
+            })
+            .await?;
+        }
         Ok(())
     }
 
     /// Delete a cached value
     pub async fn delete(&self, key: &str) -> Result<()> {
-        let full_key = format!("{}{}", KEY_PREFIX, key);
-        let mut conn = self.conn.clone();
+        let full_key = format!("{}{}", self.prefix, key);
+
+        if let Some(local) = &self.local {
+            local.entries.invalidate(&full_key);
+        }
+
+        // Not skipped by the breaker, which would leave the entry behind
+        if let Some(conn) = &self.conn {
+            let mut conn = conn.connection();
+            self.timed(async {
+                let _: () = conn.del(&full_key).await?;
+                Ok(())
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Run a Redis command on the request path through the circuit breaker,
+    /// `None` when the breaker is open and Redis is skipped
+    async fn guarded<T>(&self, command: impl Future<Output = Result<T>>) -> Result<Option<T>> {
+        // Dropping the call with this future frees a half-open probe
+        let Some(call) = self.breaker.call() else {
+            return Ok(None);
+        };
+        let result = self.timed(command).await;
+        call.record(result.is_ok());
+        result.map(Some)
+    }
+
+    /// Run a Redis command, failing it after `REDIS_TIMEOUT_MS`
+    async fn timed<T>(&self, command: impl Future<Output = Result<T>>) -> Result<T> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, command)
+                .await
+                .map_err(|_| CacheError::Timeout(timeout))?,
+            None => command.await,
+        }
+    }
+
+    /// The Redis circuit breaker
+    pub fn breaker(&self) -> &Breaker {
+        &self.breaker
+    }
+
+    /// Delete every cached entry under the key prefix, returning how many were removed
+    pub async fn flush(&self) -> Result<u64> {
+        let mut flushed: u64 = 0;
+
+        if let Some(local) = &self.local {
+            flushed += local.entries.entry_count();
+            local.entries.invalidate_all();
+        }
+
+        if let Some(conn) = &self.conn {
+            let mut conn = conn.connection();
+            let pattern = format!("{}*", self.prefix);
+
+            let keys: Vec<String> = if self.conn.as_ref().is_some_and(Redis::is_cluster) {
+                // A SCAN cursor belongs to one node, while KEYS is sent to
+                // every master and their answers combined
+                redis::cmd("KEYS").arg(&pattern).query_async(&mut conn).await?
+            } else {
+                let mut iter = conn.scan_match::<_, String>(&pattern).await?;
+                let mut keys = Vec::new();
+                while let Some(key) = iter.next_item().await {
+                    keys.push(key);
+                }
+                keys
+            };
+
+            for chunk in keys.chunks(1000) {
+                let removed: u64 = redis::cmd("UNLINK")
+                    .arg(chunk)
+                    .query_async(&mut conn)
+                    .await?;
+                flushed += removed;
+            }
+        }
+
+        Ok(flushed)
+    }
+
+    /// Generate a cache key for an exact lookup
+    pub fn make_exact_key(generation: u64, domain: &str, include_variants: bool) -> String {
+        let variants_part = if include_variants { "|variants" } else { "" };
+        format!("exact:{}:{}{}", generation, domain, variants_part)
+    }
+
+    /// Generate a cache key from the search parameters
+    ///
+    /// `generation` is the index commit opstamp, so entries computed against
+    /// an older index stop matching as soon as a new commit is picked up.
+    /// `profile` is the resolved profile name and `index` the `index=` selection.
+    ///
+    /// Keys are normalized so requests that search the same thing share an
+    /// entry: keywords are keyed as analyzed and lowercased (and sorted, see
+    /// [`key_query`]), and the TLD and category lists sorted. The effective
+    /// profile, with any
+    /// `sort` and `max_dga_score` overrides, is fingerprinted too, so a
+    /// profile redefined under the same name doesn't answer from entries
+    /// ranked by the old one. Result `fields` and `enrich` are applied to
+    /// the cached response and stay out of the key.
+    pub fn make_key(
+        generation: u64,
+        request: &SearchRequest,
+        profile: &str,
+        index: &str,
+    ) -> String {
+        let tld_part = key_list(request.tlds());
+        let lang_part = request.lang.as_deref().unwrap_or("any");
+        let category_part = format!(
+            "{}{}",
+            key_list(request.categories()),
+            if request.category_counts { "+counts" } else { "" }
+        );
+        let min_match_part = request.min_match.unwrap_or(1);
+        let stop_part = if request.stop_tokens.is_empty() { "keep" } else { "stop" };
+        let hacks_part = if request.hacks { "hacks" } else { "" };
+        let highlight_part = if request.highlight { "hl" } else { "" };
+        let stem_part = if request.stem { "" } else { "nostem" };
+        let autocorrect_part = if request.autocorrect { "ac" } else { "" };
+        format!(
+            "search:{}:{}|{}|{}|{}|{}|{}|{}@{}|{}|{}|{}|{}|{}|{}|{}",
+            generation,
+            key_query(request),
+            tld_part,
+            lang_part,
+            category_part,
+            request.limit,
+            min_match_part,
+            profile,
+            fingerprint(&request.profile),
+            request.dedupe.as_str(),
+            stop_part,
+            hacks_part,
+            highlight_part,
+            stem_part,
+            autocorrect_part,
+            index
+        )
+    }
+
+    /// Add to the scores of sorted set members in Redis
+    ///
+    /// `key` is used as given, outside the cache prefix, so flushing the
+    /// cache leaves it alone. A no-op without a Redis tier.
+    pub async fn incr_scores(&self, key: &str, deltas: &[(String, u64)]) -> Result<()> {
+        let Some(conn) = &self.conn else {
+            return Ok(());
+        };
+        let mut conn = conn.connection();
 
-        let _: () = conn.del(&full_key).await?;
+        let mut pipe = redis::pipe();
+        for (member, delta) in deltas {
+            pipe.zincr(key, member, *delta).ignore();
+        }
+        let _: () = pipe.query_async(&mut conn).await?;
         Ok(())
     }
 
-    /// Generate a cache key from query parameters
-    pub fn make_key(query: &str, tld: Option<&str>, limit: u32, min_match: Option<u32>) -> String {
-        let tld_part = tld.unwrap_or("any");
-        let min_match_part = min_match.unwrap_or(1);
-        format!("search:{}|{}|{}|{}", query, tld_part, limit, min_match_part)
+    /// The `count` highest-scored members of a Redis sorted set, best first
+    pub async fn top_scores(&self, key: &str, count: usize) -> Result<Vec<(String, u64)>> {
+        let Some(conn) = &self.conn else {
+            return Ok(Vec::new());
+        };
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut conn = conn.connection();
+
+        let top: Vec<(String, f64)> =
+            conn.zrevrange_withscores(key, 0, count as isize - 1).await?;
+        Ok(top.into_iter().map(|(member, score)| (member, score as u64)).collect())
+    }
+
+    /// Whether a Redis tier is configured
+    pub fn has_redis(&self) -> bool {
+        self.conn.is_some()
     }
 
     /// Check if cache is healthy
     pub async fn ping(&self) -> bool {
-        let mut conn = self.conn.clone();
-        redis::cmd("PING")
-            .query_async::<String>(&mut conn)
+        let Some(conn) = &self.conn else {
+            return true;
+        };
+        let mut conn = conn.connection();
+        self.timed(async { Ok(redis::cmd("PING").query_async::<Value>(&mut conn).await?) })
             .await
             .is_ok()
     }
 
     /// Get cache statistics
     pub async fn stats(&self) -> Result<CacheStats> {
-        let mut conn = self.conn.clone();
-
-        let info: String = redis::cmd("INFO")
-            .arg("stats")
-            .query_async(&mut conn)
-            .await?;
-
         // Parse basic stats from INFO output
         let mut hits: u64 = 0;
         let mut misses: u64 = 0;
 
-        for line in info.lines() {
-            if line.starts_with("keyspace_hits:") {
-                hits = line.split(':').nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-            } else if line.starts_with("keyspace_misses:") {
-                misses = line.split(':').nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+        if let Some(conn) = &self.conn {
+            let mut conn = conn.connection();
+
+            let info: Value = self
+                .timed(async { Ok(redis::cmd("INFO").arg("stats").query_async(&mut conn).await?) })
+                .await?;
+
+            // A cluster answers with each master's INFO; their counts add up
+            for info in info_texts(info) {
+                for line in info.lines() {
+                    let count = || line.split(':').nth(1).and_then(|s| s.trim().parse().ok());
+                    if line.starts_with("keyspace_hits:") {
+                        hits += count().unwrap_or(0);
+                    } else if line.starts_with("keyspace_misses:") {
+                        misses += count().unwrap_or(0);
+                    }
+                }
             }
         }
 
-        Ok(CacheStats { hits, misses })
+        let memory = self.local.as_ref().map(|local| MemoryCacheStats {
+            entries: local.entries.entry_count(),
+            hits: local.hits.load(Ordering::Relaxed),
+            misses: local.misses.load(Ordering::Relaxed),
+        });
+
+        Ok(CacheStats {
+            hits,
+            misses,
+            memory,
+        })
+    }
+}
+
+/// The INFO text of each server answering
+fn info_texts(info: Value) -> Vec<String> {
+    match info {
+        Value::Map(nodes) => nodes
+            .into_iter()
+            .filter_map(|(_, info)| redis::from_redis_value(&info).ok())
+            .collect(),
+        info => redis::from_redis_value(&info).into_iter().collect(),
     }
 }
 
@@ -111,4 +657,199 @@ impl Cache {
 pub struct CacheStats {
     pub hits: u64,
     pub misses: u64,
+    pub memory: Option<MemoryCacheStats>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct MemoryCacheStats {
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breaker::BreakerState;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn test_redis_topology_checked_before_connecting() {
+        let mut config = Config::test();
+        let err = Cache::new("redis://a:6379, redis://b:6379", &config).await.err().unwrap();
+        assert!(err.to_string().contains("set REDIS_MODE"));
+
+        config.redis_mode = RedisMode::Sentinel;
+        let err = Cache::new("redis://a:26379", &config).await.err().unwrap();
+        assert!(err.to_string().contains("REDIS_SENTINEL_MASTER"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_roundtrip() {
+        let cache = Cache::memory(10, Duration::from_secs(60));
+
+        assert_eq!(cache.get::<u32>("k").await.unwrap(), None);
+        cache.set("k", &42u32, TTL).await.unwrap();
+        assert_eq!(cache.get::<u32>("k").await.unwrap(), Some(42));
+
+        cache.delete("k").await.unwrap();
+        assert_eq!(cache.get::<u32>("k").await.unwrap(), None);
+
+        let stats = cache.stats().await.unwrap().memory.unwrap();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_flush() {
+        let cache = Cache::memory(10, Duration::from_secs(60));
+        cache.set("a", &1u32, TTL).await.unwrap();
+        cache.set("b", &2u32, TTL).await.unwrap();
+        cache.local.as_ref().unwrap().entries.run_pending_tasks();
+
+        assert_eq!(cache.flush().await.unwrap(), 2);
+        assert_eq!(cache.get::<u32>("a").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_honors_entry_ttl() {
+        let cache = Cache::memory(10, Duration::from_secs(60));
+        cache.set("short", &1u32, Duration::from_millis(20)).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cache.get::<u32>("short").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_custom_prefix_isolates_keys() {
+        let cache = Cache::memory(10, TTL).with_prefix("other:");
+        cache.set("k", &1u32, TTL).await.unwrap();
+
+        let local = cache.local.as_ref().unwrap();
+        assert!(local.entries.contains_key("other:k"));
+        assert!(!local.entries.contains_key("ds:k"));
+    }
+
+    #[test]
+    fn test_make_key_includes_generation() {
+        let request = SearchRequest::new("coffee", 50);
+        let k1 = Cache::make_key(1, &request, "default", "default");
+        let k2 = Cache::make_key(2, &request, "default", "default");
+        assert_ne!(k1, k2);
+
+        let k3 = Cache::make_key(1, &request, "seo", "default");
+        assert_ne!(k1, k3);
+
+        let mut deduped = request.clone();
+        deduped.dedupe = domain_search::Dedupe::Label;
+        assert_ne!(k1, Cache::make_key(1, &deduped, "default", "default"));
+
+        let mut stopped = request.clone();
+        stopped.stop_tokens = vec!["the".to_string()];
+        assert_ne!(k1, Cache::make_key(1, &stopped, "default", "default"));
+
+        let mut hacks = request.clone();
+        hacks.hacks = true;
+        assert_ne!(k1, Cache::make_key(1, &hacks, "default", "default"));
+
+        let mut highlighted = request.clone();
+        highlighted.highlight = true;
+        assert_ne!(k1, Cache::make_key(1, &highlighted, "default", "default"));
+
+        let mut unstemmed = request.clone();
+        unstemmed.stem = false;
+        assert_ne!(k1, Cache::make_key(1, &unstemmed, "default", "default"));
+
+        let mut corrected = request.clone();
+        corrected.autocorrect = true;
+        assert_ne!(k1, Cache::make_key(1, &corrected, "default", "default"));
+
+        let mut german = request.clone();
+        german.lang = Some("de".to_string());
+        assert_ne!(k1, Cache::make_key(1, &german, "default", "default"));
+
+        let mut finance = request.clone();
+        finance.category = Some("finance".to_string());
+        assert_ne!(k1, Cache::make_key(1, &finance, "default", "default"));
+        let mut counted = request.clone();
+        counted.category_counts = true;
+        assert_ne!(k1, Cache::make_key(1, &counted, "default", "default"));
+
+        assert_ne!(k1, Cache::make_key(1, &request, "default", "dropped"));
+
+        let mut sorted = request.clone();
+        sorted.profile.sort = vec![domain_search::SortKey::Length];
+        assert_ne!(k1, Cache::make_key(1, &sorted, "default", "default"));
+    }
+
+    #[test]
+    fn test_envelope_compresses_large_values() {
+        let small = r#"{"results":[]}"#;
+        let data = encode(small, 64).unwrap();
+        assert_eq!(data[0], TAG_JSON);
+        assert_eq!(decode(data).unwrap(), small);
+
+        let large = format!(r#"{{"results":["{}"]}}"#, "coffee".repeat(100));
+        let data = encode(&large, 64).unwrap();
+        assert_eq!(data[0], TAG_ZSTD);
+        assert!(data.len() < large.len() / 4);
+        assert_eq!(decode(data).unwrap(), large);
+        assert_eq!(encode(&large, 0).unwrap()[0], TAG_JSON);
+
+        // Entries written before the envelope are bare JSON
+        assert_eq!(decode(small.as_bytes().to_vec()).unwrap(), small);
+    }
+
+    #[test]
+    fn test_make_key_normalizes_equivalent_searches() {
+        let key = |query: &str, tld: Option<&str>| {
+            let mut request = SearchRequest::new(query, 50);
+            request.tld = tld.map(str::to_string);
+            Cache::make_key(1, &request, "default", "default")
+        };
+        let k1 = key("coffee shop", Some("com,net"));
+        assert_eq!(k1, key("Coffee  shop", Some("com,net")));
+        assert_eq!(k1, key("coffee shop", Some(".NET, com")));
+        assert_ne!(k1, key("coffee shop", Some("com")));
+
+        assert_eq!(k1, key("shop coffee", Some(".NET, com")));
+
+        // Where the order can change the response, swapped keywords don't share
+        assert_ne!(key("best coffee shop", None), key("coffee best shop", None));
+        let ordered = |query: &str, hacks: bool, autocorrect: bool| {
+            let mut request = SearchRequest::new(query, 50);
+            request.hacks = hacks;
+            request.autocorrect = autocorrect;
+            Cache::make_key(1, &request, "default", "default")
+        };
+        assert_ne!(ordered("coffee shop", true, false), ordered("shop coffee", true, false));
+        assert_ne!(ordered("coffee shop", false, true), ordered("shop coffee", false, true));
+    }
+
+    #[tokio::test]
+    async fn test_memory_only_cache_is_healthy() {
+        let cache = Cache::memory(10, Duration::from_secs(60));
+        assert!(!cache.has_redis());
+        assert!(cache.ping().await);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe_lets_redis_be_probed_again() {
+        let mut cache = Cache::memory(10, TTL);
+        cache.breaker = Arc::new(Breaker::new(0.5, Duration::ZERO));
+        for _ in 0..10 {
+            let failed = cache.guarded(async { Err::<(), _>(CacheError::Timeout(TTL)) }).await;
+            assert!(failed.is_err());
+        }
+        assert_eq!(cache.breaker.state(), BreakerState::Open);
+
+        // The half-open probe is cancelled, as by the request timeout
+        let probe = cache.guarded(std::future::pending::<Result<()>>());
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), probe).await;
+        assert!(cancelled.is_err());
+
+        assert_eq!(cache.guarded(async { Ok(()) }).await.unwrap(), Some(()));
+        assert_eq!(cache.breaker.state(), BreakerState::Closed);
+    }
 }
@@ -0,0 +1,51 @@
+use domain_core::Config;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+
+/// Gzip or brotli, as the client's `Accept-Encoding` prefers, for responses
+/// of at least `COMPRESSION_MIN_BYTES`
+///
+/// Bulk and long result lists repeat the same few fields thousands of times
+/// and shrink several-fold; small responses aren't worth the CPU. Server-sent
+/// events, gRPC and images are never compressed.
+pub fn layer(config: &Config) -> CompressionLayer<impl Predicate> {
+    let min_bytes = config.compression_min_bytes.min(u16::MAX as usize) as u16;
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .compress_when(DefaultPredicate::new().and(SizeAbove::new(min_bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn encoding(app: &Router, path: &str, accept: &str) -> Option<String> {
+        let request = Request::get(path)
+            .header(header::ACCEPT_ENCODING, accept)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_large_responses_are_compressed() {
+        let mut config = Config::test();
+        config.compression_min_bytes = 1024;
+        let app = Router::new()
+            .route("/large", get(|| async { "coffee.com\n".repeat(1000) }))
+            .route("/small", get(|| async { "coffee.com" }))
+            .layer(layer(&config));
+
+        assert_eq!(encoding(&app, "/large", "gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(encoding(&app, "/large", "br, gzip;q=0.5").await.as_deref(), Some("br"));
+        assert_eq!(encoding(&app, "/large", "identity").await, None);
+        assert_eq!(encoding(&app, "/small", "gzip").await, None);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::access_log;
+use crate::budget::{Degraded, Upstream};
+use crate::AppState;
+use axum::http::StatusCode;
+use rdap_client::{RdapClient, RdapInfo};
+use std::collections::{BTreeMap, HashSet};
+use std::time::Instant;
+
+/// RDAP registration data by domain, returned under `enrichment` with
+/// `enrich=true`
+pub type Enrichment = BTreeMap<String, RdapInfo>;
+
+/// The RDAP client for a request, None unless it asked for `enrich=true`
+///
+/// Checked before searching, so a request for enrichment that isn't
+/// configured fails without doing the work.
+pub fn client(state: &AppState, enrich: bool) -> Result<Option<&RdapClient>, (StatusCode, String)> {
+    if !enrich {
+        return Ok(None);
+    }
+    state.rdap.as_ref().map(Some).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Enrichment is not enabled (set RDAP_URL)".to_string(),
+        )
+    })
+}
+
+/// Look up the first `RDAP_ENRICH_LIMIT` distinct domains
+///
+/// Lookups the RDAP cache can't answer take from the RDAP budget; once it is
+/// spent the remaining domains go unenriched and RDAP is marked degraded.
+pub async fn lookup<'a>(
+    state: &AppState,
+    client: Option<&RdapClient>,
+    domains: impl IntoIterator<Item = &'a str>,
+    degraded: &mut Degraded,
+) -> Enrichment {
+    let Some(client) = client else {
+        return Enrichment::new();
+    };
+    let mut seen = HashSet::new();
+    let mut domains: Vec<&str> = domains
+        .into_iter()
+        .filter(|domain| seen.insert(*domain))
+        .take(state.config.rdap_enrich_limit)
+        .collect();
+
+    let uncached = domains.iter().filter(|domain| !client.is_cached(domain)).count() as u32;
+    let mut granted = state.budget.take(Upstream::Rdap, uncached);
+    if granted < uncached {
+        degraded.insert(Upstream::Rdap);
+        domains.retain(|domain| {
+            if client.is_cached(domain) {
+                return true;
+            }
+            if granted == 0 {
+                return false;
+            }
+            granted -= 1;
+            true
+        });
+    }
+    let start = Instant::now();
+    let enrichment = client.lookup_many(domains).await;
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+    access_log::note(|summary| summary.enrich_ms += elapsed_ms);
+    enrichment
+}
@@ -0,0 +1,60 @@
+use crate::request_id;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tower::BoxError;
+use utoipa::ToSchema;
+
+/// Body returned by every HTTP error
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    /// `X-Request-Id` of the failed request, for correlating with logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// JSON error body: `{"error": "...", "request_id": "..."}`
+pub fn error_response(status: StatusCode, message: &str) -> Response {
+    let body = ErrorResponse {
+        error: message.to_string(),
+        request_id: request_id::current(),
+    };
+    (status, Json(body)).into_response()
+}
+
+/// Map errors from the timeout and load-shedding layers to JSON responses
+pub async fn handle_middleware_error(err: BoxError) -> Response {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        error_response(StatusCode::REQUEST_TIMEOUT, "Request timed out")
+    } else if err.is::<tower::load_shed::error::Overloaded>() {
+        error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Server is at capacity, retry later",
+        )
+    } else {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Unhandled error: {}", err),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_middleware_errors_map_to_status() {
+        let timeout: BoxError = Box::new(tower::timeout::error::Elapsed::new());
+        let response = handle_middleware_error(timeout).await;
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let overloaded: BoxError = Box::new(tower::load_shed::error::Overloaded::new());
+        let response = handle_middleware_error(overloaded).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,349 @@
+// tonic::Status is large by design and every handler returns it
+#![allow(clippy::result_large_err)]
+
+use crate::auth::Denied;
+use crate::routes::exact::{cached_exact, DomainVariant, ExactQuery, ExactResponse};
+use crate::routes::search::{
+    cached_search, run_bulk, BulkQuery, BulkQueryResponse, BulkSearchRequest, SearchQuery,
+    SearchResponse,
+};
+use crate::AppState;
+use axum::http::StatusCode;
+use domain_search::{Dedupe, DomainHack, DomainResult, SearchResult, Suggestion};
+use futures::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("domainsearch.v1");
+}
+
+use proto::domain_search_server::{DomainSearch, DomainSearchServer};
+
+const DEFAULT_LIMIT: u32 = 50;
+
+/// gRPC front end over the same state, cache and search core as HTTP
+pub struct GrpcService {
+    state: Arc<AppState>,
+}
+
+impl GrpcService {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+
+    /// Wrap the service with the API key check used by the HTTP middleware
+    pub fn into_server(
+        self,
+    ) -> tonic::service::interceptor::InterceptedService<
+        DomainSearchServer<Self>,
+        impl tonic::service::Interceptor + Clone,
+    > {
+        let state = self.state.clone();
+        DomainSearchServer::with_interceptor(self, move |request: Request<()>| {
+            check_api_key(&state, request)
+        })
+    }
+}
+
+/// Reject calls without a valid key when authentication is enabled
+fn check_api_key(state: &AppState, request: Request<()>) -> Result<Request<()>, Status> {
+    let Some(keys) = &state.auth else {
+        return Ok(request);
+    };
+
+    let metadata = request.metadata();
+    let key = metadata
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            metadata
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .ok_or_else(|| Status::unauthenticated("Missing API key"))?;
+
+    match keys.check(key, false) {
+        Ok(_) => Ok(request),
+        Err(Denied::UnknownKey) => Err(Status::unauthenticated("Invalid API key")),
+        Err(Denied::Forbidden) => Err(Status::permission_denied("API key is not allowed")),
+        Err(Denied::RateLimited { .. }) => Err(Status::resource_exhausted("Rate limit exceeded")),
+        Err(Denied::QuotaExceeded { .. }) => {
+            Err(Status::resource_exhausted("Daily quota exceeded"))
+        }
+    }
+}
+
+/// Map handler errors onto gRPC status codes
+fn to_status((status, message): (StatusCode, String)) -> Status {
+    match status {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        _ => Status::internal(message),
+    }
+}
+
+fn limit_or_default(limit: u32) -> u32 {
+    if limit == 0 {
+        DEFAULT_LIMIT
+    } else {
+        limit
+    }
+}
+
+fn dedupe(label: bool) -> Dedupe {
+    if label {
+        Dedupe::Label
+    } else {
+        Dedupe::None
+    }
+}
+
+impl From<proto::SearchRequest> for SearchQuery {
+    fn from(request: proto::SearchRequest) -> Self {
+        Self {
+            q: request.q,
+            tld: request.tld,
+            lang: request.lang,
+            category: request.category,
+            facets: request.facets,
+            limit: limit_or_default(request.limit),
+            min_match: request.min_match,
+            profile: request.profile,
+            dedupe: dedupe(request.dedupe_label),
+            no_cache: request.no_cache,
+            debug: false,
+            explain: false,
+            timeout_ms: request.timeout_ms.map(u64::from),
+            keep_stopwords: request.keep_stopwords,
+            hacks: request.hacks,
+            highlight: request.highlight,
+            stem: request.stem.unwrap_or(true),
+            autocorrect: request.autocorrect,
+            index: request.index,
+            max_dga_score: request.max_dga_score,
+            sort: request.sort,
+            hyphen_ratio: request.hyphen_ratio,
+            enrich: false,
+            fields: None,
+        }
+    }
+}
+
+impl From<Suggestion> for proto::Suggestion {
+    fn from(suggestion: Suggestion) -> Self {
+        Self {
+            token: suggestion.token,
+            suggestion: suggestion.suggestion,
+            doc_freq: suggestion.doc_freq,
+        }
+    }
+}
+
+impl From<DomainHack> for proto::DomainHack {
+    fn from(hack: DomainHack) -> Self {
+        Self {
+            domain: hack.domain,
+            label: hack.label,
+            tld: hack.tld,
+            in_zone: hack.in_zone,
+        }
+    }
+}
+
+impl From<DomainResult> for proto::Domain {
+    fn from(domain: DomainResult) -> Self {
+        Self {
+            domain: domain.domain,
+            label: domain.label,
+            tld: domain.tld,
+            length: domain.length,
+            has_hyphen: domain.has_hyphen,
+            tokens: domain.tokens,
+            dga_score: domain.dga_score,
+            lang: domain.lang,
+            categories: domain.categories,
+        }
+    }
+}
+
+impl From<SearchResult> for proto::SearchHit {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            domain: Some(result.domain.into()),
+            match_count: result.match_count as u32,
+            score: result.score,
+            also_in: result.also_in,
+            compound_match: result.compound_match,
+            index: result.index,
+            highlighted: result.highlighted,
+        }
+    }
+}
+
+impl From<SearchResponse> for proto::SearchReply {
+    fn from(response: SearchResponse) -> Self {
+        Self {
+            results: response.results.into_iter().map(Into::into).collect(),
+            total_candidates: response.total_candidates as u64,
+            candidates_scanned: response.candidates_scanned as u64,
+            total_hits: response.total_hits,
+            total_hits_estimated: response.total_hits_estimated,
+            query_time_ms: response.query_time_ms,
+            cached: response.cached,
+            partial: response.partial,
+            hacks: response.hacks.into_iter().map(Into::into).collect(),
+            category_counts: response.category_counts.unwrap_or_default().into_iter().collect(),
+            did_you_mean: response.did_you_mean.into_iter().map(Into::into).collect(),
+            autocorrected_query: response.autocorrected_query,
+            error: None,
+        }
+    }
+}
+
+impl From<BulkQueryResponse> for proto::SearchReply {
+    fn from(result: BulkQueryResponse) -> Self {
+        Self {
+            error: result.error,
+            ..result.response.into()
+        }
+    }
+}
+
+impl From<ExactResponse> for proto::ExactReply {
+    fn from(response: ExactResponse) -> Self {
+        Self {
+            found: response.found,
+            domain: response.domain.map(Into::into),
+            query_time_ms: response.query_time_ms,
+            cached: response.cached,
+            variants: response.variants.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<DomainVariant> for proto::DomainVariant {
+    fn from(variant: DomainVariant) -> Self {
+        Self {
+            kind: variant.kind.as_str().to_string(),
+            domain: Some(variant.domain.into()),
+        }
+    }
+}
+
+type HitStream = Pin<Box<dyn Stream<Item = Result<proto::SearchHit, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl DomainSearch for GrpcService {
+    async fn search(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> Result<Response<proto::SearchReply>, Status> {
+        let params = SearchQuery::from(request.into_inner());
+        let response = cached_search(&self.state, &params).await.map_err(to_status)?;
+        Ok(Response::new(response.into()))
+    }
+
+    async fn exact_lookup(
+        &self,
+        request: Request<proto::ExactRequest>,
+    ) -> Result<Response<proto::ExactReply>, Status> {
+        let request = request.into_inner();
+        let params = ExactQuery {
+            domain: request.domain,
+            no_cache: request.no_cache,
+            include_variants: request.include_variants,
+            enrich: false,
+            fields: None,
+        };
+        let response = cached_exact(&self.state, &params).await.map_err(to_status)?;
+        Ok(Response::new(response.into()))
+    }
+
+    async fn bulk_search(
+        &self,
+        request: Request<proto::BulkSearchRequest>,
+    ) -> Result<Response<proto::BulkSearchReply>, Status> {
+        let request = request.into_inner();
+        let request = BulkSearchRequest {
+            queries: request
+                .queries
+                .into_iter()
+                .map(|q| BulkQuery {
+                    q: q.q,
+                    tld: q.tld,
+                    min_match: q.min_match,
+                    limit: q.limit,
+                    profile: q.profile,
+                    sort: q.sort,
+                    lang: q.lang,
+                    category: q.category,
+                    max_dga_score: q.max_dga_score,
+                    hyphen_ratio: q.hyphen_ratio,
+                    stem: q.stem,
+                    autocorrect: q.autocorrect,
+                    fields: None,
+                })
+                .collect(),
+            limit: limit_or_default(request.limit),
+            profile: request.profile,
+            dedupe: dedupe(request.dedupe_label),
+            no_cache: request.no_cache,
+            timeout_ms: request.timeout_ms.map(u64::from),
+            keep_stopwords: request.keep_stopwords,
+            index: request.index,
+            fields: None,
+            highlight: request.highlight,
+            stem: request.stem.unwrap_or(true),
+        };
+
+        let response = run_bulk(&self.state, request).await.map_err(to_status)?;
+        Ok(Response::new(proto::BulkSearchReply {
+            results: response.results.into_iter().map(Into::into).collect(),
+            total_time_ms: response.total_time_ms,
+            failed_count: response.failed_count as u32,
+        }))
+    }
+
+    type SearchStreamStream = HitStream;
+
+    async fn search_stream(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> Result<Response<Self::SearchStreamStream>, Status> {
+        let params = SearchQuery::from(request.into_inner());
+        let response = cached_search(&self.state, &params).await.map_err(to_status)?;
+
+        let hits = response
+            .results
+            .into_iter()
+            .map(|result| Ok(proto::SearchHit::from(result)));
+        Ok(Response::new(Box::pin(futures::stream::iter(hits))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_request_defaults_limit() {
+        let params = SearchQuery::from(proto::SearchRequest {
+            q: "coffee".to_string(),
+            limit: 0,
+            ..Default::default()
+        });
+        assert_eq!(params.limit, DEFAULT_LIMIT);
+    }
+
+    #[test]
+    fn test_errors_map_to_status_codes() {
+        let status = to_status((StatusCode::BAD_REQUEST, "bad".to_string()));
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let status = to_status((StatusCode::INTERNAL_SERVER_ERROR, "boom".to_string()));
+        assert_eq!(status.code(), tonic::Code::Internal);
+    }
+}
@@ -0,0 +1,238 @@
+use domain_core::{DatedSchema, DomainSchema, HistorySchema};
+use domain_search::Searcher;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tantivy::directory::MmapDirectory;
+use tantivy::{Index, IndexReader, ReloadPolicy};
+use tracing::info;
+use utoipa::ToSchema;
+
+/// Name of the index at `INDEX_PATH`
+pub const PRIMARY_INDEX: &str = "default";
+
+/// `index=` value that searches every index and merges the results
+pub const ALL_INDEXES: &str = "all";
+
+/// An open index with its long-lived reader
+pub struct IndexHandle {
+    pub path: PathBuf,
+    pub index: Index,
+    /// Shared by all routes, reloaded explicitly when the index changes
+    pub reader: IndexReader,
+    pub searcher: Searcher,
+    /// Bytes of the files in the index directory as of the last reload
+    size_bytes: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IndexSummary {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    pub documents: u64,
+    pub segments: usize,
+    pub generation: u64,
+}
+
+impl IndexHandle {
+    /// Open an index directory with a manually reloaded reader
+    pub fn open(path: &Path, schema: &DomainSchema) -> tantivy::Result<Self> {
+        let index = Index::open_in_dir(path)?;
+        let reader: IndexReader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = Searcher::with_reader(reader.clone(), schema.clone());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            index,
+            reader,
+            searcher,
+            size_bytes: AtomicU64::new(directory_size(path)),
+        })
+    }
+
+    /// Size of the index on disk as of the last reload
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Reload the reader and measure the index on disk again
+    pub fn reload(&self) -> tantivy::Result<()> {
+        self.reader.reload()?;
+        self.size_bytes.store(directory_size(&self.path), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Check that the index was built with the schema this server expects
+    pub fn validate_schema(&self, schema: &DomainSchema) -> Result<(), String> {
+        if self.index.schema() == schema.schema {
+            return Ok(());
+        }
+
+        let expected: Vec<&str> = schema.schema.fields().map(|(_, e)| e.name()).collect();
+        let found = self.index.schema();
+        let found: Vec<&str> = found.fields().map(|(_, e)| e.name()).collect();
+
+        Err(format!(
+            "Schema mismatch: expected fields {:?}, found {:?}",
+            expected, found
+        ))
+    }
+
+    pub fn summary(&self) -> tantivy::Result<IndexSummary> {
+        let searcher = self.reader.searcher();
+
+        Ok(IndexSummary {
+            path: self.path.clone(),
+            documents: searcher.num_docs(),
+            segments: searcher.segment_readers().len(),
+            generation: self.index.load_metas()?.opstamp,
+        })
+    }
+}
+
+/// Bytes of the files in an index directory, 0 when it can't be read
+fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// An additional index from `INDEXES`, reloaded alongside the primary one
+pub struct NamedIndex {
+    pub name: String,
+    pub handle: Arc<IndexHandle>,
+    /// Opstamp of the latest commit picked up by the reader
+    generation: AtomicU64,
+}
+
+impl NamedIndex {
+    pub fn open(name: &str, path: &Path, schema: &DomainSchema) -> tantivy::Result<Self> {
+        let handle = IndexHandle::open(path, schema)?;
+        let generation = handle.index.load_metas()?.opstamp;
+        Ok(Self {
+            name: name.to_string(),
+            handle: Arc::new(handle),
+            generation: AtomicU64::new(generation),
+        })
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Reload the reader when the index has a new commit
+    pub fn reload(&self) -> tantivy::Result<u64> {
+        let opstamp = self.handle.index.load_metas()?.opstamp;
+        if opstamp != self.generation() {
+            self.handle.reload()?;
+            let previous = self.generation.swap(opstamp, Ordering::Relaxed);
+            info!(index = self.name, previous, current = opstamp, "Index generation changed");
+        }
+        Ok(opstamp)
+    }
+}
+
+/// An index of dated domain events written by the indexer's daily sync:
+/// dropped domains (`DROPPED_INDEX_PATH`) or new ones (`NEW_DOMAINS_INDEX_PATH`)
+pub struct DatedIndex {
+    pub index: NamedIndex,
+    pub schema: DatedSchema,
+}
+
+impl DatedIndex {
+    /// Open the index, creating an empty one until the first daily sync
+    pub fn open(name: &str, path: &Path, schema: DatedSchema) -> tantivy::Result<Self> {
+        std::fs::create_dir_all(path)?;
+        Index::open_or_create(MmapDirectory::open(path)?, schema.domain.schema.clone())?;
+
+        let index = NamedIndex::open(name, path, &schema.domain)?;
+        Ok(Self { index, schema })
+    }
+}
+
+/// The domain history index (`HISTORY_INDEX_PATH`) written by the
+/// indexer's daily sync
+pub struct HistoryIndex {
+    pub index: NamedIndex,
+    pub schema: HistorySchema,
+}
+
+impl HistoryIndex {
+    /// Open the index, creating an empty one until the first daily sync
+    pub fn open(path: &Path) -> tantivy::Result<Self> {
+        let schema = HistorySchema::new();
+        std::fs::create_dir_all(path)?;
+        Index::open_or_create(MmapDirectory::open(path)?, schema.domain.schema.clone())?;
+
+        let index = NamedIndex::open("history", path, &schema.domain)?;
+        Ok(Self { index, schema })
+    }
+}
+
+/// The indices one search runs against
+pub struct IndexSelection {
+    /// `default`, a name from `INDEXES`, or `all`
+    pub name: String,
+    pub indexes: Vec<(String, Arc<IndexHandle>)>,
+    /// Changes whenever any selected index picks up a new commit
+    pub generation: u64,
+}
+
+impl IndexSelection {
+    /// Whether results from several indices are merged
+    pub fn is_fan_out(&self) -> bool {
+        self.indexes.len() > 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::schema::{Schema, STRING};
+
+    #[test]
+    fn test_validate_schema() {
+        let schema = DomainSchema::new();
+
+        let dir = tempfile::tempdir().unwrap();
+        Index::create_in_dir(dir.path(), schema.schema.clone()).unwrap();
+        let handle = IndexHandle::open(dir.path(), &schema).unwrap();
+        assert!(handle.validate_schema(&schema).is_ok());
+
+        let other = tempfile::tempdir().unwrap();
+        let mut builder = Schema::builder();
+        builder.add_text_field("domain_exact", STRING);
+        Index::create_in_dir(other.path(), builder.build()).unwrap();
+        let handle = IndexHandle::open(other.path(), &schema).unwrap();
+        assert!(handle.validate_schema(&schema).is_err());
+    }
+
+    #[test]
+    fn test_size_is_measured_on_reload() {
+        let schema = DomainSchema::new();
+        let dir = tempfile::tempdir().unwrap();
+        let index = Index::create_in_dir(dir.path(), schema.schema.clone()).unwrap();
+        let handle = IndexHandle::open(dir.path(), &schema).unwrap();
+        let empty = handle.size_bytes();
+        assert!(empty > 0);
+
+        let mut writer: tantivy::IndexWriter = index.writer(15_000_000).unwrap();
+        let domain = domain_core::Domain::new("coffee.com").normalize().unwrap();
+        writer.add_document(schema.to_document(&domain)).unwrap();
+        writer.commit().unwrap();
+        assert_eq!(handle.size_bytes(), empty);
+
+        handle.reload().unwrap();
+        assert!(handle.size_bytes() > empty);
+    }
+}
@@ -0,0 +1,253 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Finished jobs kept for `/admin/jobs`, oldest forgotten first
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for the job ahead of it
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A background admin operation and its outcome
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Job {
+    pub id: String,
+    /// Operation, e.g. `swap`, `cache_warm` or `export`
+    pub kind: String,
+    pub status: JobStatus,
+    /// Unix seconds
+    pub created: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished: Option<u64>,
+    /// Latest progress reported by a running operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub progress: Option<serde_json::Value>,
+    /// What the operation returned, once succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs long admin operations in the background, one at a time, so their
+/// requests return at once with a job to poll
+///
+/// Jobs live in memory: a restart forgets them, and a job running at
+/// shutdown is abandoned.
+#[derive(Clone)]
+pub struct JobQueue {
+    jobs: Arc<Mutex<VecDeque<Job>>>,
+    /// One job runs at a time; the others wait queued
+    running: Arc<Semaphore>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(VecDeque::new())),
+            running: Arc::new(Semaphore::new(1)),
+        }
+    }
+}
+
+impl JobQueue {
+    /// Queue an operation, returning its job as queued
+    pub fn submit<F, T>(&self, kind: &str, operation: F) -> Job
+    where
+        F: Future<Output = Result<T, String>> + Send + 'static,
+        T: Serialize,
+    {
+        self.submit_tracked(kind, |_| operation)
+    }
+
+    /// Queue an operation that reports its progress through the handle it
+    /// is given, returning its job as queued
+    pub fn submit_tracked<O, F, T>(&self, kind: &str, operation: O) -> Job
+    where
+        O: FnOnce(JobProgress) -> F + Send + 'static,
+        F: Future<Output = Result<T, String>> + Send + 'static,
+        T: Serialize,
+    {
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            status: JobStatus::Queued,
+            created: now(),
+            started: None,
+            finished: None,
+            progress: None,
+            result: None,
+            error: None,
+        };
+        self.insert(job.clone());
+
+        let queue = self.clone();
+        let id = job.id.clone();
+        tokio::spawn(async move {
+            let Ok(_permit) = queue.running.clone().acquire_owned().await else {
+                return;
+            };
+            queue.update(&id, |job| {
+                job.status = JobStatus::Running;
+                job.started = Some(now());
+            });
+            info!(job = id, "Job started");
+
+            let progress = JobProgress {
+                queue: queue.clone(),
+                id: id.clone(),
+            };
+            let outcome = operation(progress)
+                .await
+                .and_then(|value| serde_json::to_value(value).map_err(|e| e.to_string()));
+            match &outcome {
+                Ok(_) => info!(job = id, "Job succeeded"),
+                Err(e) => warn!(job = id, error = %e, "Job failed"),
+            }
+            queue.update(&id, |job| {
+                job.finished = Some(now());
+                match outcome {
+                    Ok(value) => {
+                        job.status = JobStatus::Succeeded;
+                        job.result = Some(value);
+                    }
+                    Err(e) => {
+                        job.status = JobStatus::Failed;
+                        job.error = Some(e);
+                    }
+                }
+            });
+        });
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Every known job, newest first
+    pub fn list(&self) -> Vec<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter().rev().cloned().collect()
+    }
+
+    fn insert(&self, job: Job) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.push_back(job);
+        let finished = jobs.iter().filter(|job| job.finished.is_some()).count();
+        if finished > MAX_FINISHED_JOBS {
+            if let Some(oldest) = jobs.iter().position(|job| job.finished.is_some()) {
+                jobs.remove(oldest);
+            }
+        }
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut Job)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            change(job);
+        }
+    }
+}
+
+/// Handle a running operation reports its progress through
+#[derive(Clone)]
+pub struct JobProgress {
+    queue: JobQueue,
+    id: String,
+}
+
+impl JobProgress {
+    /// Replace the job's progress, shown until it finishes
+    pub fn set(&self, progress: &impl Serialize) {
+        if let Ok(value) = serde_json::to_value(progress) {
+            self.queue.update(&self.id, |job| job.progress = Some(value));
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn wait(queue: &JobQueue, id: &str) -> Job {
+        for _ in 0..100 {
+            let job = queue.get(id).unwrap();
+            if job.finished.is_some() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {} didn't finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_run_in_order() {
+        let queue = JobQueue::default();
+        let (release, blocked) = tokio::sync::oneshot::channel::<()>();
+
+        let first = queue.submit("slow", async move {
+            blocked.await.map_err(|e| e.to_string())?;
+            Ok(1)
+        });
+        let second = queue.submit("fails", async { Err::<(), _>("no such index".to_string()) });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(queue.get(&first.id).unwrap().status, JobStatus::Running);
+        assert_eq!(queue.get(&second.id).unwrap().status, JobStatus::Queued);
+
+        release.send(()).unwrap();
+        let first = wait(&queue, &first.id).await;
+        assert_eq!(first.status, JobStatus::Succeeded);
+        assert_eq!(first.result, Some(serde_json::json!(1)));
+        let second = wait(&queue, &second.id).await;
+        assert_eq!(second.status, JobStatus::Failed);
+        assert_eq!(second.error.as_deref(), Some("no such index"));
+
+        let kinds: Vec<String> = queue.list().into_iter().map(|job| job.kind).collect();
+        assert_eq!(kinds, vec!["fails", "slow"]);
+        assert!(queue.get("unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_job_reports_progress() {
+        let queue = JobQueue::default();
+        let (release, blocked) = tokio::sync::oneshot::channel::<()>();
+
+        let job = queue.submit_tracked("tracked", |progress| async move {
+            progress.set(&serde_json::json!({"done": 1}));
+            blocked.await.map_err(|e| e.to_string())?;
+            Ok(())
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let running = queue.get(&job.id).unwrap();
+        assert_eq!(running.progress, Some(serde_json::json!({"done": 1})));
+
+        release.send(()).unwrap();
+        assert_eq!(wait(&queue, &job.id).await.status, JobStatus::Succeeded);
+    }
+}
@@ -1,58 +1,327 @@
 use anyhow::Result;
+use arc_swap::ArcSwap;
 use axum::{
-    routing::{get, post},
+    error_handling::HandleErrorLayer,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
     Router,
 };
-use domain_core::{Config, DomainSchema};
+use domain_core::config::BindAddress;
+use domain_core::{Config, DatedSchema, DomainSchema, QueryAnalyzer};
+use domain_search::{AppraisalWeights, RankingProfiles};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tantivy::Index;
+use std::time::{Duration, Instant};
+use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
-use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing::{info, warn};
+use rdap_client::RdapClient;
+use watchlist::WatchStore;
+use word_client::LocalSegmenter;
 
+mod access_log;
+mod analytics;
+mod auth;
+mod breaker;
+mod budget;
 mod cache;
+mod compression;
+mod enrich;
+mod error;
+mod grpc;
+mod index;
+mod jobs;
+mod openapi;
+mod request_id;
 mod routes;
-mod search;
+mod splitter;
+mod tls;
+mod updates;
+mod warmup;
 
+use analytics::QueryAnalytics;
+use auth::KeyStore;
+use budget::Budget;
 use cache::Cache;
+use jobs::JobQueue;
+use splitter::QuerySplitter;
+use updates::IndexUpdater;
+use index::{
+    DatedIndex, HistoryIndex, IndexHandle, IndexSelection, NamedIndex, ALL_INDEXES, PRIMARY_INDEX,
+};
+
+/// How often to check the index for new commits
+const GENERATION_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Shared application state
 pub struct AppState {
     pub config: Config,
     pub schema: DomainSchema,
-    pub index: Index,
+    /// Currently served index, swappable at runtime
+    pub index: ArcSwap<IndexHandle>,
+    /// Additional indices selectable with `index=`
+    pub extra_indexes: Vec<NamedIndex>,
+    /// Recently dropped domains, searched by `/dropped`
+    pub dropped: Option<DatedIndex>,
+    /// Newly registered domains, searched by `/new`
+    pub new_domains: Option<DatedIndex>,
+    /// Per-domain lifecycle events, served by `/history`
+    pub history: Option<HistoryIndex>,
     pub cache: Option<Cache>,
+    /// API keys and usage counters (None when auth is disabled)
+    pub auth: Option<KeyStore>,
+    /// Named ranking profiles selectable with `profile=`
+    pub profiles: RankingProfiles,
+    /// Formula weights and value bands of `/appraise`
+    pub appraisal: AppraisalWeights,
+    /// Query frequency and latency counters (None when disabled)
+    pub analytics: Option<QueryAnalytics>,
+    /// Watch rules and alerts (None when disabled)
+    pub watch: Option<WatchStore>,
+    /// RDAP lookups for `enrich=true` (None when disabled)
+    pub rdap: Option<RdapClient>,
+    /// Writes of `/admin/domains` (None when disabled)
+    pub updater: Option<IndexUpdater>,
+    /// Background admin operations polled at `/admin/jobs`
+    pub jobs: JobQueue,
+    /// Turns search queries into keywords tokenized like indexed labels
+    pub analyzer: QueryAnalyzer,
+    /// Splits single-keyword queries with the word splitter (None when
+    /// disabled)
+    pub splitter: Option<QuerySplitter>,
+    /// Quotas of word splitter, RDAP and DNS calls made at query time
+    pub budget: Budget,
+    /// Opstamp of the latest index commit, part of every cache key
+    pub generation: AtomicU64,
+    /// Server start time, for the shutdown summary
+    pub started: Instant,
+    /// Requests accepted since start
+    pub requests: AtomicU64,
+    /// Startup warmup has finished; `/health` reports 503 until then
+    pub ready: AtomicBool,
+}
+
+impl AppState {
+    /// Current index generation for cache keys
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// The currently served index
+    pub fn index(&self) -> Arc<IndexHandle> {
+        self.index.load_full()
+    }
+
+    /// Reload the shared reader and advance the generation to the new commit
+    ///
+    /// Additional, dropped-, new-domain and history indices with new commits
+    /// are reloaded too; their failures are logged so they don't block the
+    /// primary index.
+    pub fn reload(&self) -> tantivy::Result<u64> {
+        let handle = self.index.load();
+        let opstamp = handle.index.load_metas()?.opstamp;
+        handle.reload()?;
+
+        let previous = self.generation.swap(opstamp, Ordering::Relaxed);
+        if previous != opstamp {
+            info!(previous, current = opstamp, "Index generation changed");
+        }
+
+        self.reload_secondary();
+        Ok(opstamp)
+    }
+
+    /// Reload the additional, dropped-, new-domain and history indices that
+    /// have new commits
+    fn reload_secondary(&self) {
+        let dated = [&self.dropped, &self.new_domains]
+            .into_iter()
+            .flatten()
+            .map(|dated| &dated.index);
+        let history = self.history.iter().map(|history| &history.index);
+        for index in self.extra_indexes.iter().chain(dated).chain(history) {
+            if let Err(e) = index.reload() {
+                warn!(index = index.name, error = %e, "Failed to reload index reader");
+            }
+        }
+    }
+
+    /// Resolve `index=` to the indices to search
+    pub fn select_index(&self, name: Option<&str>) -> Result<IndexSelection, String> {
+        let name = name.unwrap_or(PRIMARY_INDEX);
+        let primary = || (PRIMARY_INDEX.to_string(), self.index());
+
+        let (indexes, generation) = match name {
+            PRIMARY_INDEX => (vec![primary()], self.generation()),
+            ALL_INDEXES => {
+                let mut indexes = vec![primary()];
+                let mut generation = self.generation();
+                for extra in &self.extra_indexes {
+                    indexes.push((extra.name.clone(), extra.handle.clone()));
+                    generation = generation.wrapping_add(extra.generation());
+                }
+                (indexes, generation)
+            }
+            _ => {
+                let extra = self
+                    .extra_indexes
+                    .iter()
+                    .find(|extra| extra.name == name)
+                    .ok_or_else(|| {
+                        let available: Vec<&str> = std::iter::once(PRIMARY_INDEX)
+                            .chain(self.extra_indexes.iter().map(|e| e.name.as_str()))
+                            .chain(std::iter::once(ALL_INDEXES))
+                            .collect();
+                        format!("Unknown index: {} (available: {})", name, available.join(", "))
+                    })?;
+                (vec![(extra.name.clone(), extra.handle.clone())], extra.generation())
+            }
+        };
+
+        Ok(IndexSelection {
+            name: name.to_string(),
+            indexes,
+            generation,
+        })
+    }
+
+    /// Atomically replace the served index, returning the previous one
+    pub fn swap_index(&self, handle: IndexHandle) -> tantivy::Result<Arc<IndexHandle>> {
+        let opstamp = handle.index.load_metas()?.opstamp;
+        let previous = self.index.swap(Arc::new(handle));
+        self.generation.store(opstamp, Ordering::Relaxed);
+        Ok(previous)
+    }
+
+    /// The cache to use for a request, unless the caller opted out
+    pub fn cache_for(&self, no_cache: bool) -> Option<&Cache> {
+        if no_cache {
+            None
+        } else {
+            self.cache.as_ref()
+        }
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State serving a synthetic index of `count` domains built in
+    /// `directory`, with caching, auth, analytics, watch rules and RDAP off
+    pub fn synthetic(directory: &std::path::Path, count: usize) -> Arc<Self> {
+        let taxonomy = domain_core::Taxonomy::default();
+        domain_core::synthetic::build_index(Some(directory), count, 42, &taxonomy).unwrap();
+        let schema = DomainSchema::new();
+        let index = IndexHandle::open(directory, &schema).unwrap();
+        Arc::new(AppState {
+            config: Config::test(),
+            schema,
+            index: ArcSwap::from_pointee(index),
+            extra_indexes: Vec::new(),
+            dropped: None,
+            new_domains: None,
+            history: None,
+            cache: None,
+            auth: None,
+            profiles: RankingProfiles::builtin(),
+            appraisal: AppraisalWeights::default(),
+            analytics: None,
+            watch: None,
+            rdap: None,
+            updater: None,
+            jobs: JobQueue::default(),
+            analyzer: QueryAnalyzer::default(),
+            splitter: None,
+            budget: Budget::unlimited(),
+            generation: AtomicU64::new(0),
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            ready: AtomicBool::new(true),
+        })
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info,tower_http=debug".to_string()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // Initialize logging and trace export; spans are flushed when dropped
+    let telemetry = domain_core::telemetry::init("domain-api", "info,tower_http=debug")?;
+    if telemetry.exporting() {
+        info!("Exporting traces over OTLP");
+    }
 
     let config = Config::from_env()?;
+    // Refuse a half-configured HTTPS setup before opening anything
+    let tls_files = config.tls_files()?;
 
     info!(index_path = ?config.index_path, "Opening index");
 
-    // Open Tantivy index
+    // Open Tantivy index with a shared, manually reloaded reader
     let schema = DomainSchema::new();
-    let index = Index::open_in_dir(&config.index_path)?;
+    let index = IndexHandle::open(&config.index_path, &schema)?;
+    let generation = index.index.load_metas()?.opstamp;
+    info!(
+        documents = index.reader.searcher().num_docs(),
+        generation,
+        "Index loaded"
+    );
+    if domain_core::writer_lock::is_locked(&config.index_path).unwrap_or(false) {
+        let holder = domain_core::writer_lock::holder(&config.index_path);
+        warn!(
+            holder = %holder.map_or("unknown".to_string(), |holder| holder.to_string()),
+            "Index is being written; reload once the indexer commits"
+        );
+    }
+
+    let mut extra_indexes = Vec::with_capacity(config.indexes.len());
+    for (name, path) in &config.indexes {
+        let extra = NamedIndex::open(name, path, &schema)?;
+        info!(
+            index = name,
+            path = ?path,
+            documents = extra.handle.reader.searcher().num_docs(),
+            "Additional index loaded"
+        );
+        extra_indexes.push(extra);
+    }
 
-    // Warm up the index reader
-    let reader = index.reader()?;
-    let searcher = reader.searcher();
-    info!(documents = searcher.num_docs(), "Index loaded");
+    let open_dated = |name: &str, path: &Option<PathBuf>, schema: DatedSchema| {
+        let Some(path) = path else {
+            return Ok::<_, tantivy::TantivyError>(None);
+        };
+        let dated = DatedIndex::open(name, path, schema)?;
+        info!(
+            index = name,
+            path = ?path,
+            documents = dated.index.handle.reader.searcher().num_docs(),
+            "Dated index loaded"
+        );
+        Ok(Some(dated))
+    };
+    let dropped = open_dated("dropped", &config.dropped_index_path, DatedSchema::dropped())?;
+    let new_domains =
+        open_dated("new", &config.new_domains_index_path, DatedSchema::registered())?;
+    let history = match &config.history_index_path {
+        Some(path) => {
+            let history = HistoryIndex::open(path)?;
+            info!(
+                path = ?path,
+                events = history.index.handle.reader.searcher().num_docs(),
+                "History index loaded"
+            );
+            Some(history)
+        }
+        None => None,
+    };
 
     // Initialize Redis cache (optional)
-    let cache = match &config.redis_url {
+    let redis_cache = match &config.redis_url {
         Some(url) => {
             info!(url = url, "Connecting to Redis");
-            match Cache::new(url).await {
+            match Cache::new(url, &config).await {
                 Ok(c) => {
                     info!("Redis cache enabled");
                     Some(c)
@@ -64,34 +333,420 @@ async fn main() -> Result<()> {
             }
         }
         None => {
-            info!("Running without cache (REDIS_URL not set)");
+            info!("Running without Redis cache (REDIS_URL not set)");
             None
         }
     };
 
+    // Layer the in-process cache in front of Redis (or use it alone)
+    let cache = if config.memory_cache_entries > 0 {
+        let ttl = Duration::from_secs(config.memory_cache_ttl);
+        info!(
+            entries = config.memory_cache_entries,
+            ttl_secs = config.memory_cache_ttl,
+            "In-memory cache enabled"
+        );
+        Some(match redis_cache {
+            Some(c) => c.with_memory(config.memory_cache_entries, ttl),
+            None => Cache::memory(config.memory_cache_entries, ttl),
+        })
+    } else {
+        redis_cache
+    }
+    .map(|c| c.with_prefix(&config.cache_key_prefix));
+
+    let auth = KeyStore::from_config(&config)?;
+    match &auth {
+        Some(keys) => info!(keys = keys.len(), "API key authentication enabled"),
+        None => warn!("No API keys configured, authentication disabled and /admin refused"),
+    }
+
+    let profiles = match &config.ranking_profiles_file {
+        Some(path) => RankingProfiles::load(path)?,
+        None => RankingProfiles::builtin(),
+    }
+    .with_default(&config.ranking_profile)?;
+    info!(
+        profiles = ?profiles.names().collect::<Vec<_>>(),
+        default = config.ranking_profile,
+        "Ranking profiles loaded"
+    );
+
+    let appraisal = match &config.appraisal_weights_file {
+        Some(path) => {
+            let weights = AppraisalWeights::load(path)?;
+            info!(path = %path.display(), "Appraisal weights loaded");
+            weights
+        }
+        None => AppraisalWeights::default(),
+    };
+
+    let analytics = (config.query_stats_max > 0).then(|| {
+        let mut redis_key = config.query_stats_redis_key.clone();
+        if redis_key.is_some() && !cache.as_ref().is_some_and(Cache::has_redis) {
+            warn!("QUERY_STATS_REDIS_KEY is set without Redis, keeping query stats in memory");
+            redis_key = None;
+        }
+        QueryAnalytics::new(config.query_stats_max, redis_key)
+    });
+
+    let watch = match (&config.redis_url, &config.watchlist_key) {
+        (Some(url), Some(key)) => {
+            match WatchStore::connect(url, key, config.watchlist_max_alerts).await {
+                Ok(store) => {
+                    info!(key, "Watchlist enabled");
+                    Some(store)
+                }
+                Err(e) => {
+                    warn!(error = %e, "Redis unavailable, running without watchlist");
+                    None
+                }
+            }
+        }
+        (None, Some(_)) => {
+            warn!("WATCHLIST_KEY is set without REDIS_URL, watchlist disabled");
+            None
+        }
+        _ => None,
+    };
+
+    let rdap = match &config.rdap_url {
+        Some(url) => {
+            let ttl = Duration::from_secs(config.rdap_cache_ttl);
+            let client =
+                RdapClient::new(url, config.rdap_rate_limit, config.rdap_cache_entries, ttl)?;
+            info!(
+                url,
+                rate_limit = config.rdap_rate_limit,
+                "RDAP enrichment enabled"
+            );
+            Some(client)
+        }
+        None => None,
+    };
+
+    let updater = if config.admin_writes {
+        info!("Index updates enabled at /admin/domains");
+        Some(IndexUpdater::from_config(&config)?)
+    } else {
+        None
+    };
+
+    let analyzer = match (&config.word_list_file, config.query_segmentation) {
+        (Some(path), true) => {
+            let segmenter = LocalSegmenter::load(path)?;
+            info!(words = segmenter.len(), "Query segmentation enabled");
+            QueryAnalyzer::with_segmenter(Arc::new(segmenter))
+        }
+        (None, true) => {
+            warn!("QUERY_SEGMENTATION is set without WORD_LIST_FILE, queries aren't segmented");
+            QueryAnalyzer::default()
+        }
+        _ => QueryAnalyzer::default(),
+    };
+    let splitter = if config.query_splitter {
+        info!(
+            url = config.word_splitter_url,
+            min_length = config.query_split_min_length,
+            "Single-keyword queries are split by the word splitter"
+        );
+        Some(QuerySplitter::from_config(&config)?)
+    } else {
+        None
+    };
+
     let state = Arc::new(AppState {
         config: config.clone(),
         schema,
-        index,
+        index: ArcSwap::from_pointee(index),
+        extra_indexes,
+        dropped,
+        new_domains,
+        history,
         cache,
+        auth,
+        profiles,
+        appraisal,
+        analytics,
+        watch,
+        rdap,
+        updater,
+        jobs: JobQueue::default(),
+        analyzer,
+        splitter,
+        budget: Budget::from_config(&config),
+        generation: AtomicU64::new(generation),
+        started: Instant::now(),
+        requests: AtomicU64::new(0),
+        ready: AtomicBool::new(false),
     });
 
+    let watcher = tokio::spawn(watch_generation(state.clone()));
+    let stats_flusher = tokio::spawn(flush_query_stats(state.clone()));
+    tokio::spawn(warmup::run(state.clone()));
+
     // Build router
-    let app = Router::new()
+    let mut router = Router::new()
+        .route("/openapi.json", get(openapi::spec))
         .route("/health", get(routes::health::health))
         .route("/stats", get(routes::health::stats))
-        .route("/exact", get(routes::exact::exact_lookup))
+        .route("/stats/queries", get(routes::health::query_stats))
+        .route("/stats/tlds", get(routes::health::tld_stats))
+        .route("/stats/categories", get(routes::health::category_stats))
+        .route(
+            "/exact",
+            get(routes::exact::exact_lookup).head(routes::exact::exact_exists),
+        )
+        .route(
+            "/exact/bulk",
+            post(routes::exact::bulk_exact).layer(DefaultBodyLimit::max(config.bulk_body_limit)),
+        )
         .route("/search", get(routes::search::search))
-        .route("/search/bulk", post(routes::search::bulk_search))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .route("/dropped", get(routes::dated::dropped))
+        .route("/new", get(routes::dated::new_domains))
+        .route("/new/feed", get(routes::dated::new_domains_feed))
+        .route("/history", get(routes::history::history))
+        .route("/lookalikes", get(routes::lookalikes::lookalikes))
+        .route("/appraise", get(routes::appraise::appraise))
+        .route("/available", get(routes::available::available))
+        .route("/coverage", post(routes::coverage::coverage))
+        .route("/alerts", get(routes::watch::alerts))
+        .route("/search/stream", get(routes::search::search_stream))
+        .route(
+            "/search/bulk",
+            post(routes::search::bulk_search).layer(DefaultBodyLimit::max(config.bulk_body_limit)),
+        )
+        .route(
+            "/portfolio/analyze",
+            post(routes::portfolio::analyze).layer(DefaultBodyLimit::max(config.bulk_body_limit)),
+        )
+        .route("/admin/cache/flush", post(routes::admin::flush_cache))
+        .route("/admin/reload", post(routes::admin::reload))
+        .route("/admin/index/swap", post(routes::admin::swap_index))
+        .route("/admin/jobs", get(routes::jobs::list_jobs))
+        .route("/admin/jobs/{id}", get(routes::jobs::get_job))
+        .route("/admin/jobs/swap", post(routes::jobs::swap_job))
+        .route("/admin/jobs/cache-warm", post(routes::jobs::cache_warm_job))
+        .route("/admin/jobs/cache-replay", post(routes::jobs::cache_replay_job))
+        .route("/admin/jobs/export", post(routes::jobs::export_job))
+        .route(
+            "/admin/domains",
+            post(routes::admin::add_domains).delete(routes::admin::remove_domains),
+        )
+        .route(
+            "/admin/watch",
+            get(routes::watch::list_rules).post(routes::watch::add_rule),
+        )
+        .route("/admin/watch/{id}", delete(routes::watch::remove_rule));
+
+    if config.openapi_ui {
+        router = router.route("/docs", get(openapi::docs));
+    }
 
-    let addr = format!("0.0.0.0:{}", config.api_port);
-    info!(address = addr, "Starting server");
+    let mut app = router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key,
+        ))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(error::handle_middleware_error))
+                .load_shed()
+                .concurrency_limit(config.max_concurrent_requests)
+                .timeout(Duration::from_millis(config.request_timeout_ms)),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), count_requests))
+        .layer(middleware::from_fn_with_state(state.clone(), access_log::record))
+        .layer(CorsLayer::permissive());
+    if config.compression {
+        app = app.layer(compression::layer(&config));
+    }
+    let app = app
+        .layer(TraceLayer::new_for_http().make_span_with(request_id::make_span))
+        .layer(middleware::from_fn(request_id::propagate))
+        .with_state(state.clone());
+
+    // Both servers drain on the same signal
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    let grpc = match config.grpc_port {
+        Some(port) => {
+            let addr = SocketAddr::new(config.api_bind.ip(), port);
+            info!(address = %addr, "Starting gRPC server");
+
+            let service = grpc::GrpcService::new(state.clone()).into_server();
+            let mut shutdown = shutdown_rx.clone();
+            Some(tokio::spawn(
+                tonic::transport::Server::builder()
+                    .timeout(Duration::from_millis(config.request_timeout_ms))
+                    .concurrency_limit_per_connection(config.max_concurrent_requests)
+                    .add_service(service)
+                    .serve_with_shutdown(addr, async move {
+                        let _ = shutdown.changed().await;
+                    }),
+            ))
+        }
+        None => None,
+    };
+
+    info!(address = %config.api_bind, https = tls_files.is_some(), "Starting server");
+
+    let shutdown = async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    };
+    match (&config.api_bind, tls_files) {
+        (BindAddress::Tcp(addr), Some((cert, key))) => {
+            tls::serve(*addr, cert, key, app, shutdown).await?
+        }
+        (BindAddress::Tcp(addr), None) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            axum::serve(listener, app).with_graceful_shutdown(shutdown).await?;
+        }
+        (BindAddress::Unix(path), _) => serve_unix(path, app, shutdown).await?,
+    }
+
+    if let Some(grpc) = grpc {
+        if let Err(e) = grpc.await? {
+            warn!(error = %e, "gRPC server error");
+        }
+    }
+
+    // In-flight requests have drained; stop background work
+    watcher.abort();
+    let _ = watcher.await;
+    stats_flusher.abort();
+    let _ = stats_flusher.await;
+    if let (Some(analytics), Some(cache)) = (&state.analytics, &state.cache) {
+        analytics.flush(cache).await;
+    }
+
+    info!(
+        uptime_secs = state.started.elapsed().as_secs(),
+        requests = state.requests.load(Ordering::Relaxed),
+        generation = state.generation(),
+        "Server stopped"
+    );
+
+    // Dropping the last state reference closes the Redis connection
+    drop(state);
+    drop(telemetry);
 
     Ok(())
 }
+
+/// Serve on a Unix socket, replacing the socket a previous run left behind
+/// and removing it on shutdown
+#[cfg(unix)]
+async fn serve_unix(
+    path: &Path,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            anyhow::bail!("API_BIND path exists and is not a socket: {}", path.display());
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let served = axum::serve(listener, app).with_graceful_shutdown(shutdown).await;
+    if let Err(e) = std::fs::remove_file(path) {
+        warn!(path = %path.display(), error = %e, "Failed to remove socket");
+    }
+    Ok(served?)
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(
+    path: &Path,
+    _app: Router,
+    _shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    anyhow::bail!("Unix sockets are not supported on this platform: {}", path.display())
+}
+
+/// Resolve on Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C, draining requests"),
+        _ = terminate => info!("Received SIGTERM, draining requests"),
+    }
+}
+
+/// Count accepted requests for the shutdown summary
+async fn count_requests(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+    request: axum::extract::Request,
+    next: middleware::Next,
+) -> axum::response::Response {
+    state.requests.fetch_add(1, Ordering::Relaxed);
+    next.run(request).await
+}
+
+/// Poll the index meta for new commits, reloading the shared reader
+/// so cache keys and search results roll over together after a sync
+async fn watch_generation(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(GENERATION_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let opstamp = match state.index.load().index.load_metas() {
+            Ok(metas) => metas.opstamp,
+            Err(e) => {
+                warn!(error = %e, "Failed to read index metadata");
+                continue;
+            }
+        };
+
+        if opstamp != state.generation() {
+            if let Err(e) = state.reload() {
+                warn!(error = %e, "Failed to reload index reader");
+            }
+        } else {
+            state.reload_secondary();
+        }
+    }
+}
+
+/// Periodically add recorded query counts to Redis
+async fn flush_query_stats(state: Arc<AppState>) {
+    let (Some(analytics), Some(cache)) = (&state.analytics, &state.cache) else {
+        return;
+    };
+    if !analytics.persisted() {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(analytics::FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        analytics.flush(cache).await;
+    }
+}
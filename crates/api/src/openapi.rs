@@ -0,0 +1,130 @@
+use crate::routes::{
+    admin, appraise, available, coverage, dated, exact, health, history, jobs, lookalikes,
+    portfolio, search, watch,
+};
+use axum::{response::Html, Json};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// OpenAPI document for the HTTP API
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Domain Search API",
+        description = "Keyword search over registered domain names"
+    ),
+    paths(
+        health::health,
+        health::stats,
+        health::query_stats,
+        health::tld_stats,
+        health::category_stats,
+        exact::exact_lookup,
+        exact::exact_exists,
+        exact::bulk_exact,
+        search::search,
+        dated::dropped,
+        dated::new_domains,
+        dated::new_domains_feed,
+        history::history,
+        lookalikes::lookalikes,
+        appraise::appraise,
+        available::available,
+        coverage::coverage,
+        search::search_stream,
+        search::bulk_search,
+        portfolio::analyze,
+        watch::alerts,
+        admin::flush_cache,
+        admin::reload,
+        admin::swap_index,
+        admin::add_domains,
+        admin::remove_domains,
+        jobs::list_jobs,
+        jobs::get_job,
+        jobs::swap_job,
+        jobs::cache_warm_job,
+        jobs::cache_replay_job,
+        jobs::export_job,
+        watch::list_rules,
+        watch::add_rule,
+        watch::remove_rule,
+    ),
+    components(schemas(
+        crate::error::ErrorResponse,
+        search::StreamProgress,
+        search::StreamResults,
+    )),
+    modifiers(&SecurityAddon),
+    security(("api_key" = []), ("bearer" = [])),
+    tags(
+        (name = "search", description = "Keyword and exact lookups"),
+        (name = "status", description = "Health and statistics"),
+        (name = "admin", description = "Cache and index management (admin keys only)"),
+        (name = "watch", description = "Watch rules (admin keys only) and their alerts"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the `X-API-Key` and bearer token schemes
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Serve the OpenAPI specification
+pub async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI page rendering `/openapi.json`
+pub async fn docs() -> Html<&'static str> {
+    Html(SWAGGER_UI)
+}
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>Domain Search API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_lists_all_routes() {
+        let spec = ApiDoc::openapi();
+        let paths: Vec<&str> = spec.paths.paths.keys().map(|p| p.as_str()).collect();
+
+        for path in ["/health", "/stats", "/exact", "/search", "/search/bulk", "/admin/index/swap"] {
+            assert!(paths.contains(&path), "missing {}", path);
+        }
+
+        let schemas = spec.components.unwrap().schemas;
+        assert!(schemas.contains_key("SearchResponse"));
+        assert!(schemas.contains_key("SearchResult"));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::error::ErrorResponse;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Request},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::Span;
+
+/// Header carrying the request ID, honored on requests and set on responses
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied ID kept; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Largest plain-text error body rewrapped as JSON
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the request being handled, outside of spawned tasks
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Assign every request an ID, reusing the client's `X-Request-Id` when valid
+///
+/// The ID is forwarded to inner layers in the request header (where
+/// [`make_span`] picks it up), echoed in the response header and added to
+/// JSON error bodies. Handler errors returned as plain text are wrapped in
+/// the same `{"error": ...}` body as middleware errors.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let header_value = HeaderValue::from_str(&id).expect("request IDs are visible ASCII");
+    request.headers_mut().insert(REQUEST_ID_HEADER, header_value.clone());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+
+    if is_plain_text_error(&response) {
+        let (parts, body) = response.into_parts();
+        let message = to_bytes(body, MAX_ERROR_BODY).await.unwrap_or_default();
+        let body = ErrorResponse {
+            error: String::from_utf8_lossy(&message).into_owned(),
+            request_id: Some(id),
+        };
+        let mut json = Json(body).into_response();
+        *json.status_mut() = parts.status;
+        for (name, value) in parts.headers.iter().filter(|(name, _)| {
+            **name != header::CONTENT_TYPE && **name != header::CONTENT_LENGTH
+        }) {
+            json.headers_mut().append(name, value.clone());
+        }
+        response = json;
+    }
+
+    response.headers_mut().insert(REQUEST_ID_HEADER, header_value);
+    response
+}
+
+/// Tracing span for a request, tagged with its ID and matched route
+pub fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        route,
+        request_id,
+    )
+}
+
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn is_plain_text_error(response: &Response) -> bool {
+    let status = response.status();
+    (status.is_client_error() || status.is_server_error())
+        && response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/plain"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route(
+                "/fail",
+                get(|| async { (StatusCode::BAD_REQUEST, "Query cannot be empty".to_string()) }),
+            )
+            .layer(middleware::from_fn(propagate))
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_honored_or_generated() {
+        let request = Request::get("/ok")
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc-123");
+
+        let request = Request::get("/ok")
+            .header(REQUEST_ID_HEADER, "not valid")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let generated = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert_eq!(generated.len(), 36);
+    }
+
+    #[tokio::test]
+    async fn test_handler_errors_carry_request_id() {
+        let request = Request::get("/fail")
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = to_bytes(response.into_body(), MAX_ERROR_BODY).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Query cannot be empty");
+        assert_eq!(body["request_id"], "abc-123");
+    }
+}
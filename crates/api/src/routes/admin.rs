@@ -0,0 +1,347 @@
+use crate::index::{IndexHandle, IndexSummary};
+use crate::updates::{UpdateError, UpdateOutcome};
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use domain_core::{writer_lock, Config};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct FlushResponse {
+    pub flushed: u64,
+    pub generation: u64,
+}
+
+/// Drop all cached search responses
+#[utoipa::path(
+    post,
+    path = "/admin/cache/flush",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cache flushed", body = FlushResponse),
+        (status = 404, description = "Cache is not enabled"),
+    )
+)]
+pub async fn flush_cache(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FlushResponse>, (StatusCode, String)> {
+    let cache = state.cache.as_ref().ok_or_else(|| {
+        (StatusCode::NOT_FOUND, "Cache is not enabled".to_string())
+    })?;
+
+    let flushed = cache.flush().await.map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Cache error: {}", e))
+    })?;
+
+    tracing::info!(flushed, "Cache flushed");
+
+    Ok(Json(FlushResponse {
+        flushed,
+        generation: state.generation(),
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReloadResponse {
+    pub generation: u64,
+    pub documents: u64,
+    pub segments: usize,
+}
+
+/// Reload the shared index reader to pick up the latest commit
+#[utoipa::path(
+    post,
+    path = "/admin/reload",
+    tag = "admin",
+    responses((status = 200, description = "Reader reloaded", body = ReloadResponse))
+)]
+pub async fn reload(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ReloadResponse>, (StatusCode, String)> {
+    let generation = state.reload().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Reload error: {}", e))
+    })?;
+
+    let searcher = state.index().reader.searcher();
+
+    Ok(Json(ReloadResponse {
+        generation,
+        documents: searcher.num_docs(),
+        segments: searcher.segment_readers().len(),
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SwapRequest {
+    /// Directory of the index to promote
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+
+    /// Refuse the swap if the new index has fewer documents
+    pub min_documents: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SwapResponse {
+    pub previous: IndexSummary,
+    pub current: IndexSummary,
+    pub cache_flushed: u64,
+}
+
+/// Promote a different index directory without restarting
+///
+/// The new index must use the server's schema, contain documents and not
+/// be in the middle of a write.
+/// In-flight requests finish on the old index; cached responses are flushed
+/// because opstamps are not comparable across indexes.
+#[utoipa::path(
+    post,
+    path = "/admin/index/swap",
+    tag = "admin",
+    request_body = SwapRequest,
+    responses(
+        (status = 200, description = "Index swapped", body = SwapResponse),
+        (status = 400, description = "Index outside INDEX_ROOT, missing, empty or mismatched"),
+        (status = 409, description = "An indexer is writing the index"),
+    )
+)]
+pub async fn swap_index(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SwapRequest>,
+) -> Result<Json<SwapResponse>, (StatusCode, String)> {
+    swap(&state, request).await.map(Json)
+}
+
+/// Resolve a swap path, refusing one outside `INDEX_ROOT` (by default the
+/// directory holding `INDEX_PATH`)
+fn under_index_root(config: &Config, path: &Path) -> Result<PathBuf, (StatusCode, String)> {
+    let root = match &config.index_root {
+        Some(root) => root.as_path(),
+        None => match config.index_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        },
+    };
+    let root = root.canonicalize().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Index root {}: {}", root.display(), e))
+    })?;
+    let path = path.canonicalize().map_err(|e| {
+        (StatusCode::BAD_REQUEST, format!("Index {}: {}", path.display(), e))
+    })?;
+    if path == root || !path.starts_with(&root) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Index must be under {}", root.display()),
+        ));
+    }
+    Ok(path)
+}
+
+/// Open, check and promote an index; shared with the swap job
+pub async fn swap(
+    state: &AppState,
+    request: SwapRequest,
+) -> Result<SwapResponse, (StatusCode, String)> {
+    let path = under_index_root(&state.config, &request.path)?;
+    let schema = state.schema.clone();
+    let lock_path = path.clone();
+
+    let handle = tokio::task::spawn_blocking(move || IndexHandle::open(&path, &schema))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))?
+        .map_err(|e| {
+            (StatusCode::BAD_REQUEST, format!("Failed to open index: {}", e))
+        })?;
+
+    handle
+        .validate_schema(&state.schema)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    if writer_lock::is_locked(&lock_path).unwrap_or(false) {
+        let holder = writer_lock::holder(&lock_path)
+            .map_or("an unknown process".to_string(), |holder| holder.to_string());
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Index is being written by {}", holder),
+        ));
+    }
+
+    let current = handle.summary().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Index error: {}", e))
+    })?;
+
+    let min_documents = request.min_documents.unwrap_or(1);
+    if current.documents < min_documents {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Index has {} documents, expected at least {}",
+                current.documents, min_documents
+            ),
+        ));
+    }
+
+    let previous = state.swap_index(handle).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Index error: {}", e))
+    })?;
+    let previous = previous.summary().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Index error: {}", e))
+    })?;
+
+    let cache_flushed = match &state.cache {
+        Some(cache) => cache.flush().await.unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to flush cache after index swap");
+            0
+        }),
+        None => 0,
+    };
+
+    tracing::info!(
+        previous = ?previous.path,
+        current = ?current.path,
+        documents = current.documents,
+        "Index swapped"
+    );
+
+    Ok(SwapResponse {
+        previous,
+        current,
+        cache_flushed,
+    })
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DomainsRequest {
+    /// Domains to add or delete
+    pub domains: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DomainsResponse {
+    /// Domains written, or deleted ones that were indexed
+    pub applied: u64,
+    /// Domains skipped by the indexer's filters (additions only)
+    pub filtered: u64,
+    /// Input that isn't a valid domain
+    pub invalid: Vec<String>,
+    /// Index generation serving the update
+    pub generation: u64,
+}
+
+/// Add domains to the served index, replacing any already indexed
+///
+/// Domains are segmented and tagged as the daily sync would, committed and
+/// searchable once the response returns.
+#[utoipa::path(
+    post,
+    path = "/admin/domains",
+    tag = "admin",
+    request_body = DomainsRequest,
+    responses(
+        (status = 200, description = "Domains added", body = DomainsResponse),
+        (status = 404, description = "Index updates are not enabled"),
+        (status = 409, description = "An indexer is writing the index"),
+        (status = 502, description = "Word segmentation failed"),
+    )
+)]
+pub async fn add_domains(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DomainsRequest>,
+) -> Result<Json<DomainsResponse>, (StatusCode, String)> {
+    let updater = updater(&state)?;
+    let outcome = updater.add(state.index(), &state.schema, &request.domains).await;
+    updated(&state, "added", outcome)
+}
+
+/// Delete domains from the served index
+#[utoipa::path(
+    delete,
+    path = "/admin/domains",
+    tag = "admin",
+    request_body = DomainsRequest,
+    responses(
+        (status = 200, description = "Domains deleted", body = DomainsResponse),
+        (status = 404, description = "Index updates are not enabled"),
+        (status = 409, description = "An indexer is writing the index"),
+    )
+)]
+pub async fn remove_domains(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<DomainsRequest>,
+) -> Result<Json<DomainsResponse>, (StatusCode, String)> {
+    let updater = updater(&state)?;
+    let outcome = updater.remove(state.index(), &state.schema, &request.domains).await;
+    updated(&state, "deleted", outcome)
+}
+
+fn updater(state: &AppState) -> Result<&crate::updates::IndexUpdater, (StatusCode, String)> {
+    state.updater.as_ref().ok_or_else(|| {
+        (StatusCode::NOT_FOUND, "Index updates are not enabled".to_string())
+    })
+}
+
+/// Reload the reader after an update and describe it
+fn updated(
+    state: &AppState,
+    action: &str,
+    outcome: Result<UpdateOutcome, UpdateError>,
+) -> Result<Json<DomainsResponse>, (StatusCode, String)> {
+    let outcome = outcome.map_err(|e| {
+        let status = match e {
+            UpdateError::Locked(_) => StatusCode::CONFLICT,
+            UpdateError::Segmentation(_) => StatusCode::BAD_GATEWAY,
+            UpdateError::Index(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, e.to_string())
+    })?;
+
+    let generation = state.reload().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Reload error: {}", e))
+    })?;
+
+    tracing::info!(
+        action,
+        applied = outcome.applied,
+        filtered = outcome.filtered,
+        invalid = outcome.invalid.len(),
+        generation,
+        "Domains updated"
+    );
+
+    Ok(Json(DomainsResponse {
+        applied: outcome.applied,
+        filtered: outcome.filtered,
+        invalid: outcome.invalid,
+        generation,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_paths_stay_under_index_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("index-new")).unwrap();
+        let mut config = Config::test();
+        config.index_root = Some(root.path().to_path_buf());
+
+        let inside = under_index_root(&config, &root.path().join("index-new")).unwrap();
+        assert_eq!(inside, root.path().canonicalize().unwrap().join("index-new"));
+
+        let refused = [
+            outside.path().to_path_buf(),
+            root.path().join("index-new/../.."),
+            root.path().to_path_buf(),
+            root.path().join("missing"),
+        ];
+        for path in refused {
+            let (status, _) = under_index_root(&config, &path).unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+    }
+}
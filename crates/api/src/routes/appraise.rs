@@ -0,0 +1,65 @@
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use domain_core::Domain;
+use domain_search::Appraisal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AppraiseQuery {
+    /// Full domain name (e.g., "coffeeshop.com"), registered or not
+    pub domain: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AppraiseResponse {
+    #[serde(flatten)]
+    pub appraisal: Appraisal,
+    pub query_time_ms: f64,
+}
+
+/// Rough value band of a domain from its length, words, TLD, hyphens and
+/// digits, and registrations of its label under other TLDs
+#[utoipa::path(
+    get,
+    path = "/appraise",
+    tag = "search",
+    params(AppraiseQuery),
+    responses(
+        (status = 200, description = "Value estimate", body = AppraiseResponse),
+        (status = 400, description = "Invalid domain"),
+    )
+)]
+pub async fn appraise(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AppraiseQuery>,
+) -> Result<Json<AppraiseResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    let normalized = Domain::new(&params.domain)
+        .normalize_with(state.config.label_validation)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid domain: {}", e)))?;
+
+    // Segmenting the label looks up every substring in the term dictionary
+    let search_state = state.clone();
+    let appraisal = tokio::task::spawn_blocking(move || {
+        search_state
+            .index()
+            .searcher
+            .appraise(&normalized, &search_state.appraisal)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))?
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e)))?;
+
+    Ok(Json(AppraiseResponse {
+        appraisal,
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
@@ -0,0 +1,356 @@
+use crate::budget::{Budget, Degraded, Upstream};
+use crate::routes::exact;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use domain_core::Domain;
+use futures::stream::{self, StreamExt};
+use rdap_client::RdapClient;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+
+/// Most domains checked by one `/available` request
+const MAX_DOMAINS: usize = 100;
+
+/// Longest a DNS confirmation waits for the resolver
+const DNS_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AvailableQuery {
+    /// Comma-separated domains (e.g., "coffee.com,coffee.io"), at most 100
+    pub domains: String,
+
+    /// Confirm domains missing from the index through RDAP (when `RDAP_URL`
+    /// is set) or DNS before calling them available
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AvailableResponse {
+    /// One entry per requested domain, in request order
+    pub domains: Vec<Availability>,
+    pub query_time_ms: f64,
+    /// Upstreams whose per-minute budget ran out, leaving domains
+    /// unconfirmed (`likely_available` from the index)
+    #[serde(skip_serializing_if = "Degraded::is_empty")]
+    #[schema(value_type = Vec<Upstream>)]
+    pub degraded: Degraded,
+}
+
+/// Availability of one requested domain
+#[derive(Serialize, ToSchema)]
+pub struct Availability {
+    /// The domain as requested, normalized when valid
+    pub domain: String,
+    pub status: AvailabilityStatus,
+    /// What answered; absent for invalid domains
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<AvailabilitySource>,
+    /// Why the domain couldn't be checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AvailabilityStatus {
+    /// Registered: in the zone files, known to RDAP or resolving in DNS
+    Taken,
+    /// Unknown to the registry's RDAP service
+    Available,
+    /// Not in the index (nor resolving, when confirmed through DNS); zone
+    /// files lag registrations by up to a day
+    LikelyAvailable,
+    Invalid,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AvailabilitySource {
+    Index,
+    Rdap,
+    Dns,
+}
+
+/// Check which domains are free to register
+///
+/// The index answers for registered domains. Domains it doesn't have are
+/// reported `likely_available`, or with `confirm=true` checked against RDAP,
+/// falling back to DNS when RDAP isn't configured, fails or is over budget.
+#[utoipa::path(
+    get,
+    path = "/available",
+    tag = "search",
+    params(AvailableQuery),
+    responses(
+        (status = 200, description = "Availability per domain", body = AvailableResponse),
+        (status = 400, description = "No domains, or too many"),
+    )
+)]
+pub async fn available(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AvailableQuery>,
+) -> Result<Json<AvailableResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    let requested: Vec<&str> = params
+        .domains
+        .split(',')
+        .map(str::trim)
+        .filter(|domain| !domain.is_empty())
+        .collect();
+    if requested.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "domains must not be empty".to_string()));
+    }
+    if requested.len() > MAX_DOMAINS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Maximum {} domains per request", MAX_DOMAINS),
+        ));
+    }
+
+    let normalized: Vec<Result<String, String>> = requested
+        .iter()
+        .map(|raw| {
+            Domain::new(*raw)
+                .normalize_with(state.config.label_validation)
+                .map(|d| d.domain_exact)
+                .map_err(|e| e.to_string())
+        })
+        .collect();
+
+    let valid: Vec<String> = normalized.iter().filter_map(|d| d.clone().ok()).collect();
+    let search_state = state.clone();
+    let indexed = tokio::task::spawn_blocking(move || indexed(&search_state, &valid))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))??;
+
+    let rdap = state.rdap.as_ref();
+    let checks: Vec<(Result<String, String>, String)> = normalized
+        .into_iter()
+        .zip(requested.iter().map(|raw| raw.to_string()))
+        .collect();
+    let checked: Vec<(Availability, Degraded)> = stream::iter(checks)
+        .map(|(domain, raw)| check(rdap, &state.budget, &indexed, domain, raw, params.confirm))
+        .buffered(state.config.bulk_concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut degraded = Degraded::new();
+    let domains = checked
+        .into_iter()
+        .map(|(availability, missed)| {
+            degraded.extend(missed);
+            availability
+        })
+        .collect();
+    Ok(Json(AvailableResponse {
+        domains,
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        degraded,
+    }))
+}
+
+/// Availability of one requested domain, given the indexed ones, with the
+/// upstreams whose budget ran out before it was confirmed
+async fn check(
+    rdap: Option<&RdapClient>,
+    budget: &Budget,
+    indexed: &HashSet<String>,
+    domain: Result<String, String>,
+    raw: String,
+    confirm_missing: bool,
+) -> (Availability, Degraded) {
+    let mut degraded = Degraded::new();
+    let domain = match domain {
+        Ok(domain) => domain,
+        Err(error) => {
+            let availability = Availability {
+                domain: raw,
+                status: AvailabilityStatus::Invalid,
+                source: None,
+                error: Some(error),
+            };
+            return (availability, degraded);
+        }
+    };
+    let (status, source) = if indexed.contains(&domain) {
+        (AvailabilityStatus::Taken, AvailabilitySource::Index)
+    } else if confirm_missing {
+        confirm(rdap, budget, &domain, &mut degraded).await
+    } else {
+        (AvailabilityStatus::LikelyAvailable, AvailabilitySource::Index)
+    };
+    let availability = Availability {
+        domain,
+        status,
+        source: Some(source),
+        error: None,
+    };
+    (availability, degraded)
+}
+
+/// The domains found in the index, counted without reading their documents
+fn indexed(state: &AppState, domains: &[String]) -> Result<HashSet<String>, (StatusCode, String)> {
+    let mut found = HashSet::new();
+    for domain in domains {
+        if exact::is_indexed(state, domain)? {
+            found.insert(domain.clone());
+        }
+    }
+    Ok(found)
+}
+
+/// Confirm a domain missing from the index through RDAP, or DNS when RDAP
+/// is not configured, the lookup fails or its budget is spent
+///
+/// With the DNS budget spent too, the domain stays `likely_available` from
+/// the index.
+async fn confirm(
+    rdap: Option<&RdapClient>,
+    budget: &Budget,
+    domain: &str,
+    degraded: &mut Degraded,
+) -> (AvailabilityStatus, AvailabilitySource) {
+    // RDAP answers from its cache without calling the registry
+    let rdap =
+        rdap.filter(|rdap| rdap.is_cached(domain) || budget.try_take(Upstream::Rdap, degraded));
+    if let Some(rdap) = rdap {
+        match rdap.lookup(domain).await {
+            Ok(Some(_)) => return (AvailabilityStatus::Taken, AvailabilitySource::Rdap),
+            Ok(None) => return (AvailabilityStatus::Available, AvailabilitySource::Rdap),
+            Err(e) => tracing::warn!(domain, error = %e, "RDAP lookup failed, trying DNS"),
+        }
+    }
+
+    if !budget.try_take(Upstream::Dns, degraded) {
+        return (AvailabilityStatus::LikelyAvailable, AvailabilitySource::Index);
+    }
+    // Any address means the name is delegated; a failure or timeout is no
+    // proof either way, since registered domains needn't resolve
+    let lookup = tokio::net::lookup_host((domain, 0));
+    let resolves = match tokio::time::timeout(DNS_TIMEOUT, lookup).await {
+        Ok(Ok(mut addresses)) => addresses.next().is_some(),
+        _ => false,
+    };
+    if resolves {
+        (AvailabilityStatus::Taken, AvailabilitySource::Dns)
+    } else {
+        (AvailabilityStatus::LikelyAvailable, AvailabilitySource::Dns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_confirm_through_rdap() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/domain/coffee.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/domain/unregistered-coffee.com"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let rdap = RdapClient::new(server.uri(), 100, 10, Duration::from_secs(60)).unwrap();
+        let budget = Budget::unlimited();
+        let mut degraded = Degraded::new();
+
+        assert_eq!(
+            confirm(Some(&rdap), &budget, "coffee.com", &mut degraded).await,
+            (AvailabilityStatus::Taken, AvailabilitySource::Rdap)
+        );
+        assert_eq!(
+            confirm(Some(&rdap), &budget, "unregistered-coffee.com", &mut degraded).await,
+            (AvailabilityStatus::Available, AvailabilitySource::Rdap)
+        );
+        assert!(degraded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_falls_back_to_dns() {
+        // .invalid never resolves (RFC 6761)
+        let mut degraded = Degraded::new();
+        assert_eq!(
+            confirm(None, &Budget::unlimited(), "coffee.invalid", &mut degraded).await,
+            (AvailabilityStatus::LikelyAvailable, AvailabilitySource::Dns)
+        );
+        assert!(degraded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_within_budget() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/domain/coffee.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let rdap = RdapClient::new(server.uri(), 100, 10, Duration::from_secs(60)).unwrap();
+        let budget = Budget::new(0, 1, 1);
+        let mut degraded = Degraded::new();
+
+        // Cached lookups don't count against the budget
+        for _ in 0..2 {
+            assert_eq!(
+                confirm(Some(&rdap), &budget, "coffee.com", &mut degraded).await,
+                (AvailabilityStatus::Taken, AvailabilitySource::Rdap)
+            );
+        }
+        assert!(degraded.is_empty());
+
+        // Over the RDAP budget DNS confirms, then over the DNS budget nothing does
+        assert_eq!(
+            confirm(Some(&rdap), &budget, "coffee.invalid", &mut degraded).await,
+            (AvailabilityStatus::LikelyAvailable, AvailabilitySource::Dns)
+        );
+        assert_eq!(
+            confirm(Some(&rdap), &budget, "tea.invalid", &mut degraded).await,
+            (AvailabilityStatus::LikelyAvailable, AvailabilitySource::Index)
+        );
+        assert_eq!(degraded.into_iter().collect::<Vec<_>>(), vec![Upstream::Rdap, Upstream::Dns]);
+    }
+
+    #[tokio::test]
+    async fn test_available_synthetic_index() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+        let indexed = domain_core::synthetic::SyntheticDomains::new(1, 42)
+            .next()
+            .unwrap()
+            .domain_exact;
+
+        let uri = format!("/available?domains={},not-generated.example,nodot", indexed)
+            .parse()
+            .unwrap();
+        let Json(response) = available(State(state), Query::try_from_uri(&uri).unwrap())
+            .await
+            .unwrap();
+        let statuses: Vec<AvailabilityStatus> =
+            response.domains.iter().map(|d| d.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                AvailabilityStatus::Taken,
+                AvailabilityStatus::LikelyAvailable,
+                AvailabilityStatus::Invalid
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use domain_core::Domain;
+use domain_search::extract_domain_result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tantivy::collector::TopDocs;
+use tantivy::query::BooleanQuery;
+use tantivy::Term;
+use utoipa::ToSchema;
+
+/// Most keywords in one request
+const MAX_COVERAGE_KEYWORDS: usize = 100;
+
+/// Most TLDs in one request
+const MAX_COVERAGE_TLDS: usize = 50;
+
+/// Domains looked up by one term query
+const LOOKUP_BATCH: usize = 1024;
+
+#[derive(Deserialize, ToSchema)]
+pub struct CoverageRequest {
+    /// Labels to check (e.g. `["coffee", "best coffee"]`); spaces are
+    /// dropped, so `best coffee` checks `bestcoffee`
+    pub keywords: Vec<String>,
+    /// TLDs to check each keyword under (e.g. `["com", "io"]`)
+    pub tlds: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CoverageResponse {
+    /// Row labels, normalized, in request order
+    pub keywords: Vec<String>,
+    /// Column TLDs, normalized, in request order
+    pub tlds: Vec<String>,
+    /// `registered[k][t]`: `keywords[k].tlds[t]` is in the index
+    pub registered: Vec<Vec<bool>>,
+    /// Registered combinations
+    pub registered_count: usize,
+    pub query_time_ms: f64,
+}
+
+/// Which keyword.tld combinations are registered
+#[utoipa::path(
+    post,
+    path = "/coverage",
+    tag = "search",
+    request_body = CoverageRequest,
+    responses(
+        (status = 200, description = "Registration matrix", body = CoverageResponse),
+        (status = 400, description = "Invalid keyword or TLD, or too many of either"),
+    )
+)]
+pub async fn coverage(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CoverageRequest>,
+) -> Result<Json<CoverageResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    for (name, count, max) in [
+        ("keywords", request.keywords.len(), MAX_COVERAGE_KEYWORDS),
+        ("tlds", request.tlds.len(), MAX_COVERAGE_TLDS),
+    ] {
+        if count == 0 || count > max {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("{} must list 1 to {} entries", name, max),
+            ));
+        }
+    }
+
+    // Normalize every combination up front so one bad keyword or TLD
+    // refuses the request instead of leaving holes in the matrix
+    let mut keywords = Vec::with_capacity(request.keywords.len());
+    let mut tlds = Vec::with_capacity(request.tlds.len());
+    let mut domains = Vec::with_capacity(request.keywords.len() * request.tlds.len());
+    for keyword in &request.keywords {
+        let label: String = keyword.split_whitespace().collect();
+        for (t, tld) in request.tlds.iter().enumerate() {
+            let tld = tld.trim().trim_start_matches('.');
+            let normalized = Domain::new(format!("{}.{}", label, tld))
+                .normalize_with(state.config.label_validation)
+                .map_err(|e| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid combination '{}' + '{}': {}", keyword, tld, e),
+                    )
+                })?;
+            if t == 0 {
+                keywords.push(normalized.label.clone());
+            }
+            if keywords.len() == 1 {
+                tlds.push(normalized.tld.clone());
+            }
+            domains.push(normalized.domain_exact);
+        }
+    }
+
+    let search_state = state.clone();
+    let lookup = domains.clone();
+    let found = tokio::task::spawn_blocking(move || registered(&search_state, &lookup))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))??;
+
+    let registered: Vec<Vec<bool>> = domains
+        .chunks(tlds.len())
+        .map(|row| row.iter().map(|domain| found.contains(domain)).collect())
+        .collect();
+    let registered_count = registered.iter().flatten().filter(|&&taken| taken).count();
+
+    Ok(Json(CoverageResponse {
+        keywords,
+        tlds,
+        registered,
+        registered_count,
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
+
+/// The domains among `domains` that are indexed, looked up in batches of
+/// term queries on the exact domain
+fn registered(
+    state: &AppState,
+    domains: &[String],
+) -> Result<HashSet<String>, (StatusCode, String)> {
+    let index = state.index();
+    let searcher = index.reader.searcher();
+    let unique: Vec<&String> = {
+        let mut seen = HashSet::new();
+        domains.iter().filter(|d| seen.insert(*d)).collect()
+    };
+
+    let mut found = HashSet::new();
+    for batch in unique.chunks(LOOKUP_BATCH) {
+        let terms: Vec<Term> = batch
+            .iter()
+            .map(|domain| Term::from_field_text(state.schema.domain_exact, domain))
+            .collect();
+        let query = BooleanQuery::new_multiterms_query(terms);
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(batch.len()))
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e)))?;
+        for (_score, address) in top_docs {
+            let doc = searcher
+                .doc(address)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e)))?;
+            found.insert(extract_domain_result(&state.schema, &doc).domain);
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_search::SearchRequest;
+
+    #[tokio::test]
+    async fn test_coverage_matrix() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+        let taken = {
+            let index = state.index();
+            let results = index.searcher.search(&SearchRequest::new("coffee", 1)).unwrap();
+            results.results[0].domain.clone()
+        };
+
+        let request = CoverageRequest {
+            keywords: vec![taken.label.clone(), "zzqx unregistered".to_string()],
+            tlds: vec![format!(".{}", taken.tld), "zz".to_string()],
+        };
+        let Json(response) = coverage(State(state.clone()), Json(request)).await.unwrap();
+        assert_eq!(response.keywords, vec![taken.label.clone(), "zzqxunregistered".to_string()]);
+        assert_eq!(response.tlds, vec![taken.tld.clone(), "zz".to_string()]);
+        assert_eq!(response.registered, vec![vec![true, false], vec![false, false]]);
+        assert_eq!(response.registered_count, 1);
+
+        let request = CoverageRequest {
+            keywords: vec!["coffee".to_string()],
+            tlds: vec![],
+        };
+        let (status, _) = coverage(State(state), Json(request)).await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
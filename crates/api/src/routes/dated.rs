@@ -0,0 +1,539 @@
+use super::search::{analyze_query, apply_max_dga_score, check_limit};
+use crate::budget::{Degraded, Upstream};
+use crate::enrich::{self, Enrichment};
+use crate::index::DatedIndex;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use domain_search::{SearchRequest, SearchResult};
+use serde::{Deserialize, Serialize};
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tantivy::collector::TopDocs;
+use tantivy::query::{RangeQuery, TermQuery};
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::time::{Date, Month};
+use tantivy::{DateTime, TantivyDocument, Term};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DatedQuery {
+    /// Search keywords (space-separated)
+    pub q: String,
+
+    /// How far back to look: days (`7d`), hours (`24h`) or a date
+    /// (`2024-05-01`). Default: `7d` for `/dropped`, `24h` for `/new`
+    pub since: Option<String>,
+
+    /// Filter by TLD (e.g., "com", or "com,net" for several)
+    pub tld: Option<String>,
+
+    /// Maximum results to return
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+
+    /// Minimum number of keywords that must match
+    pub min_match: Option<u32>,
+
+    /// Drop domains whose label looks more random than this, from 0
+    /// (meaningful) to 1 (algorithmically generated)
+    pub max_dga_score: Option<f64>,
+
+    /// Add RDAP registration data (registrar, creation date, status) for the
+    /// top `RDAP_ENRICH_LIMIT` domains under `enrichment`
+    #[serde(default)]
+    pub enrich: bool,
+}
+
+fn default_limit() -> u32 {
+    50
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct DroppedResponse {
+    pub results: Vec<DroppedDomain>,
+    /// Dropped domains matching the query and filters
+    pub total_candidates: usize,
+    /// Start of the window searched (RFC 3339)
+    pub since: String,
+    pub query_time_ms: f64,
+    /// Registration data by domain, with `enrich=true`
+    #[serde(default, skip_serializing_if = "Enrichment::is_empty")]
+    pub enrichment: Enrichment,
+    /// Upstreams whose per-minute budget ran out, leaving enrichment
+    /// incomplete
+    #[serde(default, skip_serializing_if = "Degraded::is_empty")]
+    #[schema(value_type = Vec<Upstream>)]
+    pub degraded: Degraded,
+}
+
+/// A domain removed from its zone
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct DroppedDomain {
+    #[serde(flatten)]
+    pub result: SearchResult,
+    /// Day the domain was removed (`YYYY-MM-DD`)
+    pub dropped_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct NewDomainsResponse {
+    pub results: Vec<NewDomain>,
+    /// New domains matching the query and filters
+    pub total_candidates: usize,
+    /// Start of the window searched (RFC 3339)
+    pub since: String,
+    pub query_time_ms: f64,
+    /// Registration data by domain, with `enrich=true`
+    #[serde(default, skip_serializing_if = "Enrichment::is_empty")]
+    pub enrichment: Enrichment,
+    /// Upstreams whose per-minute budget ran out, leaving enrichment
+    /// incomplete
+    #[serde(default, skip_serializing_if = "Degraded::is_empty")]
+    #[schema(value_type = Vec<Upstream>)]
+    pub degraded: Degraded,
+}
+
+/// A domain first seen in a daily update
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct NewDomain {
+    #[serde(flatten)]
+    pub result: SearchResult,
+    /// Day the domain first appeared (`YYYY-MM-DD`)
+    pub first_seen: String,
+}
+
+/// The dated indices written by the daily sync
+#[derive(Clone, Copy)]
+enum Dated {
+    Dropped,
+    New,
+}
+
+impl Dated {
+    fn index(self, state: &AppState) -> Option<&DatedIndex> {
+        match self {
+            Dated::Dropped => state.dropped.as_ref(),
+            Dated::New => state.new_domains.as_ref(),
+        }
+    }
+
+    /// The setting that enables the index
+    fn setting(self) -> &'static str {
+        match self {
+            Dated::Dropped => "DROPPED_INDEX_PATH",
+            Dated::New => "NEW_DOMAINS_INDEX_PATH",
+        }
+    }
+
+    fn default_since(self) -> &'static str {
+        match self {
+            Dated::Dropped => "7d",
+            Dated::New => "24h",
+        }
+    }
+}
+
+/// Matches from a dated index with their dates
+struct DatedResults {
+    results: Vec<(SearchResult, DateTime)>,
+    total_candidates: usize,
+    since: DateTime,
+}
+
+/// Keyword search over recently dropped domains
+#[utoipa::path(
+    get,
+    path = "/dropped",
+    tag = "search",
+    params(DatedQuery),
+    responses(
+        (status = 200, description = "Ranked dropped domains", body = DroppedResponse),
+        (status = 400, description = "Empty query or invalid `since`"),
+        (status = 404, description = "No dropped-domains index is configured"),
+    )
+)]
+pub async fn dropped(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DatedQuery>,
+) -> Result<Json<DroppedResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+    let rdap = enrich::client(&state, params.enrich)?;
+    let found = search_dated(&state, &params, Dated::Dropped).await?;
+
+    let domains = found.results.iter().map(|(r, _)| r.domain.domain.as_str());
+    let mut degraded = Degraded::new();
+    let enrichment = enrich::lookup(&state, rdap, domains, &mut degraded).await;
+    Ok(Json(DroppedResponse {
+        results: found
+            .results
+            .into_iter()
+            .map(|(result, date)| DroppedDomain {
+                result,
+                dropped_at: format_date(date),
+            })
+            .collect(),
+        total_candidates: found.total_candidates,
+        since: format_timestamp(found.since),
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        enrichment,
+        degraded,
+    }))
+}
+
+/// Keyword search over newly registered domains
+#[utoipa::path(
+    get,
+    path = "/new",
+    tag = "search",
+    params(DatedQuery),
+    responses(
+        (status = 200, description = "Ranked new domains", body = NewDomainsResponse),
+        (status = 400, description = "Empty query or invalid `since`"),
+        (status = 404, description = "No new-domains index is configured"),
+    )
+)]
+pub async fn new_domains(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<DatedQuery>,
+) -> Result<Json<NewDomainsResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+    let rdap = enrich::client(&state, params.enrich)?;
+    let found = search_dated(&state, &params, Dated::New).await?;
+
+    let domains = found.results.iter().map(|(r, _)| r.domain.domain.as_str());
+    let mut degraded = Degraded::new();
+    let enrichment = enrich::lookup(&state, rdap, domains, &mut degraded).await;
+    Ok(Json(NewDomainsResponse {
+        results: found
+            .results
+            .into_iter()
+            .map(|(result, date)| NewDomain {
+                result,
+                first_seen: format_date(date),
+            })
+            .collect(),
+        total_candidates: found.total_candidates,
+        since: format_timestamp(found.since),
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        enrichment,
+        degraded,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedQuery {
+    /// Search keywords (space-separated), e.g. a brand name
+    pub q: String,
+
+    /// How far back to look (default: `24h`)
+    pub since: Option<String>,
+
+    /// Filter by TLD (e.g., "com", or "com,net" for several)
+    pub tld: Option<String>,
+
+    /// Maximum items
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+
+    /// `rss` (RSS 2.0) or `json` (JSON Feed 1.1)
+    #[serde(default)]
+    pub format: FeedFormat,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Rss,
+    Json,
+}
+
+/// Feed of newly registered domains matching a query, newest first
+#[utoipa::path(
+    get,
+    path = "/new/feed",
+    tag = "search",
+    params(FeedQuery),
+    responses(
+        (status = 200, description = "RSS 2.0 or JSON Feed 1.1 document", body = String),
+        (status = 400, description = "Empty query or invalid `since`"),
+        (status = 404, description = "No new-domains index is configured"),
+    )
+)]
+pub async fn new_domains_feed(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<FeedQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let query = DatedQuery {
+        q: params.q,
+        since: params.since,
+        tld: params.tld,
+        limit: params.limit,
+        min_match: None,
+        max_dga_score: None,
+        enrich: false,
+    };
+    let mut found = search_dated(&state, &query, Dated::New).await?;
+    found.results.sort_by_key(|(_, date)| std::cmp::Reverse(*date));
+
+    let response = match params.format {
+        FeedFormat::Rss => (
+            [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+            rss_feed(&query.q, &found),
+        )
+            .into_response(),
+        FeedFormat::Json => (
+            [(header::CONTENT_TYPE, "application/feed+json")],
+            json_feed(&query.q, &found).to_string(),
+        )
+            .into_response(),
+    };
+    Ok(response)
+}
+
+/// Search a dated index for domains dated at or after `since`
+async fn search_dated(
+    state: &Arc<AppState>,
+    params: &DatedQuery,
+    which: Dated,
+) -> Result<DatedResults, (StatusCode, String)> {
+    let Some(dated) = which.index(state) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("This endpoint is disabled ({} unset)", which.setting()),
+        ));
+    };
+    let since = params.since.as_deref().unwrap_or(which.default_since());
+    let since = parse_since(since, now_secs()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let (_, profile) = state
+        .profiles
+        .get(None)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let query = analyze_query(&state.config, &state.analyzer, &params.q)?;
+    let limit = check_limit(&state.config, params.limit)?;
+    let mut request = SearchRequest::new(query, limit);
+    request.tld = params.tld.clone();
+    request.min_match = params.min_match.map(|m| m as usize);
+    request.profile = profile.clone();
+    apply_max_dga_score(&mut request.profile, params.max_dga_score)?;
+    request.stop_tokens = state.config.stop_tokens.clone();
+    request.filter = Some(Arc::new(RangeQuery::new_date_bounds(
+        dated.schema.date_field_name().to_string(),
+        Bound::Included(since),
+        Bound::Unbounded,
+    )));
+
+    let search_state = state.clone();
+    let (results, total_candidates) = tokio::task::spawn_blocking(move || {
+        let dated = which.index(&search_state).expect("checked above");
+        search(dated, &request)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))?
+    .map_err(|e| {
+        let status = if e.is_client_error() {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        (status, e.to_string())
+    })?;
+
+    Ok(DatedResults {
+        results,
+        total_candidates,
+        since,
+    })
+}
+
+/// Run a search on a dated index and read back each result's date
+fn search(
+    dated: &DatedIndex,
+    request: &SearchRequest,
+) -> domain_search::Result<(Vec<(SearchResult, DateTime)>, usize)> {
+    let handle = &dated.index.handle;
+    let results = handle.searcher.search(request)?;
+
+    // Dates aren't part of the search results; read them back per domain
+    let searcher = handle.reader.searcher();
+    let mut dated_results = Vec::with_capacity(results.results.len());
+    for result in results.results {
+        let term = Term::from_field_text(dated.schema.domain.domain_exact, &result.domain.domain);
+        let query = TermQuery::new(term, IndexRecordOption::Basic);
+        let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.pop() else {
+            continue;
+        };
+        let doc: TantivyDocument = searcher.doc(address)?;
+        let date = doc
+            .get_first(dated.schema.date)
+            .and_then(|value| value.as_datetime())
+            .unwrap_or(DateTime::MIN);
+        dated_results.push((result, date));
+    }
+
+    Ok((dated_results, results.total_candidates))
+}
+
+fn rss_feed(query: &str, found: &DatedResults) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n");
+    xml.push_str("<channel>\n");
+    xml.push_str(&format!("<title>New domains matching {}</title>\n", escape_xml(query)));
+    xml.push_str(&format!("<link>/new?q={}</link>\n", escape_xml(&query.replace(' ', "+"))));
+    xml.push_str(&format!(
+        "<description>Domains first seen since {}</description>\n",
+        format_timestamp(found.since)
+    ));
+    for (result, date) in &found.results {
+        let domain = escape_xml(&result.domain.domain);
+        xml.push_str("<item>\n");
+        xml.push_str(&format!("<title>{}</title>\n", domain));
+        xml.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}/{}</guid>\n",
+            domain,
+            format_date(*date)
+        ));
+        xml.push_str(&format!("<pubDate>{}</pubDate>\n", format_rfc2822(*date)));
+        xml.push_str(&format!(
+            "<description>Keywords: {}</description>\n",
+            escape_xml(&result.domain.tokens.join(" "))
+        ));
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn json_feed(query: &str, found: &DatedResults) -> serde_json::Value {
+    let items: Vec<serde_json::Value> = found
+        .results
+        .iter()
+        .map(|(result, date)| {
+            serde_json::json!({
+                "id": format!("{}/{}", result.domain.domain, format_date(*date)),
+                "title": result.domain.domain,
+                "content_text": format!("Keywords: {}", result.domain.tokens.join(" ")),
+                "date_published": format_timestamp(*date),
+                "tags": result.domain.tokens,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": format!("New domains matching {}", query),
+        "description": format!("Domains first seen since {}", format_timestamp(found.since)),
+        "items": items,
+    })
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub(crate) fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Parse `7d`, `24h` or `2024-05-01` into the start of the window
+pub(crate) fn parse_since(since: &str, now: i64) -> Result<DateTime, String> {
+    let invalid = || format!("Invalid since: {} (expected e.g. 7d, 24h or 2024-05-01)", since);
+    let since = since.trim();
+
+    let relative = |suffix: char, unit: i64| -> Option<Result<DateTime, String>> {
+        let count = since.strip_suffix(suffix)?;
+        Some(
+            count
+                .parse::<i64>()
+                .ok()
+                .filter(|&count| count >= 0)
+                .and_then(|count| count.checked_mul(unit))
+                .map(|seconds| DateTime::from_timestamp_secs(now - seconds))
+                .ok_or_else(invalid),
+        )
+    };
+    if let Some(parsed) = relative('d', 86_400).or_else(|| relative('h', 3_600)) {
+        return parsed;
+    }
+
+    let mut parts = since.splitn(3, '-').map(|part| part.parse::<u16>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day))) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    let month = Month::try_from(month as u8).map_err(|_| invalid())?;
+    let date = Date::from_calendar_date(year as i32, month, day as u8).map_err(|_| invalid())?;
+    Ok(DateTime::from_utc(date.midnight().assume_utc()))
+}
+
+pub(crate) fn format_date(date: DateTime) -> String {
+    date.into_utc().date().to_string()
+}
+
+pub(crate) fn format_timestamp(date: DateTime) -> String {
+    let utc = date.into_utc();
+    format!("{}T{:02}:{:02}:{:02}Z", utc.date(), utc.hour(), utc.minute(), utc.second())
+}
+
+/// RSS dates, e.g. `Sun, 02 Jun 2024 00:00:00 +0000`
+fn format_rfc2822(date: DateTime) -> String {
+    let utc = date.into_utc();
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        &utc.weekday().to_string()[..3],
+        utc.day(),
+        &utc.month().to_string()[..3],
+        utc.year(),
+        utc.hour(),
+        utc.minute(),
+        utc.second()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now = 10 * 86_400 + 3_600;
+
+        let week = parse_since("7d", now).unwrap();
+        assert_eq!(week.into_timestamp_secs(), 3 * 86_400 + 3_600);
+
+        let day = parse_since("24h", now).unwrap();
+        assert_eq!(day.into_timestamp_secs(), 9 * 86_400 + 3_600);
+
+        let date = parse_since("1970-01-03", now).unwrap();
+        assert_eq!(format_date(date), "1970-01-03");
+        assert_eq!(date.into_timestamp_secs(), 2 * 86_400);
+
+        for invalid in ["", "7w", "-1d", "2024-13-01", "yesterday"] {
+            assert!(parse_since(invalid, now).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_feed_dates_and_escaping() {
+        let date = parse_since("2024-06-02", 0).unwrap();
+        assert_eq!(format_rfc2822(date), "Sun, 02 Jun 2024 00:00:00 +0000");
+        assert_eq!(format_timestamp(date), "2024-06-02T00:00:00Z");
+        assert_eq!(escape_xml("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+    }
+}
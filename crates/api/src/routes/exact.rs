@@ -1,58 +1,424 @@
+use crate::budget::{Degraded, Upstream};
+use crate::cache::Cache;
+use crate::enrich::{self, Enrichment};
+use crate::routes::search::parse_fields;
 use crate::AppState;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
     Json,
 };
-use domain_core::Domain;
+use domain_core::domain::{should_filter_domain, TldFilter};
+use domain_core::{Domain, NormalizedDomain};
+use domain_search::{extract_domain_result, DomainResult};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tantivy::collector::TopDocs;
-use tantivy::query::TermQuery;
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::{BooleanQuery, TermQuery};
 use tantivy::schema::IndexRecordOption;
 use tantivy::Term;
 
-#[derive(Deserialize)]
+/// Shortest part kept on either side of an inserted hyphen
+const MIN_HYPHEN_PART: usize = 2;
+
+/// Most domains in one bulk lookup
+const MAX_BULK_DOMAINS: usize = 1000;
+
+/// Domains looked up by one term query
+const LOOKUP_BATCH: usize = 1024;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ExactQuery {
+    /// Full domain name (e.g., "example.com")
     pub domain: String,
+
+    /// Bypass the cache for this request
+    #[serde(default)]
+    pub no_cache: bool,
+
+    /// Also return the www, hyphen and plural counterparts found in the index
+    #[serde(default)]
+    pub include_variants: bool,
+
+    /// Add RDAP registration data (registrar, creation date, status) for the
+    /// domain and its variants under `enrichment`
+    #[serde(default)]
+    pub enrich: bool,
+
+    /// Fields of the domain and its variants to return, comma-separated
+    /// (e.g. `domain,tokens`; default: all)
+    pub fields: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct ExactResponse {
     pub found: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<DomainResult>,
     pub query_time_ms: f64,
+    pub cached: bool,
+    /// Registered near variants, with `include_variants=true`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<DomainVariant>,
+    /// Registration data by domain, with `enrich=true`
+    #[serde(default, skip_serializing_if = "Enrichment::is_empty")]
+    pub enrichment: Enrichment,
+    /// Upstreams whose per-minute budget ran out, leaving enrichment
+    /// incomplete
+    #[serde(default, skip_serializing_if = "Degraded::is_empty")]
+    #[schema(value_type = Vec<Upstream>)]
+    pub degraded: Degraded,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct DomainResult {
-    pub domain: String,
-    pub label: String,
-    pub tld: String,
-    pub length: u64,
-    pub has_hyphen: bool,
-    pub tokens: Vec<String>,
+/// A registered counterpart of the looked-up domain
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct DomainVariant {
+    pub kind: VariantKind,
+    pub domain: DomainResult,
+}
+
+/// How a variant differs from the looked-up domain
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VariantKind {
+    /// `www` prefixed to or stripped from the label
+    Www,
+    /// Hyphens removed
+    Hyphenless,
+    /// Hyphens inserted
+    Hyphenated,
+    Plural,
+    Singular,
+}
+
+impl VariantKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VariantKind::Www => "www",
+            VariantKind::Hyphenless => "hyphenless",
+            VariantKind::Hyphenated => "hyphenated",
+            VariantKind::Plural => "plural",
+            VariantKind::Singular => "singular",
+        }
+    }
 }
 
 /// Exact domain lookup
+#[utoipa::path(
+    get,
+    path = "/exact",
+    tag = "search",
+    params(ExactQuery),
+    responses(
+        (status = 200, description = "Lookup result", body = ExactResponse),
+        (status = 400, description = "Invalid domain"),
+    )
+)]
 pub async fn exact_lookup(
     State(state): State<Arc<AppState>>,
     Query(params): Query<ExactQuery>,
 ) -> Result<Json<ExactResponse>, (StatusCode, String)> {
+    let rdap = enrich::client(&state, params.enrich)?;
+    let fields = parse_fields(params.fields.as_deref())?;
+    let mut response = cached_exact(&state, &params).await?;
+    // Cached responses keep every field
+    let found = response.domain.iter_mut();
+    for domain in found.chain(response.variants.iter_mut().map(|v| &mut v.domain)) {
+        domain.fields = fields;
+    }
+
+    // The domain is looked up even when it isn't indexed: RDAP may know
+    // registrations the zone files don't have yet
+    let normalized = Domain::new(&params.domain)
+        .normalize_with(state.config.label_validation)
+        .ok();
+    let domains = normalized
+        .iter()
+        .map(|d| d.domain_exact.as_str())
+        .chain(response.variants.iter().map(|v| v.domain.domain.as_str()));
+    response.enrichment = enrich::lookup(&state, rdap, domains, &mut response.degraded).await;
+    Ok(Json(response))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExistsQuery {
+    /// Full domain name (e.g., "example.com")
+    pub domain: String,
+}
+
+/// Whether a domain is indexed, without a body
+///
+/// Counts the domain's term instead of reading its document and skips the
+/// cache, for availability checkers needing only the answer.
+#[utoipa::path(
+    head,
+    path = "/exact",
+    tag = "search",
+    params(ExistsQuery),
+    responses(
+        (status = 204, description = "Domain is indexed"),
+        (status = 404, description = "Domain is not indexed"),
+        (status = 400, description = "Invalid domain"),
+    )
+)]
+pub async fn exact_exists(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ExistsQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let normalized = Domain::new(&params.domain)
+        .normalize_with(state.config.label_validation)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid domain: {}", e)))?;
+    if is_indexed(&state, &normalized.domain_exact)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BulkExactRequest {
+    /// Domains to look up, as given (e.g. `["example.com", "Coffee.IO."]`)
+    pub domains: Vec<String>,
+
+    /// Fields of the found domains to return, comma-separated
+    /// (e.g. `domain,tokens`; default: all)
+    #[serde(default)]
+    pub fields: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkExactResponse {
+    /// One result per requested domain, in request order
+    pub results: Vec<BulkExactResult>,
+    pub found_count: usize,
+    pub invalid_count: usize,
+    pub filtered_count: usize,
+    pub not_found_count: usize,
+    pub query_time_ms: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkExactResult {
+    /// The domain as given
+    pub input: String,
+    pub found: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<DomainResult>,
+    /// Why the domain isn't in the index, when it isn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub miss: Option<MissReason>,
+    /// The normalization error of an `invalid` miss
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Why a bulk lookup found nothing
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MissReason {
+    /// Not a valid domain name
+    Invalid,
+    /// A valid domain the indexer leaves out: its TLD isn't indexed
+    /// (`INCLUDE_TLDS`, `EXCLUDE_TLDS`) or its label is filtered as noise
+    Filtered,
+    /// A valid, indexable domain missing from the zone files
+    NotFound,
+}
+
+/// Exact lookup of many domains, telling bad input from unregistered
+///
+/// Domains are normalized as the indexer normalizes them and looked up
+/// together in batched term queries, skipping the cache. A miss is
+/// `invalid`, `filtered` or `not_found`; only `not_found` suggests the
+/// domain is unregistered.
+#[utoipa::path(
+    post,
+    path = "/exact/bulk",
+    tag = "search",
+    request_body = BulkExactRequest,
+    responses(
+        (status = 200, description = "One result per domain, in request order", body = BulkExactResponse),
+        (status = 400, description = "Too many domains, or invalid fields"),
+        (status = 413, description = "Request body too large"),
+    )
+)]
+pub async fn bulk_exact(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BulkExactRequest>,
+) -> Result<Json<BulkExactResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    if request.domains.len() > MAX_BULK_DOMAINS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Maximum {} domains per bulk request", MAX_BULK_DOMAINS),
+        ));
+    }
+    let fields = parse_fields(request.fields.as_deref())?;
+
+    let normalized: Vec<Result<NormalizedDomain, String>> = request
+        .domains
+        .iter()
+        .map(|raw| {
+            Domain::new(raw)
+                .normalize_with(state.config.label_validation)
+                .map_err(|e| e.to_string())
+        })
+        .collect();
+    let lookup: Vec<String> = normalized
+        .iter()
+        .flatten()
+        .map(|domain| domain.domain_exact.clone())
+        .collect();
+    let search_state = state.clone();
+    let found = tokio::task::spawn_blocking(move || lookup_domains(&search_state, &lookup))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))??;
+
+    let tlds = TldFilter::from_config(&state.config);
+    let results: Vec<BulkExactResult> = request
+        .domains
+        .into_iter()
+        .zip(normalized)
+        .map(|(input, normalized)| {
+            let (domain, miss, error) = match normalized {
+                Err(e) => (None, Some(MissReason::Invalid), Some(e)),
+                Ok(normalized) => match found.get(&normalized.domain_exact) {
+                    Some(domain) => {
+                        let mut domain = domain.clone();
+                        domain.fields = fields;
+                        (Some(domain), None, None)
+                    }
+                    None if !tlds.allows(&normalized.tld)
+                        || should_filter_domain(&normalized.label) =>
+                    {
+                        (None, Some(MissReason::Filtered), None)
+                    }
+                    None => (None, Some(MissReason::NotFound), None),
+                },
+            };
+            BulkExactResult {
+                input,
+                found: domain.is_some(),
+                domain,
+                miss,
+                error,
+            }
+        })
+        .collect();
+
+    let count = |reason: MissReason| results.iter().filter(|r| r.miss == Some(reason)).count();
+    Ok(Json(BulkExactResponse {
+        found_count: results.iter().filter(|r| r.found).count(),
+        invalid_count: count(MissReason::Invalid),
+        filtered_count: count(MissReason::Filtered),
+        not_found_count: count(MissReason::NotFound),
+        results,
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
+
+/// The indexed documents among normalized `domains`, by domain, looked up
+/// in batches of term queries
+fn lookup_domains(
+    state: &AppState,
+    domains: &[String],
+) -> Result<HashMap<String, DomainResult>, (StatusCode, String)> {
+    let index = state.index();
+    let searcher = index.reader.searcher();
+
+    let mut found = HashMap::new();
+    for batch in domains.chunks(LOOKUP_BATCH) {
+        let terms: Vec<Term> = batch
+            .iter()
+            .map(|domain| Term::from_field_text(state.schema.domain_exact, domain))
+            .collect();
+        let query = BooleanQuery::new_multiterms_query(terms);
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(batch.len()))
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e)))?;
+        for (_score, address) in top_docs {
+            let doc = searcher
+                .doc(address)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e)))?;
+            let result = extract_domain_result(&state.schema, &doc);
+            found.insert(result.domain.clone(), result);
+        }
+    }
+    Ok(found)
+}
+
+/// Whether a normalized domain is in the primary index, counted from its
+/// postings without reading the document
+pub(crate) fn is_indexed(state: &AppState, domain: &str) -> Result<bool, (StatusCode, String)> {
+    let index = state.index();
+    let searcher = index.reader.searcher();
+    let term = Term::from_field_text(state.schema.domain_exact, domain);
+    let count = searcher
+        .search(&TermQuery::new(term, IndexRecordOption::Basic), &Count)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e)))?;
+    Ok(count > 0)
+}
+
+/// Exact lookup through the cache, shared by the HTTP and gRPC front ends
+pub(crate) async fn cached_exact(
+    state: &AppState,
+    params: &ExactQuery,
+) -> Result<ExactResponse, (StatusCode, String)> {
     let start = std::time::Instant::now();
 
     // Normalize the input domain
     let domain = Domain::new(&params.domain);
-    let normalized = domain.normalize().map_err(|e| {
-        (StatusCode::BAD_REQUEST, format!("Invalid domain: {}", e))
-    })?;
+    let normalized = domain
+        .normalize_with(state.config.label_validation)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid domain: {}", e)))?;
+
+    let cache = state.cache_for(params.no_cache);
+    let cache_key = Cache::make_exact_key(
+        state.generation(),
+        &normalized.domain_exact,
+        params.include_variants,
+    );
+
+    // Check cache first
+    if let Some(cache) = cache {
+        if let Ok(Some(cached)) = cache.get::<ExactResponse>(&cache_key).await {
+            tracing::debug!(key = %cache_key, "Cache hit");
+            let mut response = cached;
+            response.cached = true;
+            response.query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+            return Ok(response);
+        }
+    }
+
+    tracing::debug!(key = %cache_key, cacheable = cache.is_some(), "Cache miss");
+    let response = execute_exact(state, &normalized, params.include_variants)?;
+
+    // Store in cache
+    if let Some(cache) = cache {
+        let ttl = Duration::from_secs(state.config.cache_ttl_exact);
+        let _ = cache.set(&cache_key, &response, ttl).await;
+    }
+
+    Ok(response)
+}
+
+/// Look up a normalized domain in the index
+fn execute_exact(
+    state: &AppState,
+    normalized: &NormalizedDomain,
+    include_variants: bool,
+) -> Result<ExactResponse, (StatusCode, String)> {
+    let start = std::time::Instant::now();
 
     // Search for exact match
-    let reader = state.index.reader().map_err(|e| {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Index error: {}", e))
-    })?;
-    let searcher = reader.searcher();
+    let index = state.index();
+    let searcher = index.reader.searcher();
 
     let term = Term::from_field_text(state.schema.domain_exact, &normalized.domain_exact);
     let query = TermQuery::new(term, IndexRecordOption::Basic);
@@ -63,84 +429,294 @@ pub async fn exact_lookup(
             (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e))
         })?;
 
-    let query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let domain = match top_docs.first() {
+        Some((_score, doc_address)) => {
+            let doc = searcher.doc(*doc_address).map_err(|e| {
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e))
+            })?;
+
+            Some(extract_domain_result(&state.schema, &doc))
+        }
+        None => None,
+    };
+
+    let variants = if include_variants {
+        // The indexed segmentation places hyphens between words
+        let tokens = domain.as_ref().map(|d| d.tokens.as_slice()).unwrap_or_default();
+        find_variants(state, &searcher, near_variants(normalized, tokens))?
+    } else {
+        Vec::new()
+    };
+
+    Ok(ExactResponse {
+        found: domain.is_some(),
+        domain,
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+        cached: false,
+        variants,
+        enrichment: Enrichment::new(),
+        degraded: Degraded::new(),
+    })
+}
+
+/// Look up every candidate variant in one query, keeping the candidates' order
+fn find_variants(
+    state: &AppState,
+    searcher: &tantivy::Searcher,
+    candidates: Vec<(VariantKind, String)>,
+) -> Result<Vec<DomainVariant>, (StatusCode, String)> {
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let terms: Vec<Term> = candidates
+        .iter()
+        .map(|(_, domain)| Term::from_field_text(state.schema.domain_exact, domain))
+        .collect();
+    let query = BooleanQuery::new_multiterms_query(terms);
 
-    if let Some((_score, doc_address)) = top_docs.first() {
-        let doc = searcher.doc(*doc_address).map_err(|e| {
+    let top_docs = searcher
+        .search(&query, &TopDocs::with_limit(candidates.len()))
+        .map_err(|e| {
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e))
+        })?;
+
+    let mut found = Vec::with_capacity(top_docs.len());
+    for (_score, doc_address) in top_docs {
+        let doc = searcher.doc(doc_address).map_err(|e| {
             (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e))
         })?;
+        found.push(extract_domain_result(&state.schema, &doc));
+    }
 
-        let result = extract_domain_result(&state.schema, &doc);
+    Ok(candidates
+        .into_iter()
+        .filter_map(|(kind, domain)| {
+            let position = found.iter().position(|d| d.domain == domain)?;
+            Some(DomainVariant {
+                kind,
+                domain: found.swap_remove(position),
+            })
+        })
+        .collect())
+}
+
+/// Counterparts of a domain worth checking alongside it
+///
+/// `tokens` is the indexed segmentation of the label, if known; without it
+/// hyphens are tried between every pair of characters.
+fn near_variants(domain: &NormalizedDomain, tokens: &[String]) -> Vec<(VariantKind, String)> {
+    let label = domain.label.as_str();
+    let mut labels: Vec<(VariantKind, String)> = Vec::new();
+
+    match label.strip_prefix("www") {
+        Some(rest) if !rest.is_empty() => {
+            labels.push((VariantKind::Www, rest.trim_start_matches('-').to_string()))
+        }
+        _ => labels.push((VariantKind::Www, format!("www{}", label))),
+    }
 
-        Ok(Json(ExactResponse {
-            found: true,
-            domain: Some(result),
-            query_time_ms,
-        }))
+    if domain.has_hyphen {
+        labels.push((VariantKind::Hyphenless, label.replace('-', "")));
+    } else if tokens.len() > 1 {
+        labels.push((VariantKind::Hyphenated, tokens.join("-")));
     } else {
-        Ok(Json(ExactResponse {
-            found: false,
-            domain: None,
-            query_time_ms,
-        }))
-    }
-}
-
-/// Extract domain result from a Tantivy document
-pub fn extract_domain_result(
-    schema: &domain_core::DomainSchema,
-    doc: &tantivy::TantivyDocument,
-) -> DomainResult {
-    use tantivy::schema::Value;
-
-    let domain = doc
-        .get_first(schema.domain_exact)
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-
-    let label = doc
-        .get_first(schema.label)
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
-
-    // Extract TLD from domain string (facet not stored)
-    let tld = domain
-        .rsplit('.')
-        .next()
-        .unwrap_or("")
-        .to_string();
-
-    let length = doc
-        .get_first(schema.len)
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-
-    let has_hyphen = doc
-        .get_first(schema.has_hyphen)
-        .and_then(|v| v.as_u64())
-        .map(|v| v == 1)
-        .unwrap_or(false);
-
-    // Extract tokens
-    let tokens_str = doc
-        .get_first(schema.tokens)
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-
-    let tokens: Vec<String> = if tokens_str.is_empty() {
-        vec![]
+        let bounds: Vec<usize> = label.char_indices().map(|(at, _)| at).collect();
+        let last = bounds.len().saturating_sub(MIN_HYPHEN_PART);
+        for &at in bounds.iter().take(last + 1).skip(MIN_HYPHEN_PART) {
+            let (left, right) = label.split_at(at);
+            labels.push((VariantKind::Hyphenated, format!("{}-{}", left, right)));
+        }
+    }
+
+    if let Some(singular) = singular(label) {
+        labels.push((VariantKind::Singular, singular));
     } else {
-        tokens_str.split_whitespace().map(String::from).collect()
-    };
+        labels.push((VariantKind::Plural, plural(label)));
+    }
 
-    DomainResult {
-        domain,
-        label,
-        tld,
-        length,
-        has_hyphen,
-        tokens,
+    let mut variants: Vec<(VariantKind, String)> = Vec::new();
+    for (kind, variant) in labels {
+        if variant.is_empty() || variant == label {
+            continue;
+        }
+        let variant = format!("{}.{}", variant, domain.tld);
+        if !variants.iter().any(|(_, v)| *v == variant) {
+            variants.push((kind, variant));
+        }
+    }
+    variants
+}
+
+/// Singular of an English plural, `None` when the word doesn't look plural
+fn singular(word: &str) -> Option<String> {
+    if let Some(stem) = word.strip_suffix("ies") {
+        return Some(format!("{}y", stem));
+    }
+    for suffix in ["ses", "xes", "ches", "shes"] {
+        if word.ends_with(suffix) {
+            return Some(word[..word.len() - 2].to_string());
+        }
+    }
+    if word.ends_with('s') && !word.ends_with("ss") && word.len() > 3 {
+        return Some(word[..word.len() - 1].to_string());
+    }
+    None
+}
+
+/// English plural of a word
+fn plural(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix('y') {
+        if !stem.ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{}ies", stem);
+        }
+    }
+    if ["s", "x", "ch", "sh"].iter().any(|suffix| word.ends_with(suffix)) {
+        return format!("{}es", word);
+    }
+    format!("{}s", word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variants(domain: &str, tokens: &[&str]) -> Vec<(VariantKind, String)> {
+        let domain = Domain::new(domain).normalize().unwrap();
+        let tokens: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+        near_variants(&domain, &tokens)
+    }
+
+    #[test]
+    fn test_near_variants() {
+        assert_eq!(
+            variants("petshop.com", &["pet", "shop"]),
+            vec![
+                (VariantKind::Www, "wwwpetshop.com".to_string()),
+                (VariantKind::Hyphenated, "pet-shop.com".to_string()),
+                (VariantKind::Plural, "petshops.com".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            variants("www-pet-supplies.io", &[]),
+            vec![
+                (VariantKind::Www, "pet-supplies.io".to_string()),
+                (VariantKind::Hyphenless, "wwwpetsupplies.io".to_string()),
+                (VariantKind::Singular, "www-pet-supply.io".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hyphens_without_tokens() {
+        let hyphenated: Vec<String> = variants("pets.com", &[])
+            .into_iter()
+            .filter(|(kind, _)| *kind == VariantKind::Hyphenated)
+            .map(|(_, domain)| domain)
+            .collect();
+        assert_eq!(hyphenated, vec!["pe-ts.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_exact_lookup_synthetic_index() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+        let indexed = domain_core::synthetic::SyntheticDomains::new(1, 42)
+            .next()
+            .unwrap()
+            .domain_exact;
+
+        let uri = format!("/exact?domain={}", indexed.to_uppercase()).parse().unwrap();
+        let Json(found) = exact_lookup(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+            .await
+            .unwrap();
+        assert!(found.found);
+        assert_eq!(found.domain.unwrap().domain, indexed);
+
+        let uri = "/exact?domain=not-generated.example".parse().unwrap();
+        let Json(missing) = exact_lookup(State(state), Query::try_from_uri(&uri).unwrap())
+            .await
+            .unwrap();
+        assert!(!missing.found);
+    }
+
+    #[tokio::test]
+    async fn test_exact_exists() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+        let indexed = domain_core::synthetic::SyntheticDomains::new(1, 42)
+            .next()
+            .unwrap()
+            .domain_exact;
+
+        let exists = |domain: &str| {
+            let uri = format!("/exact?domain={}", domain).parse().unwrap();
+            exact_exists(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+        };
+        assert_eq!(exists(&indexed.to_uppercase()).await.unwrap(), StatusCode::NO_CONTENT);
+        assert_eq!(exists("not-generated.example").await.unwrap(), StatusCode::NOT_FOUND);
+        assert_eq!(exists("-").await.unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_exact_categorizes_misses() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+        let indexed = domain_core::synthetic::SyntheticDomains::new(1, 42)
+            .next()
+            .unwrap()
+            .domain_exact;
+
+        let request = BulkExactRequest {
+            domains: vec![
+                indexed.to_uppercase(),
+                "my_domain.com".to_string(),
+                "com.".to_string(),
+                "123456.com".to_string(),
+                "not-generated.example".to_string(),
+            ],
+            fields: Some("domain".to_string()),
+        };
+        let Json(response) = bulk_exact(State(state.clone()), Json(request)).await.unwrap();
+        let misses: Vec<_> = response.results.iter().map(|r| r.miss).collect();
+        assert_eq!(
+            misses,
+            vec![
+                None,
+                Some(MissReason::Invalid),
+                Some(MissReason::Invalid),
+                Some(MissReason::Filtered),
+                Some(MissReason::NotFound),
+            ]
+        );
+        assert_eq!(response.results[0].domain.as_ref().unwrap().domain, indexed);
+        assert!(response.results[1].error.is_some());
+        assert_eq!(response.results[4].input, "not-generated.example");
+        assert_eq!((response.found_count, response.invalid_count), (1, 2));
+        assert_eq!((response.filtered_count, response.not_found_count), (1, 1));
+
+        let request = BulkExactRequest {
+            domains: vec!["example.com".to_string(); MAX_BULK_DOMAINS + 1],
+            fields: None,
+        };
+        let err = bulk_exact(State(state), Json(request)).await.err().unwrap();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_exists_follows_label_validation() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut state = AppState::synthetic(directory.path(), 10);
+        let uri = "/exact?domain=coffee_shop.com".parse().unwrap();
+
+        let query = Query::try_from_uri(&uri).unwrap();
+        let (status, _) = exact_exists(State(state.clone()), query).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        Arc::get_mut(&mut state).unwrap().config.label_validation =
+            domain_core::LabelValidation::Lenient;
+        let query = Query::try_from_uri(&uri).unwrap();
+        assert_eq!(exact_exists(State(state), query).await.unwrap(), StatusCode::NOT_FOUND);
     }
 }
@@ -1,70 +1,167 @@
+use super::dated::{format_date, now_secs, parse_since};
+use crate::analytics::QueryStatsResponse;
+use crate::auth::KeyUsageStats;
+use crate::breaker::BreakerState;
+use crate::cache::MemoryCacheStats;
+use crate::index::IndexHandle;
 use crate::AppState;
-use axum::{extract::State, Json};
-use serde::Serialize;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use domain_core::{MergePressure, TldHistory};
+use domain_search::CategoryCounts;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Serialize)]
+/// Most queries listed by `/stats/queries`
+const MAX_QUERY_STATS_LIMIT: usize = 1000;
+
+#[derive(Serialize, ToSchema)]
 pub struct HealthResponse {
+    /// `ok`, `warming` while startup warmup queries run, or `degraded` when
+    /// the index directory can't be read
     pub status: &'static str,
+    /// Documents and segments of the open reader, which keeps serving
+    /// searches while the directory is unreadable
     pub index_documents: u64,
     pub index_segments: usize,
     pub cache_enabled: bool,
+    /// Why the status is `degraded`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct StatsResponse {
     pub index: IndexStats,
     pub cache: Option<CacheStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Vec<KeyUsageStats>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct IndexStats {
     pub documents: u64,
     pub segments: usize,
+    /// Deleted documents not yet merged away
+    pub deleted_documents: u64,
+    /// Whether deleted documents are enough of the index for `optimize`
+    /// to speed up searches
+    pub optimize_recommended: bool,
+    /// Size on disk as of the last reload
     pub size_bytes: u64,
+    /// Document counts of each segment
+    pub segment_details: Vec<SegmentStats>,
+    /// Why the index directory couldn't be read; documents and segments
+    /// are still those of the open reader
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SegmentStats {
+    pub id: String,
+    pub documents: u32,
+    pub deleted_documents: u32,
+    /// Share of the segment's documents that are deleted
+    pub deleted_ratio: f64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct CacheStats {
     pub connected: bool,
+    pub redis: bool,
     pub hits: u64,
     pub misses: u64,
+    pub memory: Option<MemoryCacheStats>,
+    /// Redis circuit breaker: `open` while Redis is skipped after failing,
+    /// `half_open` while a probe call decides whether it's back
+    pub breaker: BreakerState,
+    /// Times the breaker has opened since startup
+    pub breaker_opened: u64,
 }
 
 /// Health check endpoint
-pub async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
-    let reader = state.index.reader().expect("Failed to get reader");
-    let searcher = reader.searcher();
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "status",
+    responses(
+        (status = 200, description = "Index and cache status", body = HealthResponse),
+        (
+            status = 503,
+            description = "Startup warmup is still running, or the index is unreadable",
+            body = HealthResponse
+        ),
+    )
+)]
+pub async fn health(State(state): State<Arc<AppState>>) -> (StatusCode, Json<HealthResponse>) {
+    let index = state.index();
+    let searcher = index.reader.searcher();
+    let error = check_index(&index).err();
 
-    Json(HealthResponse {
-        status: "ok",
+    let (status_code, status) = if error.is_some() {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    } else if state.ready.load(Ordering::Relaxed) {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "warming")
+    };
+
+    let response = HealthResponse {
+        status,
         index_documents: searcher.num_docs(),
         index_segments: searcher.segment_readers().len(),
         cache_enabled: state.cache.is_some(),
-    })
+        error,
+    };
+    (status_code, Json(response))
+}
+
+/// Read the index's metadata from disk, which fails once the directory is
+/// gone, unreadable or corrupt; the open reader would keep reporting the
+/// last state it loaded
+fn check_index(index: &IndexHandle) -> Result<(), String> {
+    index
+        .index
+        .load_metas()
+        .map(|_| ())
+        .map_err(|e| format!("Index at {} is unreadable: {}", index.path.display(), e))
 }
 
 /// Detailed statistics endpoint
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "status",
+    responses((status = 200, description = "Index, cache and key usage statistics", body = StatsResponse))
+)]
 pub async fn stats(State(state): State<Arc<AppState>>) -> Json<StatsResponse> {
-    let reader = state.index.reader().expect("Failed to get reader");
-    let searcher = reader.searcher();
-
-    // Calculate index size
-    let mut size_bytes: u64 = 0;
-    if let Ok(entries) = std::fs::read_dir(&state.config.index_path) {
-        for entry in entries.flatten() {
-            if let Ok(meta) = entry.metadata() {
-                if meta.is_file() {
-                    size_bytes += meta.len();
-                }
-            }
-        }
-    }
+    let index = state.index();
+    let searcher = index.reader.searcher();
 
+    let pressure = MergePressure::from_searcher(&searcher);
     let index_stats = IndexStats {
         documents: searcher.num_docs(),
-        segments: searcher.segment_readers().len(),
-        size_bytes,
+        segments: pressure.segments.len(),
+        deleted_documents: pressure.deleted_documents(),
+        optimize_recommended: pressure.optimize_recommended(),
+        size_bytes: index.size_bytes(),
+        segment_details: pressure
+            .segments
+            .iter()
+            .map(|segment| SegmentStats {
+                deleted_ratio: segment.deleted_ratio(),
+                id: segment.id.clone(),
+                documents: segment.documents,
+                deleted_documents: segment.deleted_documents,
+            })
+            .collect(),
+        error: check_index(&index).err(),
     };
 
     let cache_stats = if let Some(cache) = &state.cache {
@@ -73,8 +170,12 @@ pub async fn stats(State(state): State<Arc<AppState>>) -> Json<StatsResponse> {
 
         Some(CacheStats {
             connected,
+            redis: cache.has_redis(),
             hits: stats.as_ref().map(|s| s.hits).unwrap_or(0),
             misses: stats.as_ref().map(|s| s.misses).unwrap_or(0),
+            memory: stats.and_then(|s| s.memory),
+            breaker: cache.breaker().state(),
+            breaker_opened: cache.breaker().opened(),
         })
     } else {
         None
@@ -83,5 +184,226 @@ pub async fn stats(State(state): State<Arc<AppState>>) -> Json<StatsResponse> {
     Json(StatsResponse {
         index: index_stats,
         cache: cache_stats,
+        keys: state.auth.as_ref().map(|a| a.usage()),
     })
 }
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryStatsQuery {
+    /// Queries per list (default: 20, max: 1000)
+    #[serde(default = "default_query_stats_limit")]
+    pub limit: usize,
+}
+
+fn default_query_stats_limit() -> usize {
+    20
+}
+
+/// Search analytics: most frequent and zero-result queries
+#[utoipa::path(
+    get,
+    path = "/stats/queries",
+    tag = "status",
+    params(QueryStatsQuery),
+    responses(
+        (status = 200, description = "Query frequency and latency", body = QueryStatsResponse),
+        (status = 404, description = "Query analytics are disabled"),
+    )
+)]
+pub async fn query_stats(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<QueryStatsQuery>,
+) -> Result<Json<QueryStatsResponse>, (StatusCode, String)> {
+    let analytics = state.analytics.as_ref().ok_or((
+        StatusCode::NOT_FOUND,
+        "Query analytics are disabled (QUERY_STATS_MAX=0)".to_string(),
+    ))?;
+
+    let limit = params.limit.min(MAX_QUERY_STATS_LIMIT);
+    Ok(Json(analytics.report(state.cache.as_ref(), limit).await))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TldStatsQuery {
+    /// Start of the window: days (`90d`) or a date (`2024-05-01`). Default: `90d`
+    pub since: Option<String>,
+
+    /// Only these TLDs, with their daily counts (e.g., "com", or "com,net")
+    pub tld: Option<String>,
+
+    /// TLDs to return, largest first (default: 50, 0 for all)
+    #[serde(default = "default_tld_stats_limit")]
+    pub limit: usize,
+}
+
+fn default_tld_stats_limit() -> usize {
+    50
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TldStatsResponse {
+    /// First snapshot in the window (`YYYY-MM-DD`)
+    pub from: Option<String>,
+    /// Latest snapshot (`YYYY-MM-DD`)
+    pub to: Option<String>,
+    /// Snapshots in the window
+    pub days: usize,
+    pub tlds: Vec<TldStat>,
+}
+
+/// Document counts of one TLD at the start and end of the window
+#[derive(Serialize, ToSchema)]
+pub struct TldStat {
+    pub tld: String,
+    pub start: u64,
+    pub end: u64,
+    pub change: i64,
+    /// Change relative to `start`; absent for TLDs new in the window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub growth_percent: Option<f64>,
+    /// Daily counts, when TLDs are selected with `tld=`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<Vec<TldPoint>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TldPoint {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub count: u64,
+}
+
+/// Per-TLD document counts over time, recorded by daily syncs
+#[utoipa::path(
+    get,
+    path = "/stats/tlds",
+    tag = "status",
+    params(TldStatsQuery),
+    responses(
+        (status = 200, description = "TLD growth over the window", body = TldStatsResponse),
+        (status = 400, description = "Invalid `since`"),
+        (status = 404, description = "TLD statistics are not enabled"),
+    )
+)]
+pub async fn tld_stats(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TldStatsQuery>,
+) -> Result<Json<TldStatsResponse>, (StatusCode, String)> {
+    let path = state.config.tld_stats_path.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "TLD statistics are not enabled (set TLD_STATS_PATH)".to_string(),
+    ))?;
+    let since = parse_since(params.since.as_deref().unwrap_or("90d"), now_secs())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let since = format_date(since);
+
+    let history = tokio::task::spawn_blocking(move || TldHistory::load(&path))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))?
+        .map_err(|e| {
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("TLD statistics error: {}", e))
+        })?;
+
+    let selected: Option<Vec<String>> = params.tld.as_deref().map(|tlds| {
+        tlds.split(',')
+            .map(|tld| tld.trim().trim_start_matches('.').to_lowercase())
+            .filter(|tld| !tld.is_empty())
+            .collect()
+    });
+    let window = || history.days.range(since.clone()..);
+
+    let mut growth = history.growth(&since);
+    if let Some(selected) = &selected {
+        growth.retain(|g| selected.contains(&g.tld));
+    }
+    if params.limit > 0 {
+        growth.truncate(params.limit);
+    }
+
+    let tlds = growth
+        .into_iter()
+        .map(|g| TldStat {
+            change: g.change(),
+            growth_percent: g.percent(),
+            series: selected.as_ref().map(|_| {
+                window()
+                    .map(|(date, counts)| TldPoint {
+                        date: date.clone(),
+                        count: counts.get(&g.tld).copied().unwrap_or(0),
+                    })
+                    .collect()
+            }),
+            tld: g.tld,
+            start: g.start,
+            end: g.end,
+        })
+        .collect();
+
+    Ok(Json(TldStatsResponse {
+        from: window().next().map(|(date, _)| date.clone()),
+        to: window().next_back().map(|(date, _)| date.clone()),
+        days: window().count(),
+        tlds,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoryStatsResponse {
+    /// Indexed domains per taxonomy category (`TAXONOMY_FILE` at index time)
+    pub categories: CategoryCounts,
+}
+
+/// Per-category document counts of the default index
+#[utoipa::path(
+    get,
+    path = "/stats/categories",
+    tag = "status",
+    responses(
+        (status = 200, description = "Domains per category", body = CategoryStatsResponse),
+    )
+)]
+pub async fn category_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<CategoryStatsResponse>, (StatusCode, String)> {
+    let index = state.index();
+    let categories = tokio::task::spawn_blocking(move || index.searcher.categories())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e)))?;
+
+    Ok(Json(CategoryStatsResponse { categories }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health_reports_broken_index() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 100);
+
+        let (status, Json(response)) = health(State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.status, "ok");
+        assert!(response.error.is_none());
+
+        std::fs::remove_file(directory.path().join("meta.json")).unwrap();
+
+        let (status, Json(response)) = health(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.status, "degraded");
+        assert!(response.error.unwrap().contains("unreadable"));
+        // The open reader still answers
+        assert_eq!(response.index_documents, 100);
+
+        let Json(stats) = stats(State(state)).await;
+        assert!(stats.index.error.is_some());
+        assert_eq!(stats.index.documents, 100);
+        let documents: u32 = stats.index.segment_details.iter().map(|s| s.documents).sum();
+        assert_eq!(documents, 100);
+        assert!(stats.index.size_bytes > 0);
+    }
+}
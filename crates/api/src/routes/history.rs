@@ -0,0 +1,122 @@
+use super::dated::format_timestamp;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use domain_core::{Domain, LifecycleEvent};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::TermQuery;
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::{DateTime, TantivyDocument, Term};
+use utoipa::{IntoParams, ToSchema};
+
+/// Most events returned for one domain
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Full domain name (e.g., "example.com")
+    pub domain: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct HistoryResponse {
+    pub domain: String,
+    /// Whether the domain is in the index now
+    pub registered: bool,
+    /// Lifecycle events, oldest first
+    pub events: Vec<HistoryEvent>,
+    pub query_time_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct HistoryEvent {
+    /// `first_seen`, `dropped` or `re_registered`
+    pub event: String,
+    /// When the daily sync that saw the event ran (RFC 3339)
+    pub timestamp: String,
+}
+
+/// Lifecycle timeline of one domain across daily syncs
+#[utoipa::path(
+    get,
+    path = "/history",
+    tag = "search",
+    params(HistoryQuery),
+    responses(
+        (status = 200, description = "Domain timeline", body = HistoryResponse),
+        (status = 400, description = "Invalid domain"),
+        (status = 404, description = "No history index is configured"),
+    )
+)]
+pub async fn history(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HistoryQuery>,
+) -> Result<Json<HistoryResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    let Some(history) = &state.history else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Domain history is not enabled (set HISTORY_INDEX_PATH)".to_string(),
+        ));
+    };
+    let normalized = Domain::new(&params.domain)
+        .normalize_with(state.config.label_validation)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid domain: {}", e)))?;
+    let search_error = |e: tantivy::TantivyError| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e))
+    };
+
+    let searcher = history.index.handle.reader.searcher();
+    let term = Term::from_field_text(history.schema.domain.domain_exact, &normalized.domain_exact);
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let found = searcher
+        .search(&query, &TopDocs::with_limit(MAX_EVENTS))
+        .map_err(search_error)?;
+
+    let mut events = Vec::with_capacity(found.len());
+    for (_, address) in found {
+        let doc: TantivyDocument = searcher.doc(address).map_err(search_error)?;
+        let event = doc
+            .get_first(history.schema.event)
+            .and_then(|value| value.as_str())
+            .and_then(LifecycleEvent::parse);
+        let date = doc
+            .get_first(history.schema.date)
+            .and_then(|value| value.as_datetime())
+            .unwrap_or(DateTime::MIN);
+        if let Some(event) = event {
+            events.push((date, event));
+        }
+    }
+    events.sort_by_key(|(date, event)| (*date, event.sync_order()));
+
+    let term = Term::from_field_text(state.schema.domain_exact, &normalized.domain_exact);
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let registered = state
+        .index()
+        .reader
+        .searcher()
+        .search(&query, &Count)
+        .map_err(search_error)?
+        > 0;
+
+    Ok(Json(HistoryResponse {
+        domain: normalized.domain_exact,
+        registered,
+        events: events
+            .into_iter()
+            .map(|(date, event)| HistoryEvent {
+                event: event.as_str().to_string(),
+                timestamp: format_timestamp(date),
+            })
+            .collect(),
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
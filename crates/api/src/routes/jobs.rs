@@ -0,0 +1,311 @@
+use crate::index::IndexHandle;
+use crate::jobs::Job;
+use crate::routes::admin::{self, SwapRequest};
+use crate::{warmup, AppState};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tantivy::schema::IndexRecordOption;
+use tantivy::{DocSet, TERMINATED};
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct JobsResponse {
+    /// Queued, running and recently finished jobs, newest first
+    pub jobs: Vec<Job>,
+}
+
+/// List background jobs
+#[utoipa::path(
+    get,
+    path = "/admin/jobs",
+    tag = "admin",
+    responses((status = 200, description = "Known jobs", body = JobsResponse))
+)]
+pub async fn list_jobs(State(state): State<Arc<AppState>>) -> Json<JobsResponse> {
+    Json(JobsResponse {
+        jobs: state.jobs.list(),
+    })
+}
+
+/// Status of a background job
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "The job", body = Job),
+        (status = 404, description = "Unknown job"),
+    )
+)]
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    state
+        .jobs
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown job: {}", id)))
+}
+
+/// Promote a different index directory in the background
+///
+/// Runs the checks and swap of `/admin/index/swap`; the job's result is its
+/// response.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/swap",
+    tag = "admin",
+    request_body = SwapRequest,
+    responses((status = 202, description = "Swap queued", body = Job))
+)]
+pub async fn swap_job(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SwapRequest>,
+) -> (StatusCode, Json<Job>) {
+    let job_state = state.clone();
+    let job = state.jobs.submit("swap", async move {
+        admin::swap(&job_state, request).await.map_err(|(_, message)| message)
+    });
+    (StatusCode::ACCEPTED, Json(job))
+}
+
+#[derive(Serialize)]
+struct CacheWarmResult {
+    cache_flushed: u64,
+    warmup: warmup::WarmupSummary,
+}
+
+/// Flush the response cache and run the startup warmup again, in the
+/// background
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/cache-warm",
+    tag = "admin",
+    responses((status = 202, description = "Flush and warmup queued", body = Job))
+)]
+pub async fn cache_warm_job(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Job>) {
+    let job_state = state.clone();
+    let job = state.jobs.submit("cache_warm", async move {
+        let cache_flushed = match &job_state.cache {
+            Some(cache) => cache.flush().await.map_err(|e| format!("Cache error: {}", e))?,
+            None => 0,
+        };
+        let warmup = warmup::warm_up(&job_state).await;
+        Ok(CacheWarmResult {
+            cache_flushed,
+            warmup,
+        })
+    });
+    (StatusCode::ACCEPTED, Json(job))
+}
+
+/// Popular queries replayed when neither the request nor
+/// `CACHE_REPLAY_QUERIES` says how many
+const DEFAULT_REPLAY_QUERIES: usize = 100;
+
+#[derive(Deserialize, Default, ToSchema)]
+pub struct CacheReplayRequest {
+    /// Most popular queries to replay (default: `CACHE_REPLAY_QUERIES`, or
+    /// 100 when that is 0)
+    pub queries: Option<usize>,
+    /// Queries searched at a time (default: `BULK_CONCURRENCY`)
+    pub concurrency: Option<usize>,
+}
+
+/// Search the most popular persisted queries into the response cache, in
+/// the background
+///
+/// Repopulates the cache after a flush or an index swap. The job's
+/// `progress` counts the queries replayed so far; its result is the final
+/// count.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/cache-replay",
+    tag = "admin",
+    request_body = CacheReplayRequest,
+    responses(
+        (status = 202, description = "Replay queued", body = Job),
+        (status = 400, description = "Concurrency of 0"),
+        (status = 404, description = "No Redis or QUERY_STATS_REDIS_KEY to replay from"),
+    )
+)]
+pub async fn cache_replay_job(
+    State(state): State<Arc<AppState>>,
+    request: Option<Json<CacheReplayRequest>>,
+) -> Result<(StatusCode, Json<Job>), (StatusCode, String)> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let persisted = state.analytics.as_ref().is_some_and(|a| a.persisted());
+    if state.cache.is_none() || !persisted {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Replaying queries needs REDIS_URL and QUERY_STATS_REDIS_KEY".to_string(),
+        ));
+    }
+    let count = request.queries.unwrap_or(match state.config.cache_replay_queries {
+        0 => DEFAULT_REPLAY_QUERIES,
+        count => count,
+    });
+    let concurrency = request.concurrency.unwrap_or(state.config.bulk_concurrency);
+    if concurrency == 0 {
+        return Err((StatusCode::BAD_REQUEST, "concurrency must be at least 1".to_string()));
+    }
+
+    let job_state = state.clone();
+    let job = state.jobs.submit_tracked("cache_replay", move |progress| async move {
+        warmup::replay(&job_state, count, concurrency, |done| progress.set(done)).await
+    });
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExportRequest {
+    /// Only domains under this TLD (default: all)
+    pub tld: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExportResult {
+    path: PathBuf,
+    domains: u64,
+}
+
+/// Write the indexed domains to a file in `EXPORT_DIR`, in the background
+///
+/// One domain per line, sorted within each index segment but not across
+/// them. The file's path is in the job's result.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/export",
+    tag = "admin",
+    request_body = ExportRequest,
+    responses(
+        (status = 202, description = "Export queued", body = Job),
+        (status = 404, description = "Exports are not enabled"),
+    )
+)]
+pub async fn export_job(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ExportRequest>,
+) -> Result<(StatusCode, Json<Job>), (StatusCode, String)> {
+    let Some(dir) = state.config.export_dir.clone() else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Exports are not enabled (set EXPORT_DIR)".to_string(),
+        ));
+    };
+    let tld = request
+        .tld
+        .map(|tld| tld.trim_start_matches('.').to_lowercase());
+    if tld.as_ref().is_some_and(|tld| !tld.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid TLD".to_string()));
+    }
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let name = format!("domains-{}-{}.txt", tld.as_deref().unwrap_or("all"), seconds);
+    let path = dir.join(name);
+    let index = state.index();
+    let job = state.jobs.submit("export", async move {
+        tokio::task::spawn_blocking(move || {
+            let domains = export_domains(&index, tld.as_deref(), &path)?;
+            Ok::<_, anyhow::Error>(ExportResult { path, domains })
+        })
+        .await
+        .map_err(|e| format!("Task error: {}", e))?
+        .map_err(|e| format!("Export error: {}", e))
+    });
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Write the live domains of an index, optionally of one TLD, returning how
+/// many were written
+///
+/// Domains are read from the `domain_exact` term dictionaries rather than
+/// the document store, checking postings only in segments with deletes.
+fn export_domains(
+    index: &IndexHandle,
+    tld: Option<&str>,
+    path: &std::path::Path,
+) -> anyhow::Result<u64> {
+    let suffix = tld.map(|tld| format!(".{}", tld));
+    let domain_exact = index.index.schema().get_field("domain_exact")?;
+    let searcher = index.reader.searcher();
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut count = 0;
+
+    for segment in searcher.segment_readers() {
+        let inverted_index = segment.inverted_index(domain_exact)?;
+        let mut terms = inverted_index.terms().stream()?;
+        while terms.advance() {
+            let Ok(domain) = std::str::from_utf8(terms.key()) else {
+                continue;
+            };
+            if suffix.as_ref().is_some_and(|suffix| !domain.ends_with(suffix.as_str())) {
+                continue;
+            }
+            if segment.has_deletes() {
+                let mut postings = inverted_index
+                    .read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic)?;
+                let mut alive = false;
+                while postings.doc() != TERMINATED {
+                    alive |= !segment.is_deleted(postings.doc());
+                    postings.advance();
+                }
+                if !alive {
+                    continue;
+                }
+            }
+            writeln!(writer, "{}", domain)?;
+            count += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_domains() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 300);
+        let output = tempfile::tempdir().unwrap();
+
+        let all = output.path().join("all.txt");
+        assert_eq!(export_domains(&state.index(), None, &all).unwrap(), 300);
+
+        let tld = domain_core::synthetic::SyntheticDomains::new(1, 42)
+            .next()
+            .unwrap()
+            .tld;
+        let some = output.path().join("some.txt");
+        let count = export_domains(&state.index(), Some(&tld), &some).unwrap();
+        let lines = std::fs::read_to_string(&some).unwrap();
+        assert!(count > 0 && count < 300);
+        assert!(lines.lines().all(|domain| domain.ends_with(&format!(".{}", tld))));
+    }
+
+    #[tokio::test]
+    async fn test_export_requires_export_dir() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 10);
+        let request = ExportRequest { tld: None };
+        let (status, _) = export_job(State(state), Json(request)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
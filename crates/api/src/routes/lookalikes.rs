@@ -0,0 +1,134 @@
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use domain_core::{confusables, Domain};
+use crate::routes::search::parse_fields;
+use domain_search::{extract_domain_result_with, DomainResult, Fields};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tantivy::collector::TopDocs;
+use tantivy::query::TermQuery;
+use tantivy::schema::IndexRecordOption;
+use tantivy::Term;
+use utoipa::{IntoParams, ToSchema};
+
+/// Most registrations sharing a skeleton that are read from the index
+const MAX_SCANNED: usize = 10_000;
+
+/// Most lookalikes returned by one request
+const MAX_LOOKALIKES_LIMIT: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LookalikesQuery {
+    /// Domain to protect (e.g., "paypal.com"); only its label is compared
+    pub domain: String,
+
+    /// Only lookalikes under this TLD
+    pub tld: Option<String>,
+
+    /// Maximum lookalikes to return (at most 1000)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Fields of each lookalike to return, comma-separated (e.g.
+    /// `domain,tld`; default: all)
+    pub fields: Option<String>,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct LookalikesResponse {
+    pub domain: String,
+    /// Confusable skeleton shared by the domain and every lookalike
+    pub skeleton: String,
+    pub results: Vec<Lookalike>,
+    /// Lookalikes found, before `limit`
+    pub total: usize,
+    pub query_time_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct Lookalike {
+    #[serde(flatten)]
+    pub domain: DomainResult,
+    /// How an IDN lookalike renders, for punycode registrations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unicode: Option<String>,
+}
+
+/// Registrations visually confusable with a domain: homoglyphs, IDN
+/// homographs and digit substitutions of its label under any TLD
+#[utoipa::path(
+    get,
+    path = "/lookalikes",
+    tag = "search",
+    params(LookalikesQuery),
+    responses(
+        (status = 200, description = "Confusable registrations", body = LookalikesResponse),
+        (status = 400, description = "Invalid domain"),
+    )
+)]
+pub async fn lookalikes(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LookalikesQuery>,
+) -> Result<Json<LookalikesResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    let normalized = Domain::new(&params.domain)
+        .normalize_with(state.config.label_validation)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid domain: {}", e)))?;
+    let skeleton = confusables::skeleton(&normalized.label);
+    let tld = params.tld.as_deref().map(|tld| tld.trim_start_matches('.').to_lowercase());
+    let limit = params.limit.min(MAX_LOOKALIKES_LIMIT);
+    let fields = parse_fields(params.fields.as_deref())?;
+
+    let index = state.index();
+    let searcher = index.reader.searcher();
+    let term = Term::from_field_text(state.schema.skeleton, &skeleton);
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let top_docs = searcher
+        .search(&query, &TopDocs::with_limit(MAX_SCANNED))
+        .map_err(|e| {
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e))
+        })?;
+
+    let mut results = Vec::new();
+    for (_score, doc_address) in top_docs {
+        let doc = searcher.doc(doc_address).map_err(|e| {
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e))
+        })?;
+        // The label and TLD are compared whether returned or not
+        let mut domain =
+            extract_domain_result_with(&state.schema, &doc, fields | Fields::LABEL | Fields::TLD);
+        // The protected label itself is a registration, not a lookalike
+        if domain.label == normalized.label {
+            continue;
+        }
+        if tld.as_ref().is_some_and(|tld| *tld != domain.tld) {
+            continue;
+        }
+        domain.fields = fields;
+        results.push(Lookalike {
+            unicode: confusables::to_unicode(&domain.domain),
+            domain,
+        });
+    }
+    results.sort_by(|a, b| a.domain.domain.cmp(&b.domain.domain));
+    let total = results.len();
+    results.truncate(limit);
+
+    Ok(Json(LookalikesResponse {
+        domain: normalized.domain_exact,
+        skeleton,
+        results,
+        total,
+        query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
@@ -1,3 +1,13 @@
+pub mod admin;
+pub mod appraise;
+pub mod available;
+pub mod coverage;
+pub mod dated;
 pub mod exact;
 pub mod health;
+pub mod history;
+pub mod jobs;
+pub mod lookalikes;
+pub mod portfolio;
 pub mod search;
+pub mod watch;
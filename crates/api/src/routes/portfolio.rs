@@ -0,0 +1,332 @@
+use crate::routes::search::{check_limit, parse_fields};
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use domain_core::{Domain, NormalizedDomain};
+use domain_search::{extract_domain_result, DomainResult, SearchRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tantivy::collector::TopDocs;
+use tantivy::query::BooleanQuery;
+use tantivy::Term;
+use utoipa::ToSchema;
+
+/// Most domains analyzed in one request
+const MAX_PORTFOLIO_DOMAINS: usize = 1000;
+
+/// Keywords of the sibling search when `MAX_QUERY_TOKENS` doesn't cap them
+const MAX_SIBLING_TOKENS: usize = 10;
+
+#[derive(Deserialize, ToSchema)]
+pub struct PortfolioRequest {
+    /// Owned domains (e.g. `["bestcoffee.com", "coffee-shop.net"]`)
+    pub domains: Vec<String>,
+    /// Most sibling domains returned (default: 50)
+    #[serde(default = "default_siblings")]
+    pub siblings: u32,
+    /// Sibling fields to return, comma-separated (default: all)
+    pub fields: Option<String>,
+}
+
+fn default_siblings() -> u32 {
+    50
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PortfolioResponse {
+    /// Each owned domain, in request order
+    pub domains: Vec<PortfolioDomain>,
+    /// Words across the portfolio, most common first
+    pub tokens: Vec<Coverage>,
+    /// TLDs across the portfolio, most common first
+    pub tlds: Vec<Coverage>,
+    /// Label lengths across the portfolio, shortest first
+    pub lengths: Vec<LengthCoverage>,
+    /// Indexed domains outside the portfolio sharing its words, those
+    /// sharing the most first
+    pub siblings: Vec<Sibling>,
+    pub query_time_ms: f64,
+}
+
+/// An owned domain as normalized and segmented
+#[derive(Serialize, ToSchema)]
+pub struct PortfolioDomain {
+    pub domain: String,
+    pub tld: String,
+    pub length: usize,
+    /// The domain is in the index
+    pub indexed: bool,
+    /// Words of the label: the indexed segmentation, or for domains not
+    /// indexed the indexed words the label splits into
+    pub tokens: Vec<String>,
+}
+
+/// How many owned domains have a word or TLD
+#[derive(Serialize, ToSchema)]
+pub struct Coverage {
+    pub value: String,
+    pub domains: usize,
+}
+
+/// How many owned domains have a label length
+#[derive(Serialize, ToSchema)]
+pub struct LengthCoverage {
+    pub length: usize,
+    pub domains: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Sibling {
+    #[serde(flatten)]
+    pub domain: DomainResult,
+    /// Portfolio words the domain has
+    pub shared_tokens: Vec<String>,
+}
+
+/// Words, TLDs and lengths a set of owned domains covers, and registered
+/// domains sharing their words
+#[utoipa::path(
+    post,
+    path = "/portfolio/analyze",
+    tag = "search",
+    request_body = PortfolioRequest,
+    responses(
+        (status = 200, description = "Portfolio coverage and siblings", body = PortfolioResponse),
+        (status = 400, description = "Invalid domain, too many domains or invalid `siblings`"),
+    )
+)]
+pub async fn analyze(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PortfolioRequest>,
+) -> Result<Json<PortfolioResponse>, (StatusCode, String)> {
+    let start = std::time::Instant::now();
+
+    if request.domains.is_empty() || request.domains.len() > MAX_PORTFOLIO_DOMAINS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("domains must list 1 to {} domains", MAX_PORTFOLIO_DOMAINS),
+        ));
+    }
+    let siblings = check_limit(&state.config, request.siblings)?;
+    let fields = parse_fields(request.fields.as_deref())?;
+    let mut normalized: Vec<NormalizedDomain> = Vec::with_capacity(request.domains.len());
+    for (i, domain) in request.domains.iter().enumerate() {
+        let domain = Domain::new(domain)
+            .normalize_with(state.config.label_validation)
+            .map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Domain {}: invalid domain: {}", i, e),
+                )
+            })?;
+        // Listing a domain twice doesn't make it cover more
+        if !normalized.iter().any(|d| d.domain_exact == domain.domain_exact) {
+            normalized.push(domain);
+        }
+    }
+
+    // Segmenting unindexed labels looks up every substring in the term
+    // dictionary
+    let search_state = state.clone();
+    let mut response =
+        tokio::task::spawn_blocking(move || analyze_portfolio(&search_state, normalized, siblings))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))??;
+    for sibling in &mut response.siblings {
+        sibling.domain.fields = fields;
+    }
+    response.query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    Ok(Json(response))
+}
+
+fn analyze_portfolio(
+    state: &AppState,
+    owned: Vec<NormalizedDomain>,
+    siblings: usize,
+) -> Result<PortfolioResponse, (StatusCode, String)> {
+    let search_error = |e: domain_search::Error| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e))
+    };
+    let index = state.index();
+    let mut indexed = lookup(state, &index.reader.searcher(), &owned)?;
+
+    let mut domains = Vec::with_capacity(owned.len());
+    for domain in &owned {
+        let found = indexed.remove(&domain.domain_exact);
+        let tokens = match &found {
+            Some(found) => found.tokens.clone(),
+            None => index
+                .searcher
+                .segment(&domain.label)
+                .map_err(search_error)?
+                .into_iter()
+                .filter(|word| word.doc_freq > 0)
+                .map(|word| word.word)
+                .collect(),
+        };
+        domains.push(PortfolioDomain {
+            domain: domain.domain_exact.clone(),
+            tld: domain.tld.clone(),
+            length: domain.label.chars().count(),
+            indexed: found.is_some(),
+            tokens,
+        });
+    }
+
+    let tokens = coverage(domains.iter().flat_map(|d| {
+        // A word repeated in one label counts once
+        let unique: HashSet<&str> = d.tokens.iter().map(String::as_str).collect();
+        unique
+    }));
+    let tlds = coverage(domains.iter().map(|d| d.tld.as_str()));
+    let mut lengths: BTreeMap<usize, usize> = BTreeMap::new();
+    for domain in &domains {
+        *lengths.entry(domain.length).or_default() += 1;
+    }
+    let lengths = lengths
+        .into_iter()
+        .map(|(length, domains)| LengthCoverage { length, domains })
+        .collect();
+
+    let max_tokens = match state.config.max_query_tokens {
+        0 => MAX_SIBLING_TOKENS,
+        max => max,
+    };
+    let keywords: Vec<&str> = tokens.iter().take(max_tokens).map(|c| c.value.as_str()).collect();
+    let siblings = if keywords.is_empty() {
+        Vec::new()
+    } else {
+        let (_, profile) = state
+            .profiles
+            .get(None)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let mut request = SearchRequest::new(keywords.join(" "), siblings + owned.len());
+        request.profile = profile.clone();
+        request.stop_tokens = state.config.stop_tokens.clone();
+        let results = index.searcher.search(&request).map_err(search_error)?;
+
+        let words: HashSet<&str> = keywords.iter().copied().collect();
+        let owned: HashSet<&str> = owned.iter().map(|d| d.domain_exact.as_str()).collect();
+        results
+            .results
+            .into_iter()
+            .filter(|r| !owned.contains(r.domain.domain.as_str()))
+            .take(siblings)
+            .map(|r| Sibling {
+                shared_tokens: r
+                    .domain
+                    .tokens
+                    .iter()
+                    .filter(|t| words.contains(t.as_str()))
+                    .cloned()
+                    .collect(),
+                domain: r.domain,
+            })
+            .collect()
+    };
+
+    Ok(PortfolioResponse {
+        domains,
+        tokens,
+        tlds,
+        lengths,
+        siblings,
+        query_time_ms: 0.0,
+    })
+}
+
+/// The indexed documents of the owned domains, by domain, in one query
+fn lookup(
+    state: &AppState,
+    searcher: &tantivy::Searcher,
+    owned: &[NormalizedDomain],
+) -> Result<HashMap<String, DomainResult>, (StatusCode, String)> {
+    let terms: Vec<Term> = owned
+        .iter()
+        .map(|d| Term::from_field_text(state.schema.domain_exact, &d.domain_exact))
+        .collect();
+    let query = BooleanQuery::new_multiterms_query(terms);
+    let top_docs = searcher
+        .search(&query, &TopDocs::with_limit(owned.len()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e)))?;
+
+    let mut found = HashMap::with_capacity(top_docs.len());
+    for (_score, address) in top_docs {
+        let doc = searcher
+            .doc(address)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e)))?;
+        let domain = extract_domain_result(&state.schema, &doc);
+        found.insert(domain.domain.clone(), domain);
+    }
+    Ok(found)
+}
+
+/// Count each value, most common first and alphabetical among ties
+fn coverage<'a>(values: impl Iterator<Item = &'a str>) -> Vec<Coverage> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut coverage: Vec<Coverage> = counts
+        .into_iter()
+        .map(|(value, domains)| Coverage {
+            value: value.to_string(),
+            domains,
+        })
+        .collect();
+    coverage.sort_by(|a, b| b.domains.cmp(&a.domains).then_with(|| a.value.cmp(&b.value)));
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_counts() {
+        let coverage = coverage(["net", "com", "com", "io"].into_iter());
+        let counts: Vec<(&str, usize)> =
+            coverage.iter().map(|c| (c.value.as_str(), c.domains)).collect();
+        assert_eq!(counts, vec![("com", 2), ("io", 1), ("net", 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_synthetic_portfolio() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 2000);
+        let owned = {
+            let index = state.index();
+            let results = index.searcher.search(&SearchRequest::new("coffee", 2)).unwrap();
+            let mut owned: Vec<String> =
+                results.results.into_iter().map(|r| r.domain.domain).collect();
+            owned.push("coffeeunregistered.io".to_string());
+            owned
+        };
+        let request = PortfolioRequest {
+            domains: owned.clone(),
+            siblings: 5,
+            fields: None,
+        };
+
+        let Json(response) = analyze(State(state.clone()), Json(request)).await.unwrap();
+        assert_eq!(response.domains.len(), 3);
+        assert!(response.domains[0].indexed && !response.domains[2].indexed);
+        assert!(response.domains[2].tokens.iter().any(|t| t == "coffee"));
+        assert_eq!(response.tokens[0].value, "coffee");
+        assert_eq!(response.tokens[0].domains, 3);
+        assert_eq!(response.lengths.iter().map(|l| l.domains).sum::<usize>(), 3);
+        assert!(!response.siblings.is_empty() && response.siblings.len() <= 5);
+        for sibling in &response.siblings {
+            assert!(!owned.contains(&sibling.domain.domain));
+            assert!(!sibling.shared_tokens.is_empty());
+        }
+
+        let request = PortfolioRequest {
+            domains: vec!["not a domain".to_string()],
+            siblings: 5,
+            fields: None,
+        };
+        let (status, _) = analyze(State(state), Json(request)).await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,296 +1,927 @@
+use crate::access_log;
+use crate::budget::{Degraded, Upstream};
 use crate::cache::Cache;
-use crate::routes::exact::{extract_domain_result, DomainResult};
-use crate::search::ranking::RankedResult;
+use crate::enrich::{self, Enrichment};
+use crate::index::{IndexSelection, ALL_INDEXES};
 use crate::AppState;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
     Json,
 };
+use domain_core::{lang, taxonomy, Config, QueryAnalyzer};
+use domain_search::{
+    CategoryCounts, Dedupe, DomainHack, Fields, RankingProfile, SearchProgress, SearchRequest,
+    SearchResult, SearchResults, SortKey, Suggestion, TokenWeight,
+};
+use futures::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::sync::Arc;
-use tantivy::collector::TopDocs;
-use tantivy::query::{BooleanQuery, Occur, TermQuery};
-use tantivy::schema::IndexRecordOption;
-use tantivy::Term;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::Instrument;
+use utoipa::{IntoParams, ToSchema};
+
+/// Candidates rescored between two `/search/stream` updates
+const STREAM_BATCH_SIZE: usize = 250;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchQuery {
     /// Search keywords (space-separated)
     pub q: String,
 
-    /// Filter by TLD (e.g., "com", "net")
+    /// Filter by TLD (e.g., "com", or "com,net" for several)
     pub tld: Option<String>,
 
+    /// Filter by the label's detected language (ISO 639-1, e.g. "de")
+    pub lang: Option<String>,
+
+    /// Filter by taxonomy category (e.g., "finance", or "finance,crypto"
+    /// for any of several)
+    pub category: Option<String>,
+
+    /// Include per-category counts of the matching domains under
+    /// `category_counts`
+    #[serde(default)]
+    pub facets: bool,
+
     /// Maximum results to return
     #[serde(default = "default_limit")]
     pub limit: u32,
 
     /// Minimum number of keywords that must match
     pub min_match: Option<u32>,
+
+    /// Ranking profile (default: `RANKING_PROFILE`)
+    pub profile: Option<String>,
+
+    /// `label` collapses the same label across TLDs into one result
+    #[serde(default)]
+    pub dedupe: Dedupe,
+
+    /// Bypass the cache for this request
+    #[serde(default)]
+    pub no_cache: bool,
+
+    /// Include per-token IDF weights (bypasses the cache)
+    #[serde(default)]
+    pub debug: bool,
+
+    /// Include a ranking breakdown per result and the Tantivy query (bypasses the cache)
+    #[serde(default)]
+    pub explain: bool,
+
+    /// Time budget in milliseconds (default: `SEARCH_TIMEOUT_MS`); on expiry the
+    /// best results so far are returned with `partial: true`
+    pub timeout_ms: Option<u64>,
+
+    /// Treat stop tokens (`STOP_TOKENS`) as full keywords
+    #[serde(default)]
+    pub keep_stopwords: bool,
+
+    /// Suggest domain hacks spelling a query word across label and TLD
+    /// (`delicious` → `delicio.us`)
+    #[serde(default)]
+    pub hacks: bool,
+
+    /// Add each result's label with the matched parts bracketed
+    /// (`best[coffee]shop`) under `highlighted`
+    #[serde(default)]
+    pub highlight: bool,
+
+    /// Match other inflections of the keywords by their stems (`shoes`
+    /// matches `shoe`); `false` matches the exact forms only
+    #[serde(default = "default_stem")]
+    pub stem: bool,
+
+    /// Search the corrections listed under `did_you_mean` in place of the
+    /// misspelled keywords
+    #[serde(default)]
+    pub autocorrect: bool,
+
+    /// Index to search: `default`, a name from `INDEXES`, or `all` to search
+    /// every index and merge the results
+    pub index: Option<String>,
+
+    /// Drop domains whose label looks more random than this, from 0
+    /// (meaningful) to 1 (algorithmically generated)
+    pub max_dga_score: Option<f64>,
+
+    /// Sort keys replacing the profile's, comma-separated (`match_count`,
+    /// `match_weight`, `length`, `bm25`, `score`, `dga_score`)
+    pub sort: Option<String>,
+
+    /// Share of hyphenated results when the profile interleaves them, from
+    /// 0 (none) to 1 (default: the profile's, 0.5 unless configured)
+    pub hyphen_ratio: Option<f64>,
+
+    /// Add RDAP registration data (registrar, creation date, status) for the
+    /// top `RDAP_ENRICH_LIMIT` domains under `enrichment`
+    #[serde(default)]
+    pub enrich: bool,
+
+    /// Result fields to return, comma-separated (e.g. `domain,tld,length`;
+    /// default: all); `domain` is always returned
+    pub fields: Option<String>,
 }
 
 fn default_limit() -> u32 {
     50
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+fn default_stem() -> bool {
+    true
+}
+
+impl SearchQuery {
+    /// A search for `q` with every other parameter at its default, as a
+    /// bare `/search?q=` request
+    pub(crate) fn new(q: impl Into<String>) -> Self {
+        Self {
+            q: q.into(),
+            tld: None,
+            lang: None,
+            category: None,
+            facets: false,
+            limit: default_limit(),
+            min_match: None,
+            profile: None,
+            dedupe: Dedupe::default(),
+            no_cache: false,
+            debug: false,
+            explain: false,
+            timeout_ms: None,
+            keep_stopwords: false,
+            hacks: false,
+            highlight: false,
+            stem: default_stem(),
+            autocorrect: false,
+            index: None,
+            max_dga_score: None,
+            sort: None,
+            hyphen_ratio: None,
+            enrich: false,
+            fields: None,
+        }
+    }
+
+    /// Analyze the query and resolve the ranking profile and index, returning
+    /// the cache key, search request and indices to search
+    async fn prepare(&self, state: &AppState) -> Result<Prepared, (StatusCode, String)> {
+        let mut query = analyze_query(&state.config, &state.analyzer, &self.q)?;
+        let limit = check_limit(&state.config, self.limit)?;
+        let mut degraded = Degraded::new();
+        if let Some(splitter) = &state.splitter {
+            query = splitter.expand(query, &state.budget, &mut degraded).await;
+        }
+        let (profile_name, profile) = state
+            .profiles
+            .get(self.profile.as_deref())
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let mut profile = profile.clone();
+        apply_max_dga_score(&mut profile, self.max_dga_score)?;
+        let mut profile_key = profile_name.to_string();
+        if let Some(max) = self.max_dga_score {
+            profile_key.push_str(&format!("+dga<={}", max));
+        }
+        if let Some(sort) = &self.sort {
+            profile.sort = parse_sort(sort)?;
+            let keys: Vec<&str> = profile.sort.iter().map(SortKey::as_str).collect();
+            profile_key.push_str(&format!("+sort={}", keys.join(",")));
+        }
+        if let Some(ratio) = self.hyphen_ratio {
+            apply_hyphen_ratio(&mut profile, ratio)?;
+            profile_key.push_str(&format!("+hyphens={}", ratio));
+        }
+
+        let lang = self.lang.as_deref().map(str::to_lowercase);
+        if let Some(lang) = lang.as_deref().filter(|code| !lang::is_supported(code)) {
+            let supported: Vec<&str> = lang::supported().collect();
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported lang '{}' (one of {})", lang, supported.join(", ")),
+            ));
+        }
+
+        let category = self.category.as_deref().map(str::to_lowercase);
+        for name in category.iter().flat_map(|c| c.split(',')).map(str::trim) {
+            if !name.is_empty() && !taxonomy::is_valid_category(name) {
+                return Err((StatusCode::BAD_REQUEST, format!("Invalid category: {}", name)));
+            }
+        }
+
+        let target = state
+            .select_index(self.index.as_deref())
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+        let request = SearchRequest {
+            query,
+            tld: self.tld.clone(),
+            lang,
+            category,
+            category_counts: self.facets,
+            limit,
+            min_match: self.min_match.map(|m| m as usize),
+            profile,
+            dedupe: self.dedupe,
+            explain: self.explain,
+            timeout: Some(self.timeout_ms.unwrap_or(state.config.search_timeout_ms))
+                .filter(|&ms| ms > 0)
+                .map(Duration::from_millis),
+            max_scanned: state.config.search_max_scanned,
+            stop_tokens: if self.keep_stopwords {
+                Vec::new()
+            } else {
+                state.config.stop_tokens.clone()
+            },
+            hacks: self.hacks,
+            highlight: self.highlight,
+            stem: self.stem,
+            autocorrect: self.autocorrect,
+            filter: None,
+        };
+
+        let cache_key =
+            Cache::make_key(target.generation, &request, &profile_key, &target.name);
+
+        Ok((cache_key, request, target, degraded))
+    }
+}
+
+type Prepared = (String, SearchRequest, IndexSelection, Degraded);
+
+/// The keywords of a query as searched and cached, rejecting queries over
+/// `MAX_QUERY_LENGTH` characters or `MAX_QUERY_TOKENS` keywords before they
+/// reach the index
+///
+/// Each keyword becomes a clause of the candidate query and widens the
+/// candidate set, so oversized queries are refused rather than truncated.
+/// Keywords are counted after analysis, which splits hyphenated and (with
+/// `QUERY_SEGMENTATION`) concatenated words.
+pub(crate) fn analyze_query(
+    config: &Config,
+    analyzer: &QueryAnalyzer,
+    q: &str,
+) -> Result<String, (StatusCode, String)> {
+    let max_length = config.max_query_length;
+    if max_length > 0 && q.chars().count() > max_length {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Query is longer than {} characters", max_length),
+        ));
+    }
+    let max_tokens = config.max_query_tokens;
+    let tokens = analyzer.tokens(q);
+    if max_tokens > 0 && tokens.len() > max_tokens {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Query has {} keywords; at most {} are allowed", tokens.len(), max_tokens),
+        ));
+    }
+    Ok(tokens.join(" "))
+}
+
+/// The requested number of results, refusing zero or more than
+/// `MAX_SEARCH_LIMIT`
+///
+/// The searcher keeps several candidates per result, so an oversized limit
+/// is refused before it sizes any buffer rather than clamped.
+pub(crate) fn check_limit(config: &Config, limit: u32) -> Result<usize, (StatusCode, String)> {
+    let max = config.max_search_limit;
+    let limit = limit as usize;
+    if limit == 0 || limit > max {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {}", max),
+        ));
+    }
+    Ok(limit)
+}
+
+/// Tighten the profile's DGA score filter to `max`
+pub(crate) fn apply_max_dga_score(
+    profile: &mut RankingProfile,
+    max: Option<f64>,
+) -> Result<(), (StatusCode, String)> {
+    let Some(max) = max else {
+        return Ok(());
+    };
+    if !(0.0..=1.0).contains(&max) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "max_dga_score must be between 0 and 1".to_string(),
+        ));
+    }
+    let filters = &mut profile.filters;
+    filters.max_dga_score = Some(filters.max_dga_score.map_or(max, |current| current.min(max)));
+    Ok(())
+}
+
+/// Replace the profile's share of hyphenated results
+fn apply_hyphen_ratio(
+    profile: &mut RankingProfile,
+    ratio: f64,
+) -> Result<(), (StatusCode, String)> {
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "hyphen_ratio must be between 0 and 1".to_string(),
+        ));
+    }
+    profile.hyphen_ratio = ratio;
+    Ok(())
+}
+
+/// Parse a comma-separated list of result fields, all of them when absent
+pub(crate) fn parse_fields(fields: Option<&str>) -> Result<Fields, (StatusCode, String)> {
+    match fields {
+        Some(list) => Fields::parse(list).map_err(|e| (StatusCode::BAD_REQUEST, e)),
+        None => Ok(Fields::ALL),
+    }
+}
+
+/// Parse a comma-separated list of sort keys
+fn parse_sort(sort: &str) -> Result<Vec<SortKey>, (StatusCode, String)> {
+    let keys: Vec<SortKey> = sort
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            SortKey::parse(name).ok_or_else(|| {
+                (StatusCode::BAD_REQUEST, format!("Unknown sort key: {}", name))
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    if keys.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "sort needs at least one key".to_string()));
+    }
+    Ok(keys)
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
     pub total_candidates: usize,
+    /// Documents matching the query that were scanned, over every pass
+    /// made to fill `limit`
+    #[serde(default)]
+    pub candidates_scanned: usize,
+    /// Domains matching the query in the whole index, for pagination
+    #[serde(default)]
+    pub total_hits: u64,
+    /// `total_hits` was extrapolated from a partial scan
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub total_hits_estimated: bool,
     pub query_time_ms: f64,
     pub cached: bool,
+    /// The time budget ran out before every match was ranked
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Domain hacks for the query words, with `hacks=true`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hacks: Vec<DomainHack>,
+    /// Matching domains per taxonomy category, with `facets=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_counts: Option<CategoryCounts>,
+    /// Likely corrections of rare keywords
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub did_you_mean: Vec<Suggestion>,
+    /// The query searched instead, with `autocorrect=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autocorrected_query: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<SearchDebug>,
+    /// Registration data by domain, with `enrich=true`
+    #[serde(default, skip_serializing_if = "Enrichment::is_empty")]
+    pub enrichment: Enrichment,
+    /// Upstreams whose per-minute budget ran out, leaving the query unsplit
+    /// or enrichment incomplete
+    #[serde(default, skip_serializing_if = "Degraded::is_empty")]
+    #[schema(value_type = Vec<Upstream>)]
+    pub degraded: Degraded,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct SearchResult {
-    #[serde(flatten)]
-    pub domain: DomainResult,
-    pub match_count: usize,
-    pub score: f32,
+impl SearchResponse {
+    /// Return only `fields` of each result
+    ///
+    /// Cached responses keep every field, so selecting happens once the
+    /// response is built or read from the cache.
+    pub(crate) fn select(&mut self, fields: Fields) {
+        for result in &mut self.results {
+            result.domain.fields = fields;
+        }
+    }
 }
 
-#[derive(Deserialize)]
+/// Ranking internals returned with `debug=true` or `explain=true`
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct SearchDebug {
+    /// Rarity weight of each query token; matching rarer tokens ranks higher
+    pub token_weights: Vec<TokenWeight>,
+    /// Candidate collection query, with `explain=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct BulkSearchRequest {
     pub queries: Vec<BulkQuery>,
     #[serde(default = "default_limit")]
     pub limit: u32,
+    /// Ranking profile for every query
+    pub profile: Option<String>,
+    /// Deduplication for every query
+    #[serde(default)]
+    pub dedupe: Dedupe,
+    #[serde(default)]
+    pub no_cache: bool,
+    /// Time budget in milliseconds for each query
+    pub timeout_ms: Option<u64>,
+    /// Treat stop tokens as full keywords in every query
+    #[serde(default)]
+    pub keep_stopwords: bool,
+    /// Index for every query (`default`, a name from `INDEXES`, or `all`)
+    pub index: Option<String>,
+    /// Result fields for every query, comma-separated (default: all)
+    pub fields: Option<String>,
+    /// Bracket the matched parts of every result's label
+    #[serde(default)]
+    pub highlight: bool,
+    /// Match other inflections of every query's keywords by their stems
+    #[serde(default = "default_stem")]
+    pub stem: bool,
 }
 
-#[derive(Deserialize)]
+/// One query of a bulk request; set options override the request's
+#[derive(Deserialize, Default, ToSchema)]
 pub struct BulkQuery {
     pub q: String,
     pub tld: Option<String>,
     pub min_match: Option<u32>,
+    /// Maximum results for this query
+    pub limit: Option<u32>,
+    /// Ranking profile for this query
+    pub profile: Option<String>,
+    /// Sort keys replacing the profile's, comma-separated
+    pub sort: Option<String>,
+    /// Filter by the label's detected language
+    pub lang: Option<String>,
+    /// Filter by taxonomy category, comma-separated for any of several
+    pub category: Option<String>,
+    /// Drop domains whose label looks more random than this (0-1)
+    pub max_dga_score: Option<f64>,
+    /// Share of hyphenated results when the profile interleaves them (0-1)
+    pub hyphen_ratio: Option<f64>,
+    /// Match other inflections of the keywords by their stems
+    pub stem: Option<bool>,
+    /// Search the corrections of misspelled keywords instead
+    #[serde(default)]
+    pub autocorrect: bool,
+    /// Result fields for this query, comma-separated
+    pub fields: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct BulkSearchResponse {
-    pub results: Vec<SearchResponse>,
+    pub results: Vec<BulkQueryResponse>,
+    /// Number of queries that failed (their `error` is set)
+    pub failed_count: usize,
     pub total_time_ms: f64,
 }
 
+/// Outcome of one bulk query
+#[derive(Serialize, ToSchema)]
+pub struct BulkQueryResponse {
+    #[serde(flatten)]
+    pub response: SearchResponse,
+    /// Why the query failed; absent when it ran (even with no matches)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// Keyword search endpoint
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Ranked matches", body = SearchResponse),
+        (status = 400, description = "Empty or invalid query"),
+    )
+)]
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchQuery>,
 ) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    let rdap = enrich::client(&state, params.enrich)?;
+    let fields = parse_fields(params.fields.as_deref())?;
+    let mut response = cached_search(&state, &params).await?;
+    response.select(fields);
+
+    let domains = response.results.iter().map(|r| r.domain.domain.as_str());
+    response.enrichment = enrich::lookup(&state, rdap, domains, &mut response.degraded).await;
+    Ok(Json(response))
+}
+
+/// Search through the cache, shared by the HTTP and gRPC front ends
+pub(crate) async fn cached_search(
+    state: &AppState,
+    params: &SearchQuery,
+) -> Result<SearchResponse, (StatusCode, String)> {
+    let response = search_through_cache(state, params).await?;
+    record_query(state, params, &response);
+    Ok(response)
+}
+
+/// Answer from the cache, or search and cache the response, without
+/// counting the search in the query analytics
+pub(crate) async fn search_through_cache(
+    state: &AppState,
+    params: &SearchQuery,
+) -> Result<SearchResponse, (StatusCode, String)> {
     let start = std::time::Instant::now();
 
-    // Check cache first
-    if let Some(cache) = &state.cache {
-        let cache_key = Cache::make_key(
-            &params.q,
-            params.tld.as_deref(),
-            params.limit,
-            params.min_match,
-        );
+    let (cache_key, request, target, degraded) = params.prepare(state).await?;
 
+    let debug = params.debug || params.explain;
+    let cache = state.cache_for(params.no_cache || debug);
+    let ttl = Duration::from_secs(state.config.cache_ttl);
+
+    // Check cache first
+    if let Some(cache) = cache {
         if let Ok(Some(cached)) = cache.get::<SearchResponse>(&cache_key).await {
+            tracing::debug!(key = %cache_key, "Cache hit");
             let mut response = cached;
             response.cached = true;
             response.query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
-            return Ok(Json(response));
+            response.degraded = degraded;
+            let results = response.results.len();
+            access_log::note_search(true, response.total_candidates, results, Duration::ZERO);
+            return Ok(response);
         }
     }
 
     // Execute search
-    let response = execute_search(&state, &params).await?;
-
-    // Store in cache
-    if let Some(cache) = &state.cache {
-        let cache_key = Cache::make_key(
-            &params.q,
-            params.tld.as_deref(),
-            params.limit,
-            params.min_match,
-        );
-        let _ = cache.set(&cache_key, &response).await;
+    tracing::debug!(key = %cache_key, cacheable = cache.is_some(), "Cache miss");
+    let searched = Instant::now();
+    let mut response = execute_search(target, request, debug).await?;
+    let results = response.results.len();
+    access_log::note_search(false, response.total_candidates, results, searched.elapsed());
+
+    // Store in cache; partial results would hide better matches until expiry
+    if let Some(cache) = cache.filter(|_| !response.partial) {
+        let _ = cache.set(&cache_key, &response, ttl).await;
     }
+    response.degraded = degraded;
+    Ok(response)
+}
 
-    Ok(Json(response))
+/// Count a completed search in the query analytics
+fn record_query(state: &AppState, params: &SearchQuery, response: &SearchResponse) {
+    if let Some(analytics) = &state.analytics {
+        analytics.record(&params.q, response.results.len(), response.query_time_ms);
+    }
 }
 
 /// Execute the actual search
 async fn execute_search(
-    state: &AppState,
-    params: &SearchQuery,
+    target: IndexSelection,
+    request: SearchRequest,
+    debug: bool,
 ) -> Result<SearchResponse, (StatusCode, String)> {
     let start = std::time::Instant::now();
+    let limit = request.limit;
 
-    // Parse query into tokens
-    let query_tokens: Vec<String> = params
-        .q
-        .to_lowercase()
-        .split_whitespace()
-        .map(String::from)
-        .collect();
+    // Tantivy search and rescoring are CPU-bound; keep them off the async workers
+    let span = tracing::Span::current();
+    let searched = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| {
+            target
+                .indexes
+                .iter()
+                .map(|(name, index)| {
+                    index.searcher.search(&request).map(|results| (name.clone(), results))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+    })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Task error: {}", e)))?
+        .map_err(|e| {
+            let status = if e.is_client_error() {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, e.to_string())
+        })?;
+    let results = merge_results(searched, limit);
 
-    if query_tokens.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Query cannot be empty".to_string()));
-    }
+    let query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
 
-    let min_match = params.min_match.unwrap_or(1) as usize;
+    let debug = debug.then_some(SearchDebug {
+        token_weights: results.token_weights,
+        query: results.query,
+    });
 
-    // Build Tantivy query (OR of all tokens)
-    let mut token_queries: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+    Ok(SearchResponse {
+        results: results.results,
+        total_candidates: results.total_candidates,
+        candidates_scanned: results.candidates_scanned,
+        total_hits: results.total_hits,
+        total_hits_estimated: results.total_hits_estimated,
+        query_time_ms,
+        cached: false,
+        partial: results.partial,
+        hacks: results.hacks,
+        category_counts: results.category_counts,
+        did_you_mean: results.did_you_mean,
+        autocorrected_query: results.autocorrected,
+        debug,
+        enrichment: Enrichment::new(),
+        degraded: Degraded::new(),
+    })
+}
 
-    for token in &query_tokens {
-        let term = Term::from_field_text(state.schema.tokens, token);
-        let term_query = TermQuery::new(term, IndexRecordOption::WithFreqs);
-        token_queries.push((Occur::Should, Box::new(term_query)));
+/// Merge per-index results, taking each index's results in turn
+///
+/// Scores aren't comparable across indices (IDF differs per corpus), so each
+/// index keeps its own ranking and results alternate between indices in rank
+/// order. A domain found in several indices is listed once, from the first.
+/// Results are tagged with their index when more than one was searched.
+fn merge_results(searched: Vec<(String, SearchResults)>, limit: usize) -> SearchResults {
+    let mut merged = SearchResults {
+        results: Vec::new(),
+        total_candidates: 0,
+        candidates_scanned: 0,
+        total_hits: 0,
+        total_hits_estimated: false,
+        token_weights: Vec::new(),
+        query: None,
+        partial: false,
+        hacks: Vec::new(),
+        category_counts: None,
+        did_you_mean: Vec::new(),
+        autocorrected: None,
+    };
+    if searched.len() == 1 {
+        return searched.into_iter().next().map_or(merged, |(_, results)| results);
     }
 
-    // Note: TLD filtering is done post-query for better performance
-    // Facet queries are expensive; filtering during result processing is faster
-
-    let query = BooleanQuery::new(token_queries);
-    let num_query_tokens = query_tokens.len();
-    let tld_filter = params.tld.as_ref().map(|t| t.to_lowercase());
-
-    // Get reader and searcher
-    let reader = state.index.reader().map_err(|e| {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Index error: {}", e))
-    })?;
-    let searcher = reader.searcher();
-
-    // Smart candidate limit based on query complexity
-    // Single keyword: fewer candidates needed (BM25 order is already good)
-    // Multi-keyword: need more candidates to find high match-count results
-    // TLD filter: need more candidates since we'll filter many out
-    let base_limit = if num_query_tokens == 1 {
-        params.limit as usize * 20
-    } else {
-        params.limit as usize * 50
-    };
-    let candidate_limit = if tld_filter.is_some() {
-        base_limit.min(3000) // More candidates for TLD filtering
-    } else {
-        base_limit.min(1000)
-    };
+    let mut lists = Vec::with_capacity(searched.len());
+    for (name, results) in searched {
+        merged.total_candidates += results.total_candidates;
+        merged.candidates_scanned += results.candidates_scanned;
+        merged.total_hits += results.total_hits;
+        merged.total_hits_estimated |= results.total_hits_estimated;
+        merged.partial |= results.partial;
+        if merged.token_weights.is_empty() {
+            merged.token_weights = results.token_weights;
+            merged.query = results.query;
+        }
+        // Corrections come from the index with the most evidence for them
+        for suggestion in results.did_you_mean {
+            match merged.did_you_mean.iter_mut().find(|s| s.token == suggestion.token) {
+                Some(existing) if existing.doc_freq < suggestion.doc_freq => *existing = suggestion,
+                Some(_) => {}
+                None => merged.did_you_mean.push(suggestion),
+            }
+        }
+        if merged.autocorrected.is_none() {
+            merged.autocorrected = results.autocorrected;
+        }
+        if let Some(counts) = results.category_counts {
+            let merged_counts = merged.category_counts.get_or_insert_with(CategoryCounts::new);
+            for (category, count) in counts {
+                *merged_counts.entry(category).or_default() += count;
+            }
+        }
+        for hack in results.hacks {
+            match merged.hacks.iter_mut().find(|h| h.domain == hack.domain) {
+                Some(existing) => existing.in_zone |= hack.in_zone,
+                None => merged.hacks.push(hack),
+            }
+        }
+        lists.push((name, results.results.into_iter()));
+    }
+    merged.did_you_mean.sort_by(|a, b| a.token.cmp(&b.token));
 
-    let top_docs = searcher
-        .search(&query, &TopDocs::with_limit(candidate_limit))
-        .map_err(|e| {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Search error: {}", e))
-        })?;
+    let mut seen: HashSet<String> = HashSet::new();
+    loop {
+        let mut exhausted = true;
+        for (name, list) in &mut lists {
+            if merged.results.len() >= limit {
+                return merged;
+            }
+            let Some(mut result) = list.next() else {
+                continue;
+            };
+            exhausted = false;
+            if seen.insert(result.domain.domain.clone()) {
+                result.index = Some(name.clone());
+                merged.results.push(result);
+            }
+        }
+        if exhausted {
+            return merged;
+        }
+    }
+}
 
-    // Rescore candidates by match count
-    let mut ranked_results: Vec<RankedResult> = Vec::with_capacity(candidate_limit);
-    let mut perfect_matches = 0usize;
-    let target_results = params.limit as usize;
+/// `progress` event payload of `/search/stream`
+#[derive(Serialize, ToSchema)]
+pub struct StreamProgress {
+    pub candidates_scanned: usize,
+    pub total_candidates: usize,
+    pub elapsed_ms: f64,
+}
 
-    for (bm25_score, doc_address) in top_docs {
-        let doc = searcher.doc(doc_address).map_err(|e| {
-            (StatusCode::INTERNAL_SERVER_ERROR, format!("Doc error: {}", e))
-        })?;
+/// `results` event payload of `/search/stream`
+#[derive(Serialize, ToSchema)]
+pub struct StreamResults {
+    pub results: Vec<SearchResult>,
+    pub candidates_scanned: usize,
+}
 
-        let domain_result = extract_domain_result(&state.schema, &doc);
+/// Progressive keyword search over Server-Sent Events
+///
+/// Emits `progress` and `results` events as candidate batches are rescored,
+/// then a `done` event carrying the final `SearchResponse` (or an `error`
+/// event). Cached responses are sent straight away as `done`.
+#[utoipa::path(
+    get,
+    path = "/search/stream",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Event stream of `progress`, `results` and `done` events", content_type = "text/event-stream", body = String),
+    )
+)]
+pub async fn search_stream(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (tx, rx) = mpsc::channel(16);
+    // Keep the request span (and its ID) on the detached search
+    tokio::spawn(stream_search(state, params, tx).instrument(tracing::Span::current()));
 
-        // Count how many query tokens appear in the domain's tokens
-        let doc_tokens: std::collections::HashSet<&str> =
-            domain_result.tokens.iter().map(|s| s.as_str()).collect();
+    Sse::new(ReceiverStream::new(rx).map(Ok)).keep_alive(KeepAlive::default())
+}
 
-        let match_count = query_tokens
-            .iter()
-            .filter(|qt| doc_tokens.contains(qt.as_str()))
-            .count();
+/// Run a search, forwarding progress snapshots to the event stream
+async fn stream_search(state: Arc<AppState>, params: SearchQuery, tx: mpsc::Sender<Event>) {
+    let start = Instant::now();
 
-        // Filter by minimum match count
-        if match_count < min_match {
-            continue;
+    let prepared = match parse_fields(params.fields.as_deref()) {
+        Ok(fields) => params.prepare(&state).await.map(|prepared| (fields, prepared)),
+        Err(e) => Err(e),
+    };
+    let (fields, (cache_key, request, target, degraded)) = match prepared {
+        Ok(prepared) => prepared,
+        Err((_, msg)) => {
+            let _ = tx.send(error_event(&msg)).await;
+            return;
         }
+    };
+    if target.is_fan_out() {
+        let msg = format!("Streaming searches one index; index={} is not supported", ALL_INDEXES);
+        let _ = tx.send(error_event(&msg)).await;
+        return;
+    }
 
-        // Filter by TLD if specified
-        if let Some(ref tld) = tld_filter {
-            if &domain_result.tld != tld {
-                continue;
-            }
+    // As in search_through_cache, debug and explain searches bypass the cache
+    let cache = state.cache_for(params.no_cache || params.debug || params.explain);
+    if let Some(cache) = cache {
+        if let Ok(Some(mut cached)) = cache.get::<SearchResponse>(&cache_key).await {
+            tracing::debug!(key = %cache_key, "Cache hit");
+            cached.cached = true;
+            cached.query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+            record_query(&state, &params, &cached);
+            cached.select(fields);
+            cached.degraded = degraded;
+            let _ = tx.send(sse_event("done", &cached)).await;
+            return;
         }
+    }
 
-        // Track perfect matches for early termination
-        if match_count == num_query_tokens {
-            perfect_matches += 1;
-        }
+    let index = target.indexes[0].1.clone();
+    let progress_tx = tx.clone();
+
+    let span = tracing::Span::current();
+    let result = tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        index
+            .searcher
+            .search_progressive(&request, STREAM_BATCH_SIZE, |progress| {
+                send_progress(&progress_tx, progress, fields, start)
+            })
+    })
+    .await;
 
-        ranked_results.push(RankedResult {
-            domain: domain_result,
-            match_count,
-            bm25_score,
-        });
+    let event = match result {
+        Ok(Ok(results)) => {
+            let mut response = SearchResponse {
+                results: results.results,
+                total_candidates: results.total_candidates,
+                candidates_scanned: results.candidates_scanned,
+                total_hits: results.total_hits,
+                total_hits_estimated: results.total_hits_estimated,
+                query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                cached: false,
+                partial: results.partial,
+                hacks: results.hacks,
+                category_counts: results.category_counts,
+                did_you_mean: results.did_you_mean,
+                autocorrected_query: results.autocorrected,
+                debug: None,
+                enrichment: Enrichment::new(),
+                degraded: Degraded::new(),
+            };
 
-        // Early termination: if we have enough perfect matches, stop
-        if perfect_matches >= target_results * 2 {
-            break;
+            // An abandoned or timed out search is incomplete and must not be cached
+            if let Some(cache) = cache.filter(|_| !tx.is_closed() && !response.partial) {
+                let ttl = Duration::from_secs(state.config.cache_ttl);
+                let _ = cache.set(&cache_key, &response, ttl).await;
+            }
+            record_query(&state, &params, &response);
+            response.select(fields);
+            response.degraded = degraded;
+            sse_event("done", &response)
         }
-    }
+        Ok(Err(e)) => error_event(&e.to_string()),
+        Err(e) => error_event(&format!("Task error: {}", e)),
+    };
 
-    // Separate hyphenated and non-hyphenated domains
-    let (mut hyphenated, mut non_hyphenated): (Vec<_>, Vec<_>) = ranked_results
-        .into_iter()
-        .partition(|r| r.domain.has_hyphen);
-
-    // Sort each group by: match_count DESC, length ASC, bm25 DESC
-    let sort_fn = |a: &RankedResult, b: &RankedResult| {
-        b.match_count
-            .cmp(&a.match_count)
-            .then_with(|| a.domain.length.cmp(&b.domain.length))
-            .then_with(|| b.bm25_score.partial_cmp(&a.bm25_score).unwrap_or(std::cmp::Ordering::Equal))
+    let _ = tx.send(event).await;
+}
+
+/// Send one batch snapshot; returns `false` once the client has disconnected
+fn send_progress(
+    tx: &mpsc::Sender<Event>,
+    mut progress: SearchProgress,
+    fields: Fields,
+    start: Instant,
+) -> bool {
+    let stats = StreamProgress {
+        candidates_scanned: progress.candidates_scanned,
+        total_candidates: progress.total_candidates,
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
     };
-    hyphenated.sort_by(sort_fn);
-    non_hyphenated.sort_by(sort_fn);
-
-    let total_candidates = hyphenated.len() + non_hyphenated.len();
-
-    // Interleave results 50/50 (hyphenated first, then non-hyphenated, alternating)
-    let limit = params.limit as usize;
-    let mut results: Vec<SearchResult> = Vec::with_capacity(limit);
-
-    let mut hyp_iter = hyphenated.into_iter().peekable();
-    let mut non_hyp_iter = non_hyphenated.into_iter().peekable();
-
-    // Alternate: hyphenated, non-hyphenated, hyphenated, non-hyphenated...
-    while results.len() < limit {
-        // Add hyphenated first
-        if let Some(r) = hyp_iter.next() {
-            results.push(SearchResult {
-                domain: r.domain,
-                match_count: r.match_count,
-                score: r.bm25_score,
-            });
-        }
-        if results.len() >= limit {
-            break;
-        }
-        // Then add non-hyphenated
-        if let Some(r) = non_hyp_iter.next() {
-            results.push(SearchResult {
-                domain: r.domain,
-                match_count: r.match_count,
-                score: r.bm25_score,
-            });
-        }
-        // If both are exhausted, break
-        if hyp_iter.peek().is_none() && non_hyp_iter.peek().is_none() {
-            break;
-        }
+    for result in &mut progress.results {
+        result.domain.fields = fields;
     }
+    let results = StreamResults {
+        results: progress.results,
+        candidates_scanned: progress.candidates_scanned,
+    };
 
-    let query_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    tx.blocking_send(sse_event("progress", &stats)).is_ok()
+        && tx.blocking_send(sse_event("results", &results)).is_ok()
+}
 
-    Ok(SearchResponse {
-        results,
-        total_candidates,
-        query_time_ms,
-        cached: false,
-    })
+fn sse_event<T: Serialize>(name: &str, data: &T) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|e| error_event(&e.to_string()))
+}
+
+fn error_event(message: &str) -> Event {
+    Event::default()
+        .event("error")
+        .data(serde_json::json!({ "error": message }).to_string())
 }
 
 /// Bulk search endpoint
+#[utoipa::path(
+    post,
+    path = "/search/bulk",
+    tag = "search",
+    request_body = BulkSearchRequest,
+    responses(
+        (status = 200, description = "One response per query, in request order", body = BulkSearchResponse),
+        (status = 400, description = "Too many queries, or one too long"),
+        (status = 413, description = "Request body too large"),
+    )
+)]
 pub async fn bulk_search(
     State(state): State<Arc<AppState>>,
     Json(request): Json<BulkSearchRequest>,
 ) -> Result<Json<BulkSearchResponse>, (StatusCode, String)> {
+    run_bulk(&state, request).await.map(Json)
+}
+
+/// Run every query of a bulk request, shared by the HTTP and gRPC front ends
+pub(crate) async fn run_bulk(
+    state: &AppState,
+    request: BulkSearchRequest,
+) -> Result<BulkSearchResponse, (StatusCode, String)> {
     let start = std::time::Instant::now();
 
     if request.queries.len() > 100 {
@@ -299,66 +930,343 @@ pub async fn bulk_search(
             "Maximum 100 queries per bulk request".to_string(),
         ));
     }
+    let default_fields = parse_fields(request.fields.as_deref())?;
+    check_limit(&state.config, request.limit)?;
+    // Refuse the whole request rather than running its sane queries only
+    let mut fields = Vec::with_capacity(request.queries.len());
+    for (i, query) in request.queries.iter().enumerate() {
+        let check = || {
+            analyze_query(&state.config, &state.analyzer, &query.q)?;
+            if let Some(limit) = query.limit {
+                check_limit(&state.config, limit)?;
+            }
+            match &query.fields {
+                Some(list) => parse_fields(Some(list)),
+                None => Ok(default_fields),
+            }
+        };
+        fields.push(check().map_err(|(status, msg)| (status, format!("Query {}: {}", i, msg)))?);
+    }
 
-    let mut results = Vec::with_capacity(request.queries.len());
-
-    for query in &request.queries {
-        let params = SearchQuery {
-            q: query.q.clone(),
-            tld: query.tld.clone(),
-            limit: request.limit,
+    let queries: Vec<SearchQuery> = request
+        .queries
+        .into_iter()
+        .map(|query| SearchQuery {
+            q: query.q,
+            tld: query.tld,
+            lang: query.lang,
+            category: query.category,
+            facets: false,
+            limit: query.limit.unwrap_or(request.limit),
             min_match: query.min_match,
-        };
+            profile: query.profile.or_else(|| request.profile.clone()),
+            dedupe: request.dedupe,
+            no_cache: request.no_cache,
+            debug: false,
+            explain: false,
+            timeout_ms: request.timeout_ms,
+            keep_stopwords: request.keep_stopwords,
+            hacks: false,
+            highlight: request.highlight,
+            stem: query.stem.unwrap_or(request.stem),
+            autocorrect: query.autocorrect,
+            index: request.index.clone(),
+            max_dga_score: query.max_dga_score,
+            sort: query.sort,
+            hyphen_ratio: query.hyphen_ratio,
+            enrich: false,
+            fields: None,
+        })
+        .collect();
 
-        // Check cache
-        if let Some(cache) = &state.cache {
-            let cache_key = Cache::make_key(
-                &params.q,
-                params.tld.as_deref(),
-                params.limit,
-                params.min_match,
-            );
-
-            if let Ok(Some(cached)) = cache.get::<SearchResponse>(&cache_key).await {
-                let mut response = cached;
-                response.cached = true;
-                results.push(response);
-                continue;
-            }
-        }
+    // Run queries concurrently, bounded so one request can't claim every worker
+    let mut results: Vec<BulkQueryResponse> = stream::iter(queries)
+        .map(|params| bulk_query(state, params))
+        .buffered(state.config.bulk_concurrency.max(1))
+        .collect()
+        .await;
+    for (result, fields) in results.iter_mut().zip(fields) {
+        result.response.select(fields);
+    }
 
-        // Execute search
-        match execute_search(&state, &params).await {
-            Ok(response) => {
-                // Cache result
-                if let Some(cache) = &state.cache {
-                    let cache_key = Cache::make_key(
-                        &params.q,
-                        params.tld.as_deref(),
-                        params.limit,
-                        params.min_match,
-                    );
-                    let _ = cache.set(&cache_key, &response).await;
-                }
-                results.push(response);
-            }
-            Err((_, msg)) => {
-                // Return empty result for failed queries
-                results.push(SearchResponse {
+    let failed_count = results.iter().filter(|r| r.error.is_some()).count();
+    let total_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    Ok(BulkSearchResponse {
+        results,
+        failed_count,
+        total_time_ms,
+    })
+}
+
+/// Run one bulk query through the cache and search path
+async fn bulk_query(state: &AppState, params: SearchQuery) -> BulkQueryResponse {
+    let start = Instant::now();
+    match cached_search(state, &params).await {
+        Ok(response) => BulkQueryResponse {
+            response,
+            error: None,
+        },
+        Err((_, msg)) => {
+            tracing::warn!(query = %params.q, error = %msg, "Bulk query failed");
+            // Report the failure so callers can tell it apart from "no matches"
+            BulkQueryResponse {
+                response: SearchResponse {
                     results: vec![],
                     total_candidates: 0,
-                    query_time_ms: 0.0,
+                    candidates_scanned: 0,
+                    total_hits: 0,
+                    total_hits_estimated: false,
+                    query_time_ms: start.elapsed().as_secs_f64() * 1000.0,
                     cached: false,
-                });
-                tracing::warn!(query = %query.q, error = %msg, "Bulk query failed");
+                    partial: false,
+                    hacks: vec![],
+                    category_counts: None,
+                    did_you_mean: vec![],
+                    autocorrected_query: None,
+                    debug: None,
+                    enrichment: Enrichment::new(),
+                    degraded: Degraded::new(),
+                },
+                error: Some(msg),
             }
         }
     }
+}
 
-    let total_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(Json(BulkSearchResponse {
-        results,
-        total_time_ms,
-    }))
+    fn empty_response() -> SearchResponse {
+        SearchResponse {
+            results: vec![],
+            total_candidates: 0,
+            candidates_scanned: 0,
+            total_hits: 0,
+            total_hits_estimated: false,
+            query_time_ms: 0.0,
+            cached: false,
+            partial: false,
+            hacks: vec![],
+            category_counts: None,
+            did_you_mean: vec![],
+            autocorrected_query: None,
+            debug: None,
+            enrichment: Enrichment::new(),
+            degraded: Degraded::new(),
+        }
+    }
+
+    #[test]
+    fn test_bulk_query_error_is_reported() {
+        let failed = BulkQueryResponse {
+            response: empty_response(),
+            error: Some("Query cannot be empty".to_string()),
+        };
+        let json = serde_json::to_value(&failed).unwrap();
+        assert_eq!(json["error"], "Query cannot be empty");
+        assert_eq!(json["total_candidates"], 0);
+
+        let ok = BulkQueryResponse {
+            response: empty_response(),
+            error: None,
+        };
+        let json = serde_json::to_value(&ok).unwrap();
+        assert!(json.get("error").is_none());
+    }
+
+    fn results(domains: &[&str], total_candidates: usize) -> SearchResults {
+        SearchResults {
+            results: domains
+                .iter()
+                .map(|domain| {
+                    let (label, tld) = domain.split_once('.').unwrap();
+                    SearchResult {
+                        domain: domain_search::DomainResult {
+                            domain: domain.to_string(),
+                            label: label.to_string(),
+                            tld: tld.to_string(),
+                            length: label.len() as u64,
+                            has_hyphen: false,
+                            tokens: vec![],
+                            dga_score: 0.0,
+                            lang: None,
+                            categories: vec![],
+                            fields: Fields::ALL,
+                        },
+                        match_count: 1,
+                        score: 1.0,
+                        compound_match: false,
+                        highlighted: None,
+                        also_in: vec![],
+                        explanation: None,
+                        index: None,
+                    }
+                })
+                .collect(),
+            total_candidates,
+            candidates_scanned: total_candidates,
+            total_hits: total_candidates as u64,
+            total_hits_estimated: false,
+            token_weights: vec![],
+            query: None,
+            partial: false,
+            hacks: vec![],
+            category_counts: None,
+            did_you_mean: vec![],
+            autocorrected: None,
+        }
+    }
+
+    #[test]
+    fn test_analyze_query_limits() {
+        let mut config = Config::test();
+        config.max_query_tokens = 3;
+        config.max_query_length = 20;
+        let analyzer = QueryAnalyzer::default();
+        let query = analyze_query(&config, &analyzer, "Best-Coffee  shop").unwrap();
+        assert_eq!(query, "best coffee shop");
+
+        let (status, msg) = analyze_query(&config, &analyzer, "a b c-d").unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(msg, "Query has 4 keywords; at most 3 are allowed");
+        assert!(analyze_query(&config, &analyzer, &"x".repeat(21)).is_err());
+
+        config.max_query_tokens = 0;
+        config.max_query_length = 0;
+        assert!(analyze_query(&config, &analyzer, &"word ".repeat(200)).is_ok());
+    }
+
+    #[test]
+    fn test_check_limit() {
+        let mut config = Config::test();
+        config.max_search_limit = 100;
+        assert_eq!(check_limit(&config, 1).unwrap(), 1);
+        assert_eq!(check_limit(&config, 100).unwrap(), 100);
+        let (status, msg) = check_limit(&config, 1_000_000).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(msg, "limit must be between 1 and 100");
+        assert!(check_limit(&config, 0).is_err());
+    }
+
+    #[test]
+    fn test_sort_and_dga_overrides() {
+        assert_eq!(
+            parse_sort("dga_score, length").unwrap(),
+            vec![SortKey::DgaScore, SortKey::Length]
+        );
+        assert_eq!(parse_sort("random").unwrap_err().0, StatusCode::BAD_REQUEST);
+        assert!(parse_sort(",").is_err());
+
+        let mut profile = RankingProfile::default();
+        profile.filters.max_dga_score = Some(0.4);
+        apply_max_dga_score(&mut profile, Some(0.6)).unwrap();
+        assert_eq!(profile.filters.max_dga_score, Some(0.4));
+        apply_max_dga_score(&mut profile, Some(0.2)).unwrap();
+        assert_eq!(profile.filters.max_dga_score, Some(0.2));
+        assert!(apply_max_dga_score(&mut profile, Some(1.5)).is_err());
+
+        apply_hyphen_ratio(&mut profile, 0.0).unwrap();
+        assert_eq!(profile.hyphen_ratio, 0.0);
+        assert!(apply_hyphen_ratio(&mut profile, -0.1).is_err());
+    }
+
+    #[test]
+    fn test_merge_results_alternates_indexes() {
+        let searched = vec![
+            ("default".to_string(), results(&["a.com", "b.com", "c.com"], 3)),
+            ("dropped".to_string(), results(&["x.com", "a.com"], 2)),
+        ];
+        let merged = merge_results(searched, 4);
+
+        let domains: Vec<&str> = merged.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, vec!["a.com", "x.com", "b.com", "c.com"]);
+        assert_eq!(merged.results[1].index.as_deref(), Some("dropped"));
+        assert_eq!(merged.total_candidates, 5);
+        assert_eq!(merged.total_hits, 5);
+
+        let single = merge_results(vec![("default".to_string(), results(&["a.com"], 1))], 4);
+        assert_eq!(single.results[0].index, None);
+    }
+
+    #[tokio::test]
+    async fn test_search_synthetic_index() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 2000);
+
+        let uri = "/search?q=coffee&tld=com&limit=10".parse().unwrap();
+        let Json(response) = search(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+            .await
+            .unwrap();
+        assert!(!response.results.is_empty());
+        assert!(response.results.len() <= 10);
+        assert!(response.results.iter().all(|r| {
+            r.domain.tld == "com" && r.domain.tokens.iter().any(|t| t == "coffee")
+        }));
+
+        let uri = "/search?q=coffee&sort=bogus".parse().unwrap();
+        let Err((status, _)) = search(State(state), Query::try_from_uri(&uri).unwrap()).await
+        else {
+            panic!("unknown sort key accepted");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_search_selects_fields() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+
+        let uri = "/search?q=coffee&limit=5&fields=tld,length".parse().unwrap();
+        let Json(response) = search(State(state.clone()), Query::try_from_uri(&uri).unwrap())
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        let first = json["results"][0].as_object().unwrap();
+        assert!(first.contains_key("domain") && first.contains_key("length"));
+        assert!(first.contains_key("score"));
+        assert!(!first.contains_key("tokens") && !first.contains_key("label"));
+
+        let uri = "/search?q=coffee&fields=tld,bogus".parse().unwrap();
+        let Err((status, _)) = search(State(state), Query::try_from_uri(&uri).unwrap()).await
+        else {
+            panic!("unknown field accepted");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    #[tokio::test]
+    async fn test_bulk_per_query_options() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 500);
+        let request: BulkSearchRequest = serde_json::from_value(serde_json::json!({
+            "queries": [
+                {"q": "coffee"},
+                {"q": "coffee", "limit": 2, "tld": "com", "fields": "tld"},
+            ],
+            "limit": 5,
+        }))
+        .unwrap();
+
+        let response = run_bulk(&state, request).await.unwrap();
+        let (all, narrowed) = (&response.results[0].response, &response.results[1].response);
+        assert!(all.results.len() > 2);
+        assert!(narrowed.results.len() <= 2);
+        assert!(narrowed.results.iter().all(|r| r.domain.tld == "com"));
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["results"][0]["results"][0].get("tokens").is_some());
+        assert!(json["results"][1]["results"][0].get("tokens").is_none());
+
+        let request = BulkSearchRequest {
+            queries: vec![BulkQuery {
+                q: "coffee".to_string(),
+                limit: Some(1_000_000),
+                ..Default::default()
+            }],
+            ..serde_json::from_value(serde_json::json!({"queries": []})).unwrap()
+        };
+        let (status, msg) = run_bulk(&state, request).await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(msg.starts_with("Query 0: limit"));
+    }
 }
@@ -0,0 +1,162 @@
+use crate::budget::{Degraded, Upstream};
+use crate::enrich::{self, Enrichment};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+use watchlist::{Alert, WatchRule, WatchStore};
+
+/// Most alerts returned by one `/alerts` request
+const MAX_ALERTS_LIMIT: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AlertsQuery {
+    /// Only alerts raised by this rule id
+    pub rule: Option<String>,
+
+    /// Maximum alerts to return, newest first (at most 1000)
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Add RDAP registration data (registrar, creation date, status) for the
+    /// newest `RDAP_ENRICH_LIMIT` alerted domains under `enrichment`
+    #[serde(default)]
+    pub enrich: bool,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AlertsResponse {
+    pub alerts: Vec<Alert>,
+    /// Registration data by domain, with `enrich=true`
+    #[serde(skip_serializing_if = "Enrichment::is_empty")]
+    pub enrichment: Enrichment,
+    /// Upstreams whose per-minute budget ran out, leaving enrichment
+    /// incomplete
+    #[serde(skip_serializing_if = "Degraded::is_empty")]
+    #[schema(value_type = Vec<Upstream>)]
+    pub degraded: Degraded,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct WatchRulesResponse {
+    pub rules: Vec<WatchRule>,
+}
+
+fn store(state: &AppState) -> Result<&WatchStore, (StatusCode, String)> {
+    state.watch.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "Watchlist is not enabled (set REDIS_URL and WATCHLIST_KEY)".to_string(),
+        )
+    })
+}
+
+fn store_error(e: watchlist::Error) -> (StatusCode, String) {
+    match e {
+        watchlist::Error::InvalidRule(message) => (StatusCode::BAD_REQUEST, message),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, format!("Watchlist error: {}", e)),
+    }
+}
+
+/// Alerts raised by watch rules during daily syncs, newest first
+#[utoipa::path(
+    get,
+    path = "/alerts",
+    tag = "watch",
+    params(AlertsQuery),
+    responses(
+        (status = 200, description = "Recent alerts", body = AlertsResponse),
+        (status = 404, description = "Watchlist is not enabled"),
+    )
+)]
+pub async fn alerts(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AlertsQuery>,
+) -> Result<Json<AlertsResponse>, (StatusCode, String)> {
+    let limit = params.limit.min(MAX_ALERTS_LIMIT);
+    let store = store(&state)?;
+    let rdap = enrich::client(&state, params.enrich)?;
+    let alerts = store
+        .alerts(limit, params.rule.as_deref())
+        .await
+        .map_err(store_error)?;
+
+    let domains = alerts.iter().map(|alert| alert.domain.as_str());
+    let mut degraded = Degraded::new();
+    let enrichment = enrich::lookup(&state, rdap, domains, &mut degraded).await;
+    Ok(Json(AlertsResponse {
+        alerts,
+        enrichment,
+        degraded,
+    }))
+}
+
+/// List the watch rules
+#[utoipa::path(
+    get,
+    path = "/admin/watch",
+    tag = "watch",
+    responses(
+        (status = 200, description = "Stored rules", body = WatchRulesResponse),
+        (status = 404, description = "Watchlist is not enabled"),
+    )
+)]
+pub async fn list_rules(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<WatchRulesResponse>, (StatusCode, String)> {
+    let rules = store(&state)?.rules().await.map_err(store_error)?;
+    Ok(Json(WatchRulesResponse { rules }))
+}
+
+/// Add a watch rule, evaluated from the next daily sync on
+#[utoipa::path(
+    post,
+    path = "/admin/watch",
+    tag = "watch",
+    request_body = WatchRule,
+    responses(
+        (status = 201, description = "Rule stored with its new id", body = WatchRule),
+        (status = 400, description = "Invalid rule"),
+        (status = 404, description = "Watchlist is not enabled"),
+    )
+)]
+pub async fn add_rule(
+    State(state): State<Arc<AppState>>,
+    Json(rule): Json<WatchRule>,
+) -> Result<(StatusCode, Json<WatchRule>), (StatusCode, String)> {
+    let rule = store(&state)?.add_rule(rule).await.map_err(store_error)?;
+    tracing::info!(id = rule.id, name = rule.name, "Watch rule added");
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// Delete a watch rule; alerts it already raised are kept
+#[utoipa::path(
+    delete,
+    path = "/admin/watch/{id}",
+    tag = "watch",
+    params(("id" = String, Path, description = "Rule id")),
+    responses(
+        (status = 204, description = "Rule deleted"),
+        (status = 404, description = "Unknown rule, or watchlist is not enabled"),
+    )
+)]
+pub async fn remove_rule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !store(&state)?.remove_rule(&id).await.map_err(store_error)? {
+        return Err((StatusCode::NOT_FOUND, format!("Unknown watch rule: {}", id)));
+    }
+    tracing::info!(id, "Watch rule removed");
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,146 @@
+use crate::budget::{Budget, Degraded, Upstream};
+use domain_core::Config;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+use word_client::WordClient;
+
+/// Longest wait for the word splitter before searching the query as typed
+const SPLIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Splits remembered, so a repeated query costs one word splitter call
+const SPLIT_CACHE_ENTRIES: u64 = 50_000;
+
+/// How long a split is remembered
+const SPLIT_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Expands single-keyword queries (`luxurywatchdeals`) into their words with
+/// the word splitter API, as labels were split at index time
+///
+/// Only a query that is one keyword of at least `QUERY_SPLIT_MIN_LENGTH`
+/// characters is sent. Splits are cached in memory; when the splitter fails,
+/// is slow or its budget is spent the query is searched as typed.
+pub struct QuerySplitter {
+    client: WordClient,
+    min_length: usize,
+    splits: moka::sync::Cache<String, Arc<Vec<String>>>,
+}
+
+impl QuerySplitter {
+    pub fn new(client: WordClient, min_length: usize) -> Self {
+        Self {
+            client,
+            min_length,
+            splits: moka::sync::Cache::builder()
+                .max_capacity(SPLIT_CACHE_ENTRIES)
+                .time_to_live(SPLIT_CACHE_TTL)
+                .build(),
+        }
+    }
+
+    /// Splitter calling `WORD_SPLITTER_URL`
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let client = WordClient::new(
+            &config.word_splitter_url,
+            &config.word_splitter_user,
+            &config.word_splitter_pass,
+            Some(1),
+            Some(1),
+        )?;
+        Ok(Self::new(client, config.query_split_min_length))
+    }
+
+    /// The analyzed query with a lone long keyword replaced by its words
+    ///
+    /// Cached splits are free; others take a call from the word splitter
+    /// budget, and the splitter is marked degraded when none is left.
+    pub async fn expand(&self, query: String, budget: &Budget, degraded: &mut Degraded) -> String {
+        if query.contains(' ')
+            || query.len() < self.min_length
+            || !query.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return query;
+        }
+        if let Some(words) = self.splits.get(&query) {
+            return words.join(" ");
+        }
+        if !budget.try_take(Upstream::WordSplitter, degraded) {
+            return query;
+        }
+
+        let words = match tokio::time::timeout(SPLIT_TIMEOUT, self.client.segment_single(&query))
+            .await
+        {
+            Ok(Ok(words)) if !words.is_empty() => words,
+            Ok(Ok(_)) => vec![query.clone()],
+            Ok(Err(e)) => {
+                warn!(query, error = %e, "Query split failed, searching it whole");
+                return query;
+            }
+            Err(_) => {
+                warn!(query, "Query split timed out, searching it whole");
+                return query;
+            }
+        };
+        debug!(query, words = ?words, "Query split");
+        let expanded = words.join(" ");
+        self.splits.insert(query, Arc::new(words));
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_expand_single_keyword() {
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "results": [{"label": "luxurywatchdeals", "segmentation": ["luxury", "watch", "deals"]}]
+        });
+        Mock::given(method("POST"))
+            .and(path("/segment/bulk"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            // The second expansion is served from the cache
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = WordClient::new(server.uri(), "user", "pass", None, None).unwrap();
+        let splitter = QuerySplitter::new(client, 8);
+        let budget = Budget::new(1, 0, 0);
+        let mut degraded = Degraded::new();
+
+        for _ in 0..2 {
+            let expanded = splitter.expand("luxurywatchdeals".into(), &budget, &mut degraded).await;
+            assert_eq!(expanded, "luxury watch deals");
+        }
+        // Short, several or non-alphabetic keywords aren't sent
+        for query in ["coffee", "coffee shopping", "xn--caf-dma"] {
+            assert_eq!(splitter.expand(query.into(), &budget, &mut degraded).await, query);
+        }
+        assert!(degraded.is_empty());
+        // The one call budgeted went to the first split
+        let expanded = splitter.expand("cheapflightdeals".into(), &budget, &mut degraded).await;
+        assert_eq!(expanded, "cheapflightdeals");
+        assert_eq!(degraded.into_iter().collect::<Vec<_>>(), vec![Upstream::WordSplitter]);
+    }
+
+    #[tokio::test]
+    async fn test_expand_falls_back_on_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        let client = WordClient::new(server.uri(), "user", "pass", None, None).unwrap();
+        let splitter = QuerySplitter::new(client, 8);
+
+        let budget = Budget::unlimited();
+        let mut degraded = Degraded::new();
+        let expanded = splitter.expand("luxurywatchdeals".into(), &budget, &mut degraded).await;
+        assert_eq!(expanded, "luxurywatchdeals");
+    }
+}
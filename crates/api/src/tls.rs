@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Load a PEM certificate chain and private key
+pub async fn load(cert: &Path, key: &Path) -> Result<RustlsConfig> {
+    // Several providers may be linked in; the first install wins
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert, key).await.with_context(|| {
+        format!("loading TLS certificate {} and key {}", cert.display(), key.display())
+    })
+}
+
+/// Load the files again, keeping the served certificate when they are broken
+///
+/// Returns whether the new certificate is now served; connections already
+/// open keep the one they were set up with.
+pub async fn reload(config: &RustlsConfig, cert: &Path, key: &Path) -> bool {
+    match config.reload_from_pem_file(cert, key).await {
+        Ok(()) => {
+            info!(cert = %cert.display(), "TLS certificate reloaded");
+            true
+        }
+        Err(e) => {
+            warn!(
+                cert = %cert.display(),
+                error = %e,
+                "Failed to reload TLS certificate, keeping the current one"
+            );
+            false
+        }
+    }
+}
+
+/// Reload the certificate on every SIGHUP, e.g. after a renewal
+#[cfg(unix)]
+async fn reload_on_hangup(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            warn!(error = %e, "Failed to listen for SIGHUP, TLS certificate won't reload");
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        reload(&config, &cert, &key).await;
+    }
+}
+
+#[cfg(not(unix))]
+async fn reload_on_hangup(_config: RustlsConfig, _cert: PathBuf, _key: PathBuf) {}
+
+/// Serve HTTPS on `addr` until `shutdown` completes, then drain open
+/// connections
+pub async fn serve(
+    addr: SocketAddr,
+    cert: &Path,
+    key: &Path,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    let config = load(cert, key).await?;
+    let reloader = tokio::spawn(reload_on_hangup(
+        config.clone(),
+        cert.to_path_buf(),
+        key.to_path_buf(),
+    ));
+
+    let handle = Handle::new();
+    let draining = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        draining.graceful_shutdown(None);
+    });
+
+    let served = axum_server::bind_rustls(addr, config)
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await;
+    reloader.abort();
+    Ok(served?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_certificate(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = dir.join(format!("{name}.pem"));
+        let key = dir.join(format!("{name}.key"));
+        std::fs::write(&cert, certified.cert.pem()).unwrap();
+        std::fs::write(&key, certified.key_pair.serialize_pem()).unwrap();
+        (cert, key)
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_certificate_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_certificate(dir.path(), "first");
+        let config = load(&cert, &key).await.unwrap();
+        let first = config.get_inner();
+
+        let (renewed_cert, renewed_key) = write_certificate(dir.path(), "renewed");
+        assert!(reload(&config, &renewed_cert, &renewed_key).await);
+        let renewed = config.get_inner();
+        assert!(!std::sync::Arc::ptr_eq(&first, &renewed));
+
+        std::fs::write(&renewed_cert, "not a certificate").unwrap();
+        assert!(!reload(&config, &renewed_cert, &renewed_key).await);
+        assert!(std::sync::Arc::ptr_eq(&renewed, &config.get_inner()));
+
+        assert!(load(&dir.path().join("missing.pem"), &key).await.is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::index::IndexHandle;
+use domain_core::domain::should_filter_domain;
+use domain_core::{
+    writer_lock, Config, Domain, DomainSchema, LabelValidation, NormalizedDomain, Taxonomy,
+};
+use std::sync::Arc;
+use tantivy::collector::Count;
+use tantivy::query::TermQuery;
+use tantivy::schema::IndexRecordOption;
+use tantivy::{IndexWriter, Term};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use word_client::{LocalSegmenter, Segmenter, WordClient};
+
+/// Writer heap per update; requests carry at most a few thousand domains
+const WRITER_HEAP: usize = 50 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    /// Another process, usually the indexer, holds the writer
+    #[error("{0}")]
+    Locked(String),
+
+    #[error("Word segmentation failed: {0}")]
+    Segmentation(#[from] word_client::Error),
+
+    #[error("Index error: {0}")]
+    Index(String),
+}
+
+impl From<domain_core::Error> for UpdateError {
+    fn from(e: domain_core::Error) -> Self {
+        match e {
+            domain_core::Error::WriterLocked { .. } => UpdateError::Locked(e.to_string()),
+            e => UpdateError::Index(e.to_string()),
+        }
+    }
+}
+
+impl From<tantivy::TantivyError> for UpdateError {
+    fn from(e: tantivy::TantivyError) -> Self {
+        UpdateError::Index(e.to_string())
+    }
+}
+
+/// What an update did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UpdateOutcome {
+    /// Domains written or deleted
+    pub applied: u64,
+    /// Domains skipped by the indexer's filters
+    pub filtered: u64,
+    /// Input that isn't a valid domain
+    pub invalid: Vec<String>,
+}
+
+/// Adds and deletes domains of the served index from the API process
+///
+/// The writer is only taken for the length of one update, so the indexer's
+/// daily sync can still run; updates made while it holds the writer fail
+/// with [`UpdateError::Locked`]. Domains are normalized, filtered, segmented
+/// and tagged as the daily sync would, but only the primary index is
+/// written: dated, history and watch outputs are left to the daily sync.
+pub struct IndexUpdater {
+    segmenter: Segmenter,
+    taxonomy: Taxonomy,
+    validation: LabelValidation,
+    /// Segment hostname labels separately (`INDEX_SUBDOMAIN_LABELS`)
+    subdomains: bool,
+    /// Stored with the tokens of every update (`SEGMENTATION_VERSION`)
+    segmentation_version: u64,
+    /// Serializes updates, which would otherwise race for the writer
+    lock: Mutex<()>,
+}
+
+impl IndexUpdater {
+    pub fn new(segmenter: Segmenter, taxonomy: Taxonomy) -> Self {
+        Self {
+            segmenter,
+            taxonomy,
+            validation: LabelValidation::Strict,
+            subdomains: false,
+            segmentation_version: 1,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Updater segmenting with `WORD_LIST_FILE` or else the word splitter
+    /// API, tagging with `TAXONOMY_FILE` and checking labels as
+    /// `LABEL_VALIDATION` says
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let segmenter = match &config.word_list_file {
+            Some(path) => Segmenter::Local(LocalSegmenter::load(path)?.into()),
+            None => Segmenter::Remote(WordClient::new(
+                &config.word_splitter_url,
+                &config.word_splitter_user,
+                &config.word_splitter_pass,
+                Some(config.word_batch_size),
+                Some(4),
+            )?),
+        };
+        let taxonomy = match &config.taxonomy_file {
+            Some(path) => Taxonomy::load(path)?,
+            None => Taxonomy::default(),
+        };
+        Ok(Self {
+            validation: config.label_validation,
+            subdomains: config.index_subdomain_labels,
+            segmentation_version: config.segmentation_version,
+            ..Self::new(segmenter, taxonomy)
+        })
+    }
+
+    /// Index domains, replacing any already indexed
+    pub async fn add(
+        &self,
+        handle: Arc<IndexHandle>,
+        schema: &DomainSchema,
+        domains: &[String],
+    ) -> Result<UpdateOutcome, UpdateError> {
+        let (mut normalized, mut outcome) = normalize(domains, self.validation);
+        normalized.retain(|domain| !should_filter_domain(&domain.label));
+        outcome.filtered = (domains.len() - outcome.invalid.len() - normalized.len()) as u64;
+
+        if !normalized.is_empty() {
+            let labels = normalized.iter().map(|d| d.segment_source(self.subdomains)).collect();
+            let segments = self.segmenter.segment_batch(labels).await?;
+            for (domain, (_, tokens)) in normalized.iter_mut().zip(segments) {
+                domain.categories = self.taxonomy.tag(&domain.label, &tokens);
+                domain.tokens = tokens;
+                domain.seg_version = self.segmentation_version;
+            }
+        }
+
+        let _guard = self.lock.lock().await;
+        let schema = schema.clone();
+        outcome.applied = spawn_write(handle, move |writer| {
+            for domain in &normalized {
+                let term = Term::from_field_text(schema.domain_exact, &domain.domain_exact);
+                writer.delete_term(term);
+                writer.add_document(schema.to_document(domain))?;
+            }
+            Ok(normalized.len() as u64)
+        })
+        .await?;
+        Ok(outcome)
+    }
+
+    /// Delete domains, counting the ones that were indexed
+    pub async fn remove(
+        &self,
+        handle: Arc<IndexHandle>,
+        schema: &DomainSchema,
+        domains: &[String],
+    ) -> Result<UpdateOutcome, UpdateError> {
+        let (normalized, mut outcome) = normalize(domains, self.validation);
+
+        let _guard = self.lock.lock().await;
+        let schema = schema.clone();
+        let searcher = handle.reader.searcher();
+        outcome.applied = spawn_write(handle, move |writer| {
+            let mut deleted = 0;
+            for domain in &normalized {
+                let term = Term::from_field_text(schema.domain_exact, &domain.domain_exact);
+                let query = TermQuery::new(term.clone(), IndexRecordOption::Basic);
+                if searcher.search(&query, &Count)? > 0 {
+                    deleted += 1;
+                }
+                writer.delete_term(term);
+            }
+            Ok(deleted)
+        })
+        .await?;
+        Ok(outcome)
+    }
+}
+
+/// Normalize raw domains, setting aside the invalid ones
+fn normalize(
+    domains: &[String],
+    validation: LabelValidation,
+) -> (Vec<NormalizedDomain>, UpdateOutcome) {
+    let mut outcome = UpdateOutcome::default();
+    let mut normalized = Vec::with_capacity(domains.len());
+    for raw in domains {
+        match Domain::new(raw).normalize_with(validation) {
+            Ok(domain) => normalized.push(domain),
+            Err(_) => outcome.invalid.push(raw.clone()),
+        }
+    }
+    (normalized, outcome)
+}
+
+/// Take the writer of the served index, apply `write` and commit, off the
+/// async runtime
+async fn spawn_write<F>(handle: Arc<IndexHandle>, write: F) -> Result<u64, UpdateError>
+where
+    F: FnOnce(&mut IndexWriter) -> tantivy::Result<u64> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut writer: IndexWriter =
+            writer_lock::open_writer(&handle.index, &handle.path, WRITER_HEAP)?;
+        let applied = write(&mut writer)?;
+        writer.commit()?;
+        writer.wait_merging_threads()?;
+        Ok(applied)
+    })
+    .await
+    .map_err(|e| UpdateError::Index(format!("Task error: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+    use domain_search::SearchRequest;
+
+    fn updater() -> IndexUpdater {
+        IndexUpdater::new(
+            Segmenter::Local(LocalSegmenter::new(["coffee", "shop"]).into()),
+            Taxonomy::default(),
+        )
+    }
+
+    fn domains(domains: &[&str]) -> Vec<String> {
+        domains.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 100);
+        let updater = updater();
+        let before = state.index().reader.searcher().num_docs();
+
+        let added = updater
+            .add(state.index(), &state.schema, &domains(&["CoffeeShop.com", "not a domain"]))
+            .await
+            .unwrap();
+        assert_eq!(added.applied, 1);
+        assert_eq!(added.invalid, vec!["not a domain"]);
+
+        state.reload().unwrap();
+        let searcher = state.index().reader.searcher();
+        assert_eq!(searcher.num_docs(), before + 1);
+        let request = SearchRequest::new("coffee shop", 100);
+        let results = state.index().searcher.search(&request).unwrap();
+        assert!(results.results.iter().any(|r| r.domain.domain == "coffeeshop.com"));
+
+        let removed = updater
+            .remove(state.index(), &state.schema, &domains(&["coffeeshop.com", "gone.io"]))
+            .await
+            .unwrap();
+        assert_eq!(removed.applied, 1);
+        state.reload().unwrap();
+        assert_eq!(state.index().reader.searcher().num_docs(), before);
+    }
+
+    #[tokio::test]
+    async fn test_locked_by_indexer() {
+        let directory = tempfile::tempdir().unwrap();
+        let state = AppState::synthetic(directory.path(), 10);
+        let _writer: IndexWriter =
+            writer_lock::open_writer(&state.index().index, directory.path(), 15_000_000).unwrap();
+
+        let error = updater()
+            .add(state.index(), &state.schema, &domains(&["coffee.com"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, UpdateError::Locked(_)), "{}", error);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::routes::search::{search_through_cache, SearchQuery};
+use crate::AppState;
+use domain_search::SearchRequest;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Results requested per warmup query, matching the `/search` default
+const WARMUP_LIMIT: usize = 50;
+
+/// Run representative searches so the first real ones don't pay for cold
+/// page cache and term dictionaries, then mark the instance ready
+///
+/// Queries come from `WARMUP_QUERIES_FILE` followed by the most popular
+/// persisted queries (`WARMUP_POPULAR_QUERIES`). Results are discarded and
+/// neither cached nor counted in the query analytics.
+///
+/// With `CACHE_REPLAY_QUERIES` set, the most popular queries are then
+/// replayed into the response cache while the instance already serves.
+pub async fn run(state: Arc<AppState>) {
+    warm_up(&state).await;
+    state.ready.store(true, Ordering::Relaxed);
+
+    let count = state.config.cache_replay_queries;
+    if count > 0 && state.cache.is_some() {
+        let concurrency = state.config.bulk_concurrency;
+        if let Err(e) = replay(&state, count, concurrency, |_| {}).await {
+            warn!(error = %e, "Cache replay skipped");
+        }
+    }
+}
+
+/// Queries searched by a warmup
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct WarmupSummary {
+    pub queries: usize,
+    pub failed: usize,
+    pub elapsed_ms: u64,
+}
+
+/// Search the warmup queries once, as at startup
+pub async fn warm_up(state: &Arc<AppState>) -> WarmupSummary {
+    let mut queries = match &state.config.warmup_queries_file {
+        Some(path) => match load_queries(path) {
+            Ok(queries) => queries,
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Failed to read warmup queries");
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+    if let (Some(analytics), Some(cache)) = (&state.analytics, &state.cache) {
+        let popular = analytics
+            .popular(cache, state.config.warmup_popular_queries)
+            .await;
+        for query in popular {
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+    }
+
+    if queries.is_empty() {
+        return WarmupSummary::default();
+    }
+
+    let start = Instant::now();
+    let count = queries.len();
+    info!(queries = count, "Warming up index");
+
+    let warm_state = state.clone();
+    let failed = tokio::task::spawn_blocking(move || warm(&warm_state, &queries))
+        .await
+        .unwrap_or(count);
+
+    let summary = WarmupSummary {
+        queries: count,
+        failed,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    };
+    info!(
+        queries = count,
+        failed,
+        elapsed_ms = summary.elapsed_ms,
+        "Warmup complete"
+    );
+    summary
+}
+
+/// Queries of a cache replay searched so far; the replay's result once done
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReplayProgress {
+    /// Queries to replay
+    pub queries: usize,
+    /// Queries searched so far
+    pub done: usize,
+    /// Queries whose response was still cached
+    pub already_cached: usize,
+    pub failed: usize,
+    pub elapsed_ms: u64,
+}
+
+/// Search the `count` most popular persisted queries through the response
+/// cache, `concurrency` at a time, so their next requests are cache hits,
+/// reporting progress after each query
+///
+/// Each query is searched as a bare `/search?q=` request, the form most of
+/// them arrived in. Replays aren't counted in the query analytics.
+pub async fn replay(
+    state: &AppState,
+    count: usize,
+    concurrency: usize,
+    progress: impl Fn(&ReplayProgress),
+) -> Result<ReplayProgress, String> {
+    let (Some(analytics), Some(cache)) = (&state.analytics, &state.cache) else {
+        return Err("Replaying queries needs REDIS_URL and QUERY_STATS_REDIS_KEY".to_string());
+    };
+    if !analytics.persisted() {
+        return Err("Replaying queries needs QUERY_STATS_REDIS_KEY".to_string());
+    }
+
+    let start = Instant::now();
+    let queries = analytics.popular(cache, count).await;
+    let mut summary = ReplayProgress {
+        queries: queries.len(),
+        ..ReplayProgress::default()
+    };
+    progress(&summary);
+    info!(queries = summary.queries, concurrency, "Replaying popular queries into the cache");
+
+    let mut searches = stream::iter(queries)
+        .map(|q| async move { search_through_cache(state, &SearchQuery::new(q)).await })
+        .buffer_unordered(concurrency.max(1));
+    while let Some(outcome) = searches.next().await {
+        summary.done += 1;
+        match outcome {
+            Ok(response) if response.cached => summary.already_cached += 1,
+            Ok(_) => {}
+            Err(_) => summary.failed += 1,
+        }
+        summary.elapsed_ms = start.elapsed().as_millis() as u64;
+        progress(&summary);
+    }
+
+    info!(
+        queries = summary.queries,
+        already_cached = summary.already_cached,
+        failed = summary.failed,
+        elapsed_ms = summary.elapsed_ms,
+        "Cache replay complete"
+    );
+    Ok(summary)
+}
+
+/// Search each query with the default profile, returning how many failed
+fn warm(state: &AppState, queries: &[String]) -> usize {
+    let Ok((_, profile)) = state.profiles.get(None) else {
+        return queries.len();
+    };
+    let index = state.index();
+
+    queries
+        .iter()
+        .filter(|query| {
+            let mut request = SearchRequest::new(state.analyzer.normalize(query), WARMUP_LIMIT);
+            request.profile = profile.clone();
+            request.stop_tokens = state.config.stop_tokens.clone();
+            index.searcher.search(&request).is_err()
+        })
+        .count()
+}
+
+/// One query per line; blank lines and `#` comments are skipped
+fn load_queries(path: &Path) -> std::io::Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(parse_queries(&contents))
+}
+
+fn parse_queries(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_queries_skips_comments() {
+        let queries = parse_queries("# popular\ncloud hosting\n\n  pet shop  \n#coffee\n");
+        assert_eq!(queries, vec!["cloud hosting", "pet shop"]);
+    }
+}
@@ -1,7 +1,13 @@
+use crate::domain::LabelValidation;
 use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::env;
-use std::path::PathBuf;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+/// Stop tokens used when `STOP_TOKENS` is unset
+const DEFAULT_STOP_TOKENS: &str = "the,a,an,my,our,your,of,and,or,for,to,in,on,at,by,online";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -14,21 +20,265 @@ pub struct Config {
     /// Word splitter API password
     pub word_splitter_pass: String,
 
+    /// Word list (most frequent first) for segmenting labels locally instead
+    /// of through the word splitter API
+    pub word_list_file: Option<PathBuf>,
+
     /// Zonefile API token
     pub zonefile_token: String,
 
     /// Zonefile API base URL
     pub zonefile_api_url: String,
 
+    /// Directory under which each indexer run downloading zonefiles gets its
+    /// own subdirectory
+    pub work_dir: PathBuf,
+
     /// Path to the Tantivy index
     pub index_path: PathBuf,
 
-    /// Redis URL for caching
+    /// Directory `/admin/index/swap` may promote indexes from (None: the
+    /// one holding `index_path`)
+    pub index_root: Option<PathBuf>,
+
+    /// Additional named indices served alongside `index_path`
+    pub indexes: Vec<(String, PathBuf)>,
+
+    /// Index of domains removed by daily updates, with their drop date
+    pub dropped_index_path: Option<PathBuf>,
+
+    /// Index of domains first seen in daily updates, with that date
+    pub new_domains_index_path: Option<PathBuf>,
+
+    /// Index of per-domain lifecycle events recorded by daily updates
+    pub history_index_path: Option<PathBuf>,
+
+    /// CSV file of daily per-TLD document counts recorded by daily updates
+    pub tld_stats_path: Option<PathBuf>,
+
+    /// Cap on bytes written per second by daily updates, in MB (0 = unlimited)
+    pub daily_write_limit_mb: u64,
+
+    /// Defer large segment merges during daily updates to `optimize`
+    pub daily_low_priority_merges: bool,
+
+    /// Daily window (UTC, `HH:MM-HH:MM`) in which daily updates merge the
+    /// index's segments once it crosses an optimize threshold (unset disables)
+    pub optimize_window: Option<String>,
+
+    /// Segments that make daily updates merge the index (0 = no limit)
+    pub optimize_max_segments: usize,
+
+    /// Share of deleted documents that makes daily updates merge the index
+    /// (0 = no limit)
+    pub optimize_deleted_ratio: f64,
+
+    /// Redis URL for caching (`rediss://` for TLS); with `REDIS_MODE`
+    /// `cluster` or `sentinel`, comma-separated seed nodes or sentinels
     pub redis_url: Option<String>,
 
+    /// How the Redis servers in `REDIS_URL` are deployed
+    pub redis_mode: RedisMode,
+
+    /// Redis ACL username, overriding the one in `REDIS_URL`
+    pub redis_username: Option<String>,
+
+    /// Redis password, overriding the one in `REDIS_URL`
+    pub redis_password: Option<String>,
+
+    /// Master name the sentinels monitor, with `REDIS_MODE=sentinel`
+    pub redis_sentinel_master: Option<String>,
+
+    /// Multiplexed connections to a standalone or sentinel-managed Redis,
+    /// used in turn
+    pub redis_pool_size: usize,
+
+    /// Milliseconds a cache read or write waits for Redis before counting
+    /// as failed (0 waits indefinitely)
+    pub redis_timeout_ms: u64,
+
+    /// Share of recent Redis calls failing that opens the circuit breaker,
+    /// skipping Redis until it cools down (0 disables the breaker)
+    pub redis_breaker_failure_rate: f64,
+
+    /// Seconds the breaker stays open before a probe call is let through
+    pub redis_breaker_cooldown_secs: u64,
+
+    /// TTL in seconds for cached /search responses
+    pub cache_ttl: u64,
+
+    /// TTL in seconds for cached /exact responses
+    pub cache_ttl_exact: u64,
+
+    /// Prefix for all cache keys
+    pub cache_key_prefix: String,
+
+    /// Cached values at least this many bytes of JSON are stored in Redis
+    /// zstd-compressed (0 stores every value uncompressed)
+    pub cache_compress_min_bytes: usize,
+
+    /// Maximum entries in the in-process response cache (0 disables it)
+    pub memory_cache_entries: u64,
+
+    /// TTL in seconds for the in-process response cache
+    pub memory_cache_ttl: u64,
+
     /// API server port
     pub api_port: u16,
 
+    /// Where the API listens, `0.0.0.0:api_port` unless `API_BIND` is set
+    pub api_bind: BindAddress,
+
+    /// PEM certificate chain served over HTTPS, with `tls_key_file`
+    /// (None serves plain HTTP)
+    pub tls_cert_file: Option<PathBuf>,
+
+    /// PEM private key of `tls_cert_file`
+    pub tls_key_file: Option<PathBuf>,
+
+    /// gRPC server port (None disables gRPC)
+    pub grpc_port: Option<u16>,
+
+    /// Per-request timeout in milliseconds
+    pub request_timeout_ms: u64,
+
+    /// Default time budget for ranking one search in milliseconds (0 = none)
+    pub search_timeout_ms: u64,
+
+    /// Matching documents a search may scan, over every pass, collecting
+    /// more candidates for a result list short of its limit (0 = one pass)
+    pub search_max_scanned: usize,
+
+    /// Maximum concurrently processed requests; excess requests get 503
+    pub max_concurrent_requests: usize,
+
+    /// Maximum request body size in bytes for /search/bulk
+    pub bulk_body_limit: usize,
+
+    /// Queries of one /search/bulk request executed concurrently
+    pub bulk_concurrency: usize,
+
+    /// Most keywords in one search query (0 = unlimited)
+    pub max_query_tokens: usize,
+
+    /// Longest search query in characters (0 = unlimited)
+    pub max_query_length: usize,
+
+    /// Largest `limit` one search may ask for; larger ones get 400
+    pub max_search_limit: usize,
+
+    /// Split concatenated query words (`bestcoffee`) with `WORD_LIST_FILE`
+    pub query_segmentation: bool,
+
+    /// Split single-keyword queries with the word splitter API
+    pub query_splitter: bool,
+
+    /// Shortest single-keyword query sent to the word splitter
+    pub query_split_min_length: usize,
+
+    /// API keys accepted by the server (empty disables auth)
+    pub api_keys: Vec<String>,
+
+    /// JSON file with per-key names, limits and admin flags
+    pub api_keys_file: Option<PathBuf>,
+
+    /// Default per-key requests per minute (0 = unlimited)
+    pub rate_limit_per_minute: u32,
+
+    /// Default per-key requests per day (0 = unlimited)
+    pub daily_quota: u64,
+
+    /// Serve Swagger UI at /docs
+    pub openapi_ui: bool,
+
+    /// Log one summary event per API request (target `access`)
+    pub access_log: bool,
+
+    /// Compress API responses for clients accepting gzip or brotli
+    pub compression: bool,
+
+    /// Smallest response body compressed, in bytes (at most 65535)
+    pub compression_min_bytes: usize,
+
+    /// Accept domain additions and deletions at /admin/domains
+    pub admin_writes: bool,
+
+    /// Directory receiving the domain lists of export jobs (None disables them)
+    pub export_dir: Option<PathBuf>,
+
+    /// JSON file with named ranking profiles (merged over the built-ins)
+    pub ranking_profiles_file: Option<PathBuf>,
+
+    /// Ranking profile used when a request doesn't name one
+    pub ranking_profile: String,
+
+    /// JSON file overriding the `/appraise` formula weights and value bands
+    pub appraisal_weights_file: Option<PathBuf>,
+
+    /// JSON file mapping categories to tokens, tagged onto domains at index time
+    pub taxonomy_file: Option<PathBuf>,
+
+    /// Directory keeping the checkpoint, status and reject log of full builds
+    /// (None keeps no sidecar state)
+    pub state_dir: Option<PathBuf>,
+
+    /// Filler tokens ignored by `min_match` and down-weighted in ranking
+    pub stop_tokens: Vec<String>,
+
+    /// Distinct queries tracked by query analytics (0 disables analytics)
+    pub query_stats_max: usize,
+
+    /// Redis key base under which query counts are persisted (None keeps them in memory)
+    pub query_stats_redis_key: Option<String>,
+
+    /// File of queries (one per line) searched at startup before reporting ready
+    pub warmup_queries_file: Option<PathBuf>,
+
+    /// Most popular persisted queries also searched at startup
+    pub warmup_popular_queries: usize,
+
+    /// Most popular persisted queries searched into the response cache at
+    /// startup, after the warmup (0 = none)
+    pub cache_replay_queries: usize,
+
+    /// Redis key base for watch rules and alerts (None disables the watchlist)
+    pub watchlist_key: Option<String>,
+
+    /// Alerts kept in Redis, newest first
+    pub watchlist_max_alerts: usize,
+
+    /// Webhook receiving alerts of rules without their own
+    pub watchlist_webhook_url: Option<String>,
+
+    /// Webhooks told when full builds and daily syncs finish, daily syncs
+    /// optimize, downloads fail or an index has another schema or can't be
+    /// read or written
+    pub pipeline_webhook_urls: Vec<String>,
+
+    /// RDAP service for `enrich=true` lookups (None disables enrichment)
+    pub rdap_url: Option<String>,
+
+    /// RDAP lookups sent per second
+    pub rdap_rate_limit: u32,
+
+    /// Maximum RDAP lookups kept in memory
+    pub rdap_cache_entries: u64,
+
+    /// TTL in seconds for cached RDAP lookups
+    pub rdap_cache_ttl: u64,
+
+    /// Most domains enriched per response
+    pub rdap_enrich_limit: usize,
+
+    /// Word splitter calls per minute for query splitting (0 = unlimited)
+    pub word_splitter_budget: u32,
+
+    /// RDAP lookups per minute for enrichment and availability (0 = unlimited)
+    pub rdap_budget: u32,
+
+    /// DNS lookups per minute for availability (0 = unlimited)
+    pub dns_budget: u32,
+
     /// IndexWriter heap size in bytes (default: 4GB)
     pub index_heap_size: usize,
 
@@ -37,40 +287,445 @@ pub struct Config {
 
     /// Batch size for indexing commits
     pub index_batch_size: usize,
+
+    /// TLDs indexed by `full` and `daily` (empty indexes every TLD)
+    pub include_tlds: Vec<String>,
+
+    /// TLDs never indexed by `full` and `daily`
+    pub exclude_tlds: Vec<String>,
+
+    /// How `full` and `daily` check the characters of domain labels
+    pub label_validation: LabelValidation,
+
+    /// Segment each label of a hostname left of its public suffix
+    /// (`shop.example.co.uk` → `shop`, `example`) instead of the whole label
+    pub index_subdomain_labels: bool,
+
+    /// Version of the word segmentation, stored with every document it
+    /// segments; bump it after changing the word list or splitter so
+    /// `retokenize` segments older documents again
+    pub segmentation_version: u64,
 }
 
 impl Config {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self> {
+        Self::load(false)
+    }
+
+    /// Load configuration for offline indexing, where the word splitter and
+    /// zonefile API credentials aren't needed
+    pub fn from_env_offline() -> Result<Self> {
+        Self::load(true)
+    }
+
+    /// Certificate and key to serve HTTPS with, None for plain HTTP
+    ///
+    /// Both files or neither must be set, and only for a TCP `API_BIND`.
+    pub fn tls_files(&self) -> Result<Option<(&Path, &Path)>> {
+        match (&self.tls_cert_file, &self.tls_key_file) {
+            (None, None) => Ok(None),
+            (Some(_), Some(_)) if matches!(self.api_bind, BindAddress::Unix(_)) => Err(
+                Error::Config("TLS_CERT_FILE can't be served on a Unix socket".to_string()),
+            ),
+            (Some(cert), Some(key)) => Ok(Some((cert, key))),
+            _ => Err(Error::Config(
+                "TLS_CERT_FILE and TLS_KEY_FILE must be set together".to_string(),
+            )),
+        }
+    }
+
+    fn load(offline: bool) -> Result<Self> {
         dotenvy::dotenv().ok();
 
+        // Credentials of the remote services; empty when offline
+        let credential = |name: &str| match env::var(name) {
+            Ok(value) => Ok(value),
+            Err(_) if offline => Ok(String::new()),
+            Err(_) => Err(Error::Config(format!("{} not set", name))),
+        };
+
         Ok(Self {
             word_splitter_url: env::var("WORD_SPLITTER_URL")
                 .unwrap_or_else(|_| "https://moustafamahdy.xyz/word-splitter-api".to_string()),
 
-            word_splitter_user: env::var("WORD_SPLITTER_USER")
-                .map_err(|_| Error::Config("WORD_SPLITTER_USER not set".to_string()))?,
+            word_splitter_user: credential("WORD_SPLITTER_USER")?,
 
-            word_splitter_pass: env::var("WORD_SPLITTER_PASS")
-                .map_err(|_| Error::Config("WORD_SPLITTER_PASS not set".to_string()))?,
+            word_splitter_pass: credential("WORD_SPLITTER_PASS")?,
 
-            zonefile_token: env::var("ZONEFILE_TOKEN")
-                .map_err(|_| Error::Config("ZONEFILE_TOKEN not set".to_string()))?,
+            word_list_file: env::var("WORD_LIST_FILE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            zonefile_token: credential("ZONEFILE_TOKEN")?,
 
             zonefile_api_url: env::var("ZONEFILE_API_URL")
                 .unwrap_or_else(|_| "https://domains-monitor.com/api/v1".to_string()),
 
+            work_dir: env::var("WORK_DIR")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| env::temp_dir().join("zonefile-indexer")),
+
             index_path: env::var("INDEX_PATH")
                 .map(PathBuf::from)
                 .unwrap_or_else(|_| PathBuf::from("./data/index")),
 
+            index_root: env::var("INDEX_ROOT")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            indexes: env::var("INDEXES")
+                .map(|s| parse_indexes(&s))
+                .unwrap_or_else(|_| Ok(Vec::new()))?,
+
+            dropped_index_path: env::var("DROPPED_INDEX_PATH")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            new_domains_index_path: env::var("NEW_DOMAINS_INDEX_PATH")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            history_index_path: env::var("HISTORY_INDEX_PATH")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            tld_stats_path: env::var("TLD_STATS_PATH")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            daily_write_limit_mb: env::var("DAILY_WRITE_LIMIT_MB")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+
+            daily_low_priority_merges: env::var("DAILY_LOW_PRIORITY_MERGES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
+            optimize_window: env::var("OPTIMIZE_WINDOW").ok().filter(|s| !s.is_empty()),
+
+            optimize_max_segments: env::var("OPTIMIZE_MAX_SEGMENTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(50),
+
+            optimize_deleted_ratio: env::var("OPTIMIZE_DELETED_RATIO")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.1),
+
             redis_url: env::var("REDIS_URL").ok(),
 
+            redis_mode: match env::var("REDIS_MODE").ok().filter(|s| !s.is_empty()) {
+                Some(mode) => mode.parse()?,
+                None => RedisMode::Standalone,
+            },
+
+            redis_username: env::var("REDIS_USERNAME").ok().filter(|s| !s.is_empty()),
+
+            redis_password: env::var("REDIS_PASSWORD").ok().filter(|s| !s.is_empty()),
+
+            redis_sentinel_master: env::var("REDIS_SENTINEL_MASTER")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            redis_pool_size: env::var("REDIS_POOL_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&size| size > 0)
+                .unwrap_or(1),
+
+            redis_timeout_ms: env::var("REDIS_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(500),
+
+            redis_breaker_failure_rate: env::var("REDIS_BREAKER_FAILURE_RATE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|rate: &f64| (0.0..=1.0).contains(rate))
+                .unwrap_or(0.5),
+
+            redis_breaker_cooldown_secs: env::var("REDIS_BREAKER_COOLDOWN_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
+
+            cache_ttl: env::var("CACHE_TTL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(86400), // 24 hours
+
+            cache_ttl_exact: env::var("CACHE_TTL_EXACT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(3600), // 1 hour
+
+            cache_key_prefix: env::var("CACHE_KEY_PREFIX").unwrap_or_else(|_| "ds:".to_string()),
+
+            cache_compress_min_bytes: env::var("CACHE_COMPRESS_MIN_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(8192),
+
+            memory_cache_entries: env::var("MEMORY_CACHE_ENTRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5_000),
+
+            memory_cache_ttl: env::var("MEMORY_CACHE_TTL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(300), // 5 minutes
+
             api_port: env::var("API_PORT")
                 .ok()
                 .and_then(|p| p.parse().ok())
                 .unwrap_or(3000),
 
+            grpc_port: env::var("GRPC_PORT").ok().and_then(|p| p.parse().ok()),
+
+            api_bind: match env::var("API_BIND").ok().filter(|s| !s.is_empty()) {
+                Some(bind) => bind.parse()?,
+                None => BindAddress::any(
+                    env::var("API_PORT")
+                        .ok()
+                        .and_then(|p| p.parse().ok())
+                        .unwrap_or(3000),
+                ),
+            },
+
+            tls_cert_file: env::var("TLS_CERT_FILE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            tls_key_file: env::var("TLS_KEY_FILE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            request_timeout_ms: env::var("REQUEST_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30_000),
+
+            search_timeout_ms: env::var("SEARCH_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+
+            search_max_scanned: env::var("SEARCH_MAX_SCANNED")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1_000_000),
+
+            max_concurrent_requests: env::var("MAX_CONCURRENT_REQUESTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(256),
+
+            bulk_body_limit: env::var("BULK_BODY_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(256 * 1024), // 256KB
+
+            bulk_concurrency: env::var("BULK_CONCURRENCY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(4),
+
+            max_query_tokens: env::var("MAX_QUERY_TOKENS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10),
+
+            max_query_length: env::var("MAX_QUERY_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(256),
+
+            max_search_limit: env::var("MAX_SEARCH_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&max| max > 0)
+                .unwrap_or(1000),
+
+            query_segmentation: env::var("QUERY_SEGMENTATION")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
+            query_splitter: env::var("QUERY_SPLITTER")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
+            query_split_min_length: env::var("QUERY_SPLIT_MIN_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(8),
+
+            api_keys: env::var("API_KEYS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|k| k.trim().to_string())
+                        .filter(|k| !k.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+
+            api_keys_file: env::var("API_KEYS_FILE").ok().map(PathBuf::from),
+
+            rate_limit_per_minute: env::var("RATE_LIMIT_PER_MINUTE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(600),
+
+            daily_quota: env::var("DAILY_QUOTA")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+
+            openapi_ui: env::var("OPENAPI_UI")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
+            access_log: env::var("ACCESS_LOG")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+
+            compression: env::var("COMPRESSION")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+
+            compression_min_bytes: env::var("COMPRESSION_MIN_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1024),
+
+            admin_writes: env::var("ADMIN_WRITES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
+            export_dir: env::var("EXPORT_DIR")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            ranking_profiles_file: env::var("RANKING_PROFILES_FILE").ok().map(PathBuf::from),
+
+            ranking_profile: env::var("RANKING_PROFILE").unwrap_or_else(|_| "default".to_string()),
+
+            appraisal_weights_file: env::var("APPRAISAL_WEIGHTS_FILE").ok().map(PathBuf::from),
+
+            taxonomy_file: env::var("TAXONOMY_FILE").ok().map(PathBuf::from),
+
+            state_dir: env::var("STATE_DIR")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            stop_tokens: env::var("STOP_TOKENS")
+                .unwrap_or_else(|_| DEFAULT_STOP_TOKENS.to_string())
+                .split(',')
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+
+            query_stats_max: env::var("QUERY_STATS_MAX")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10_000),
+
+            query_stats_redis_key: env::var("QUERY_STATS_REDIS_KEY")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            warmup_queries_file: env::var("WARMUP_QUERIES_FILE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from),
+
+            warmup_popular_queries: env::var("WARMUP_POPULAR_QUERIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(100),
+
+            cache_replay_queries: env::var("CACHE_REPLAY_QUERIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+
+            watchlist_key: env::var("WATCHLIST_KEY").ok().filter(|s| !s.is_empty()),
+
+            watchlist_max_alerts: env::var("WATCHLIST_MAX_ALERTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10_000),
+
+            watchlist_webhook_url: env::var("WATCHLIST_WEBHOOK_URL")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            pipeline_webhook_urls: env::var("PIPELINE_WEBHOOK_URLS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect(),
+
+            rdap_url: env::var("RDAP_URL").ok().filter(|s| !s.is_empty()),
+
+            rdap_rate_limit: env::var("RDAP_RATE_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5),
+
+            rdap_cache_entries: env::var("RDAP_CACHE_ENTRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(50_000),
+
+            rdap_cache_ttl: env::var("RDAP_CACHE_TTL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(86400),
+
+            rdap_enrich_limit: env::var("RDAP_ENRICH_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(20),
+
+            word_splitter_budget: env::var("WORD_SPLITTER_BUDGET")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(600),
+
+            rdap_budget: env::var("RDAP_BUDGET")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1200),
+
+            dns_budget: env::var("DNS_BUDGET")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1200),
+
             index_heap_size: env::var("INDEX_HEAP_SIZE")
                 .ok()
                 .and_then(|s| s.parse().ok())
@@ -85,32 +740,304 @@ impl Config {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(1_000_000), // Commit every 1M docs
+
+            include_tlds: env::var("INCLUDE_TLDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+
+            exclude_tlds: env::var("EXCLUDE_TLDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+
+            label_validation: match env::var("LABEL_VALIDATION").ok().filter(|s| !s.is_empty()) {
+                Some(validation) => validation.parse()?,
+                None => LabelValidation::Strict,
+            },
+
+            index_subdomain_labels: env::var("INDEX_SUBDOMAIN_LABELS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+
+            segmentation_version: env::var("SEGMENTATION_VERSION")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1),
         })
     }
 
     /// Create a test configuration
-    #[cfg(test)]
+    #[cfg(any(test, feature = "test-support"))]
     pub fn test() -> Self {
         Self {
             word_splitter_url: "http://localhost:8080".to_string(),
             word_splitter_user: "test".to_string(),
             word_splitter_pass: "test".to_string(),
+            word_list_file: None,
             zonefile_token: "test-token".to_string(),
             zonefile_api_url: "http://localhost:8081".to_string(),
+            work_dir: env::temp_dir().join("zonefile-indexer-test"),
             index_path: PathBuf::from("/tmp/test-index"),
+            index_root: None,
+            indexes: Vec::new(),
+            dropped_index_path: None,
+            new_domains_index_path: None,
+            history_index_path: None,
+            tld_stats_path: None,
+            daily_write_limit_mb: 0,
+            daily_low_priority_merges: false,
+            optimize_window: None,
+            optimize_max_segments: 50,
+            optimize_deleted_ratio: 0.1,
             redis_url: None,
+            redis_mode: RedisMode::Standalone,
+            redis_username: None,
+            redis_password: None,
+            redis_sentinel_master: None,
+            redis_pool_size: 1,
+            redis_timeout_ms: 500,
+            redis_breaker_failure_rate: 0.5,
+            redis_breaker_cooldown_secs: 30,
+            cache_ttl: 86400,
+            cache_ttl_exact: 3600,
+            cache_key_prefix: "ds:".to_string(),
+            cache_compress_min_bytes: 8192,
+            memory_cache_entries: 100,
+            memory_cache_ttl: 60,
             api_port: 3000,
+            grpc_port: None,
+            api_bind: BindAddress::any(3000),
+            tls_cert_file: None,
+            tls_key_file: None,
+            request_timeout_ms: 30_000,
+            search_timeout_ms: 0,
+            search_max_scanned: 1_000_000,
+            max_concurrent_requests: 256,
+            bulk_body_limit: 256 * 1024,
+            bulk_concurrency: 4,
+            max_query_tokens: 10,
+            max_query_length: 256,
+            max_search_limit: 1000,
+            query_segmentation: false,
+            query_splitter: false,
+            query_split_min_length: 8,
+            api_keys: Vec::new(),
+            api_keys_file: None,
+            rate_limit_per_minute: 600,
+            daily_quota: 0,
+            openapi_ui: false,
+            access_log: false,
+            compression: true,
+            compression_min_bytes: 1024,
+            admin_writes: false,
+            export_dir: None,
+            ranking_profiles_file: None,
+            ranking_profile: "default".to_string(),
+            appraisal_weights_file: None,
+            taxonomy_file: None,
+            state_dir: None,
+            stop_tokens: Vec::new(),
+            query_stats_max: 100,
+            query_stats_redis_key: None,
+            warmup_queries_file: None,
+            warmup_popular_queries: 0,
+            cache_replay_queries: 0,
+            watchlist_key: None,
+            watchlist_max_alerts: 100,
+            watchlist_webhook_url: None,
+            pipeline_webhook_urls: Vec::new(),
+            rdap_url: None,
+            rdap_rate_limit: 5,
+            rdap_cache_entries: 100,
+            rdap_cache_ttl: 60,
+            rdap_enrich_limit: 20,
+            word_splitter_budget: 0,
+            rdap_budget: 0,
+            dns_budget: 0,
             index_heap_size: 50 * 1024 * 1024, // 50MB for tests
             word_batch_size: 10,
             index_batch_size: 100,
+            include_tlds: Vec::new(),
+            exclude_tlds: Vec::new(),
+            label_validation: LabelValidation::Strict,
+            index_subdomain_labels: false,
+            segmentation_version: 1,
         }
     }
 }
 
+/// Names reserved for the primary index and for fanning out to every index
+const RESERVED_INDEX_NAMES: [&str; 2] = ["default", "all"];
+
+/// Address the API server listens on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    /// Unix domain socket, for a reverse proxy on the same host
+    Unix(PathBuf),
+}
+
+impl BindAddress {
+    /// Every interface on `port`
+    pub fn any(port: u16) -> Self {
+        Self::Tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))
+    }
+
+    /// Interface the gRPC server shares: the TCP address's, or loopback
+    /// when the API listens on a socket, so moving HTTP onto a local socket
+    /// doesn't expose gRPC on every interface
+    pub fn ip(&self) -> IpAddr {
+        match self {
+            Self::Tcp(addr) => addr.ip(),
+            Self::Unix(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+}
+
+impl std::str::FromStr for BindAddress {
+    type Err = Error;
+
+    /// `host:port` or `unix:/path/to.sock`
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if let Some(path) = value.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(Error::Config("API_BIND unix: needs a socket path".to_string()));
+            }
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        value.parse().map(Self::Tcp).map_err(|_| {
+            Error::Config(format!("API_BIND is not host:port or unix:/path: {}", value))
+        })
+    }
+}
+
+impl fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// How the Redis servers of `REDIS_URL` are deployed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedisMode {
+    /// One server
+    #[default]
+    Standalone,
+    /// Redis Cluster, discovered from one or more seed nodes
+    Cluster,
+    /// The master of a set monitored by Redis Sentinel
+    Sentinel,
+}
+
+impl std::str::FromStr for RedisMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "standalone" => Ok(Self::Standalone),
+            "cluster" => Ok(Self::Cluster),
+            "sentinel" => Ok(Self::Sentinel),
+            other => Err(Error::Config(format!(
+                "REDIS_MODE is not standalone, cluster or sentinel: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Parse `name=path` pairs separated by commas
+fn parse_indexes(value: &str) -> Result<Vec<(String, PathBuf)>> {
+    let mut indexes: Vec<(String, PathBuf)> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, path) = entry
+            .split_once('=')
+            .map(|(name, path)| (name.trim(), path.trim()))
+            .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+            .ok_or_else(|| Error::Config(format!("INDEXES entry is not name=path: {}", entry)))?;
+
+        if RESERVED_INDEX_NAMES.contains(&name) {
+            return Err(Error::Config(format!("INDEXES name is reserved: {}", name)));
+        }
+        if indexes.iter().any(|(n, _)| n == name) {
+            return Err(Error::Config(format!("INDEXES name is repeated: {}", name)));
+        }
+        indexes.push((name.to_string(), PathBuf::from(path)));
+    }
+    Ok(indexes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_indexes() {
+        let indexes = parse_indexes("gtlds=/data/gtlds, dropped = /data/dropped,").unwrap();
+        assert_eq!(
+            indexes,
+            vec![
+                ("gtlds".to_string(), PathBuf::from("/data/gtlds")),
+                ("dropped".to_string(), PathBuf::from("/data/dropped")),
+            ]
+        );
+
+        assert!(parse_indexes("dropped").is_err());
+        assert!(parse_indexes("all=/data/all").is_err());
+        assert!(parse_indexes("a=/x,a=/y").is_err());
+    }
+
+    #[test]
+    fn test_parse_redis_mode() {
+        assert_eq!("cluster".parse::<RedisMode>().unwrap(), RedisMode::Cluster);
+        assert_eq!(" Sentinel ".parse::<RedisMode>().unwrap(), RedisMode::Sentinel);
+        assert_eq!("standalone".parse::<RedisMode>().unwrap(), RedisMode::Standalone);
+        assert!("replicated".parse::<RedisMode>().is_err());
+    }
+
+    #[test]
+    fn test_parse_bind_address() {
+        let bind: BindAddress = "127.0.0.1:3000".parse().unwrap();
+        assert_eq!(bind, BindAddress::Tcp("127.0.0.1:3000".parse().unwrap()));
+        assert_eq!(bind.to_string(), "127.0.0.1:3000");
+        assert!("[::1]:3000".parse::<BindAddress>().is_ok());
+
+        let bind: BindAddress = "unix:/run/ds.sock".parse().unwrap();
+        assert_eq!(bind, BindAddress::Unix(PathBuf::from("/run/ds.sock")));
+        assert_eq!(bind.to_string(), "unix:/run/ds.sock");
+        assert_eq!(bind.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert!("unix:".parse::<BindAddress>().is_err());
+        assert!("localhost".parse::<BindAddress>().is_err());
+        assert_eq!(BindAddress::any(3000).to_string(), "0.0.0.0:3000");
+    }
+
+    #[test]
+    fn test_tls_files() {
+        let mut config = Config::test();
+        assert_eq!(config.tls_files().unwrap(), None);
+
+        config.tls_cert_file = Some(PathBuf::from("/etc/ds/cert.pem"));
+        assert!(config.tls_files().is_err());
+
+        config.tls_key_file = Some(PathBuf::from("/etc/ds/key.pem"));
+        let (cert, key) = config.tls_files().unwrap().unwrap();
+        assert_eq!((cert, key), (Path::new("/etc/ds/cert.pem"), Path::new("/etc/ds/key.pem")));
+
+        config.api_bind = BindAddress::Unix(PathBuf::from("/run/ds.sock"));
+        assert!(config.tls_files().is_err());
+    }
+
     #[test]
     fn test_config_defaults() {
         let config = Config::test();
@@ -0,0 +1,150 @@
+/// Character sequences that read as another, replaced after the per-character
+/// mapping
+const SEQUENCES: &[(&str, &str)] = &[("rn", "m"), ("vv", "w")];
+
+/// Visual skeleton of a label, shared by labels likely to be mistaken for
+/// each other: `pаypal` (Cyrillic `а`), `paypa1` and `paypal` all reduce to
+/// `paypal`
+///
+/// Covers Unicode confusables (UTS #39) from the scripts most used in IDN
+/// homographs plus the digit substitutions common in typosquats. Punycode
+/// (`xn--`) labels are decoded first, so IDN homographs share the skeleton
+/// of the ASCII label they imitate.
+pub fn skeleton(label: &str) -> String {
+    let unicode;
+    let label = if label.starts_with("xn--") {
+        unicode = idna::domain_to_unicode(label).0;
+        unicode.as_str()
+    } else {
+        label
+    };
+
+    let mut skeleton: String = label.chars().flat_map(char::to_lowercase).map(fold).collect();
+    for (sequence, replacement) in SEQUENCES {
+        if skeleton.contains(sequence) {
+            skeleton = skeleton.replace(sequence, replacement);
+        }
+    }
+    skeleton
+}
+
+/// Unicode form of a domain with punycode (`xn--`) labels, None for plain
+/// ASCII domains
+pub fn to_unicode(domain: &str) -> Option<String> {
+    if !domain.split('.').any(|label| label.starts_with("xn--")) {
+        return None;
+    }
+    let (unicode, result) = idna::domain_to_unicode(domain);
+    result.ok().map(|_| unicode)
+}
+
+/// The ASCII character `c` is most easily mistaken for, or `c` itself
+fn fold(c: char) -> char {
+    match c {
+        // Digits standing in for letters
+        '0' => 'o',
+        '1' => 'l',
+        '3' => 'e',
+        '4' => 'a',
+        '5' => 's',
+        '7' => 't',
+        '8' => 'b',
+
+        // Latin letters with diacritics
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => 's',
+        'ţ' | 'ť' | 'ŧ' | 'ț' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+
+        // Cyrillic
+        'а' => 'a',
+        'Ь' | 'ь' => 'b',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ё' => 'e',
+        'һ' => 'h',
+        'і' | 'ї' => 'i',
+        'ј' => 'j',
+        'ӏ' => 'l',
+        'п' => 'n',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'г' => 'r',
+        'ѕ' => 's',
+        'ц' => 'u',
+        'ѵ' => 'v',
+        'ԝ' => 'w',
+        'х' => 'x',
+        'у' => 'y',
+
+        // Greek
+        'α' => 'a',
+        'β' => 'b',
+        'ε' => 'e',
+        'η' => 'n',
+        'ι' => 'i',
+        'κ' => 'k',
+        'ν' => 'v',
+        'ο' | 'σ' => 'o',
+        'ρ' => 'p',
+        'τ' => 't',
+        'υ' => 'u',
+        'χ' => 'x',
+        'γ' => 'y',
+
+        // Fullwidth Latin
+        'ａ'..='ｚ' => char::from(b'a' + (c as u32 - 'ａ' as u32) as u8),
+        '０'..='９' => fold(char::from(b'0' + (c as u32 - '０' as u32) as u8)),
+
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_substitutions() {
+        assert_eq!(skeleton("paypal"), "paypal");
+        assert_eq!(skeleton("paypa1"), "paypal");
+        assert_eq!(skeleton("g00gle"), "google");
+        assert_eq!(skeleton("rnicrosoft"), "microsoft");
+        assert_eq!(skeleton("vvalmart"), "walmart");
+        assert_eq!(skeleton("pay-pal"), "pay-pal");
+    }
+
+    #[test]
+    fn test_homographs() {
+        // Cyrillic а and о
+        assert_eq!(skeleton("pаypаl"), "paypal");
+        assert_eq!(skeleton("gооgle"), "google");
+        assert_eq!(skeleton("ｐａｙｐａｌ"), "paypal");
+        assert_eq!(skeleton("café"), "cafe");
+    }
+
+    #[test]
+    fn test_punycode_labels_are_decoded() {
+        let punycode = idna::domain_to_ascii("pаypаl").unwrap();
+        assert!(punycode.starts_with("xn--"));
+        assert_eq!(skeleton(&punycode), "paypal");
+        assert_eq!(to_unicode(&format!("{}.com", punycode)).unwrap(), "pаypаl.com");
+        assert_eq!(to_unicode("paypal.com"), None);
+    }
+}
@@ -0,0 +1,147 @@
+/// Letter pairs common in English words and names; the rest count as rare
+const COMMON_BIGRAMS: &str = "th he in er an re on at en nd ti es or te of ed is it al ar st to \
+    nt ng se ha as ou io le ve co me de hi ri ro ic ne ea ra ce li ch ll be ma si om ur ca el ta \
+    la ns di fo ho pe ec pr no ct us ac ot il tr ly nc et ut ss so rs un lo wa ge ie wh ee wi em \
+    ad ol rt po we na ul ni ts mo ow pa im mi ai sh ir su id os iv ia am fi ci vi pl ig tu ev ld \
+    ry mp fe bl ab gh ty op wo sa ay ex ke fr oo av ag if ap gr od bo sp rd do uc bu ei ov by rm \
+    ep tt oc fa ef cu rn sc gi da yo cr cl du ga qu ue ff ba ey ls va um pp ua up lu go ht ru ug \
+    ds lt pi rc rr eg au ck ew mu br bi pt ak pu ui rg ib tl ny ki rk ys ob mm fu ph og ms ye ud \
+    mb ip ub oi rl gu dr hu ok gs kn nk oa tw sk sm sn sl sw ze za zo oz ya yl ym xi xt ox ax nf \
+    nv nw hy cy gy fy my py dy ky vy wy sy ty zy ks lk lf lp lm lv rv rp rb rf rh nh wn wr bs cs \
+    ps ft fl gl kl vo vu ju jo ja je ji";
+
+/// Bit `b` of entry `a` is set when the letter pair `a`,`b` is common
+const COMMON: [u32; 26] = bigram_table(COMMON_BIGRAMS.as_bytes());
+
+const fn bigram_table(list: &[u8]) -> [u32; 26] {
+    let mut table = [0u32; 26];
+    let mut i = 0;
+    while i + 1 < list.len() {
+        let (a, b) = (list[i], list[i + 1]);
+        if a.is_ascii_lowercase() && b.is_ascii_lowercase() {
+            table[(a - b'a') as usize] |= 1 << (b - b'a');
+        }
+        i += 1;
+    }
+    table
+}
+
+/// Labels shorter than this are too short to judge and score 0
+const MIN_LENGTH: usize = 5;
+
+/// Labels shorter than this have their score scaled down by length
+const FULL_CONFIDENCE_LENGTH: usize = 8;
+
+/// How random a label looks, from 0 (human-meaningful) to 1 (algorithmically
+/// generated), rounded to three decimals
+///
+/// Combines the share of letter pairs rare in English, the character
+/// entropy, the longest consonant run and digits mixed into letters. Scores
+/// above about 0.5 are typical of DGA and keyboard-mash names. Punycode
+/// labels are scored on the ASCII letters and digits of their Unicode form.
+pub fn score(label: &str) -> f64 {
+    let unicode;
+    let label = if label.starts_with("xn--") {
+        unicode = idna::domain_to_unicode(label).0;
+        unicode.as_str()
+    } else {
+        label
+    };
+
+    let parts: Vec<&[u8]> = label
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::as_bytes)
+        .collect();
+    let chars: Vec<u8> = parts.concat().to_ascii_lowercase();
+    let length = chars.len();
+    let letters = chars.iter().filter(|c| c.is_ascii_alphabetic()).count();
+    if length < MIN_LENGTH || letters == 0 {
+        return 0.0;
+    }
+    let digits = length - letters;
+
+    let (mut pairs, mut rare) = (0usize, 0usize);
+    for part in &parts {
+        for pair in part.windows(2) {
+            let (a, b) = (pair[0].to_ascii_lowercase(), pair[1].to_ascii_lowercase());
+            pairs += 1;
+            let numeric = a.is_ascii_digit() && b.is_ascii_digit();
+            if !numeric && !is_common(a, b) {
+                rare += 1;
+            }
+        }
+    }
+    let rare = if pairs > 0 { rare as f64 / pairs as f64 } else { 0.0 };
+
+    let mut counts = [0usize; 36];
+    for &c in &chars {
+        let slot = if c.is_ascii_digit() { 26 + (c - b'0') } else { c - b'a' };
+        counts[slot as usize] += 1;
+    }
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / length as f64;
+            -p * p.log2()
+        })
+        .sum();
+    let entropy = entropy / (length as f64).log2();
+
+    let mut run = 0usize;
+    let mut longest_run = 0usize;
+    for &c in &chars {
+        if c.is_ascii_alphabetic() && !b"aeiouy".contains(&c) {
+            run += 1;
+            longest_run = longest_run.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    let consonants = (longest_run.saturating_sub(2) as f64 / 3.0).min(1.0);
+
+    let mixed_digits = if digits > 0 {
+        (digits as f64 * 3.0 / length as f64).min(1.0)
+    } else {
+        0.0
+    };
+
+    let score = 0.5 * rare + 0.15 * entropy + 0.2 * consonants + 0.15 * mixed_digits;
+    let confidence = (length as f64 / FULL_CONFIDENCE_LENGTH as f64).min(1.0);
+    (score.min(1.0) * confidence * 1000.0).round() / 1000.0
+}
+
+fn is_common(a: u8, b: u8) -> bool {
+    a.is_ascii_lowercase()
+        && b.is_ascii_lowercase()
+        && COMMON[(a - b'a') as usize] & (1 << (b - b'a')) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_score_low() {
+        for label in ["google", "coffeeshop", "paypal", "stackoverflow", "car4sale", "my-pet-shop"]
+        {
+            assert!(score(label) < 0.4, "{} scored {}", label, score(label));
+        }
+    }
+
+    #[test]
+    fn test_random_labels_score_high() {
+        for label in ["xjq7kz3vbw9p", "kq3hd8slpz", "vhbwvcrqx", "sdfhsdjkfh", "mnbvcxzlkj"] {
+            assert!(score(label) > 0.6, "{} scored {}", label, score(label));
+        }
+    }
+
+    #[test]
+    fn test_short_and_numeric_labels() {
+        assert_eq!(score("xqz"), 0.0);
+        assert_eq!(score("1234567"), 0.0);
+        assert!(score("xqzkv") < score("xqzkvbwj"));
+        assert!((0.0..=1.0).contains(&score("zzzzzzzzzzzz")));
+    }
+}
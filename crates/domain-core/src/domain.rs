@@ -1,5 +1,13 @@
+use crate::config::Config;
 use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Longest DNS label, in octets
+const MAX_LABEL_LEN: usize = 63;
+
+/// Longest domain name in presentation form, without the trailing dot
+const MAX_DOMAIN_LEN: usize = 253;
 
 /// Raw domain input before normalization
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +35,43 @@ pub struct NormalizedDomain {
 
     /// Segmented tokens from word splitter (filled later)
     pub tokens: Vec<String>,
+
+    /// Taxonomy categories of the tokens (filled later)
+    #[serde(default)]
+    pub categories: Vec<String>,
+
+    /// `SEGMENTATION_VERSION` of the word splitter that produced `tokens`,
+    /// 0 when they didn't come from it
+    #[serde(default)]
+    pub seg_version: u64,
+}
+
+/// How strictly `Domain::normalize_with` checks the characters of labels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelValidation {
+    /// Every label is letters, digits and inner hyphens (LDH) after IDNA
+    /// conversion, 1 to 63 characters
+    #[default]
+    Strict,
+    /// Domains IDNA can't convert are kept lowercased as given, with
+    /// whatever characters they have
+    Lenient,
+}
+
+impl std::str::FromStr for LabelValidation {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "strict" => Ok(Self::Strict),
+            "lenient" => Ok(Self::Lenient),
+            other => Err(Error::Config(format!(
+                "LABEL_VALIDATION is not strict or lenient: {}",
+                other
+            ))),
+        }
+    }
 }
 
 impl Domain {
@@ -34,13 +79,20 @@ impl Domain {
         Self { raw: raw.into() }
     }
 
-    /// Normalize the domain
+    /// Normalize the domain, rejecting labels that aren't valid DNS
+    /// hostnames (see [`LabelValidation::Strict`])
     ///
     /// - Lowercase
     /// - IDNA/punycode normalization
     /// - Strip trailing dot
     /// - Extract label and TLD
     pub fn normalize(&self) -> Result<NormalizedDomain> {
+        self.normalize_with(LabelValidation::Strict)
+    }
+
+    /// Normalize the domain, checking its labels as `validation` says;
+    /// invalid characters fail with [`Error::InvalidCharacters`]
+    pub fn normalize_with(&self, validation: LabelValidation) -> Result<NormalizedDomain> {
         let raw = self.raw.trim();
 
         // Strip trailing dot
@@ -52,40 +104,59 @@ impl Domain {
         // IDNA normalization (handles punycode and unicode domains)
         let domain_normalized = match idna::domain_to_ascii(&domain_lower) {
             Ok(d) => d,
+            Err(_) if validation == LabelValidation::Strict => {
+                return Err(Error::InvalidCharacters(format!(
+                    "Not a valid IDNA domain: {}",
+                    self.raw
+                )));
+            }
             Err(_) => {
                 // If IDNA fails, use lowercase version
                 domain_lower.clone()
             }
         };
-
-        // Split into label and TLD
+        // Split into label and TLD; a TLD alone (`com.`) has no label to index
         let parts: Vec<&str> = domain_normalized.rsplitn(2, '.').collect();
 
         if parts.len() < 2 {
             return Err(Error::InvalidDomain(format!(
-                "Domain must have at least one dot: {}",
+                "Domain must have a label and a TLD: {}",
                 self.raw
             )));
         }
 
-        let tld = parts[0].to_string();
-        let label = parts[1].to_string();
-
-        // Validate label length (DNS limit)
-        if label.len() > 63 {
+        // DNS limits hold for every label, not just the leftmost
+        let labels = || domain_normalized.split('.');
+        if labels().any(str::is_empty) {
             return Err(Error::InvalidDomain(format!(
-                "Label exceeds 63 characters: {}",
+                "Empty label: {}",
                 self.raw
             )));
         }
-
-        // Validate label is not empty
-        if label.is_empty() {
+        if labels().any(|label| label.len() > MAX_LABEL_LEN) {
             return Err(Error::InvalidDomain(format!(
-                "Empty label: {}",
-                self.raw
+                "Label exceeds {} characters: {}",
+                MAX_LABEL_LEN, self.raw
             )));
         }
+        if domain_normalized.len() > MAX_DOMAIN_LEN {
+            return Err(Error::InvalidDomain(format!(
+                "Domain exceeds {} characters: {}",
+                MAX_DOMAIN_LEN, self.raw
+            )));
+        }
+
+        if validation == LabelValidation::Strict {
+            if let Some(label) = labels().find(|label| !is_ldh(label)) {
+                return Err(Error::InvalidCharacters(format!(
+                    "Label '{}' is not letters, digits and inner hyphens: {}",
+                    label, self.raw
+                )));
+            }
+        }
+
+        let tld = parts[0].to_string();
+        let label = parts[1].to_string();
 
         let has_hyphen = label.contains('-');
         let len = label.len() as u16;
@@ -97,6 +168,8 @@ impl Domain {
             len,
             has_hyphen,
             tokens: Vec::new(),
+            categories: Vec::new(),
+            seg_version: 0,
         })
     }
 }
@@ -115,11 +188,34 @@ impl NormalizedDomain {
         u64::from_be_bytes(id_bytes)
     }
 
+    /// Text the word splitter segments for the domain's tokens
+    ///
+    /// By default the whole label. With `subdomains`, each label left of
+    /// the public suffix instead, joined by hyphens so the splitter treats
+    /// them as separate parts, in order: `shop.example.co.uk` gives
+    /// `shop-example`.
+    pub fn segment_source(&self, subdomains: bool) -> String {
+        if !subdomains || !self.label.contains('.') {
+            return self.label.clone();
+        }
+        let mut parts: Vec<&str> = self.label.split('.').collect();
+        if parts.len() > 1 && is_second_level_suffix(parts[parts.len() - 1], &self.tld) {
+            parts.pop();
+        }
+        parts.join("-")
+    }
+
     /// Set tokens from word segmentation
     pub fn with_tokens(mut self, tokens: Vec<String>) -> Self {
         self.tokens = tokens;
         self
     }
+
+    /// Set categories from the taxonomy
+    pub fn with_categories(mut self, categories: Vec<String>) -> Self {
+        self.categories = categories;
+        self
+    }
 }
 
 /// Check if a domain should be filtered out during indexing
@@ -138,15 +234,83 @@ pub fn should_filter_domain(label: &str) -> bool {
     }
 
     // Filter labels that start with digit and contain only digits/hyphens
-    if label.starts_with(|c: char| c.is_ascii_digit()) {
-        if label.chars().all(|c| c.is_ascii_digit() || c == '-') {
-            return true;
-        }
+    // (pure numeric labels are handled by the length rule above)
+    if label.starts_with(|c: char| c.is_ascii_digit())
+        && label.contains('-')
+        && label.chars().all(|c| c.is_ascii_digit() || c == '-')
+    {
+        return true;
     }
 
     false
 }
 
+/// TLDs indexed: only the included ones when any are, and never the
+/// excluded ones
+#[derive(Debug, Clone, Default)]
+pub struct TldFilter {
+    include: HashSet<String>,
+    exclude: HashSet<String>,
+}
+
+impl TldFilter {
+    /// Filter of TLDs given as in zone files (`com`) or with a leading dot;
+    /// IDN TLDs may be given in Unicode
+    pub fn new<S: AsRef<str>>(
+        include: impl IntoIterator<Item = S>,
+        exclude: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self {
+            include: include.into_iter().filter_map(normalize_tld).collect(),
+            exclude: exclude.into_iter().filter_map(normalize_tld).collect(),
+        }
+    }
+
+    /// Filter of `INCLUDE_TLDS` and `EXCLUDE_TLDS`
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.include_tlds, &config.exclude_tlds)
+    }
+
+    /// Whether every TLD is indexed
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether domains under a normalized TLD are indexed
+    pub fn allows(&self, tld: &str) -> bool {
+        (self.include.is_empty() || self.include.contains(tld)) && !self.exclude.contains(tld)
+    }
+}
+
+/// Registry second levels under country-code TLDs (`co.uk`, `com.au`),
+/// the common part of the public suffix list beyond the TLD itself
+const SECOND_LEVEL_SUFFIXES: &[&str] = &[
+    "ac", "co", "com", "edu", "gov", "ltd", "me", "mil", "net", "ne", "or", "org", "plc",
+];
+
+/// Whether `label` directly under `tld` is a public suffix, not a name
+fn is_second_level_suffix(label: &str, tld: &str) -> bool {
+    tld.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(&label)
+}
+
+/// Whether a DNS label is a valid hostname label: ASCII letters, digits
+/// and hyphens, not starting or ending with a hyphen; length is checked
+/// separately in both validation modes
+fn is_ldh(label: &str) -> bool {
+    !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// A TLD in the form `NormalizedDomain::tld` takes, None when blank
+fn normalize_tld(tld: impl AsRef<str>) -> Option<String> {
+    let tld = tld.as_ref().trim().trim_start_matches('.').to_lowercase();
+    if tld.is_empty() {
+        return None;
+    }
+    Some(idna::domain_to_ascii(&tld).unwrap_or(tld))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,6 +370,68 @@ mod tests {
         assert!(domain.normalize().is_err());
     }
 
+    #[test]
+    fn test_strict_validation_rejects_non_ldh_labels() {
+        for raw in ["my_domain.com", "-coffee.com", "coffee-.com", "coffee.c_m"] {
+            let err = Domain::new(raw).normalize().unwrap_err();
+            assert!(matches!(err, Error::InvalidCharacters(_)), "{}", raw);
+        }
+        assert!(Domain::new("xn--mnchen-3ya.de").normalize().is_ok());
+        assert!(Domain::new("1-800-flowers.com").normalize().is_ok());
+
+        // Lenient mode keeps what IDNA lets through or can't convert
+        let lenient = Domain::new("my_domain.com").normalize_with(LabelValidation::Lenient);
+        assert_eq!(lenient.unwrap().label, "my_domain");
+        assert!(Domain::new("nodot").normalize_with(LabelValidation::Lenient).is_err());
+
+        assert_eq!("Lenient".parse::<LabelValidation>().unwrap(), LabelValidation::Lenient);
+        assert!("loose".parse::<LabelValidation>().is_err());
+    }
+
+    #[test]
+    fn test_length_limits_apply_to_every_label() {
+        let long = "a".repeat(64);
+        for validation in [LabelValidation::Strict, LabelValidation::Lenient] {
+            for raw in [
+                "com.".to_string(),
+                ".com".to_string(),
+                "a..com".to_string(),
+                format!("www.{}.com", long),
+                format!("{}.com", long),
+                format!("example.{}", long),
+            ] {
+                let err = Domain::new(&raw).normalize_with(validation).unwrap_err();
+                assert!(matches!(err, Error::InvalidDomain(_)), "{}", raw);
+            }
+
+            // 4 labels of 63 plus 3 dots is 255, over the 253 total
+            let label = "a".repeat(63);
+            let too_long = [label.as_str(); 4].join(".");
+            let err = Domain::new(&too_long).normalize_with(validation).unwrap_err();
+            assert!(err.to_string().contains("253"));
+
+            // 253 exactly, with or without the trailing dot
+            let max = format!("{}.{}", [label.as_str(); 3].join("."), "a".repeat(61));
+            assert_eq!(max.len(), 253);
+            assert!(Domain::new(&max).normalize_with(validation).is_ok());
+            assert!(Domain::new(format!("{}.", max)).normalize_with(validation).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_segment_source_splits_subdomains() {
+        let normalize = |raw: &str| Domain::new(raw).normalize().unwrap();
+
+        let domain = normalize("shop.example.co.uk");
+        assert_eq!(domain.segment_source(false), "shop.example.co");
+        assert_eq!(domain.segment_source(true), "shop-example");
+        assert_eq!(normalize("blog.coffee.com").segment_source(true), "blog-coffee");
+        assert_eq!(normalize("coffee.co").segment_source(true), "coffee");
+        // Only two-letter TLDs have registry second levels
+        assert_eq!(normalize("shop.co.network").segment_source(true), "shop-co");
+        assert_eq!(normalize("co.uk").segment_source(true), "co");
+    }
+
     #[test]
     fn test_should_filter_numeric() {
         assert!(should_filter_domain("123456"));
@@ -225,4 +451,22 @@ mod tests {
         assert!(should_filter_domain("1-2-3"));
         assert!(!should_filter_domain("a-1-2"));
     }
+
+    #[test]
+    fn test_tld_filter() {
+        let all = TldFilter::default();
+        assert!(all.is_empty());
+        assert!(all.allows("xyz"));
+
+        let legacy = TldFilter::new(["com", ".NET", " org "], ["net"]);
+        assert!(!legacy.is_empty());
+        assert!(legacy.allows("com"));
+        assert!(legacy.allows("org"));
+        assert!(!legacy.allows("net"));
+        assert!(!legacy.allows("xyz"));
+
+        let no_idn = TldFilter::new(Vec::<&str>::new(), ["рф"]);
+        assert!(!no_idn.allows("xn--p1ai"));
+        assert!(no_idn.allows("ru"));
+    }
 }
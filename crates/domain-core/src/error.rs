@@ -1,3 +1,5 @@
+use crate::writer_lock::Holder;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +7,9 @@ pub enum Error {
     #[error("Invalid domain: {0}")]
     InvalidDomain(String),
 
+    #[error("Invalid DNS characters: {0}")]
+    InvalidCharacters(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -16,6 +21,16 @@ pub enum Error {
 
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+
+    #[error(
+        "Index {path:?} is locked by another writer ({}); if that run crashed, \
+         see `domain-indexer unlock`",
+        .holder.as_ref().map_or("holder unknown".to_string(), ToString::to_string)
+    )]
+    WriterLocked {
+        path: PathBuf,
+        holder: Option<Holder>,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
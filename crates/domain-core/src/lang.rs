@@ -0,0 +1,96 @@
+use whatlang::{Detector, Lang};
+
+/// Languages a label can be tagged with, by ISO 639-1 code
+const LANGUAGES: &[(Lang, &str)] = &[
+    (Lang::Eng, "en"),
+    (Lang::Deu, "de"),
+    (Lang::Fra, "fr"),
+    (Lang::Spa, "es"),
+    (Lang::Ita, "it"),
+    (Lang::Por, "pt"),
+    (Lang::Nld, "nl"),
+    (Lang::Pol, "pl"),
+    (Lang::Swe, "sv"),
+    (Lang::Tur, "tr"),
+    (Lang::Rus, "ru"),
+    (Lang::Ukr, "uk"),
+    (Lang::Ara, "ar"),
+    (Lang::Heb, "he"),
+    (Lang::Ell, "el"),
+    (Lang::Hin, "hi"),
+    (Lang::Tha, "th"),
+    (Lang::Cmn, "zh"),
+    (Lang::Jpn, "ja"),
+    (Lang::Kor, "ko"),
+];
+
+/// Fewer letters than this are too little text to tell languages apart
+const MIN_LETTERS: usize = 6;
+
+/// Detections less confident than this are left untagged
+const MIN_CONFIDENCE: f64 = 0.05;
+
+/// Probable language of a label's tokens, as an ISO 639-1 code
+///
+/// Detection runs on a few words at most, so it is a best guess: short or
+/// ambiguous labels get `None` rather than a coin flip. Punycode labels
+/// are detected from their Unicode form.
+pub fn detect(label: &str, tokens: &[String]) -> Option<&'static str> {
+    let text = if label.starts_with("xn--") {
+        idna::domain_to_unicode(label).0
+    } else if tokens.is_empty() {
+        label.replace('-', " ")
+    } else {
+        tokens.join(" ")
+    };
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_LETTERS {
+        return None;
+    }
+
+    let detector = Detector::with_allowlist(LANGUAGES.iter().map(|(lang, _)| *lang).collect());
+    let info = detector.detect(&text)?;
+    if info.confidence() < MIN_CONFIDENCE {
+        return None;
+    }
+    LANGUAGES
+        .iter()
+        .find(|(lang, _)| *lang == info.lang())
+        .map(|(_, code)| *code)
+}
+
+/// Whether `code` is a language [`detect`] can return
+pub fn is_supported(code: &str) -> bool {
+    LANGUAGES.iter().any(|(_, supported)| *supported == code)
+}
+
+/// ISO 639-1 codes of the languages [`detect`] can return
+pub fn supported() -> impl Iterator<Item = &'static str> {
+    LANGUAGES.iter().map(|(_, code)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detects_from_tokens() {
+        assert_eq!(detect("cheapflights", &tokens(&["cheap", "flights"])), Some("en"));
+        assert_eq!(
+            detect("meinschoenergarten", &tokens(&["mein", "schoener", "garten"])),
+            Some("de")
+        );
+        assert_eq!(detect("ferienwohnung", &[]), Some("de"));
+    }
+
+    #[test]
+    fn test_short_labels_are_untagged() {
+        assert_eq!(detect("pizza", &tokens(&["pizza"])), None);
+        assert_eq!(detect("abc123", &[]), None);
+        assert!(is_supported("de"));
+        assert!(!is_supported("deu"));
+    }
+}
@@ -1,9 +1,24 @@
 pub mod config;
+pub mod confusables;
+pub mod dga;
 pub mod domain;
 pub mod error;
+pub mod lang;
+pub mod query;
 pub mod schema;
+pub mod segments;
+pub mod stem;
+pub mod synthetic;
+pub mod taxonomy;
+pub mod telemetry;
+pub mod tld_stats;
+pub mod writer_lock;
 
 pub use config::Config;
-pub use domain::{Domain, NormalizedDomain};
+pub use domain::{Domain, LabelValidation, NormalizedDomain};
 pub use error::Error;
-pub use schema::DomainSchema;
+pub use query::QueryAnalyzer;
+pub use schema::{DatedSchema, DomainSchema, HistorySchema, LifecycleEvent};
+pub use segments::MergePressure;
+pub use taxonomy::Taxonomy;
+pub use tld_stats::TldHistory;
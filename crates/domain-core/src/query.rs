@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+/// Prefix of IDNA-encoded labels
+const IDNA_PREFIX: &str = "xn--";
+
+/// Splits concatenated words (`bestcoffeeshop`) the way labels are split
+/// at index time
+pub trait Segment: Send + Sync {
+    fn segment(&self, word: &str) -> Vec<String>;
+}
+
+/// Turns a search query into keywords tokenized like indexed labels
+///
+/// Words are lowercased and split at hyphens and other punctuation, as the
+/// segmentation of labels splits at hyphens (`best-coffee` → `best`
+/// `coffee`). Non-ASCII words take their IDNA form, which is how IDN labels
+/// are indexed (`café` → `xn--caf-dma`). With a segmenter, concatenated
+/// words are also split into their words (`bestcoffee` → `best` `coffee`).
+#[derive(Clone, Default)]
+pub struct QueryAnalyzer {
+    segmenter: Option<Arc<dyn Segment>>,
+}
+
+impl QueryAnalyzer {
+    /// Analyzer that segments concatenated words with `segmenter`
+    pub fn with_segmenter(segmenter: Arc<dyn Segment>) -> Self {
+        Self {
+            segmenter: Some(segmenter),
+        }
+    }
+
+    /// Whether concatenated words are segmented
+    pub fn segments(&self) -> bool {
+        self.segmenter.is_some()
+    }
+
+    /// Keywords of a query, in order
+    pub fn tokens(&self, query: &str) -> Vec<String> {
+        let tokens = tokens(query);
+        let Some(segmenter) = &self.segmenter else {
+            return tokens;
+        };
+        tokens
+            .into_iter()
+            .flat_map(|token| {
+                // Punycode isn't made of words
+                if token.starts_with(IDNA_PREFIX) {
+                    return vec![token];
+                }
+                let words = segmenter.segment(&token);
+                if words.is_empty() {
+                    vec![token]
+                } else {
+                    words
+                }
+            })
+            .collect()
+    }
+
+    /// The query's keywords joined by spaces, the form searched and cached
+    pub fn normalize(&self, query: &str) -> String {
+        self.tokens(query).join(" ")
+    }
+}
+
+/// Keywords of a query without segmentation, in order
+///
+/// See [`QueryAnalyzer`].
+pub fn tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in query.split_whitespace() {
+        let word = word.to_lowercase();
+        // Already IDNA-encoded; its hyphens aren't word boundaries
+        if word.starts_with(IDNA_PREFIX) {
+            tokens.push(word);
+            continue;
+        }
+        for part in word.split(|c: char| !c.is_alphanumeric()) {
+            if part.is_empty() {
+                continue;
+            }
+            if part.is_ascii() {
+                tokens.push(part.to_string());
+            } else {
+                tokens.push(idna::domain_to_ascii(part).unwrap_or_else(|_| part.to_string()));
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits off known words from the front
+    struct Words(&'static [&'static str]);
+
+    impl Segment for Words {
+        fn segment(&self, word: &str) -> Vec<String> {
+            let mut rest = word;
+            let mut words = Vec::new();
+            while let Some(known) = self.0.iter().find(|known| rest.starts_with(*known)) {
+                words.push(known.to_string());
+                rest = &rest[known.len()..];
+            }
+            if !rest.is_empty() {
+                words.push(rest.to_string());
+            }
+            words
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(tokens("Best-Coffee  shop"), vec!["best", "coffee", "shop"]);
+        assert_eq!(tokens("coffee_shop.com, 24/7"), vec!["coffee", "shop", "com", "24", "7"]);
+        assert_eq!(tokens("café"), vec!["xn--caf-dma"]);
+        assert_eq!(tokens("XN--caf-dma"), vec!["xn--caf-dma"]);
+        assert!(tokens(" -- ").is_empty());
+    }
+
+    #[test]
+    fn test_segmented_tokens() {
+        let analyzer = QueryAnalyzer::default();
+        assert!(!analyzer.segments());
+        assert_eq!(analyzer.normalize("bestcoffee shop"), "bestcoffee shop");
+
+        let analyzer = QueryAnalyzer::with_segmenter(Arc::new(Words(&["best", "coffee"])));
+        assert_eq!(analyzer.normalize("bestcoffee shop"), "best coffee shop");
+        assert_eq!(analyzer.normalize("best-coffee"), "best coffee");
+        assert_eq!(analyzer.normalize("café"), "xn--caf-dma");
+    }
+}
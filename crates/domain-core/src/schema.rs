@@ -1,9 +1,13 @@
+use crate::confusables;
+use crate::dga;
 use crate::domain::NormalizedDomain;
+use crate::lang;
+use crate::stem;
 use tantivy::schema::{
-    Facet, FacetOptions, Field, NumericOptions, Schema, TextFieldIndexing, TextOptions,
-    STORED, STRING,
+    DateOptions, Facet, FacetOptions, Field, NumericOptions, Schema, SchemaBuilder,
+    TextFieldIndexing, TextOptions, STORED, STRING,
 };
-use tantivy::TantivyDocument;
+use tantivy::{DateTime, TantivyDocument};
 
 /// Tantivy schema for domain search
 #[derive(Clone)]
@@ -17,11 +21,25 @@ pub struct DomainSchema {
     pub len: Field,
     pub has_hyphen: Field,
     pub label: Field,
+    pub skeleton: Field,
+    pub dga_score: Field,
+    pub lang: Field,
+    pub category: Field,
+    pub stems: Field,
+    pub seg_version: Field,
 }
 
 impl DomainSchema {
     /// Create a new schema for domain indexing
     pub fn new() -> Self {
+        Self::build(|_| ()).0
+    }
+
+    /// Build the domain fields, then let `extra` append more
+    ///
+    /// Extra fields come last so the domain fields keep the same ids, and a
+    /// [`DomainSchema`] can search indices built with the extended schema.
+    fn build<T>(extra: impl FnOnce(&mut SchemaBuilder) -> T) -> (Self, T) {
         let mut schema_builder = Schema::builder();
 
         // domain_exact: STRING (not tokenized) - for exact lookup + delete
@@ -65,9 +83,43 @@ impl DomainSchema {
             .set_stored();
         let label = schema_builder.add_text_field("label", label_options);
 
+        // skeleton: STRING - the label's visual skeleton, for lookalike lookup
+        let skeleton = schema_builder.add_text_field("skeleton", STRING);
+
+        // dga_score: f64 FAST - label randomness, for filtering and sorting
+        let dga_score = schema_builder.add_f64_field(
+            "dga_score",
+            NumericOptions::default().set_fast().set_stored(),
+        );
+
+        // lang: STRING - probable language of the label (ISO 639-1), if any
+        let lang = schema_builder.add_text_field("lang", STRING | STORED);
+
+        // category: FACET - taxonomy categories of the tokens (e.g., /finance)
+        // STORED so results can list them
+        let category =
+            schema_builder.add_facet_field("category", FacetOptions::default().set_stored());
+
+        // stems: TEXT - light stems of the tokens (`shoes` → `shoe`), so
+        // queries can match other inflections
+        let stem_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("default")
+                .set_index_option(tantivy::schema::IndexRecordOption::WithFreqs),
+        );
+        let stems = schema_builder.add_text_field("stems", stem_options);
+
+        // seg_version: u64 FAST - SEGMENTATION_VERSION of the tokens,
+        // so `retokenize` finds documents segmented by an older one
+        let seg_version = schema_builder.add_u64_field(
+            "seg_version",
+            NumericOptions::default().set_fast().set_stored(),
+        );
+
+        let extra = extra(&mut schema_builder);
         let schema = schema_builder.build();
 
-        Self {
+        let domain_schema = Self {
             schema,
             domain_exact,
             tokens,
@@ -75,7 +127,14 @@ impl DomainSchema {
             len,
             has_hyphen,
             label,
-        }
+            skeleton,
+            dga_score,
+            lang,
+            category,
+            stems,
+            seg_version,
+        };
+        (domain_schema, extra)
     }
 
     /// Convert a normalized domain to a Tantivy document
@@ -102,6 +161,29 @@ impl DomainSchema {
         // label
         doc.add_text(self.label, &domain.label);
 
+        // skeleton
+        doc.add_text(self.skeleton, confusables::skeleton(&domain.label));
+
+        // dga_score
+        doc.add_f64(self.dga_score, dga::score(&domain.label));
+
+        // lang
+        if let Some(lang) = lang::detect(&domain.label, &domain.tokens) {
+            doc.add_text(self.lang, lang);
+        }
+
+        // categories as facets (e.g., "/finance")
+        for category in &domain.categories {
+            doc.add_facet(self.category, Facet::from_path(vec![category]));
+        }
+
+        // stems - joined like tokens
+        let stems: Vec<_> = domain.tokens.iter().map(|token| stem::stem(token)).collect();
+        doc.add_text(self.stems, stems.join(" "));
+
+        // seg_version
+        doc.add_u64(self.seg_version, domain.seg_version);
+
         doc
     }
 }
@@ -112,6 +194,134 @@ impl Default for DomainSchema {
     }
 }
 
+/// Tantivy schema for indices of dated domain events: the domain fields
+/// plus the day of the event
+#[derive(Clone)]
+pub struct DatedSchema {
+    /// Domain fields, over the full dated schema
+    pub domain: DomainSchema,
+    pub date: Field,
+}
+
+impl DatedSchema {
+    /// Domains removed from their zones, dated by the day they dropped
+    pub fn dropped() -> Self {
+        Self::with_date_field("dropped_at")
+    }
+
+    /// Newly registered domains, dated by the day they were first seen
+    pub fn registered() -> Self {
+        Self::with_date_field("first_seen")
+    }
+
+    fn with_date_field(name: &str) -> Self {
+        let (domain, date) = DomainSchema::build(|builder| {
+            // date: DATE FAST - for `since` range filters
+            let options = DateOptions::default().set_indexed().set_fast().set_stored();
+            builder.add_date_field(name, options)
+        });
+        Self { domain, date }
+    }
+
+    /// Name of the date field, for range queries
+    pub fn date_field_name(&self) -> &str {
+        self.domain.schema.get_field_name(self.date)
+    }
+
+    /// Convert a dated domain to a Tantivy document
+    pub fn to_document(&self, domain: &NormalizedDomain, date: DateTime) -> TantivyDocument {
+        let mut doc = self.domain.to_document(domain);
+        doc.add_date(self.date, date);
+        doc
+    }
+}
+
+/// A change in a domain's registration, as seen by the daily syncs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// Added without any earlier history
+    FirstSeen,
+    /// Removed from its zone
+    Dropped,
+    /// Added again after dropping
+    ReRegistered,
+}
+
+impl LifecycleEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleEvent::FirstSeen => "first_seen",
+            LifecycleEvent::Dropped => "dropped",
+            LifecycleEvent::ReRegistered => "re_registered",
+        }
+    }
+
+    /// Order of events recorded by the same sync, which applies removals
+    /// before additions
+    pub fn sync_order(&self) -> u8 {
+        match self {
+            LifecycleEvent::Dropped => 0,
+            LifecycleEvent::FirstSeen | LifecycleEvent::ReRegistered => 1,
+        }
+    }
+
+    pub fn parse(event: &str) -> Option<Self> {
+        match event {
+            "first_seen" => Some(LifecycleEvent::FirstSeen),
+            "dropped" => Some(LifecycleEvent::Dropped),
+            "re_registered" => Some(LifecycleEvent::ReRegistered),
+            _ => None,
+        }
+    }
+}
+
+/// Tantivy schema for the domain history index: the domain fields plus a
+/// lifecycle event and the time of the sync that saw it, one document per
+/// event
+#[derive(Clone)]
+pub struct HistorySchema {
+    /// Domain fields, over the full history schema
+    pub domain: DomainSchema,
+    pub event: Field,
+    pub date: Field,
+}
+
+impl HistorySchema {
+    pub fn new() -> Self {
+        let (domain, (event, date)) = DomainSchema::build(|builder| {
+            // event: STRING - the LifecycleEvent name
+            let event = builder.add_text_field("event", STRING | STORED);
+            // date: DATE FAST - sync time, for ordering the timeline
+            let options = DateOptions::default().set_indexed().set_fast().set_stored();
+            (event, builder.add_date_field("date", options))
+        });
+        Self {
+            domain,
+            event,
+            date,
+        }
+    }
+
+    /// Convert a domain event to a Tantivy document
+    pub fn to_document(
+        &self,
+        domain: &NormalizedDomain,
+        event: LifecycleEvent,
+        date: DateTime,
+    ) -> TantivyDocument {
+        let mut doc = self.domain.to_document(domain);
+        doc.add_text(self.event, event.as_str());
+        doc.add_date(self.date, date);
+        doc
+    }
+}
+
+impl Default for HistorySchema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,10 +338,17 @@ mod tests {
         assert!(schema.schema.get_field("len").is_ok());
         assert!(schema.schema.get_field("has_hyphen").is_ok());
         assert!(schema.schema.get_field("label").is_ok());
+        assert!(schema.schema.get_field("skeleton").is_ok());
+        assert!(schema.schema.get_field("dga_score").is_ok());
+        assert!(schema.schema.get_field("lang").is_ok());
+        assert!(schema.schema.get_field("category").is_ok());
+        assert!(schema.schema.get_field("stems").is_ok());
     }
 
     #[test]
     fn test_to_document() {
+        use tantivy::schema::Value;
+
         let schema = DomainSchema::new();
 
         let domain = Domain::new("middleofnight.com");
@@ -145,5 +362,55 @@ mod tests {
         assert!(doc.get_first(schema.tokens).is_some());
         assert!(doc.get_first(schema.tld).is_some());
         assert!(doc.get_first(schema.len).is_some());
+        assert!(doc.get_first(schema.category).is_none());
+
+        let mut normalized = normalized.with_categories(vec!["time".to_string()]);
+        let doc = schema.to_document(&normalized);
+        let category = doc.get_first(schema.category).and_then(|v| v.as_facet());
+        assert_eq!(category, Some(&Facet::from("/time")));
+
+        normalized.tokens = vec!["running".to_string(), "shoes".to_string()];
+        let doc = schema.to_document(&normalized);
+        assert_eq!(doc.get_first(schema.stems).and_then(|v| v.as_str()), Some("run shoe"));
+
+        normalized.seg_version = 3;
+        let doc = schema.to_document(&normalized);
+        let version = doc.get_first(schema.seg_version).and_then(|v| v.as_u64());
+        assert_eq!(version, Some(3));
+    }
+
+    #[test]
+    fn test_dated_schema_keeps_domain_fields() {
+        let dropped = DatedSchema::dropped();
+        let schema = DomainSchema::new();
+
+        assert_eq!(dropped.domain.tokens, schema.tokens);
+        assert_eq!(dropped.domain.label, schema.label);
+        assert_eq!(dropped.date_field_name(), "dropped_at");
+        assert_eq!(DatedSchema::registered().date_field_name(), "first_seen");
+
+        let normalized = Domain::new("coffee.com").normalize().unwrap();
+        let doc = dropped.to_document(&normalized, DateTime::from_timestamp_secs(86_400));
+        assert!(doc.get_first(dropped.date).is_some());
+    }
+
+    #[test]
+    fn test_history_schema() {
+        let history = HistorySchema::new();
+        assert_eq!(history.domain.domain_exact, DomainSchema::new().domain_exact);
+
+        for event in [
+            LifecycleEvent::FirstSeen,
+            LifecycleEvent::Dropped,
+            LifecycleEvent::ReRegistered,
+        ] {
+            assert_eq!(LifecycleEvent::parse(event.as_str()), Some(event));
+        }
+
+        let normalized = Domain::new("coffee.com").normalize().unwrap();
+        let date = DateTime::from_timestamp_secs(86_400);
+        let doc = history.to_document(&normalized, LifecycleEvent::Dropped, date);
+        assert!(doc.get_first(history.event).is_some());
+        assert!(doc.get_first(history.date).is_some());
     }
 }
@@ -0,0 +1,114 @@
+use tantivy::Searcher;
+
+/// Share of deleted documents at which `optimize` is worth running
+pub const OPTIMIZE_DELETED_RATIO: f64 = 0.1;
+
+/// Live and deleted documents of one segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentUsage {
+    pub id: String,
+    pub documents: u32,
+    pub deleted_documents: u32,
+}
+
+impl SegmentUsage {
+    /// Share of the segment's documents that are deleted
+    pub fn deleted_ratio(&self) -> f64 {
+        ratio(
+            self.deleted_documents as u64,
+            self.documents as u64 + self.deleted_documents as u64,
+        )
+    }
+}
+
+/// Deleted documents an index carries until its segments are merged
+///
+/// Daily syncs delete dropped domains, which stay in their segments (and
+/// are read by every search) until a merge rewrites them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePressure {
+    pub segments: Vec<SegmentUsage>,
+}
+
+impl MergePressure {
+    pub fn from_searcher(searcher: &Searcher) -> Self {
+        let segments = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment| SegmentUsage {
+                id: segment.segment_id().uuid_string(),
+                documents: segment.num_docs(),
+                deleted_documents: segment.num_deleted_docs(),
+            })
+            .collect();
+        Self { segments }
+    }
+
+    pub fn documents(&self) -> u64 {
+        self.segments.iter().map(|s| s.documents as u64).sum()
+    }
+
+    pub fn deleted_documents(&self) -> u64 {
+        self.segments.iter().map(|s| s.deleted_documents as u64).sum()
+    }
+
+    /// Share of all documents that are deleted
+    pub fn deleted_ratio(&self) -> f64 {
+        let deleted = self.deleted_documents();
+        ratio(deleted, self.documents() + deleted)
+    }
+
+    /// Whether deleted documents have reached `OPTIMIZE_DELETED_RATIO`
+    pub fn optimize_recommended(&self) -> bool {
+        self.crosses(0, OPTIMIZE_DELETED_RATIO)
+    }
+
+    /// Whether the index has more than `max_segments` segments or at least
+    /// `deleted_ratio` deleted documents, either being 0 for no limit
+    pub fn crosses(&self, max_segments: usize, deleted_ratio: f64) -> bool {
+        (max_segments > 0 && self.segments.len() > max_segments)
+            || (deleted_ratio > 0.0 && self.deleted_ratio() >= deleted_ratio)
+    }
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Domain, DomainSchema};
+    use tantivy::{Index, IndexWriter, Term};
+
+    #[test]
+    fn test_deletes_recommend_optimize() {
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer: IndexWriter = index.writer(15_000_000).unwrap();
+        for name in ["a", "b", "c", "d", "e"] {
+            let domain = Domain::new(format!("{name}.com")).normalize().unwrap();
+            writer.add_document(schema.to_document(&domain)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let pressure = MergePressure::from_searcher(&index.reader().unwrap().searcher());
+        assert_eq!(pressure.documents(), 5);
+        assert!(!pressure.optimize_recommended());
+
+        writer.delete_term(Term::from_field_text(schema.domain_exact, "a.com"));
+        writer.commit().unwrap();
+
+        let pressure = MergePressure::from_searcher(&index.reader().unwrap().searcher());
+        assert_eq!(pressure.documents(), 4);
+        assert_eq!(pressure.deleted_documents(), 1);
+        assert_eq!(pressure.segments[0].deleted_ratio(), 0.2);
+        assert!(pressure.optimize_recommended());
+        assert!(!pressure.crosses(1, 0.5));
+        assert!(!pressure.crosses(0, 0.0));
+    }
+}
@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+
+/// Shortest stem left by stripping `-s` or `-es`
+const MIN_PLURAL_STEM: usize = 3;
+
+/// Shortest stem left by stripping `-ing` or `-ed`, before a doubled final
+/// consonant is undone (`running` → `runn` → `run`)
+const MIN_VERB_STEM: usize = 4;
+
+/// Light stem of a token: plural `-s`/`-es` and verb `-ing`/`-ed` endings
+/// stripped and `-ies` turned into `-y`, so `shoes` and `shoe`, `parties`
+/// and `party`, or `hosting` and `host` share a stem
+///
+/// Deliberately conservative: stems must keep a vowel and a few letters, so
+/// `bus`, `glass`, `king` and `speed` stay as they are. Only lowercase ASCII
+/// words are stemmed; punycode and tokens with digits are returned as is.
+pub fn stem(token: &str) -> Cow<'_, str> {
+    if token.starts_with("xn--") || !token.bytes().all(|b| b.is_ascii_lowercase()) {
+        return Cow::Borrowed(token);
+    }
+    if let Some(stem) = token.strip_suffix("ies").filter(|stem| stem.len() >= 2) {
+        return Cow::Owned(format!("{stem}y"));
+    }
+    Cow::Borrowed(verb_stem(token).or_else(|| plural_stem(token)).unwrap_or(token))
+}
+
+fn plural_stem(token: &str) -> Option<&str> {
+    let stem = if let Some(stem) = token.strip_suffix("es").filter(|stem| {
+        ["ss", "sh", "ch", "x", "z"].iter().any(|end| stem.ends_with(end))
+    }) {
+        stem
+    } else if token.ends_with("ss") || token.ends_with("us") || token.ends_with("is") {
+        return None;
+    } else {
+        token.strip_suffix('s')?
+    };
+    (stem.len() >= MIN_PLURAL_STEM && has_vowel(stem)).then_some(stem)
+}
+
+fn verb_stem(token: &str) -> Option<&str> {
+    if token.ends_with("eed") {
+        return None;
+    }
+    let stem = token
+        .strip_suffix("ing")
+        .or_else(|| token.strip_suffix("ed"))?;
+    if stem.len() < MIN_VERB_STEM || !has_vowel(stem) {
+        return None;
+    }
+    // `shopping` → `shop`, but `calling` → `call`
+    let bytes = stem.as_bytes();
+    let last = bytes[bytes.len() - 1];
+    if last == bytes[bytes.len() - 2] && !b"aeioulsz".contains(&last) {
+        return Some(&stem[..stem.len() - 1]);
+    }
+    Some(stem)
+}
+
+fn has_vowel(stem: &str) -> bool {
+    stem.bytes().any(|b| b"aeiouy".contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        for (token, expected) in [
+            ("shoes", "shoe"),
+            ("shoe", "shoe"),
+            ("cars", "car"),
+            ("boxes", "box"),
+            ("watches", "watch"),
+            ("classes", "class"),
+            ("parties", "party"),
+            ("pies", "pie"),
+            ("hosting", "host"),
+            ("hosted", "host"),
+            ("shopping", "shop"),
+            ("running", "run"),
+            ("calling", "call"),
+            ("bus", "bus"),
+            ("glass", "glass"),
+            ("analysis", "analysis"),
+            ("king", "king"),
+            ("string", "string"),
+            ("speed", "speed"),
+            ("red", "red"),
+            ("gps", "gps"),
+            ("web3s", "web3s"),
+            ("xn--caf-dma", "xn--caf-dma"),
+        ] {
+            assert_eq!(stem(token), expected, "{token}");
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::domain::{Domain, NormalizedDomain};
+use crate::error::Result;
+use crate::schema::DomainSchema;
+use crate::taxonomy::Taxonomy;
+use std::collections::HashSet;
+use std::path::Path;
+use tantivy::{Index, IndexWriter};
+
+/// Words the generated labels are made of; a few German and French ones
+/// give language detection something to find
+const WORDS: &[&str] = &[
+    "best", "cheap", "coffee", "shop", "cloud", "hosting", "pet", "care", "my", "the", "online",
+    "travel", "deals", "green", "energy", "smart", "home", "city", "food", "market", "pay",
+    "bank", "crypto", "coin", "health", "clinic", "fit", "life", "web", "design", "studio",
+    "car", "rental", "book", "store", "tech", "labs", "data", "secure", "mail", "blue", "red",
+    "sun", "moon", "star", "river", "lake", "mountain", "garden", "house", "kitchen", "chef",
+    "pizza", "burger", "wine", "beer", "tea", "fresh", "organic", "natural", "beauty", "style",
+    "fashion", "shoes", "bag", "jewel", "gold", "silver", "art", "photo", "video", "music",
+    "game", "play", "sport", "team", "club", "school", "learn", "code", "dev", "app", "soft",
+    "net", "link", "hub", "box", "cart", "buy", "sell", "trade", "invest", "loan", "credit",
+    "money", "cash", "fund", "insure", "law", "legal", "doctor", "dental", "pharmacy", "yoga",
+    "hotel", "trip", "tour", "flight", "beach", "island", "global", "local", "easy",
+    "fast", "pro", "prime", "max", "one", "first", "new", "top", "super", "mega", "world",
+    "reisen", "billige", "flug", "haus", "garten", "voyage", "maison", "cuisine", "paris",
+];
+
+/// Top-level domains by share of generated domains, in percent
+const TLDS: &[(&str, u64)] = &[
+    ("com", 50),
+    ("net", 10),
+    ("org", 10),
+    ("io", 8),
+    ("de", 7),
+    ("co", 5),
+    ("ai", 5),
+    ("app", 5),
+];
+
+/// Digit look-alikes used for typosquat-style labels
+const SUBSTITUTIONS: &[(char, char)] = &[('o', '0'), ('l', '1'), ('e', '3'), ('a', '4')];
+
+/// Deterministic stream of plausible, distinct domains with their tokens
+/// filled in as the word splitter would
+///
+/// The same `seed` always yields the same domains in the same order, so
+/// tests and benchmarks can rely on an index without a zone file or the
+/// word splitter. Labels are mostly one to three dictionary words, some
+/// hyphenated or with a number, plus random strings and digit-substituted
+/// typosquats.
+pub struct SyntheticDomains {
+    state: u64,
+    remaining: usize,
+    seen: HashSet<String>,
+}
+
+impl SyntheticDomains {
+    pub fn new(count: usize, seed: u64) -> Self {
+        Self {
+            state: seed,
+            remaining: count,
+            seen: HashSet::with_capacity(count),
+        }
+    }
+
+    /// SplitMix64
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn word(&mut self) -> &'static str {
+        WORDS[self.below(WORDS.len() as u64) as usize]
+    }
+
+    fn words(&mut self, count: usize) -> Vec<String> {
+        (0..count).map(|_| self.word().to_string()).collect()
+    }
+
+    fn tld(&mut self) -> &'static str {
+        let mut roll = self.below(100);
+        for (tld, share) in TLDS {
+            if roll < *share {
+                return tld;
+            }
+            roll -= share;
+        }
+        TLDS[0].0
+    }
+
+    /// A label and its tokens
+    fn label(&mut self) -> (String, Vec<String>) {
+        match self.below(100) {
+            0..=39 => {
+                let words = self.words(2);
+                (words.concat(), words)
+            }
+            40..=54 => {
+                let words = self.words(1);
+                (words.concat(), words)
+            }
+            55..=69 => {
+                let words = self.words(3);
+                (words.concat(), words)
+            }
+            70..=79 => {
+                let words = self.words(2);
+                (words.join("-"), words)
+            }
+            80..=87 => {
+                let mut words = self.words(1);
+                words.push(self.below(1000).to_string());
+                (words.concat(), words)
+            }
+            88..=94 => {
+                let length = 8 + self.below(5);
+                let label: String = (0..length)
+                    .map(|_| char::from(b'a' + self.below(26) as u8))
+                    .collect();
+                (label.clone(), vec![label])
+            }
+            _ => {
+                let word = self.word();
+                let (from, to) = SUBSTITUTIONS[self.below(SUBSTITUTIONS.len() as u64) as usize];
+                let label = word.replacen(from, &to.to_string(), 1);
+                // The splitter finds no words in a typosquat
+                let tokens = if label == word { vec![label.clone()] } else { Vec::new() };
+                (label, tokens)
+            }
+        }
+    }
+}
+
+impl Iterator for SyntheticDomains {
+    type Item = NormalizedDomain;
+
+    fn next(&mut self) -> Option<NormalizedDomain> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            let (label, tokens) = self.label();
+            let domain = format!("{}.{}", label, self.tld());
+            if !self.seen.insert(domain.clone()) {
+                continue;
+            }
+            let Ok(normalized) = Domain::new(domain).normalize() else {
+                continue;
+            };
+            self.remaining -= 1;
+            return Some(normalized.with_tokens(tokens));
+        }
+    }
+}
+
+/// Build an index of `count` synthetic domains, in `directory` or in memory,
+/// tagged with `taxonomy`
+pub fn build_index(
+    directory: Option<&Path>,
+    count: usize,
+    seed: u64,
+    taxonomy: &Taxonomy,
+) -> Result<Index> {
+    let schema = DomainSchema::new();
+    let index = match directory {
+        Some(directory) => {
+            std::fs::create_dir_all(directory)?;
+            Index::create_in_dir(directory, schema.schema.clone())?
+        }
+        None => Index::create_in_ram(schema.schema.clone()),
+    };
+
+    let mut writer: IndexWriter = index.writer(100_000_000)?;
+    for mut domain in SyntheticDomains::new(count, seed) {
+        domain.categories = taxonomy.tag(&domain.label, &domain.tokens);
+        writer.add_document(schema.to_document(&domain))?;
+    }
+    writer.commit()?;
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_domains() {
+        let first: Vec<String> = SyntheticDomains::new(500, 7).map(|d| d.domain_exact).collect();
+        let second: Vec<String> = SyntheticDomains::new(500, 7).map(|d| d.domain_exact).collect();
+        let other: Vec<String> = SyntheticDomains::new(500, 8).map(|d| d.domain_exact).collect();
+
+        assert_eq!(first.len(), 500);
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(first.iter().collect::<HashSet<_>>().len(), 500);
+    }
+
+    #[test]
+    fn test_domains_are_varied() {
+        let domains: Vec<NormalizedDomain> = SyntheticDomains::new(2000, 1).collect();
+
+        assert!(domains.iter().any(|d| d.has_hyphen));
+        assert!(domains.iter().any(|d| d.tld == "io"));
+        assert!(domains.iter().any(|d| d.tokens.len() == 3));
+        assert!(domains.iter().any(|d| d.tokens.is_empty()));
+        assert!(domains.iter().all(|d| d.label.len() <= 63));
+    }
+
+    #[test]
+    fn test_build_index() {
+        let index = build_index(None, 1000, 42, &Taxonomy::default()).unwrap();
+        assert_eq!(index.reader().unwrap().searcher().num_docs(), 1000);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::error::{Error, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Maps label tokens to the categories they belong to, e.g. `loan` and `bank`
+/// to `finance`
+///
+/// Loaded from a JSON object of category names to their tokens:
+///
+/// ```json
+/// { "finance": ["bank", "loan", "credit"], "crypto": ["bitcoin", "nft"] }
+/// ```
+///
+/// A token may belong to several categories. Matching is on whole tokens
+/// from word segmentation, plus the label itself for single-word labels.
+#[derive(Debug, Clone, Default)]
+pub struct Taxonomy {
+    categories: HashMap<String, Vec<String>>,
+}
+
+impl Taxonomy {
+    /// Load a taxonomy file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let taxonomy: HashMap<String, Vec<String>> = serde_json::from_str(&content)?;
+        Self::new(taxonomy)
+    }
+
+    /// Build a taxonomy from category names and their tokens
+    pub fn new(taxonomy: HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut categories: HashMap<String, Vec<String>> = HashMap::new();
+        for (category, tokens) in taxonomy {
+            let category = category.trim().to_lowercase();
+            if !is_valid_category(&category) {
+                return Err(Error::Config(format!(
+                    "Invalid taxonomy category '{}' (use letters, digits, '-' and '_')",
+                    category
+                )));
+            }
+            for token in tokens {
+                let token = token.trim().to_lowercase();
+                if token.is_empty() {
+                    continue;
+                }
+                let entry = categories.entry(token).or_default();
+                if !entry.contains(&category) {
+                    entry.push(category.clone());
+                }
+            }
+        }
+        Ok(Self { categories })
+    }
+
+    /// Whether no token maps to a category
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+
+    /// Sorted, deduplicated categories of a label and its tokens
+    pub fn tag(&self, label: &str, tokens: &[String]) -> Vec<String> {
+        if self.categories.is_empty() {
+            return Vec::new();
+        }
+        let categories: BTreeSet<&String> = tokens
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(label))
+            .filter_map(|token| self.categories.get(token))
+            .flatten()
+            .collect();
+        categories.into_iter().cloned().collect()
+    }
+}
+
+/// Category names become facet paths, so they are kept to one simple segment
+pub fn is_valid_category(category: &str) -> bool {
+    !category.is_empty()
+        && category
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taxonomy() -> Taxonomy {
+        let json = r#"{
+            "finance": ["bank", "Loan", "credit"],
+            "crypto": ["bitcoin", "coin", "credit"],
+            "Health": ["clinic"]
+        }"#;
+        Taxonomy::new(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    fn tokens(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tag_tokens() {
+        let taxonomy = taxonomy();
+        assert_eq!(taxonomy.tag("bestloan", &tokens(&["best", "loan"])), vec!["finance"]);
+        assert_eq!(
+            taxonomy.tag("bitcoinbank", &tokens(&["bitcoin", "bank"])),
+            vec!["crypto", "finance"]
+        );
+        assert_eq!(taxonomy.tag("credit", &[]), vec!["crypto", "finance"]);
+        assert_eq!(taxonomy.tag("myclinic", &tokens(&["my", "clinic"])), vec!["health"]);
+        assert!(taxonomy.tag("coffeeshop", &tokens(&["coffee", "shop"])).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_category() {
+        let taxonomy = HashMap::from([("adult/18".to_string(), vec!["x".to_string()])]);
+        assert!(matches!(Taxonomy::new(taxonomy), Err(Error::Config(_))));
+        assert!(Taxonomy::default().tag("bank", &tokens(&["bank"])).is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::error::{Error, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use std::str::FromStr;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+/// Standard OpenTelemetry variable naming the OTLP/gRPC collector
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Standard OpenTelemetry variable overriding the reported service name
+pub const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+/// Variable choosing the [`LogFormat`]
+pub const LOG_FORMAT_ENV: &str = "LOG_FORMAT";
+
+/// How log lines are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One line per event with its span context
+    #[default]
+    Full,
+    /// Multi-line, for reading in a terminal
+    Pretty,
+    /// Shorter lines, without span names
+    Compact,
+    /// One JSON object per line, event and span fields flattened into it,
+    /// for Loki or ELK
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "full" => Ok(Self::Full),
+            "pretty" => Ok(Self::Pretty),
+            "compact" => Ok(Self::Compact),
+            "json" => Ok(Self::Json),
+            other => Err(Error::Config(format!(
+                "Invalid {}: {} (expected full, pretty, compact or json)",
+                LOG_FORMAT_ENV, other
+            ))),
+        }
+    }
+}
+
+impl LogFormat {
+    fn layer<S>(self) -> Box<dyn Layer<S> + Send + Sync>
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        let layer = tracing_subscriber::fmt::layer();
+        match self {
+            Self::Full => layer.boxed(),
+            Self::Pretty => layer.pretty().boxed(),
+            Self::Compact => layer.compact().boxed(),
+            Self::Json => layer
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(false)
+                .boxed(),
+        }
+    }
+}
+
+/// Installed tracing; flushes pending spans to the collector when dropped
+pub struct Telemetry {
+    provider: Option<TracerProvider>,
+}
+
+impl Telemetry {
+    /// Whether spans are exported over OTLP
+    pub fn exporting(&self) -> bool {
+        self.provider.is_some()
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global tracing subscriber
+///
+/// Logs are filtered by `RUST_LOG` (falling back to `default_filter`) and
+/// written in the `LOG_FORMAT` [`LogFormat`]. When
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans are also exported to that
+/// collector as `service_name` (or `OTEL_SERVICE_NAME`). Must be called
+/// inside a multi-threaded Tokio runtime, and the returned guard kept alive
+/// until exit.
+pub fn init(service_name: &str, default_filter: &str) -> Result<Telemetry> {
+    let filter = tracing_subscriber::EnvFilter::new(
+        std::env::var("RUST_LOG").unwrap_or_else(|_| default_filter.to_string()),
+    );
+    let format: LogFormat = std::env::var(LOG_FORMAT_ENV).unwrap_or_default().parse()?;
+
+    let endpoint = std::env::var(OTLP_ENDPOINT_ENV)
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty());
+    let provider = match endpoint {
+        Some(endpoint) => {
+            let service_name = std::env::var(SERVICE_NAME_ENV)
+                .unwrap_or_else(|_| service_name.to_string());
+            Some(tracer_provider(&endpoint, service_name)?)
+        }
+        None => None,
+    };
+
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("domain-search"))
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(format.layer())
+        .with(otel_layer)
+        .init();
+
+    Ok(Telemetry { provider })
+}
+
+fn tracer_provider(endpoint: &str, service_name: String) -> Result<TracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| Error::Config(format!("Invalid OTLP exporter: {}", e)))?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new("service.name", service_name)]))
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!("".parse::<LogFormat>().unwrap(), LogFormat::Full);
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("compact".parse::<LogFormat>().unwrap(), LogFormat::Compact);
+        assert!("logfmt".parse::<LogFormat>().is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::{Error, Result};
+use crate::schema::DomainSchema;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use tantivy::collector::FacetCollector;
+use tantivy::query::AllQuery;
+
+/// Per-TLD document counts of one day
+pub type TldCounts = BTreeMap<String, u64>;
+
+/// Daily per-TLD document counts, by date (`YYYY-MM-DD`)
+///
+/// Stored as CSV lines `date,tld,count`, one snapshot per day; recording a
+/// day again replaces its counts.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TldHistory {
+    pub days: BTreeMap<String, TldCounts>,
+}
+
+/// How one TLD changed between two snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct TldGrowth {
+    pub tld: String,
+    /// Count in the first snapshot of the window (0 if the TLD is newer)
+    pub start: u64,
+    /// Count in the latest snapshot (0 if the TLD is gone)
+    pub end: u64,
+}
+
+impl TldGrowth {
+    pub fn change(&self) -> i64 {
+        self.end as i64 - self.start as i64
+    }
+
+    /// Relative change in percent, None for TLDs new in the window
+    pub fn percent(&self) -> Option<f64> {
+        (self.start > 0).then(|| self.change() as f64 * 100.0 / self.start as f64)
+    }
+}
+
+impl TldHistory {
+    /// Read the stored counts; a missing file is an empty history
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut days: BTreeMap<String, TldCounts> = BTreeMap::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || {
+                Error::Config(format!("{}:{}: invalid line", path.display(), number + 1))
+            };
+            let mut fields = line.split(',');
+            let (Some(date), Some(tld), Some(count), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            let count = count.trim().parse().map_err(|_| invalid())?;
+            days.entry(date.to_string()).or_default().insert(tld.to_string(), count);
+        }
+        Ok(Self { days })
+    }
+
+    /// Write the history, replacing the file atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("tmp");
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&temp)?);
+        for (date, counts) in &self.days {
+            for (tld, count) in counts {
+                writeln!(file, "{},{},{}", date, tld, count)?;
+            }
+        }
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Store a day's counts, replacing any recorded earlier that day
+    pub fn record(&mut self, date: &str, counts: TldCounts) {
+        self.days.insert(date.to_string(), counts);
+    }
+
+    /// Growth of every TLD from the first snapshot on or after `since`
+    /// (`YYYY-MM-DD`) to the latest one, largest TLDs first
+    pub fn growth(&self, since: &str) -> Vec<TldGrowth> {
+        let mut window = self.days.range(since.to_string()..);
+        let (Some((_, first)), Some((_, last))) = (window.next(), self.days.last_key_value())
+        else {
+            return Vec::new();
+        };
+
+        let mut growth: Vec<TldGrowth> = first
+            .keys()
+            .chain(last.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|tld| TldGrowth {
+                tld: tld.clone(),
+                start: first.get(tld).copied().unwrap_or(0),
+                end: last.get(tld).copied().unwrap_or(0),
+            })
+            .collect();
+        growth.sort_by_key(|g| std::cmp::Reverse(g.end));
+        growth
+    }
+}
+
+/// Live documents per TLD in an index
+pub fn count_tlds(searcher: &tantivy::Searcher, schema: &DomainSchema) -> Result<TldCounts> {
+    let field_name = schema.schema.get_field_name(schema.tld);
+    let mut collector = FacetCollector::for_field(field_name);
+    collector.add_facet("/");
+    let facets = searcher.search(&AllQuery, &collector)?;
+
+    Ok(facets
+        .get("/")
+        .filter_map(|(facet, count)| {
+            let tld = facet.to_path().last()?.to_string();
+            Some((tld, count))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Domain;
+    use tantivy::{Index, IndexWriter};
+
+    #[test]
+    fn test_count_tlds() {
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer: IndexWriter = index.writer(15_000_000).unwrap();
+        for domain in ["coffee.com", "tea.com", "coffee.net"] {
+            let normalized = Domain::new(domain).normalize().unwrap();
+            writer.add_document(schema.to_document(&normalized)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let counts = count_tlds(&index.reader().unwrap().searcher(), &schema).unwrap();
+        assert_eq!(counts.get("com"), Some(&2));
+        assert_eq!(counts.get("net"), Some(&1));
+    }
+
+    #[test]
+    fn test_history_round_trip_and_growth() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tld-stats.csv");
+        assert!(TldHistory::load(&path).unwrap().days.is_empty());
+
+        let counts = |entries: &[(&str, u64)]| -> TldCounts {
+            entries.iter().map(|(tld, n)| (tld.to_string(), *n)).collect()
+        };
+        let mut history = TldHistory::default();
+        history.record("2024-01-01", counts(&[("com", 100), ("net", 50)]));
+        history.record("2024-02-01", counts(&[("com", 110), ("net", 40)]));
+        history.record("2024-03-01", counts(&[("com", 120), ("net", 45), ("dev", 5)]));
+        history.record("2024-03-01", counts(&[("com", 125), ("net", 45), ("dev", 5)]));
+        history.save(&path).unwrap();
+
+        let loaded = TldHistory::load(&path).unwrap();
+        assert_eq!(loaded, history);
+
+        let growth = loaded.growth("2024-01-15");
+        let tlds: Vec<_> = growth.iter().map(|g| (g.tld.as_str(), g.start, g.end)).collect();
+        assert_eq!(tlds, vec![("com", 110, 125), ("net", 40, 45), ("dev", 0, 5)]);
+        assert_eq!(growth[0].change(), 15);
+        assert_eq!(growth[2].percent(), None);
+        assert!(loaded.growth("2025-01-01").is_empty());
+    }
+}
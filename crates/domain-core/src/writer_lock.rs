@@ -0,0 +1,169 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tantivy::directory::error::LockError;
+use tantivy::directory::{Directory, MmapDirectory, INDEX_WRITER_LOCK};
+use tantivy::schema::document::Document;
+use tantivy::{Index, IndexWriter, TantivyError};
+use tracing::warn;
+
+/// Tantivy's writer lock in an index directory
+pub const LOCK_FILE: &str = ".tantivy-writer.lock";
+
+/// Who took the writer, written beside the lock
+pub const PID_FILE: &str = ".tantivy-writer.pid";
+
+/// The process that took an index writer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Holder {
+    pub pid: u32,
+    pub host: String,
+    pub command: String,
+    /// Unix seconds
+    pub started: u64,
+}
+
+impl Holder {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: hostname(),
+            command: std::env::args().collect::<Vec<_>>().join(" "),
+            started: now(),
+        }
+    }
+
+    /// Whether the process still runs, when that can be told: only for
+    /// processes on this host, and only where `/proc` exists
+    pub fn is_running(&self) -> Option<bool> {
+        if self.host != hostname() || !Path::new("/proc/self").exists() {
+            return None;
+        }
+        Some(Path::new(&format!("/proc/{}", self.pid)).exists())
+    }
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elapsed = now().saturating_sub(self.started);
+        write!(
+            f,
+            "pid {} on {} (`{}`), started {}s ago",
+            self.pid, self.host, self.command, elapsed
+        )?;
+        match self.is_running() {
+            Some(true) => write!(f, ", still running"),
+            Some(false) => write!(f, ", no longer running"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The holder recorded for an index, if any
+///
+/// The file is left behind when its writer goes away, so it only says who
+/// holds the lock while the lock is actually taken (see [`is_locked`]).
+pub fn holder(index_path: &Path) -> Option<Holder> {
+    let content = std::fs::read_to_string(index_path.join(PID_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Whether some process holds the writer of an index
+pub fn is_locked(index_path: &Path) -> Result<bool> {
+    match MmapDirectory::open(index_path)
+        .map_err(TantivyError::from)?
+        .acquire_lock(&INDEX_WRITER_LOCK)
+    {
+        Ok(_lock) => Ok(false),
+        Err(LockError::LockBusy) => Ok(true),
+        Err(LockError::IoError(e)) => Err(Error::Io(std::io::Error::new(e.kind(), e.to_string()))),
+    }
+}
+
+/// Take the writer of an index at `index_path`, recording this process as
+/// its holder
+///
+/// Fails with [`Error::WriterLocked`], naming the holder when known, if
+/// another process is writing the index.
+pub fn open_writer<D: Document>(
+    index: &Index,
+    index_path: &Path,
+    heap_size: usize,
+) -> Result<IndexWriter<D>> {
+    let writer = match index.writer(heap_size) {
+        Ok(writer) => writer,
+        Err(TantivyError::LockFailure(LockError::LockBusy, _)) => {
+            return Err(Error::WriterLocked {
+                path: index_path.to_path_buf(),
+                holder: holder(index_path),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let pid_file = index_path.join(PID_FILE);
+    let written = serde_json::to_string(&Holder::current())
+        .map_err(Error::from)
+        .and_then(|json| Ok(std::fs::write(&pid_file, json)?));
+    if let Err(e) = written {
+        warn!(path = ?pid_file, error = %e, "Failed to record the index writer");
+    }
+    Ok(writer)
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_default()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DomainSchema;
+
+    #[test]
+    fn test_open_writer_reports_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = Index::create_in_dir(dir.path(), DomainSchema::new().schema).unwrap();
+        assert!(!is_locked(dir.path()).unwrap());
+
+        let writer: IndexWriter = open_writer(&index, dir.path(), 15_000_000).unwrap();
+        assert!(is_locked(dir.path()).unwrap());
+        let recorded = holder(dir.path()).unwrap();
+        assert_eq!(recorded.pid, std::process::id());
+        assert_ne!(recorded.is_running(), Some(false));
+
+        match open_writer::<tantivy::TantivyDocument>(&index, dir.path(), 15_000_000) {
+            Err(Error::WriterLocked { holder, .. }) => assert_eq!(holder, Some(recorded)),
+            other => panic!("expected a locked writer, got {:?}", other.map(|_| ())),
+        }
+
+        drop(writer);
+        assert!(!is_locked(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_holder_display() {
+        let holder = Holder {
+            pid: 42,
+            host: "elsewhere.invalid".to_string(),
+            command: "domain-indexer daily".to_string(),
+            started: now(),
+        };
+        assert_eq!(holder.is_running(), None);
+        assert!(holder
+            .to_string()
+            .starts_with("pid 42 on elsewhere.invalid (`domain-indexer daily`)"));
+    }
+}
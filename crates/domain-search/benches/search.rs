@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use domain_core::{synthetic, DomainSchema, Taxonomy};
+use domain_search::{Dedupe, SearchRequest, Searcher};
+
+/// Domains in the benchmark index
+const DOCS: usize = 50_000;
+
+/// An in-memory index of synthetic domains, the same on every run
+fn index() -> Searcher {
+    let index = synthetic::build_index(None, DOCS, 42, &Taxonomy::default()).unwrap();
+    Searcher::new(&index, DomainSchema::new()).unwrap()
+}
+
+fn bench_search(c: &mut Criterion) {
+    let searcher = index();
+    let mut group = c.benchmark_group("search");
+
+    for query in ["coffee", "cheap coffee shop", "petcare", "the best online store"] {
+        let request = SearchRequest::new(query, 50);
+        group.bench_with_input(BenchmarkId::new("plain", query), &request, |b, request| {
+            b.iter(|| searcher.search(request).unwrap())
+        });
+    }
+
+    let mut request = SearchRequest::new("cloud hosting", 50);
+    request.min_match = Some(2);
+    group.bench_function("min_match", |b| b.iter(|| searcher.search(&request).unwrap()));
+
+    let mut request = SearchRequest::new("cloud hosting", 50);
+    request.tld = Some("com,io".to_string());
+    group.bench_function("tld_filter", |b| b.iter(|| searcher.search(&request).unwrap()));
+
+    let mut request = SearchRequest::new("coffee", 50);
+    request.dedupe = Dedupe::Label;
+    group.bench_function("dedupe", |b| b.iter(|| searcher.search(&request).unwrap()));
+
+    let mut request = SearchRequest::new("smart home", 50);
+    request.hacks = true;
+    group.bench_function("hacks", |b| b.iter(|| searcher.search(&request).unwrap()));
+
+    let request = SearchRequest::new("coffee", 50);
+    group.bench_function("progressive", |b| {
+        b.iter(|| searcher.search_progressive(&request, 250, |_| true).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
@@ -0,0 +1,399 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Longest word looked up when segmenting a label
+const MAX_WORD_LENGTH: usize = 20;
+
+/// Labels this short or shorter get the full length factor
+const PREMIUM_LENGTH: usize = 3;
+
+/// Labels this long or longer get no length factor
+const WORTHLESS_LENGTH: usize = 20;
+
+/// Registrations of the same label from which the comparables factor is 1
+const COMPARABLES_CAP: usize = 20;
+
+/// A value band, reached from `min_score` up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ValueBand {
+    pub min_score: f64,
+    pub band: String,
+}
+
+/// Weights of the appraisal formula
+///
+/// The score is the sum of each factor (0-1) times its weight, clamped to
+/// 0-100. `hyphen` and `digits` count once for labels containing any and are
+/// normally negative.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AppraisalWeights {
+    /// For shortness: 1 at 3 characters or fewer, 0 at 20 or more
+    pub length: f64,
+    /// For dictionary words: how common the label's words are among indexed
+    /// names, times the share of the label they cover
+    pub words: f64,
+    /// For the TLD, valued by `tld_values`
+    pub tld: f64,
+    /// For registrations of the same label under other TLDs
+    pub comparables: f64,
+    pub hyphen: f64,
+    pub digits: f64,
+    /// Value of each TLD, 0-1
+    pub tld_values: BTreeMap<String, f64>,
+    /// Value of TLDs missing from `tld_values`
+    pub other_tld: f64,
+    /// Value bands, in any order; a score gets the band with the highest
+    /// `min_score` it reaches
+    pub bands: Vec<ValueBand>,
+}
+
+impl Default for AppraisalWeights {
+    fn default() -> Self {
+        let tld_values = [
+            ("com", 1.0),
+            ("ai", 0.7),
+            ("io", 0.6),
+            ("net", 0.5),
+            ("org", 0.5),
+            ("co", 0.5),
+        ];
+        let bands = [
+            (80.0, "$10,000+"),
+            (60.0, "$2,500-$10,000"),
+            (40.0, "$500-$2,500"),
+            (20.0, "$100-$500"),
+            (0.0, "under $100"),
+        ];
+        Self {
+            length: 30.0,
+            words: 30.0,
+            tld: 20.0,
+            comparables: 20.0,
+            hyphen: -15.0,
+            digits: -10.0,
+            tld_values: tld_values
+                .into_iter()
+                .map(|(tld, value)| (tld.to_string(), value))
+                .collect(),
+            other_tld: 0.2,
+            bands: bands
+                .into_iter()
+                .map(|(min_score, band)| ValueBand {
+                    min_score,
+                    band: band.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl AppraisalWeights {
+    /// Default weights overlaid with a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| Error::InvalidAppraisal(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&data)
+            .map_err(|e| Error::InvalidAppraisal(format!("{}: {}", path.display(), e)))
+    }
+
+    fn band(&self, score: f64) -> String {
+        self.bands
+            .iter()
+            .filter(|band| score >= band.min_score)
+            .max_by(|a, b| a.min_score.total_cmp(&b.min_score))
+            .map(|band| band.band.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// A word of an appraised label, with the indexed names containing it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AppraisedWord {
+    pub word: String,
+    /// 0 for runs of characters that aren't an indexed word
+    pub doc_freq: u64,
+}
+
+/// One term of the appraisal formula
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AppraisalFactor {
+    /// `length`, `words`, `tld`, `comparables`, `hyphen` or `digits`
+    pub factor: String,
+    /// 0-1
+    pub value: f64,
+    pub weight: f64,
+    /// `value` times `weight`, the factor's share of the score
+    pub contribution: f64,
+}
+
+/// Heuristic value estimate of a domain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Appraisal {
+    pub domain: String,
+    /// Whether the domain is in the index
+    pub registered: bool,
+    /// 0-100
+    pub score: f64,
+    pub band: String,
+    pub factors: Vec<AppraisalFactor>,
+    /// Segmentation of the label into indexed words
+    pub words: Vec<AppraisedWord>,
+    /// Other TLDs with the same label registered
+    pub comparables: Vec<String>,
+}
+
+/// What the index knows about an appraised domain
+pub(crate) struct Evidence {
+    pub registered: bool,
+    pub words: Vec<AppraisedWord>,
+    pub comparables: Vec<String>,
+    /// Documents in the index
+    pub num_docs: u64,
+}
+
+/// Score a domain from the index's evidence
+pub(crate) fn appraise(
+    domain: &domain_core::NormalizedDomain,
+    evidence: Evidence,
+    weights: &AppraisalWeights,
+) -> Appraisal {
+    let label = domain.label.as_str();
+
+    let length = label.chars().count();
+    let length_value = if length <= PREMIUM_LENGTH {
+        1.0
+    } else {
+        let span = (WORTHLESS_LENGTH - PREMIUM_LENGTH) as f64;
+        (1.0 - (length - PREMIUM_LENGTH) as f64 / span).max(0.0)
+    };
+
+    let known: Vec<&AppraisedWord> = evidence.words.iter().filter(|w| w.doc_freq > 0).collect();
+    let covered: usize = known.iter().map(|w| w.word.len()).sum();
+    let total: usize = evidence.words.iter().map(|w| w.word.len()).sum();
+    let words_value = if known.is_empty() || total == 0 {
+        0.0
+    } else {
+        let max = (evidence.num_docs as f64).ln_1p();
+        let frequency = known
+            .iter()
+            .map(|w| (w.doc_freq as f64).ln_1p() / max)
+            .sum::<f64>()
+            / known.len() as f64;
+        (frequency * covered as f64 / total as f64).min(1.0)
+    };
+
+    let tld_value = weights
+        .tld_values
+        .get(&domain.tld)
+        .copied()
+        .unwrap_or(weights.other_tld);
+    let comparables_value =
+        (evidence.comparables.len() as f64).ln_1p() / (COMPARABLES_CAP as f64).ln_1p();
+    let flag = |present: bool| if present { 1.0 } else { 0.0 };
+
+    let factors: Vec<AppraisalFactor> = [
+        ("length", length_value, weights.length),
+        ("words", words_value, weights.words),
+        ("tld", tld_value, weights.tld),
+        ("comparables", comparables_value.min(1.0), weights.comparables),
+        ("hyphen", flag(domain.has_hyphen), weights.hyphen),
+        ("digits", flag(label.chars().any(|c| c.is_ascii_digit())), weights.digits),
+    ]
+    .into_iter()
+    .map(|(factor, value, weight)| AppraisalFactor {
+        factor: factor.to_string(),
+        value: round(value),
+        weight,
+        contribution: round(value * weight),
+    })
+    .collect();
+
+    let score = round(factors.iter().map(|f| f.contribution).sum::<f64>().clamp(0.0, 100.0));
+    Appraisal {
+        domain: domain.domain_exact.clone(),
+        registered: evidence.registered,
+        score,
+        band: weights.band(score),
+        factors,
+        words: evidence.words,
+        comparables: evidence.comparables,
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Split a label into the most probable sequence of indexed words
+///
+/// Hyphens separate words; characters not covered by any word are kept as
+/// runs with a `doc_freq` of 0. `doc_freq` gives the indexed names
+/// containing a word, out of `num_docs`.
+pub(crate) fn segment(
+    label: &str,
+    num_docs: u64,
+    mut doc_freq: impl FnMut(&str) -> Result<u64>,
+) -> Result<Vec<AppraisedWord>> {
+    let total = (num_docs as f64).max(1.0) + 1.0;
+    // Any indexed word is more probable than a single unknown character
+    let unknown = (1.0 / total).ln() - 1.0;
+    let mut doc_freqs: HashMap<&str, u64> = HashMap::new();
+
+    let mut words = Vec::new();
+    for part in label.split('-').filter(|part| !part.is_empty()) {
+        if !part.is_ascii() {
+            words.push(AppraisedWord {
+                word: part.to_string(),
+                doc_freq: 0,
+            });
+            continue;
+        }
+
+        // best[i]: log probability of the best split of part[..i], and
+        // where its last word starts
+        let n = part.len();
+        let mut best: Vec<(f64, usize)> = vec![(f64::NEG_INFINITY, 0); n + 1];
+        best[0].0 = 0.0;
+        for end in 1..=n {
+            for start in end.saturating_sub(MAX_WORD_LENGTH)..end {
+                let word = &part[start..end];
+                let freq = match doc_freqs.get(word) {
+                    Some(&freq) => freq,
+                    None => {
+                        let freq = doc_freq(word)?;
+                        doc_freqs.insert(word, freq);
+                        freq
+                    }
+                };
+                let cost = if freq > 0 && (word.len() > 1 || n == 1) {
+                    (freq as f64 / total).ln()
+                } else if word.len() == 1 {
+                    unknown
+                } else {
+                    continue;
+                };
+                if best[start].0 + cost > best[end].0 {
+                    best[end] = (best[start].0 + cost, start);
+                }
+            }
+        }
+
+        let mut bounds = vec![n];
+        let mut at = n;
+        while at > 0 {
+            at = best[at].1;
+            bounds.push(at);
+        }
+        bounds.reverse();
+
+        // Merge adjacent unknown characters into one run
+        let mut part_words: Vec<AppraisedWord> = Vec::new();
+        for window in bounds.windows(2) {
+            let word = &part[window[0]..window[1]];
+            let freq = doc_freqs.get(word).copied().unwrap_or(0);
+            let freq = if word.len() > 1 || n == 1 { freq } else { 0 };
+            match part_words.last_mut() {
+                Some(last) if last.doc_freq == 0 && freq == 0 => last.word.push_str(word),
+                _ => part_words.push(AppraisedWord {
+                    word: word.to_string(),
+                    doc_freq: freq,
+                }),
+            }
+        }
+        words.extend(part_words);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::Domain;
+
+    fn dictionary(word: &str) -> Result<u64> {
+        Ok(match word {
+            "coffee" => 5_000,
+            "shop" => 20_000,
+            "cof" => 10,
+            "fee" => 800,
+            "best" => 30_000,
+            _ => 0,
+        })
+    }
+
+    fn words(label: &str) -> Vec<(String, u64)> {
+        segment(label, 1_000_000, dictionary)
+            .unwrap()
+            .into_iter()
+            .map(|w| (w.word, w.doc_freq))
+            .collect()
+    }
+
+    #[test]
+    fn test_segment_prefers_common_words() {
+        assert_eq!(
+            words("coffeeshop"),
+            vec![("coffee".to_string(), 5_000), ("shop".to_string(), 20_000)]
+        );
+        assert_eq!(
+            words("best-xqzcoffee"),
+            vec![
+                ("best".to_string(), 30_000),
+                ("xqz".to_string(), 0),
+                ("coffee".to_string(), 5_000),
+            ]
+        );
+        assert_eq!(words("xqz"), vec![("xqz".to_string(), 0)]);
+    }
+
+    #[test]
+    fn test_appraise_factors_and_band() {
+        let weights = AppraisalWeights::default();
+        let appraise_domain = |domain: &str, comparables: usize| {
+            let domain = Domain::new(domain).normalize().unwrap();
+            let evidence = Evidence {
+                registered: true,
+                words: segment(&domain.label, 1_000_000, dictionary).unwrap(),
+                comparables: vec!["net".to_string(); comparables],
+                num_docs: 1_000_000,
+            };
+            appraise(&domain, evidence, &weights)
+        };
+
+        let good = appraise_domain("coffeeshop.com", 12);
+        let poor = appraise_domain("xqz-coffee-shop-24.biz", 0);
+        assert!(good.score > poor.score);
+        assert!(good.score > 50.0, "{:?}", good);
+        assert_eq!(poor.band, "under $100");
+
+        let hyphen = poor.factors.iter().find(|f| f.factor == "hyphen").unwrap();
+        assert_eq!(hyphen.contribution, -15.0);
+        let sum: f64 = good.factors.iter().map(|f| f.contribution).sum();
+        assert!((sum - good.score).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_weights_file_overrides_defaults() {
+        let dir = std::env::temp_dir().join(format!("appraisal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("weights.json");
+        std::fs::write(&path, r#"{"hyphen": 0, "tld_values": {"dev": 0.9}}"#).unwrap();
+
+        let weights = AppraisalWeights::load(&path).unwrap();
+        assert_eq!(weights.hyphen, 0.0);
+        assert_eq!(weights.length, 30.0);
+        assert_eq!(weights.tld_values.get("dev"), Some(&0.9));
+        assert_eq!(weights.tld_values.get("com"), None);
+        assert_eq!(weights.band(65.0), "$2,500-$10,000");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+use crate::compound::Variant;
+use crate::profile::{Interleave, RankingProfile};
+use crate::ranking::Signals;
+use crate::result::TokenWeight;
+use domain_core::DomainSchema;
+use std::sync::Arc;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::columnar::Column;
+use tantivy::fieldnorm::FieldNormReader;
+use tantivy::postings::SegmentPostings;
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{DocAddress, DocId, DocSet, Score, SegmentOrdinal, SegmentReader, Term};
+
+/// Share of a token's weight added to `match_weight` when it matched by its
+/// stem only, so the form searched for ranks above other inflections
+const STEM_MATCH_WEIGHT: f32 = 0.5;
+
+/// Share of a token's weight added to `match_weight` when it matched a word
+/// of an unsegmented label, below a stem since the label isn't segmented
+const LABEL_MATCH_WEIGHT: f32 = 0.25;
+
+/// A document that passed the filters, with everything needed to rank it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Candidate {
+    pub doc: DocAddress,
+    pub signals: Signals,
+    /// Bit `i` is set when query token `i` matched (first 64 tokens)
+    pub matched: u64,
+}
+
+/// The best `k` candidates under a ranking profile
+///
+/// Candidates are buffered and pruned back to `k` whenever the buffer
+/// reaches `2k`, so each push costs amortized O(1) comparisons.
+#[derive(Clone)]
+pub(crate) struct TopK {
+    candidates: Vec<Candidate>,
+    k: usize,
+    profile: Arc<RankingProfile>,
+    /// Candidates were pruned past the best `k`
+    truncated: bool,
+}
+
+impl TopK {
+    fn new(k: usize, profile: Arc<RankingProfile>) -> Self {
+        Self {
+            candidates: Vec::new(),
+            k,
+            profile,
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, candidate: Candidate) {
+        self.candidates.push(candidate);
+        if self.candidates.len() >= self.k.saturating_mul(2) {
+            self.prune();
+        }
+    }
+
+    fn prune(&mut self) {
+        if self.candidates.len() > self.k {
+            let profile = &self.profile;
+            self.candidates
+                .select_nth_unstable_by(self.k, |a, b| profile.compare(&a.signals, &b.signals));
+            self.candidates.truncate(self.k);
+            self.truncated = true;
+        }
+    }
+
+    fn merge(&mut self, other: TopK) {
+        self.truncated |= other.truncated;
+        for candidate in other.candidates {
+            self.push(candidate);
+        }
+    }
+
+    /// More than `k` candidates were pushed
+    fn over_k(&self) -> bool {
+        self.truncated || self.candidates.len() > self.k
+    }
+
+    /// The best `k` candidates, best first
+    fn into_sorted(mut self) -> Vec<Candidate> {
+        self.prune();
+        let profile = &self.profile;
+        self.candidates
+            .sort_by(|a, b| profile.compare(&a.signals, &b.signals));
+        self.candidates
+    }
+}
+
+/// Best candidates of one or more segments
+#[derive(Clone)]
+pub(crate) struct Candidates {
+    /// Hyphenated domains, kept apart when the profile interleaves them
+    hyphenated: TopK,
+    /// All other domains
+    plain: TopK,
+    /// Documents matching the query
+    pub scanned: usize,
+    /// Documents that passed the match count and profile filters
+    pub matched: usize,
+}
+
+impl Candidates {
+    fn new(k: usize, profile: Arc<RankingProfile>) -> Self {
+        Self {
+            hyphenated: TopK::new(k, profile.clone()),
+            plain: TopK::new(k, profile),
+            scanned: 0,
+            matched: 0,
+        }
+    }
+
+    fn push(&mut self, candidate: Candidate) {
+        self.matched += 1;
+        let interleaved = self.plain.profile.interleave == Interleave::Hyphen;
+        if interleaved && candidate.signals.has_hyphen {
+            self.hyphenated.push(candidate);
+        } else {
+            self.plain.push(candidate);
+        }
+    }
+
+    pub fn merge(&mut self, other: Candidates) {
+        self.hyphenated.merge(other.hyphenated);
+        self.plain.merge(other.plain);
+        self.scanned += other.scanned;
+        self.matched += other.matched;
+    }
+
+    /// Whether candidates that could be listed were left out for the best
+    /// `k`, so collecting more could lengthen a short result list
+    ///
+    /// Hyphenated candidates only count when the profile lists any.
+    pub fn truncated(&self) -> bool {
+        let hyphens_listed = self.plain.profile.hyphen_ratio > 0.0;
+        self.plain.over_k() || (hyphens_listed && self.hyphenated.over_k())
+    }
+
+    /// The best hyphenated candidates followed by the best plain ones
+    pub fn into_sorted(self) -> Vec<Candidate> {
+        let mut candidates = self.hyphenated.into_sorted();
+        candidates.extend(self.plain.into_sorted());
+        candidates
+    }
+}
+
+/// Ranks every document matching the query in a single pass
+///
+/// Match count and weight come from the postings of the query tokens and
+/// their compound variants, label length, hyphenation and DGA score from
+/// fast fields, so filtering and ranking happen during collection and only
+/// the winners' stored documents are loaded.
+pub(crate) struct CandidateCollector {
+    /// Distinct index terms of all variants, in `tokens`, `stems` or `label`
+    terms: Vec<Term>,
+    variants: Arc<[CompiledVariant]>,
+    keywords: Arc<[Keyword]>,
+    tokens_field: Field,
+    len_field: String,
+    has_hyphen_field: String,
+    dga_score_field: String,
+    min_match: usize,
+    profile: Arc<RankingProfile>,
+    k: usize,
+}
+
+impl CandidateCollector {
+    pub fn new(
+        schema: &DomainSchema,
+        tokens: &[TokenWeight],
+        variants: &[Variant],
+        min_match: usize,
+        profile: &RankingProfile,
+        k: usize,
+    ) -> Self {
+        let mut terms: Vec<Term> = Vec::new();
+        let variants = variants
+            .iter()
+            .map(|variant| CompiledVariant {
+                terms: variant
+                    .tokens
+                    .iter()
+                    .map(|token| {
+                        let field = if variant.stemmed {
+                            schema.stems
+                        } else if variant.label {
+                            schema.label
+                        } else {
+                            schema.tokens
+                        };
+                        let term = Term::from_field_text(field, token);
+                        terms.iter().position(|t| *t == term).unwrap_or_else(|| {
+                            terms.push(term);
+                            terms.len() - 1
+                        })
+                    })
+                    .collect(),
+                covers: variant.covers.clone(),
+                compound: variant.compound,
+                label: variant.label,
+                share: if variant.stemmed {
+                    STEM_MATCH_WEIGHT
+                } else if variant.label {
+                    LABEL_MATCH_WEIGHT
+                } else {
+                    1.0
+                },
+            })
+            .collect();
+
+        Self {
+            terms,
+            variants,
+            keywords: tokens
+                .iter()
+                .map(|token| Keyword {
+                    weight: token.weight,
+                    stop: token.stop,
+                })
+                .collect(),
+            tokens_field: schema.tokens,
+            len_field: schema.schema.get_field_name(schema.len).to_string(),
+            has_hyphen_field: schema.schema.get_field_name(schema.has_hyphen).to_string(),
+            dga_score_field: schema.schema.get_field_name(schema.dga_score).to_string(),
+            min_match,
+            profile: Arc::new(profile.clone()),
+            k: k.max(1),
+        }
+    }
+
+    pub fn empty(&self) -> Candidates {
+        Candidates::new(self.k, self.profile.clone())
+    }
+}
+
+/// A query token as scored when matched
+struct Keyword {
+    /// IDF weight, added to `match_weight`
+    weight: f32,
+    /// Matches don't count towards `match_count`
+    stop: bool,
+}
+
+/// A [`Variant`] with its tokens resolved to positions in `terms`
+struct CompiledVariant {
+    terms: Vec<usize>,
+    covers: Vec<usize>,
+    compound: bool,
+    /// Matched against the label, for documents without tokens only
+    label: bool,
+    /// Share of the covered tokens' weight added to `match_weight`
+    share: f32,
+}
+
+impl Collector for CandidateCollector {
+    type Fruit = Candidates;
+    type Child = CandidateSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let mut postings = Vec::with_capacity(self.terms.len());
+        for term in &self.terms {
+            let inverted_index = segment.inverted_index(term.field())?;
+            postings.push(inverted_index.read_postings(term, IndexRecordOption::Basic)?);
+        }
+
+        let fast_fields = segment.fast_fields();
+
+        Ok(CandidateSegmentCollector {
+            segment_ord,
+            postings,
+            present: vec![false; self.terms.len()],
+            variants: self.variants.clone(),
+            keywords: self.keywords.clone(),
+            covered: vec![false; self.keywords.len()],
+            shares: vec![0.0; self.keywords.len()],
+            tokens_norms: segment.get_fieldnorms_reader(self.tokens_field)?,
+            len: fast_fields.u64(&self.len_field)?,
+            has_hyphen: fast_fields.u64(&self.has_hyphen_field)?,
+            // Indices built before the score was added rank every label as 0
+            dga_score: match segment.schema().get_field(&self.dga_score_field) {
+                Ok(_) => Some(fast_fields.f64(&self.dga_score_field)?),
+                Err(_) => None,
+            },
+            min_match: self.min_match,
+            profile: self.profile.clone(),
+            candidates: self.empty(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Candidates>) -> tantivy::Result<Candidates> {
+        let mut candidates = self.empty();
+        for fruit in segment_fruits {
+            candidates.merge(fruit);
+        }
+        Ok(candidates)
+    }
+}
+
+pub(crate) struct CandidateSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    /// Postings of each term, `None` when the segment lacks it
+    postings: Vec<Option<SegmentPostings>>,
+    /// Whether the current document contains each term
+    present: Vec<bool>,
+    variants: Arc<[CompiledVariant]>,
+    keywords: Arc<[Keyword]>,
+    /// Whether each query token matched the current document
+    covered: Vec<bool>,
+    /// Share of each covered query token's weight, below 1 when it matched
+    /// by its stem or label only
+    shares: Vec<f32>,
+    /// Token counts, zero for documents the word splitter failed on
+    tokens_norms: FieldNormReader,
+    len: Column<u64>,
+    has_hyphen: Column<u64>,
+    /// `None` in indices built without the field
+    dga_score: Option<Column<f64>>,
+    min_match: usize,
+    profile: Arc<RankingProfile>,
+    candidates: Candidates,
+}
+
+impl CandidateSegmentCollector {
+    /// Candidates collected so far
+    pub fn candidates(&self) -> &Candidates {
+        &self.candidates
+    }
+}
+
+impl SegmentCollector for CandidateSegmentCollector {
+    type Fruit = Candidates;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.candidates.scanned += 1;
+
+        for (present, postings) in self.present.iter_mut().zip(&mut self.postings) {
+            *present = postings.as_mut().is_some_and(|p| contains(p, doc));
+        }
+
+        // Query tokens come first, then stems and label words, so a compound
+        // only counts for tokens that didn't match on their own, and a stem
+        // or label word for tokens that matched in no other form
+        let mut signals = Signals {
+            bm25_score: score,
+            ..Signals::default()
+        };
+        let segmented = self.tokens_norms.fieldnorm_id(doc) != 0;
+        self.covered.fill(false);
+        for variant in self.variants.iter() {
+            if variant.label && segmented {
+                continue;
+            }
+            if variant.terms.iter().all(|&t| self.present[t]) {
+                for &i in &variant.covers {
+                    if !self.covered[i] {
+                        self.covered[i] = true;
+                        self.shares[i] = variant.share;
+                        signals.compound_match |= variant.compound;
+                    }
+                }
+            }
+        }
+
+        let mut matched = 0u64;
+        for (i, keyword) in self.keywords.iter().enumerate() {
+            if !self.covered[i] {
+                continue;
+            }
+            if !keyword.stop {
+                signals.match_count += 1;
+            }
+            signals.match_weight += keyword.weight * self.shares[i];
+            if i < 64 {
+                matched |= 1 << i;
+            }
+        }
+        if signals.match_count < self.min_match {
+            return;
+        }
+
+        signals.length = self.len.first(doc).unwrap_or(0);
+        signals.has_hyphen = self.has_hyphen.first(doc).unwrap_or(0) != 0;
+        signals.dga_score = self.dga_score.as_ref().and_then(|c| c.first(doc)).unwrap_or(0.0);
+        if !self.profile.filters.accepts(&signals) {
+            return;
+        }
+
+        self.candidates.push(Candidate {
+            doc: DocAddress::new(self.segment_ord, doc),
+            signals,
+            matched,
+        });
+    }
+
+    fn harvest(self) -> Candidates {
+        self.candidates
+    }
+}
+
+/// Whether a postings list contains `doc`; documents must be checked in order
+fn contains(postings: &mut SegmentPostings, doc: DocId) -> bool {
+    if postings.doc() < doc {
+        postings.seek(doc);
+    }
+    postings.doc() == doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(doc: DocId, match_count: usize, length: u64) -> Candidate {
+        Candidate {
+            doc: DocAddress::new(0, doc),
+            signals: Signals {
+                match_count,
+                length,
+                ..Signals::default()
+            },
+            matched: 0,
+        }
+    }
+
+    #[test]
+    fn test_top_k_keeps_best_candidates() {
+        let mut top = TopK::new(2, Arc::new(RankingProfile::default()));
+        for (doc, (match_count, length)) in [(1, 5), (3, 10), (2, 5), (1, 3), (3, 8)]
+            .into_iter()
+            .enumerate()
+        {
+            top.push(candidate(doc as DocId, match_count, length));
+        }
+
+        let docs: Vec<DocId> = top.into_sorted().iter().map(|c| c.doc.doc_id).collect();
+        assert_eq!(docs, vec![4, 1]);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::error::Result;
+use domain_core::stem;
+
+/// Shortest part kept when splitting a query token in two
+const MIN_SPLIT_LEN: usize = 3;
+
+/// One way for a document to match some of the query tokens
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Variant {
+    /// Index tokens that must all be present
+    pub tokens: Vec<String>,
+    /// Positions of the query tokens matched when they are
+    pub covers: Vec<usize>,
+    /// A joined or split form rather than the query token itself
+    pub compound: bool,
+    /// A query token's stem, matched against the `stems` field
+    pub stemmed: bool,
+    /// A query token matched against the `label` field, for documents whose
+    /// segmentation failed
+    pub label: bool,
+}
+
+/// The query tokens plus compound forms that occur in the index
+///
+/// Adjacent query tokens are joined in both orders (`pet care` matches
+/// `petcare` and `carepet`), and each query token is split into two indexed
+/// tokens (`petcare` matches a domain tokenized as `pet` + `care`), so
+/// segmentation differences between the query and the index don't hide
+/// domains. Forms with no documents are dropped. The query tokens come
+/// first, one per position.
+pub(crate) fn variants(
+    query_tokens: &[String],
+    mut doc_freq: impl FnMut(&str) -> Result<u64>,
+) -> Result<Vec<Variant>> {
+    let mut variants: Vec<Variant> = query_tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| Variant {
+            tokens: vec![token.clone()],
+            covers: vec![i],
+            compound: false,
+            stemmed: false,
+            label: false,
+        })
+        .collect();
+
+    let mut add = |variants: &mut Vec<Variant>, variant: Variant| -> Result<()> {
+        if variants.iter().any(|v| v.tokens == variant.tokens) {
+            return Ok(());
+        }
+        for token in &variant.tokens {
+            if doc_freq(token)? == 0 {
+                return Ok(());
+            }
+        }
+        variants.push(variant);
+        Ok(())
+    };
+
+    for (i, pair) in query_tokens.windows(2).enumerate() {
+        for (first, second) in [(&pair[0], &pair[1]), (&pair[1], &pair[0])] {
+            let variant = Variant {
+                tokens: vec![format!("{}{}", first, second)],
+                covers: vec![i, i + 1],
+                compound: true,
+                stemmed: false,
+                label: false,
+            };
+            add(&mut variants, variant)?;
+        }
+    }
+
+    for (i, token) in query_tokens.iter().enumerate() {
+        let bounds: Vec<usize> = token.char_indices().map(|(at, _)| at).collect();
+        let last = bounds.len().saturating_sub(MIN_SPLIT_LEN);
+        for &at in bounds.iter().take(last + 1).skip(MIN_SPLIT_LEN) {
+            let (left, right) = token.split_at(at);
+            let variant = Variant {
+                tokens: vec![left.to_string(), right.to_string()],
+                covers: vec![i],
+                compound: true,
+                stemmed: false,
+                label: false,
+            };
+            add(&mut variants, variant)?;
+        }
+    }
+
+    Ok(variants)
+}
+
+/// The stems of the query tokens (`shoes` → `shoe`), matching documents
+/// with any inflection of a token that share its stem
+///
+/// Appended after the other variants, so a stem only counts for tokens that
+/// matched in no other form.
+pub(crate) fn stem_variants(query_tokens: &[String]) -> Vec<Variant> {
+    query_tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| Variant {
+            tokens: vec![stem::stem(token).into_owned()],
+            covers: vec![i],
+            compound: false,
+            stemmed: true,
+            label: false,
+        })
+        .collect()
+}
+
+/// The query tokens as words of the label (`pet` matches `pet-shop.com`
+/// with no tokens stored), so domains the word splitter failed on stay
+/// discoverable
+///
+/// Only whole words between hyphens match: `coffee` finds
+/// `best-coffee.com` but not `bestcoffeeshop.com`. Appended last, and only
+/// counted for documents with no tokens, so segmented domains are matched
+/// and ranked by their tokens alone.
+pub(crate) fn label_variants(query_tokens: &[String]) -> Vec<Variant> {
+    query_tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| Variant {
+            tokens: vec![token.clone()],
+            covers: vec![i],
+            compound: false,
+            stemmed: false,
+            label: true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(query: &str) -> Vec<String> {
+        query.split_whitespace().map(String::from).collect()
+    }
+
+    fn indexed(token: &str) -> Result<u64> {
+        let known = ["pet", "care", "petcare", "shop", "petshop"];
+        Ok(known.contains(&token) as u64)
+    }
+
+    #[test]
+    fn test_joins_adjacent_tokens() {
+        let variants = variants(&tokens("pet care"), indexed).unwrap();
+
+        assert_eq!(variants.len(), 3);
+        assert_eq!(variants[2].tokens, vec!["petcare"]);
+        assert_eq!(variants[2].covers, vec![0, 1]);
+        assert!(variants[2].compound);
+    }
+
+    #[test]
+    fn test_splits_compound_tokens() {
+        let variants = variants(&tokens("petcare shop"), indexed).unwrap();
+
+        let splits: Vec<&Variant> = variants.iter().filter(|v| v.tokens.len() == 2).collect();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].tokens, vec!["pet", "care"]);
+        assert_eq!(splits[0].covers, vec![0]);
+    }
+
+    #[test]
+    fn test_stem_variants() {
+        let variants = stem_variants(&tokens("running shoes"));
+
+        assert_eq!(variants[0].tokens, vec!["run"]);
+        assert_eq!(variants[1].tokens, vec!["shoe"]);
+        assert_eq!(variants[1].covers, vec![1]);
+        assert!(variants.iter().all(|v| v.stemmed && !v.compound));
+    }
+
+    #[test]
+    fn test_label_variants() {
+        let variants = label_variants(&tokens("pet shop"));
+
+        assert_eq!(variants[1].tokens, vec!["shop"]);
+        assert_eq!(variants[1].covers, vec![1]);
+        assert!(variants.iter().all(|v| v.label && !v.stemmed && !v.compound));
+    }
+}
@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Query cannot be empty")]
+    EmptyQuery,
+
+    #[error("Unknown ranking profile: {0}")]
+    UnknownProfile(String),
+
+    #[error("Invalid ranking profiles: {0}")]
+    InvalidProfiles(String),
+
+    #[error("Invalid appraisal weights: {0}")]
+    InvalidAppraisal(String),
+
+    #[error("Tantivy error: {0}")]
+    Tantivy(#[from] tantivy::TantivyError),
+}
+
+impl Error {
+    /// Whether the error was caused by the request rather than the index
+    pub fn is_client_error(&self) -> bool {
+        matches!(self, Error::EmptyQuery | Error::UnknownProfile(_))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// A domain spelling a query word across label and TLD (`delicio.us`)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DomainHack {
+    pub domain: String,
+    pub label: String,
+    pub tld: String,
+    /// The domain is registered (present in the index)
+    pub in_zone: bool,
+}
+
+/// Ways to write `word` as a label followed by one of `tlds`
+///
+/// The label must be a valid hostname label: non-empty, and not starting or
+/// ending with a hyphen.
+pub(crate) fn splits<'a>(
+    word: &'a str,
+    tlds: &'a [String],
+) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+    tlds.iter().filter_map(move |tld| {
+        let label = word.strip_suffix(tld.as_str())?;
+        let valid = !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_alphanumeric() || c == '-');
+        valid.then_some((label, tld.as_str()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_across_tld() {
+        let tlds: Vec<String> = ["us", "to", "com"].iter().map(|t| t.to_string()).collect();
+
+        let found: Vec<_> = splits("delicious", &tlds).collect();
+        assert_eq!(found, vec![("delicio", "us")]);
+
+        let found: Vec<_> = splits("photo", &tlds).collect();
+        assert_eq!(found, vec![("pho", "to")]);
+
+        // The whole word is not a label
+        assert_eq!(splits("com", &tlds).count(), 0);
+        assert_eq!(splits("re-us", &tlds).count(), 0);
+    }
+}
@@ -0,0 +1,147 @@
+use domain_core::stem::stem;
+
+/// A label with the parts matching query tokens in brackets
+/// (`best[coffee]shop`)
+///
+/// `tokens` is the label's indexed segmentation and `matched` the query
+/// tokens the domain matched, in query order. Matches are placed on segment
+/// boundaries where they can be: a segment equal to a matched token or
+/// sharing its stem (`shoe` for `shoes`), two segments spelling one
+/// (`petcare` indexed as `pet` + `care`), or one segment spelling two
+/// adjacent matched tokens (`pet care` indexed as `petcare`). A matched token
+/// found on no boundary is highlighted where it first occurs in the label.
+pub(crate) fn highlight(label: &str, tokens: &[String], matched: &[String]) -> String {
+    let spans = token_spans(label, tokens);
+    let mut marks: Vec<(usize, usize)> = Vec::new();
+    let mut covered = vec![false; matched.len()];
+
+    let mark = |marks: &mut Vec<(usize, usize)>, range: (usize, usize)| {
+        let free = marks.iter().all(|&(start, end)| range.1 <= start || range.0 >= end);
+        if free {
+            marks.push(range);
+        }
+        free
+    };
+
+    for (i, &(start, end, token)) in spans.iter().enumerate() {
+        let found = matched
+            .iter()
+            .position(|m| m == token)
+            .or_else(|| matched.iter().position(|m| stem(m) == stem(token)));
+        if let Some(m) = found {
+            covered[m] |= mark(&mut marks, (start, end));
+            continue;
+        }
+        // Two adjacent query tokens joined into this segment, in either order
+        let joined = matched.windows(2).position(|pair| {
+            let (first, second) = (&pair[0], &pair[1]);
+            token.len() == first.len() + second.len()
+                && ((token.starts_with(first.as_str()) && token.ends_with(second.as_str()))
+                    || (token.starts_with(second.as_str()) && token.ends_with(first.as_str())))
+        });
+        if let Some(m) = joined {
+            if mark(&mut marks, (start, end)) {
+                covered[m] = true;
+                covered[m + 1] = true;
+            }
+            continue;
+        }
+        // A query token split across this segment and the next
+        if let Some(&(_, next_end, next)) = spans.get(i + 1) {
+            let split = matched.iter().position(|m| {
+                m.len() == token.len() + next.len()
+                    && m.starts_with(token)
+                    && m.ends_with(next)
+            });
+            if let Some(m) = split {
+                covered[m] |= mark(&mut marks, (start, next_end));
+            }
+        }
+    }
+
+    for (m, token) in matched.iter().enumerate() {
+        if covered[m] || token.is_empty() {
+            continue;
+        }
+        for (start, _) in label.match_indices(token.as_str()) {
+            if mark(&mut marks, (start, start + token.len())) {
+                break;
+            }
+        }
+    }
+
+    marks.sort_unstable();
+    let mut highlighted = String::with_capacity(label.len() + 2 * marks.len());
+    let mut cursor = 0;
+    for (start, end) in marks {
+        highlighted.push_str(&label[cursor..start]);
+        highlighted.push('[');
+        highlighted.push_str(&label[start..end]);
+        highlighted.push(']');
+        cursor = end;
+    }
+    highlighted.push_str(&label[cursor..]);
+    highlighted
+}
+
+/// Byte range of each segment in the label, skipping segments not found in
+/// order (e.g. from an outdated segmentation)
+fn token_spans<'a>(label: &str, tokens: &'a [String]) -> Vec<(usize, usize, &'a str)> {
+    let mut spans = Vec::with_capacity(tokens.len());
+    let mut cursor = 0;
+    for token in tokens.iter().filter(|token| !token.is_empty()) {
+        if let Some(at) = label[cursor..].find(token.as_str()) {
+            let start = cursor + at;
+            cursor = start + token.len();
+            spans.push((start, cursor, token.as_str()));
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_highlight_segments() {
+        let tokens = strings(&["best", "coffee", "shop"]);
+        let coffee = strings(&["coffee"]);
+        assert_eq!(highlight("bestcoffeeshop", &tokens, &coffee), "best[coffee]shop");
+        assert_eq!(
+            highlight("bestcoffeeshop", &tokens, &strings(&["coffee", "shop"])),
+            "best[coffee][shop]"
+        );
+        let hyphenated = strings(&["coffee", "shop"]);
+        assert_eq!(highlight("coffee-shop", &hyphenated, &strings(&["shop"])), "coffee-[shop]");
+        // Matched by stem
+        let shoe = strings(&["best", "shoe"]);
+        assert_eq!(highlight("bestshoe", &shoe, &strings(&["shoes"])), "best[shoe]");
+    }
+
+    #[test]
+    fn test_highlight_compounds() {
+        // Query `pet care`, domain segmented as `petcare`
+        let joined = strings(&["my", "petcare"]);
+        assert_eq!(highlight("mypetcare", &joined, &strings(&["pet", "care"])), "my[petcare]");
+        let reversed = strings(&["carepet"]);
+        assert_eq!(highlight("carepet", &reversed, &strings(&["pet", "care"])), "[carepet]");
+
+        // Query `petcare`, domain segmented as `pet` + `care`
+        let split = strings(&["pet", "care", "hub"]);
+        assert_eq!(highlight("petcarehub", &split, &strings(&["petcare"])), "[petcare]hub");
+    }
+
+    #[test]
+    fn test_highlight_without_segments() {
+        assert_eq!(highlight("getcoffee", &[], &strings(&["coffee"])), "get[coffee]");
+        // Overlapping occurrences keep the first match
+        assert_eq!(highlight("banana", &[], &strings(&["ana", "nan"])), "b[ana]na");
+        assert_eq!(highlight("coffee", &[], &[]), "coffee");
+        assert_eq!(highlight("coffee", &strings(&["tea"]), &strings(&["tea"])), "coffee");
+    }
+}
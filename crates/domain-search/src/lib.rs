@@ -0,0 +1,22 @@
+mod appraise;
+mod collector;
+mod compound;
+mod error;
+mod hacks;
+mod highlight;
+pub mod profile;
+pub mod ranking;
+pub mod result;
+mod searcher;
+mod spelling;
+
+pub use appraise::{Appraisal, AppraisalFactor, AppraisalWeights, AppraisedWord, ValueBand};
+pub use error::{Error, Result};
+pub use hacks::DomainHack;
+pub use profile::{RankingProfile, RankingProfiles, SortKey, DEFAULT_PROFILE};
+pub use result::{
+    extract_domain_result, extract_domain_result_with, Bucket, CategoryCounts, DomainResult,
+    Explanation, Fields, SearchResult, SearchResults, TokenWeight,
+};
+pub use searcher::{Dedupe, SearchProgress, SearchRequest, Searcher};
+pub use spelling::Suggestion;
@@ -0,0 +1,537 @@
+use crate::error::{Error, Result};
+use crate::ranking::{RankedResult, Signals};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Name of the profile matching the original hard-coded ranking
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Sort criteria, each applied in its natural direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// More matched tokens first
+    MatchCount,
+    /// Rarer matched tokens first (sum of their IDF weights)
+    MatchWeight,
+    /// Shorter labels first
+    Length,
+    /// Higher BM25 score first
+    Bm25,
+    /// Higher weighted score (see [`Boosts`]) first
+    Score,
+    /// Less random-looking labels first
+    DgaScore,
+}
+
+impl SortKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::MatchCount => "match_count",
+            SortKey::MatchWeight => "match_weight",
+            SortKey::Length => "length",
+            SortKey::Bm25 => "bm25",
+            SortKey::Score => "score",
+            SortKey::DgaScore => "dga_score",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            SortKey::MatchCount,
+            SortKey::MatchWeight,
+            SortKey::Length,
+            SortKey::Bm25,
+            SortKey::Score,
+            SortKey::DgaScore,
+        ]
+        .into_iter()
+        .find(|key| key.as_str() == name)
+    }
+}
+
+/// How the sorted results are merged into the final list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interleave {
+    /// Alternate hyphenated and plain domains by the profile's
+    /// `hyphen_ratio`
+    #[default]
+    Hyphen,
+    /// A single ranked list
+    None,
+}
+
+/// Filters applied to candidates during collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileFilters {
+    /// Drop domains whose label is longer than this
+    pub max_length: Option<u64>,
+
+    /// Drop hyphenated domains
+    pub exclude_hyphens: bool,
+
+    /// Require every query token to match (overrides `min_match`)
+    pub all_tokens: bool,
+
+    /// Drop domains whose label looks more random than this (0-1)
+    pub max_dga_score: Option<f64>,
+}
+
+impl ProfileFilters {
+    pub fn accepts(&self, signals: &Signals) -> bool {
+        if self.exclude_hyphens && signals.has_hyphen {
+            return false;
+        }
+        if self.max_dga_score.is_some_and(|max| signals.dga_score > max) {
+            return false;
+        }
+        match self.max_length {
+            Some(max) => signals.length <= max,
+            None => true,
+        }
+    }
+}
+
+/// Weights of the `score` sort key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Boosts {
+    /// Per matched token, as a fraction of 10 tokens
+    pub match_count: f64,
+    /// For shortness, as a fraction of the 63 character maximum
+    pub length: f64,
+    /// For BM25, capped at a score of 20
+    pub bm25: f64,
+    /// Per unit of matched-token IDF weight
+    pub match_weight: f64,
+    /// Added for hyphenated domains (negative to penalize)
+    pub hyphen: f64,
+    /// Multiplier of the weighted score per TLD, e.g. `{"com": 1.5}`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tlds: BTreeMap<String, f64>,
+    /// Multipliers of the weighted score for short labels; every rule a
+    /// label fits applies
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<LabelBoost>,
+}
+
+impl Default for Boosts {
+    fn default() -> Self {
+        Self {
+            match_count: 100.0,
+            length: 10.0,
+            bm25: 1.0,
+            match_weight: 0.0,
+            hyphen: 0.0,
+            tlds: BTreeMap::new(),
+            labels: Vec::new(),
+        }
+    }
+}
+
+impl Boosts {
+    /// Whether any TLD or label multiplier is configured
+    pub fn has_multipliers(&self) -> bool {
+        !self.tlds.is_empty() || !self.labels.is_empty()
+    }
+
+    /// Product of the label rules a label of `length` fits
+    pub fn length_factor(&self, length: u64) -> f64 {
+        self.labels
+            .iter()
+            .filter(|rule| length <= rule.max_length)
+            .map(|rule| rule.factor)
+            .product()
+    }
+
+    /// Multiplier of `tld`, 1 when it has none
+    pub fn tld_factor(&self, tld: &str) -> f64 {
+        self.tlds.get(tld).copied().unwrap_or(1.0)
+    }
+
+    /// The multipliers applying to a domain, for explanations
+    pub fn applied(&self, tld: &str, length: u64) -> Vec<AppliedBoost> {
+        let tld = self.tlds.get(tld).map(|&factor| AppliedBoost {
+            rule: format!("tld .{}", tld),
+            factor,
+        });
+        let labels = self
+            .labels
+            .iter()
+            .filter(|rule| length <= rule.max_length)
+            .map(|rule| AppliedBoost {
+                rule: format!("length <= {}", rule.max_length),
+                factor: rule.factor,
+            });
+        tld.into_iter().chain(labels).collect()
+    }
+
+    /// TLD keys lowercased and without a leading dot, as domains are indexed
+    fn normalize(&mut self) {
+        self.tlds = std::mem::take(&mut self.tlds)
+            .into_iter()
+            .map(|(tld, factor)| (tld.trim_start_matches('.').to_lowercase(), factor))
+            .collect();
+    }
+}
+
+/// Multiplier for labels of at most `max_length` characters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LabelBoost {
+    pub max_length: u64,
+    pub factor: f64,
+}
+
+/// A multiplier applied to a result's weighted score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AppliedBoost {
+    /// The matching rule, e.g. `tld .com` or `length <= 8`
+    pub rule: String,
+    pub factor: f64,
+}
+
+/// A named ranking configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingProfile {
+    pub sort: Vec<SortKey>,
+    pub interleave: Interleave,
+    /// Share of hyphenated results when interleaving, from 0 (none) to 1
+    pub hyphen_ratio: f64,
+    pub filters: ProfileFilters,
+    pub boosts: Boosts,
+}
+
+impl Default for RankingProfile {
+    fn default() -> Self {
+        Self {
+            sort: vec![
+                SortKey::MatchCount,
+                SortKey::MatchWeight,
+                SortKey::Length,
+                SortKey::Bm25,
+            ],
+            interleave: Interleave::Hyphen,
+            hyphen_ratio: 0.5,
+            filters: ProfileFilters::default(),
+            boosts: Boosts::default(),
+        }
+    }
+}
+
+impl RankingProfile {
+    /// Compare two candidates by the profile's sort keys, best first
+    ///
+    /// TLDs aren't known before the document is loaded, so `score` here
+    /// leaves out TLD multipliers; [`Self::compare_results`] applies them.
+    pub fn compare(&self, a: &Signals, b: &Signals) -> Ordering {
+        self.compare_with(
+            a,
+            b,
+            || a.score(&self.boosts),
+            || b.score(&self.boosts),
+        )
+    }
+
+    /// Compare two loaded results, TLD multipliers included
+    pub fn compare_results(&self, a: &RankedResult, b: &RankedResult) -> Ordering {
+        self.compare_with(
+            &a.signals(),
+            &b.signals(),
+            || a.score(&self.boosts),
+            || b.score(&self.boosts),
+        )
+    }
+
+    fn compare_with(
+        &self,
+        a: &Signals,
+        b: &Signals,
+        a_score: impl Fn() -> f64,
+        b_score: impl Fn() -> f64,
+    ) -> Ordering {
+        self.sort
+            .iter()
+            .map(|key| match key {
+                SortKey::MatchCount => b.match_count.cmp(&a.match_count),
+                SortKey::MatchWeight => b
+                    .match_weight
+                    .partial_cmp(&a.match_weight)
+                    .unwrap_or(Ordering::Equal),
+                SortKey::Length => a.length.cmp(&b.length),
+                SortKey::Bm25 => b
+                    .bm25_score
+                    .partial_cmp(&a.bm25_score)
+                    .unwrap_or(Ordering::Equal),
+                SortKey::Score => b_score().partial_cmp(&a_score()).unwrap_or(Ordering::Equal),
+                SortKey::DgaScore => a
+                    .dga_score
+                    .partial_cmp(&b.dga_score)
+                    .unwrap_or(Ordering::Equal),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Built-in and configured ranking profiles, selectable by name
+#[derive(Debug, Clone)]
+pub struct RankingProfiles {
+    profiles: BTreeMap<String, RankingProfile>,
+    default: String,
+}
+
+impl Default for RankingProfiles {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl RankingProfiles {
+    /// `default`, `brandable`, `exact-match` and `seo`
+    pub fn builtin() -> Self {
+        let mut profiles = BTreeMap::new();
+
+        profiles.insert(DEFAULT_PROFILE.to_string(), RankingProfile::default());
+
+        // Short, clean names first
+        profiles.insert(
+            "brandable".to_string(),
+            RankingProfile {
+                sort: vec![
+                    SortKey::Length,
+                    SortKey::MatchCount,
+                    SortKey::MatchWeight,
+                    SortKey::Bm25,
+                ],
+                interleave: Interleave::None,
+                hyphen_ratio: 0.5,
+                filters: ProfileFilters {
+                    max_length: Some(12),
+                    exclude_hyphens: true,
+                    all_tokens: false,
+                    max_dga_score: None,
+                },
+                boosts: Boosts::default(),
+            },
+        );
+
+        // Only domains containing every keyword
+        profiles.insert(
+            "exact-match".to_string(),
+            RankingProfile {
+                interleave: Interleave::None,
+                filters: ProfileFilters {
+                    all_tokens: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        // Keyword-rich names; length matters little, hyphens are fine
+        profiles.insert(
+            "seo".to_string(),
+            RankingProfile {
+                sort: vec![SortKey::Score],
+                interleave: Interleave::None,
+                hyphen_ratio: 0.5,
+                filters: ProfileFilters::default(),
+                boosts: Boosts {
+                    match_count: 100.0,
+                    length: 2.0,
+                    bm25: 5.0,
+                    match_weight: 5.0,
+                    hyphen: 0.0,
+                    ..Default::default()
+                },
+            },
+        );
+
+        Self {
+            profiles,
+            default: DEFAULT_PROFILE.to_string(),
+        }
+    }
+
+    /// Built-in profiles overlaid with a JSON file mapping names to profiles
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| Error::InvalidProfiles(format!("{}: {}", path.display(), e)))?;
+        let mut configured: BTreeMap<String, RankingProfile> = serde_json::from_str(&data)
+            .map_err(|e| Error::InvalidProfiles(format!("{}: {}", path.display(), e)))?;
+
+        for (name, profile) in &mut configured {
+            // Multipliers only scale the weighted score
+            if profile.boosts.has_multipliers() && !profile.sort.contains(&SortKey::Score) {
+                return Err(Error::InvalidProfiles(format!(
+                    "{}: profile '{}' has TLD or label boosts but doesn't sort by score",
+                    path.display(),
+                    name
+                )));
+            }
+            if !(0.0..=1.0).contains(&profile.hyphen_ratio) {
+                return Err(Error::InvalidProfiles(format!(
+                    "{}: profile '{}' has a hyphen_ratio outside 0 to 1",
+                    path.display(),
+                    name
+                )));
+            }
+            profile.boosts.normalize();
+        }
+
+        let mut profiles = Self::builtin();
+        profiles.profiles.extend(configured);
+        Ok(profiles)
+    }
+
+    /// Use another profile when a request doesn't name one
+    pub fn with_default(mut self, name: &str) -> Result<Self> {
+        if !self.profiles.contains_key(name) {
+            return Err(Error::InvalidProfiles(format!(
+                "default profile '{}' is not defined",
+                name
+            )));
+        }
+        self.default = name.to_string();
+        Ok(self)
+    }
+
+    /// Look up a profile, falling back to the default when `name` is `None`
+    pub fn get(&self, name: Option<&str>) -> Result<(&str, &RankingProfile)> {
+        let name = name.unwrap_or(&self.default);
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, profile)| (name.as_str(), profile))
+            .ok_or_else(|| Error::UnknownProfile(name.to_string()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_signals(match_count: usize, length: u64, bm25: f32) -> Signals {
+        Signals {
+            match_count,
+            match_weight: match_count as f32,
+            bm25_score: bm25,
+            length,
+            ..Signals::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_keys_apply_in_order() {
+        let more_matches = make_signals(3, 20, 1.0);
+        let shorter = make_signals(2, 5, 1.0);
+
+        let default = RankingProfile::default();
+        assert_eq!(default.compare(&more_matches, &shorter), Ordering::Less);
+
+        let brandable = RankingProfiles::builtin();
+        let (_, brandable) = brandable.get(Some("brandable")).unwrap();
+        assert_eq!(brandable.compare(&more_matches, &shorter), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_dga_score_filter_and_sort() {
+        let word = Signals {
+            dga_score: 0.1,
+            ..make_signals(1, 10, 1.0)
+        };
+        let random = Signals {
+            dga_score: 0.8,
+            ..make_signals(2, 10, 1.0)
+        };
+
+        let mut profile = RankingProfile::default();
+        assert_eq!(profile.compare(&word, &random), Ordering::Greater);
+        profile.sort.insert(0, SortKey::DgaScore);
+        assert_eq!(profile.compare(&word, &random), Ordering::Less);
+
+        profile.filters.max_dga_score = Some(0.5);
+        assert!(profile.filters.accepts(&word));
+        assert!(!profile.filters.accepts(&random));
+        assert_eq!(SortKey::parse("dga_score"), Some(SortKey::DgaScore));
+        assert_eq!(SortKey::parse("random"), None);
+    }
+
+    #[test]
+    fn test_profile_file_overrides_builtins() {
+        let dir = std::env::temp_dir().join(format!("profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("profiles.json");
+        std::fs::write(
+            &path,
+            r#"{"short": {"sort": ["length"], "filters": {"max_length": 6}}}"#,
+        )
+        .unwrap();
+
+        let profiles = RankingProfiles::load(&path).unwrap().with_default("short").unwrap();
+        let (name, profile) = profiles.get(None).unwrap();
+        assert_eq!(name, "short");
+        assert_eq!(profile.sort, vec![SortKey::Length]);
+        assert_eq!(profile.interleave, Interleave::Hyphen);
+        assert_eq!(profile.hyphen_ratio, 0.5);
+        assert!(profiles.get(Some("seo")).is_ok());
+        assert!(profiles.get(Some("missing")).unwrap_err().is_client_error());
+
+        std::fs::write(&path, r#"{"short": {"hyphen_ratio": 1.5}}"#).unwrap();
+        assert!(RankingProfiles::load(&path).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_multiplier_boosts() {
+        let dir = std::env::temp_dir().join(format!("boosts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("profiles.json");
+        std::fs::write(
+            &path,
+            r#"{"market": {"sort": ["score"], "boosts": {
+                "tlds": {".COM": 1.5, "io": 1.2},
+                "labels": [{"max_length": 8, "factor": 1.3}]
+            }}}"#,
+        )
+        .unwrap();
+        let profiles = RankingProfiles::load(&path).unwrap();
+        let (_, profile) = profiles.get(Some("market")).unwrap();
+        let boosts = &profile.boosts;
+
+        assert_eq!(boosts.tld_factor("com"), 1.5);
+        assert_eq!(boosts.tld_factor("net"), 1.0);
+        assert_eq!(boosts.length_factor(8), 1.3);
+        assert_eq!(boosts.length_factor(9), 1.0);
+
+        let short = make_signals(1, 6, 1.0);
+        let long = make_signals(1, 20, 1.0);
+        let plain = Boosts::default();
+        assert!((short.score(boosts) - short.score(&plain) * 1.3).abs() < 1e-9);
+        assert_eq!(long.score(boosts), long.score(&plain));
+
+        let applied = boosts.applied("com", 6);
+        assert_eq!(applied.len(), 2);
+        assert_eq!(applied[0].rule, "tld .com");
+        assert_eq!(applied[1].rule, "length <= 8");
+        assert!(boosts.applied("net", 9).is_empty());
+
+        // Multipliers only apply to the score key
+        std::fs::write(&path, r#"{"market": {"boosts": {"tlds": {"com": 1.5}}}}"#).unwrap();
+        assert!(RankingProfiles::load(&path).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use crate::profile::Boosts;
+use crate::result::{Bucket, DomainResult, Explanation, SearchResult};
+
+/// Per-document ranking inputs, available without loading the stored document
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Signals {
+    pub match_count: usize,
+    /// Sum of the IDF weights of the matched query tokens
+    pub match_weight: f32,
+    pub bm25_score: f32,
+    /// Label length
+    pub length: u64,
+    pub has_hyphen: bool,
+    /// Label randomness, 0 (meaningful) to 1 (generated)
+    pub dga_score: f64,
+    /// Some query tokens matched only as a joined or split compound
+    pub compound_match: bool,
+}
+
+impl Signals {
+    /// Weighted score with the given boosts, scaled by their label
+    /// multipliers
+    pub fn score(&self, boosts: &Boosts) -> f64 {
+        // Normalize match_count to 0-1 range (assuming max 10 keywords)
+        let match_score = (self.match_count as f64) / 10.0;
+
+        let length_score = self.length_score();
+
+        // Normalize BM25 (typically 0-20 range)
+        let bm25_normalized = (self.bm25_score as f64).min(20.0) / 20.0;
+
+        let hyphen_score = if self.has_hyphen { 1.0 } else { 0.0 };
+
+        // Weighted combination
+        let weighted = match_score * boosts.match_count
+            + length_score * boosts.length
+            + bm25_normalized * boosts.bm25
+            + self.match_weight as f64 * boosts.match_weight
+            + hyphen_score * boosts.hyphen;
+
+        weighted * boosts.length_factor(self.length)
+    }
+
+    /// Length normalized to 0-1 (shorter is better, max 63 chars)
+    pub fn length_score(&self) -> f64 {
+        1.0 - (self.length as f64 / 63.0)
+    }
+}
+
+/// A search result with ranking information
+#[derive(Clone)]
+pub struct RankedResult {
+    pub domain: DomainResult,
+    pub match_count: usize,
+    /// Sum of the IDF weights of the matched query tokens
+    pub match_weight: f32,
+    pub bm25_score: f32,
+    /// Other TLDs folded into this result by label deduplication
+    pub also_in: Vec<String>,
+    /// Query tokens found in the domain (only collected when explaining)
+    pub matched_tokens: Vec<String>,
+    /// Some query tokens matched only as a joined or split compound
+    pub compound_match: bool,
+    /// Label with the matched parts bracketed, when highlighting
+    pub highlighted: Option<String>,
+}
+
+impl RankedResult {
+    /// Calculate a combined score for ranking
+    ///
+    /// Priority order:
+    /// 1. match_count (higher is better)
+    /// 2. domain length (shorter is better)
+    /// 3. BM25 score (higher is better)
+    pub fn combined_score(&self) -> f64 {
+        self.score(&Boosts::default())
+    }
+
+    /// Weighted score with the given boosts, scaled by their TLD and label
+    /// multipliers
+    pub fn score(&self, boosts: &Boosts) -> f64 {
+        self.signals().score(boosts) * boosts.tld_factor(&self.domain.tld)
+    }
+
+    /// Length normalized to 0-1 (shorter is better, max 63 chars)
+    pub fn length_score(&self) -> f64 {
+        self.signals().length_score()
+    }
+
+    pub fn signals(&self) -> Signals {
+        Signals {
+            match_count: self.match_count,
+            match_weight: self.match_weight,
+            bm25_score: self.bm25_score,
+            length: self.domain.length,
+            has_hyphen: self.domain.has_hyphen,
+            dga_score: self.domain.dga_score,
+            compound_match: self.compound_match,
+        }
+    }
+
+    /// Convert to a result, attaching a ranking breakdown when `explain` is set
+    pub fn into_result(self, boosts: &Boosts, bucket: Bucket, explain: bool) -> SearchResult {
+        let explanation = explain.then(|| Explanation {
+            bm25: self.bm25_score,
+            matched_tokens: self.matched_tokens.clone(),
+            match_count: self.match_count,
+            match_weight: self.match_weight,
+            length_score: self.length_score() * boosts.length,
+            score: self.score(boosts),
+            boosts: boosts.clone(),
+            applied_boosts: boosts.applied(&self.domain.tld, self.domain.length),
+            bucket,
+        });
+
+        let mut result = SearchResult::from(self);
+        result.explanation = explanation;
+        result
+    }
+}
+
+impl From<RankedResult> for SearchResult {
+    fn from(mut r: RankedResult) -> Self {
+        r.also_in.sort();
+        SearchResult {
+            domain: r.domain,
+            match_count: r.match_count,
+            score: r.bm25_score,
+            compound_match: r.compound_match,
+            highlighted: r.highlighted,
+            also_in: r.also_in,
+            explanation: None,
+            index: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::Fields;
+
+    fn make_result(match_count: usize, length: u64, bm25: f32) -> RankedResult {
+        RankedResult {
+            domain: DomainResult {
+                domain: "test.com".to_string(),
+                label: "test".to_string(),
+                tld: "com".to_string(),
+                length,
+                has_hyphen: false,
+                tokens: vec![],
+                dga_score: 0.0,
+                lang: None,
+                categories: vec![],
+                fields: Fields::ALL,
+            },
+            match_count,
+            match_weight: match_count as f32,
+            bm25_score: bm25,
+            also_in: vec![],
+            matched_tokens: vec![],
+            compound_match: false,
+            highlighted: None,
+        }
+    }
+
+    #[test]
+    fn test_ranking_prefers_more_matches() {
+        let r1 = make_result(3, 10, 5.0);
+        let r2 = make_result(2, 10, 5.0);
+
+        assert!(r1.combined_score() > r2.combined_score());
+    }
+
+    #[test]
+    fn test_ranking_prefers_shorter_domains() {
+        let r1 = make_result(2, 5, 5.0);
+        let r2 = make_result(2, 20, 5.0);
+
+        assert!(r1.combined_score() > r2.combined_score());
+    }
+
+    #[test]
+    fn test_ranking_match_count_dominates() {
+        // More matches should beat shorter domain
+        let r1 = make_result(3, 20, 5.0);
+        let r2 = make_result(2, 5, 5.0);
+
+        assert!(r1.combined_score() > r2.combined_score());
+    }
+
+    #[test]
+    fn test_tld_boost_reorders_results() {
+        let mut net = make_result(2, 10, 5.0);
+        net.domain.tld = "net".to_string();
+        let com = make_result(2, 12, 5.0);
+        let boosts = Boosts {
+            tlds: [("com".to_string(), 1.5)].into(),
+            ..Boosts::default()
+        };
+        let profile = crate::RankingProfile {
+            sort: vec![crate::SortKey::Score],
+            boosts,
+            ..Default::default()
+        };
+
+        assert!(net.combined_score() > com.combined_score());
+        assert!(profile.compare_results(&com, &net).is_lt());
+        // Without the TLD, only the label multipliers apply
+        assert!(profile.compare(&com.signals(), &net.signals()).is_gt());
+
+        let explained = com.into_result(&profile.boosts, Bucket::All, true);
+        let explanation = explained.explanation.unwrap();
+        assert_eq!(explanation.applied_boosts.len(), 1);
+        assert_eq!(explanation.applied_boosts[0].factor, 1.5);
+    }
+}
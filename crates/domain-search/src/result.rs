@@ -0,0 +1,382 @@
+use crate::hacks::DomainHack;
+use crate::profile::{AppliedBoost, Boosts};
+use crate::spelling::Suggestion;
+use domain_core::DomainSchema;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::ops::BitOr;
+use tantivy::TantivyDocument;
+
+/// Stored fields of an indexed domain
+///
+/// Only the fields in `fields` are serialized (`domain` always is), so
+/// responses can leave out what the caller didn't ask for.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DomainResult {
+    pub domain: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub tld: String,
+    #[serde(default)]
+    pub length: u64,
+    #[serde(default)]
+    pub has_hyphen: bool,
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// How random the label looks, 0 (meaningful) to 1 (generated)
+    #[serde(default)]
+    pub dga_score: f64,
+    /// Probable language of the label (ISO 639-1), when detected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Taxonomy categories of the label's words
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Fields to serialize
+    #[serde(skip)]
+    pub fields: Fields,
+}
+
+impl Serialize for DomainResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = self.fields;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("domain", &self.domain)?;
+        if fields.contains(Fields::LABEL) {
+            map.serialize_entry("label", &self.label)?;
+        }
+        if fields.contains(Fields::TLD) {
+            map.serialize_entry("tld", &self.tld)?;
+        }
+        if fields.contains(Fields::LENGTH) {
+            map.serialize_entry("length", &self.length)?;
+        }
+        if fields.contains(Fields::HAS_HYPHEN) {
+            map.serialize_entry("has_hyphen", &self.has_hyphen)?;
+        }
+        if fields.contains(Fields::TOKENS) {
+            map.serialize_entry("tokens", &self.tokens)?;
+        }
+        if fields.contains(Fields::DGA_SCORE) {
+            map.serialize_entry("dga_score", &self.dga_score)?;
+        }
+        if let Some(lang) = self.lang.as_ref().filter(|_| fields.contains(Fields::LANG)) {
+            map.serialize_entry("lang", lang)?;
+        }
+        if fields.contains(Fields::CATEGORIES) && !self.categories.is_empty() {
+            map.serialize_entry("categories", &self.categories)?;
+        }
+        map.end()
+    }
+}
+
+/// A set of `DomainResult` fields, parsed from a `fields=label,tld` list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fields(u16);
+
+impl Fields {
+    /// Just the domain, which every result carries
+    pub const DOMAIN: Fields = Fields(0);
+    pub const LABEL: Fields = Fields(1);
+    pub const TLD: Fields = Fields(1 << 1);
+    pub const LENGTH: Fields = Fields(1 << 2);
+    pub const HAS_HYPHEN: Fields = Fields(1 << 3);
+    pub const TOKENS: Fields = Fields(1 << 4);
+    pub const DGA_SCORE: Fields = Fields(1 << 5);
+    pub const LANG: Fields = Fields(1 << 6);
+    pub const CATEGORIES: Fields = Fields(1 << 7);
+    pub const ALL: Fields = Fields((1 << 8) - 1);
+
+    const NAMES: [(&'static str, Fields); 9] = [
+        ("domain", Fields::DOMAIN),
+        ("label", Fields::LABEL),
+        ("tld", Fields::TLD),
+        ("length", Fields::LENGTH),
+        ("has_hyphen", Fields::HAS_HYPHEN),
+        ("tokens", Fields::TOKENS),
+        ("dga_score", Fields::DGA_SCORE),
+        ("lang", Fields::LANG),
+        ("categories", Fields::CATEGORIES),
+    ];
+
+    /// Parse a comma-separated list of field names
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut fields = Fields::DOMAIN;
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let (_, field) = Self::NAMES
+                .iter()
+                .find(|(known, _)| *known == name)
+                .ok_or_else(|| format!("Unknown field: {}", name))?;
+            fields = fields | *field;
+        }
+        Ok(fields)
+    }
+
+    pub fn contains(self, other: Fields) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Fields::ALL
+    }
+}
+
+impl BitOr for Fields {
+    type Output = Fields;
+
+    fn bitor(self, other: Fields) -> Fields {
+        Fields(self.0 | other.0)
+    }
+}
+
+/// A ranked search hit
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub domain: DomainResult,
+    pub match_count: usize,
+    pub score: f32,
+    /// Some query tokens matched only joined (`pet care` → `petcare`) or
+    /// split (`petcare` → `pet` + `care`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compound_match: bool,
+    /// Label with the parts matching the query in brackets
+    /// (`best[coffee]shop`), when highlighting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlighted: Option<String>,
+    /// Other TLDs with the same label, when deduplicating by label
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<String>,
+    /// Ranking breakdown, when explaining
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
+    /// Index the result came from, when several indices were searched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+}
+
+/// Interleave group a result was drawn from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Bucket {
+    Hyphenated,
+    Plain,
+    /// The profile doesn't interleave
+    All,
+}
+
+/// How a result was scored and placed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Explanation {
+    pub bm25: f32,
+    pub matched_tokens: Vec<String>,
+    pub match_count: usize,
+    /// Sum of the matched tokens' IDF weights
+    pub match_weight: f32,
+    /// Contribution of label length to `score`
+    pub length_score: f64,
+    /// Weighted score under the profile's boosts
+    pub score: f64,
+    /// Boosts of the ranking profile
+    pub boosts: Boosts,
+    /// TLD and label multipliers included in `score`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_boosts: Vec<AppliedBoost>,
+    pub bucket: Bucket,
+}
+
+/// Rarity weight of one query token
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TokenWeight {
+    pub token: String,
+    /// Number of indexed domains containing the token
+    pub doc_freq: u64,
+    /// BM25-style inverse document frequency, scaled down for stop tokens
+    pub weight: f32,
+    /// Filler token excluded from `min_match` and `match_count`
+    #[serde(default)]
+    pub stop: bool,
+}
+
+/// Output of a single search
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    pub results: Vec<SearchResult>,
+    /// Number of candidates left after match count and TLD filtering
+    pub total_candidates: usize,
+    /// Documents matching the query scanned, over every collection pass
+    pub candidates_scanned: usize,
+    /// Documents matching the query and filters in the whole index: the
+    /// same as `total_candidates` for complete scans, extrapolated when
+    /// the time budget cut the scan short
+    pub total_hits: u64,
+    /// `total_hits` is an estimate
+    pub total_hits_estimated: bool,
+    /// IDF weight of each query token, in query order
+    pub token_weights: Vec<TokenWeight>,
+    /// The Tantivy query that collected candidates, when explaining
+    pub query: Option<String>,
+    /// The time budget ran out; results cover only the documents scanned
+    pub partial: bool,
+    /// Domain hacks for the query words, when requested
+    pub hacks: Vec<DomainHack>,
+    /// Documents per category among those matching the query and filters,
+    /// when requested
+    pub category_counts: Option<CategoryCounts>,
+    /// Likely corrections of rare query tokens
+    pub did_you_mean: Vec<Suggestion>,
+    /// The query searched instead, when `autocorrect` applied corrections
+    pub autocorrected: Option<String>,
+}
+
+/// Document counts by taxonomy category
+pub type CategoryCounts = BTreeMap<String, u64>;
+
+/// Extract domain result from a Tantivy document
+pub fn extract_domain_result(schema: &DomainSchema, doc: &TantivyDocument) -> DomainResult {
+    extract_domain_result_with(schema, doc, Fields::ALL)
+}
+
+/// Extract the given fields of a Tantivy document; the others are left
+/// empty and aren't serialized
+pub fn extract_domain_result_with(
+    schema: &DomainSchema,
+    doc: &TantivyDocument,
+    fields: Fields,
+) -> DomainResult {
+    use tantivy::schema::Value;
+
+    let domain = doc
+        .get_first(schema.domain_exact)
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let label = if fields.contains(Fields::LABEL) {
+        doc.get_first(schema.label)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    } else {
+        String::new()
+    };
+
+    // Extract TLD from domain string (facet not stored)
+    let tld = if fields.contains(Fields::TLD) {
+        domain.rsplit('.').next().unwrap_or("").to_string()
+    } else {
+        String::new()
+    };
+
+    let length = doc
+        .get_first(schema.len)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+
+    let has_hyphen = doc
+        .get_first(schema.has_hyphen)
+        .and_then(|v| v.as_u64())
+        .map(|v| v == 1)
+        .unwrap_or(false);
+
+    // Extract tokens
+    let tokens_str = doc
+        .get_first(schema.tokens)
+        .and_then(|v| v.as_str())
+        .filter(|_| fields.contains(Fields::TOKENS))
+        .unwrap_or("");
+
+    let tokens: Vec<String> = if tokens_str.is_empty() {
+        vec![]
+    } else {
+        tokens_str.split_whitespace().map(String::from).collect()
+    };
+
+    let dga_score = doc
+        .get_first(schema.dga_score)
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+
+    let lang = doc
+        .get_first(schema.lang)
+        .and_then(|v| v.as_str())
+        .filter(|_| fields.contains(Fields::LANG))
+        .map(String::from);
+
+    let categories = if fields.contains(Fields::CATEGORIES) {
+        doc.get_all(schema.category)
+            .filter_map(|v| v.as_facet())
+            .filter_map(|facet| facet.to_path().last().map(|category| category.to_string()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    DomainResult {
+        domain,
+        label,
+        tld,
+        length,
+        has_hyphen,
+        tokens,
+        dga_score,
+        lang,
+        categories,
+        fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain() -> DomainResult {
+        DomainResult {
+            domain: "coffeeshop.com".to_string(),
+            label: "coffeeshop".to_string(),
+            tld: "com".to_string(),
+            length: 10,
+            has_hyphen: false,
+            tokens: vec!["coffee".to_string(), "shop".to_string()],
+            dga_score: 0.1,
+            lang: Some("en".to_string()),
+            categories: vec!["food".to_string()],
+            fields: Fields::ALL,
+        }
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let fields = Fields::parse("tld, length,").unwrap();
+        assert_eq!(fields, Fields::TLD | Fields::LENGTH);
+        assert!(fields.contains(Fields::DOMAIN));
+        assert!(!fields.contains(Fields::TOKENS));
+        assert_eq!(Fields::parse("domain").unwrap(), Fields::DOMAIN);
+        assert_eq!(Fields::parse("score").unwrap_err(), "Unknown field: score");
+    }
+
+    #[test]
+    fn test_serialize_selected_fields() {
+        let mut result = domain();
+        let all = serde_json::to_value(&result).unwrap();
+        assert_eq!(all.as_object().unwrap().len(), 9);
+
+        result.fields = Fields::parse("tld,length").unwrap();
+        let json = serde_json::to_value(&result).unwrap();
+        let expected = serde_json::json!({"domain": "coffeeshop.com", "tld": "com", "length": 10});
+        assert_eq!(json, expected);
+
+        let parsed: DomainResult = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.length, 10);
+        assert!(parsed.tokens.is_empty());
+    }
+}
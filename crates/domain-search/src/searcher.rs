@@ -0,0 +1,1666 @@
+use crate::appraise::{self, Appraisal, AppraisalWeights, AppraisedWord, Evidence};
+use crate::collector::{Candidate, CandidateCollector, Candidates};
+use crate::compound::{self, Variant};
+use crate::hacks::{self, DomainHack};
+use crate::highlight::highlight;
+use crate::error::{Error, Result};
+use crate::profile::{Interleave, RankingProfile};
+use crate::ranking::RankedResult;
+use crate::spelling::{self, Suggestion};
+use crate::result::{
+    extract_domain_result, Bucket, CategoryCounts, SearchResult, SearchResults, TokenWeight,
+};
+use domain_core::{Domain, DomainSchema, NormalizedDomain};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tantivy::collector::{Collector, Count, FacetCollector, SegmentCollector, TopDocs};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, EnableScoring, Occur, Query, TermQuery,
+};
+use tantivy::schema::{Facet, IndexRecordOption};
+use tantivy::{DocSet, Index, IndexReader, ReloadPolicy, Term, TERMINATED};
+use tracing::{debug, info_span};
+
+/// Parameters for a keyword search
+#[derive(Debug, Clone)]
+pub struct SearchRequest {
+    /// Search keywords (space-separated)
+    pub query: String,
+
+    /// Filter by TLD (e.g., "com", or "com,net" for several)
+    pub tld: Option<String>,
+
+    /// Filter by the label's detected language (ISO 639-1, e.g. "de")
+    pub lang: Option<String>,
+
+    /// Filter by taxonomy category (e.g., "finance", or "finance,crypto"
+    /// for any of several)
+    pub category: Option<String>,
+
+    /// Count matching documents per category
+    pub category_counts: bool,
+
+    /// Maximum results to return
+    pub limit: usize,
+
+    /// Minimum number of keywords that must match (default: 1)
+    pub min_match: Option<usize>,
+
+    /// Sort order, interleaving and filters
+    pub profile: RankingProfile,
+
+    /// Collapse results sharing a label across TLDs
+    pub dedupe: Dedupe,
+
+    /// Attach a ranking breakdown to each result
+    pub explain: bool,
+
+    /// Stop collecting after this long and return the best results so far
+    pub timeout: Option<Duration>,
+
+    /// Matching documents scanned, over every pass, before a result list
+    /// short of `limit` stops being collected again with more candidates
+    /// kept (0 never does)
+    pub max_scanned: usize,
+
+    /// Filler tokens (e.g. "the") that don't count towards `min_match` or
+    /// `match_count` and weigh little in ranking
+    pub stop_tokens: Vec<String>,
+
+    /// Suggest domain hacks spelling query words across label and TLD
+    pub hacks: bool,
+
+    /// Bracket the parts of each result's label that matched the query
+    pub highlight: bool,
+
+    /// Also match other inflections of the query tokens by their stems
+    /// (`shoes` matches `shoe`); exact forms still rank first
+    pub stem: bool,
+
+    /// Search the corrections in `did_you_mean` instead of the rare tokens
+    /// they correct
+    pub autocorrect: bool,
+
+    /// Extra clause every result must match (e.g. a date range on the
+    /// dropped-domains index); it doesn't contribute to scores
+    pub filter: Option<Arc<dyn Query>>,
+}
+
+/// Result deduplication mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Dedupe {
+    /// Return every matching domain
+    #[default]
+    None,
+    /// Keep the best domain per label, listing the other TLDs in `also_in`
+    Label,
+}
+
+impl Dedupe {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dedupe::None => "none",
+            Dedupe::Label => "label",
+        }
+    }
+}
+
+impl SearchRequest {
+    pub fn new(query: impl Into<String>, limit: usize) -> Self {
+        Self {
+            query: query.into(),
+            tld: None,
+            lang: None,
+            category: None,
+            category_counts: false,
+            limit,
+            min_match: None,
+            profile: RankingProfile::default(),
+            dedupe: Dedupe::None,
+            explain: false,
+            timeout: None,
+            max_scanned: DEFAULT_MAX_SCANNED,
+            stop_tokens: Vec::new(),
+            hacks: false,
+            highlight: false,
+            stem: true,
+            autocorrect: false,
+            filter: None,
+        }
+    }
+
+    /// Query keywords, tokenized like indexed labels (see
+    /// [`domain_core::query::tokens`])
+    pub fn tokens(&self) -> Vec<String> {
+        domain_core::query::tokens(&self.query)
+    }
+
+    /// Lowercased TLDs of the comma-separated `tld` filter
+    pub fn tlds(&self) -> Vec<String> {
+        self.tld
+            .iter()
+            .flat_map(|tld| tld.split(','))
+            .map(|tld| tld.trim().trim_start_matches('.').to_lowercase())
+            .filter(|tld| !tld.is_empty())
+            .collect()
+    }
+
+    /// Lowercased categories of the comma-separated `category` filter
+    pub fn categories(&self) -> Vec<String> {
+        self.category
+            .iter()
+            .flat_map(|category| category.split(','))
+            .map(|category| category.trim().to_lowercase())
+            .filter(|category| !category.is_empty())
+            .collect()
+    }
+}
+
+/// Snapshot of a search in progress
+#[derive(Debug, Clone)]
+pub struct SearchProgress {
+    /// Best results among the documents scanned so far
+    pub results: Vec<SearchResult>,
+
+    /// Matching documents scanned so far
+    pub candidates_scanned: usize,
+
+    /// Documents kept so far after match count, TLD and profile filtering
+    pub total_candidates: usize,
+}
+
+/// Callback receiving a snapshot after each batch; returns `false` to stop
+type ProgressFn<'a> = &'a mut dyn FnMut(SearchProgress) -> bool;
+
+/// Share of a stop token's IDF weight and BM25 contribution that is kept
+const STOP_TOKEN_WEIGHT: f32 = 0.1;
+
+/// Matching documents collected between two time budget checks
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+/// Candidates kept per result when deduplicating by label
+const DEDUPE_HEADROOM: usize = 10;
+
+/// Candidates kept per result when TLD multipliers rescore them after loading
+const TLD_BOOST_HEADROOM: usize = 4;
+
+/// Default of [`SearchRequest::max_scanned`]
+const DEFAULT_MAX_SCANNED: usize = 1_000_000;
+
+/// Times the candidates kept can grow for a result list short of `limit`
+const MAX_ESCALATIONS: usize = 3;
+
+/// Growth of the candidates kept at each escalation
+const ESCALATION_FACTOR: usize = 4;
+
+/// Entries kept in the IDF cache before it is cleared
+const IDF_CACHE_CAPACITY: usize = 100_000;
+
+/// TLDs present in the index for one searcher generation
+#[derive(Default)]
+struct TldCache {
+    generation: u64,
+    tlds: Option<Arc<Vec<String>>>,
+}
+
+/// Document frequencies of query tokens for one searcher generation
+#[derive(Default)]
+struct IdfCache {
+    generation: u64,
+    doc_freqs: HashMap<String, u64>,
+}
+
+impl IdfCache {
+    fn doc_freq(
+        &mut self,
+        searcher: &tantivy::Searcher,
+        schema: &DomainSchema,
+        token: &str,
+    ) -> Result<u64> {
+        if let Some(&doc_freq) = self.doc_freqs.get(token) {
+            return Ok(doc_freq);
+        }
+        let term = Term::from_field_text(schema.tokens, token);
+        let doc_freq = searcher.doc_freq(&term)?;
+        self.doc_freqs.insert(token.to_string(), doc_freq);
+        Ok(doc_freq)
+    }
+}
+
+/// Keyword searcher over a domain index
+///
+/// Every document matching a query token in the `tokens` field is scored by
+/// match count, token rarity, length and BM25 during collection; only the
+/// best few under the ranking profile are loaded, deduplicated and
+/// interleaved.
+#[derive(Clone)]
+pub struct Searcher {
+    reader: IndexReader,
+    schema: DomainSchema,
+    idf_cache: Arc<Mutex<IdfCache>>,
+    tld_cache: Arc<Mutex<TldCache>>,
+}
+
+impl Searcher {
+    /// Open a searcher that picks up new commits automatically
+    pub fn new(index: &Index, schema: DomainSchema) -> Result<Self> {
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()?;
+        Ok(Self::with_reader(reader, schema))
+    }
+
+    /// Use an existing reader, e.g. one shared with other lookups
+    pub fn with_reader(reader: IndexReader, schema: DomainSchema) -> Self {
+        Self {
+            reader,
+            schema,
+            idf_cache: Arc::default(),
+            tld_cache: Arc::default(),
+        }
+    }
+
+    pub fn reader(&self) -> &IndexReader {
+        &self.reader
+    }
+
+    pub fn schema(&self) -> &DomainSchema {
+        &self.schema
+    }
+
+    /// Execute a keyword search
+    pub fn search(&self, request: &SearchRequest) -> Result<SearchResults> {
+        self.execute(request, None)
+    }
+
+    /// Execute a keyword search, reporting the current ranking after every
+    /// `batch_size` matching documents
+    ///
+    /// `on_progress` returns `false` to abandon the search early (e.g. when the
+    /// client has gone away); the results gathered so far are still returned.
+    pub fn search_progressive(
+        &self,
+        request: &SearchRequest,
+        batch_size: usize,
+        mut on_progress: impl FnMut(SearchProgress) -> bool,
+    ) -> Result<SearchResults> {
+        self.execute(request, Some((batch_size.max(1), &mut on_progress)))
+    }
+
+    /// Estimate a domain's value from its label, TLD and the index: how
+    /// common its words are among indexed names and how many other TLDs
+    /// have the label registered
+    pub fn appraise(
+        &self,
+        domain: &NormalizedDomain,
+        weights: &AppraisalWeights,
+    ) -> Result<Appraisal> {
+        let searcher = self.reader.searcher();
+        let num_docs = searcher.num_docs();
+        let words = self.segment_with(&searcher, &domain.label)?;
+
+        let term = Term::from_field_text(self.schema.domain_exact, &domain.domain_exact);
+        let lookup = TermQuery::new(term, IndexRecordOption::Basic);
+        let registered = searcher.search(&lookup, &Count)? > 0;
+
+        let tlds = self.indexed_tlds(&searcher)?;
+        let terms: Vec<Term> = tlds
+            .iter()
+            .filter(|tld| **tld != domain.tld)
+            .map(|tld| {
+                let other = format!("{}.{}", domain.label, tld);
+                Term::from_field_text(self.schema.domain_exact, &other)
+            })
+            .collect();
+        let mut comparables = Vec::new();
+        if !terms.is_empty() {
+            let query = BooleanQuery::new_multiterms_query(terms);
+            for (_, address) in searcher.search(&query, &TopDocs::with_limit(tlds.len()))? {
+                let doc = searcher.doc(address)?;
+                let found = extract_domain_result(&self.schema, &doc);
+                comparables.push(found.tld);
+            }
+        }
+        comparables.sort();
+
+        let evidence = Evidence {
+            registered,
+            words,
+            comparables,
+            num_docs,
+        };
+        Ok(appraise::appraise(domain, evidence, weights))
+    }
+
+    /// Split a label into the most probable sequence of indexed words, as
+    /// appraisal does; runs of characters that aren't an indexed word have
+    /// a `doc_freq` of 0
+    pub fn segment(&self, label: &str) -> Result<Vec<AppraisedWord>> {
+        self.segment_with(&self.reader.searcher(), label)
+    }
+
+    fn segment_with(
+        &self,
+        searcher: &tantivy::Searcher,
+        label: &str,
+    ) -> Result<Vec<AppraisedWord>> {
+        appraise::segment(label, searcher.num_docs(), |word| {
+            let term = Term::from_field_text(self.schema.tokens, word);
+            Ok(searcher.doc_freq(&term)?)
+        })
+    }
+
+    #[tracing::instrument(name = "search", skip_all, fields(query = %request.query))]
+    fn execute(
+        &self,
+        request: &SearchRequest,
+        mut progress: Option<(usize, ProgressFn<'_>)>,
+    ) -> Result<SearchResults> {
+        let start = Instant::now();
+        let query_tokens = request.tokens();
+
+        if query_tokens.is_empty() {
+            return Err(Error::EmptyQuery);
+        }
+
+        // A query made only of stop tokens is searched as written
+        let mut stop_tokens: HashSet<&str> =
+            request.stop_tokens.iter().map(String::as_str).collect();
+        if query_tokens.iter().all(|t| stop_tokens.contains(t.as_str())) {
+            stop_tokens.clear();
+        }
+
+        let searcher = self.reader.searcher();
+        let did_you_mean = info_span!("spelling")
+            .in_scope(|| self.did_you_mean(&searcher, &query_tokens, &stop_tokens))?;
+        if request.autocorrect && !did_you_mean.is_empty() {
+            let mut corrected = request.clone();
+            corrected.query = query_tokens
+                .iter()
+                .map(|token| match did_you_mean.iter().find(|s| s.token == *token) {
+                    Some(suggestion) => suggestion.suggestion.as_str(),
+                    None => token.as_str(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            corrected.autocorrect = false;
+            let mut results = self.execute(&corrected, progress)?;
+            results.did_you_mean = did_you_mean;
+            results.autocorrected = Some(corrected.query);
+            return Ok(results);
+        }
+
+        let (token_weights, variants, query) = info_span!("build_query").in_scope(|| {
+            let token_weights = self.token_weights(&searcher, &query_tokens, &stop_tokens)?;
+            let mut variants = {
+                let mut cache = self.idf_cache(&searcher);
+                compound::variants(&query_tokens, |token| {
+                    cache.doc_freq(&searcher, &self.schema, token)
+                })?
+            };
+            if request.stem && self.has_stems(&searcher) {
+                variants.extend(compound::stem_variants(&query_tokens));
+            }
+            variants.extend(compound::label_variants(&query_tokens));
+            let query =
+                self.build_query(&token_weights, &variants, request);
+            Ok::<_, Error>((token_weights, variants, query))
+        })?;
+
+        let profile = &request.profile;
+        let num_keywords = token_weights.iter().filter(|t| !t.stop).count();
+        let min_match = if profile.filters.all_tokens {
+            num_keywords
+        } else {
+            request.min_match.unwrap_or(1)
+        };
+
+        // Deduplication folds several domains into one result, so keep extra
+        let mut keep = match request.dedupe {
+            Dedupe::None => request.limit,
+            Dedupe::Label => request.limit.saturating_mul(DEDUPE_HEADROOM),
+        };
+        // TLDs are only known once loaded, so a boosted TLD may rise from
+        // below the cut
+        if !profile.boosts.tlds.is_empty() {
+            keep = keep.saturating_mul(TLD_BOOST_HEADROOM);
+        }
+        let deadline = request.timeout.map(|timeout| start + timeout);
+        // Progress snapshots can't be taken back, so streamed searches don't
+        // escalate
+        let escalate = progress.is_none();
+        let mut candidates_scanned = 0;
+        let mut escalations = 0;
+        let (total_candidates, scanned_share, results) = loop {
+            let collector = CandidateCollector::new(
+                &self.schema,
+                &token_weights,
+                &variants,
+                min_match,
+                profile,
+                keep,
+            );
+            let (candidates, scanned_share) = info_span!("tantivy_search").in_scope(|| {
+                if deadline.is_none() && progress.is_none() {
+                    Ok::<_, Error>((searcher.search(&query, &collector)?, None))
+                } else {
+                    self.collect_incrementally(
+                        &searcher, &query, &collector, request, deadline, progress.take(),
+                    )
+                }
+            })?;
+
+            let pass_scanned = candidates.scanned;
+            candidates_scanned += pass_scanned;
+            let total_candidates = candidates.matched;
+            let truncated = candidates.truncated();
+            let ranked_results = info_span!("load_documents")
+                .in_scope(|| self.load_candidates(&searcher, candidates, request))?;
+            let results = info_span!("rank").in_scope(|| rank(ranked_results, request));
+
+            // Deduplication can fold the kept candidates into fewer results
+            // than asked for; collect more while the scan budget allows
+            let short = results.len() < request.limit && truncated && scanned_share.is_none();
+            let affordable = candidates_scanned + pass_scanned <= request.max_scanned;
+            if escalate && short && affordable && escalations < MAX_ESCALATIONS {
+                escalations += 1;
+                keep = keep.saturating_mul(ESCALATION_FACTOR);
+                debug!(keep, escalations, "Escalating candidates for a short result list");
+                continue;
+            }
+            break (total_candidates, scanned_share, results);
+        };
+
+        let category_counts = if request.category_counts {
+            Some(self.count_categories(&searcher, &query)?)
+        } else {
+            None
+        };
+
+        let hacks = if request.hacks {
+            self.domain_hacks(&searcher, &query_tokens)?
+        } else {
+            Vec::new()
+        };
+
+        let partial = scanned_share.is_some();
+        let total_hits = estimate_total(total_candidates, scanned_share);
+
+        Ok(SearchResults {
+            results,
+            total_candidates,
+            candidates_scanned,
+            total_hits,
+            total_hits_estimated: partial,
+            token_weights,
+            query: request.explain.then(|| format!("{:?}", query)),
+            partial,
+            hacks,
+            category_counts,
+            did_you_mean,
+            autocorrected: None,
+        })
+    }
+
+    /// Corrections of the rare query tokens, stop tokens excepted
+    ///
+    /// Sorted by token, so swapped keywords get the same list.
+    fn did_you_mean(
+        &self,
+        searcher: &tantivy::Searcher,
+        query_tokens: &[String],
+        stop_tokens: &HashSet<&str>,
+    ) -> Result<Vec<Suggestion>> {
+        let num_docs = searcher.num_docs();
+        let mut suggestions = Vec::new();
+        for token in query_tokens {
+            if stop_tokens.contains(token.as_str()) {
+                continue;
+            }
+            let doc_freq = self.idf_cache(searcher).doc_freq(searcher, &self.schema, token)?;
+            if !spelling::is_rare(token, doc_freq, num_docs) {
+                continue;
+            }
+            let field = self.schema.tokens;
+            if let Some(suggestion) = spelling::suggest(searcher, field, token, doc_freq)? {
+                suggestions.push(suggestion);
+            }
+        }
+        suggestions.sort_by(|a, b| a.token.cmp(&b.token));
+        Ok(suggestions)
+    }
+
+    /// Documents per taxonomy category in the whole index
+    pub fn categories(&self) -> Result<CategoryCounts> {
+        self.count_categories(&self.reader.searcher(), &AllQuery)
+    }
+
+    fn count_categories(
+        &self,
+        searcher: &tantivy::Searcher,
+        query: &dyn Query,
+    ) -> Result<CategoryCounts> {
+        let field_name = self.schema.schema.get_field_name(self.schema.category);
+        let mut collector = FacetCollector::for_field(field_name);
+        collector.add_facet("/");
+        let facets = searcher.search(query, &collector)?;
+
+        Ok(facets
+            .get("/")
+            .filter_map(|(facet, count)| {
+                let category = facet.to_path().last()?.to_string();
+                Some((category, count))
+            })
+            .collect())
+    }
+
+    /// Domain hacks for each query token and for the whole query joined,
+    /// shortest label first
+    fn domain_hacks(
+        &self,
+        searcher: &tantivy::Searcher,
+        query_tokens: &[String],
+    ) -> Result<Vec<DomainHack>> {
+        let tlds = self.indexed_tlds(searcher)?;
+
+        let mut words: Vec<String> = query_tokens.to_vec();
+        if query_tokens.len() > 1 {
+            words.push(query_tokens.concat());
+        }
+
+        let mut hacks: Vec<DomainHack> = Vec::new();
+        for word in &words {
+            for (label, tld) in hacks::splits(word, &tlds) {
+                let Ok(normalized) = Domain::new(format!("{}.{}", label, tld)).normalize() else {
+                    continue;
+                };
+                if hacks.iter().any(|h| h.domain == normalized.domain_exact) {
+                    continue;
+                }
+
+                let domain = &normalized.domain_exact;
+                let term = Term::from_field_text(self.schema.domain_exact, domain);
+                let lookup = TermQuery::new(term, IndexRecordOption::Basic);
+                let in_zone = searcher.search(&lookup, &Count)? > 0;
+
+                hacks.push(DomainHack {
+                    domain: normalized.domain_exact,
+                    label: normalized.label,
+                    tld: normalized.tld,
+                    in_zone,
+                });
+            }
+        }
+
+        hacks.sort_by(|a, b| {
+            a.label.len().cmp(&b.label.len()).then_with(|| a.domain.cmp(&b.domain))
+        });
+        Ok(hacks)
+    }
+
+    /// Every TLD in the index, read from the facet dictionaries once per
+    /// searcher generation
+    fn indexed_tlds(&self, searcher: &tantivy::Searcher) -> Result<Arc<Vec<String>>> {
+        let generation = searcher.generation().generation_id();
+
+        let mut cache = self.tld_cache.lock().unwrap();
+        if let Some(tlds) = cache.tlds.as_ref().filter(|_| cache.generation == generation) {
+            return Ok(tlds.clone());
+        }
+
+        let mut tlds: BTreeSet<String> = BTreeSet::new();
+        for segment in searcher.segment_readers() {
+            let inverted_index = segment.inverted_index(self.schema.tld)?;
+            let mut terms = inverted_index
+                .terms()
+                .stream()
+                .map_err(tantivy::TantivyError::from)?;
+            while terms.advance() {
+                if let Ok(facet) = Facet::from_encoded(terms.key().to_vec()) {
+                    if let Some(tld) = facet.to_path().last() {
+                        tlds.insert(tld.to_string());
+                    }
+                }
+            }
+        }
+
+        let tlds = Arc::new(tlds.into_iter().collect::<Vec<_>>());
+        cache.generation = generation;
+        cache.tlds = Some(tlds.clone());
+        Ok(tlds)
+    }
+
+    /// Run the collector segment by segment, reporting the current ranking
+    /// every `batch_size` matching documents and stopping at `deadline`
+    ///
+    /// Returns the candidates collected and, when the deadline cut collection
+    /// short, the share of the index's documents scanned by then.
+    fn collect_incrementally(
+        &self,
+        searcher: &tantivy::Searcher,
+        query: &dyn Query,
+        collector: &CandidateCollector,
+        request: &SearchRequest,
+        deadline: Option<Instant>,
+        mut progress: Option<(usize, ProgressFn<'_>)>,
+    ) -> Result<(Candidates, Option<f64>)> {
+        let weight = query.weight(EnableScoring::enabled_from_searcher(searcher))?;
+        let mut collected = collector.empty();
+        let mut scanned = 0usize;
+        let total_docs: u64 = searcher.segment_readers().iter().map(|s| s.max_doc() as u64).sum();
+        let mut docs_before = 0u64;
+
+        for (segment_ord, segment) in searcher.segment_readers().iter().enumerate() {
+            let mut segment_collector = collector.for_segment(segment_ord as u32, segment)?;
+            let mut scorer = weight.scorer(segment, 1.0)?;
+            let alive_bitset = segment.alive_bitset();
+
+            let mut doc = scorer.doc();
+            while doc != TERMINATED {
+                if alive_bitset.is_none_or(|alive| alive.is_alive(doc)) {
+                    if let Some(deadline) = deadline {
+                        if scanned.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                            && Instant::now() >= deadline
+                        {
+                            collected.merge(segment_collector.harvest());
+                            let share = (docs_before + doc as u64) as f64 / total_docs as f64;
+                            return Ok((collected, Some(share)));
+                        }
+                    }
+                    if let Some((batch_size, on_progress)) = progress.as_mut() {
+                        if scanned > 0 && scanned.is_multiple_of(*batch_size) {
+                            let mut snapshot = collected.clone();
+                            snapshot.merge(segment_collector.candidates().clone());
+                            let total_candidates = snapshot.matched;
+                            let ranked_results = self.load_candidates(searcher, snapshot, request)?;
+                            let snapshot = SearchProgress {
+                                results: rank(ranked_results, request),
+                                candidates_scanned: scanned,
+                                total_candidates,
+                            };
+                            if !on_progress(snapshot) {
+                                collected.merge(segment_collector.harvest());
+                                return Ok((collected, None));
+                            }
+                        }
+                    }
+                    segment_collector.collect(doc, scorer.score());
+                    scanned += 1;
+                }
+                doc = scorer.advance();
+            }
+
+            collected.merge(segment_collector.harvest());
+            docs_before += segment.max_doc() as u64;
+        }
+
+        Ok((collected, None))
+    }
+
+    /// Load the stored documents of the best candidates
+    fn load_candidates(
+        &self,
+        searcher: &tantivy::Searcher,
+        candidates: Candidates,
+        request: &SearchRequest,
+    ) -> Result<Vec<RankedResult>> {
+        let query_tokens = request.tokens();
+        let mut ranked_results: Vec<RankedResult> = Vec::new();
+        let mut by_label: Option<HashMap<String, usize>> =
+            (request.dedupe == Dedupe::Label).then(HashMap::new);
+
+        for Candidate { doc, signals, matched } in candidates.into_sorted() {
+            let doc = searcher.doc(doc)?;
+            let domain_result = extract_domain_result(&self.schema, &doc);
+
+            let matched_tokens: Vec<String> = if request.explain || request.highlight {
+                query_tokens
+                    .iter()
+                    .take(64)
+                    .enumerate()
+                    .filter(|(i, _)| matched & (1 << i) != 0)
+                    .map(|(_, token)| token.clone())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let highlighted = request.highlight.then(|| {
+                highlight(&domain_result.label, &domain_result.tokens, &matched_tokens)
+            });
+
+            let candidate = RankedResult {
+                domain: domain_result,
+                match_count: signals.match_count,
+                match_weight: signals.match_weight,
+                bm25_score: signals.bm25_score,
+                also_in: Vec::new(),
+                matched_tokens,
+                compound_match: signals.compound_match,
+                highlighted,
+            };
+
+            // Fold the same label under other TLDs into the best-ranked one
+            if let Some(by_label) = by_label.as_mut() {
+                if let Some(&i) = by_label.get(&candidate.domain.label) {
+                    merge_duplicate(&mut ranked_results[i], candidate, &request.profile);
+                    continue;
+                }
+                by_label.insert(candidate.domain.label.clone(), ranked_results.len());
+            }
+
+            ranked_results.push(candidate);
+        }
+
+        Ok(ranked_results)
+    }
+
+    /// Whether the index has the `stems` field; indices built before it was
+    /// added are searched without stemming
+    fn has_stems(&self, searcher: &tantivy::Searcher) -> bool {
+        searcher.schema().get_field("stems").ok() == Some(self.schema.stems)
+    }
+
+    /// The IDF cache, cleared when the searcher generation changes or it
+    /// grows too large
+    fn idf_cache(&self, searcher: &tantivy::Searcher) -> MutexGuard<'_, IdfCache> {
+        let generation = searcher.generation().generation_id();
+
+        let mut cache = self.idf_cache.lock().unwrap();
+        if cache.generation != generation || cache.doc_freqs.len() >= IDF_CACHE_CAPACITY {
+            cache.generation = generation;
+            cache.doc_freqs.clear();
+        }
+        cache
+    }
+
+    /// IDF weight of each query token, with document frequencies cached per
+    /// searcher generation
+    fn token_weights(
+        &self,
+        searcher: &tantivy::Searcher,
+        query_tokens: &[String],
+        stop_tokens: &HashSet<&str>,
+    ) -> Result<Vec<TokenWeight>> {
+        let num_docs = searcher.num_docs();
+        let mut cache = self.idf_cache(searcher);
+
+        query_tokens
+            .iter()
+            .map(|token| {
+                let doc_freq = cache.doc_freq(searcher, &self.schema, token)?;
+                let stop = stop_tokens.contains(token.as_str());
+                let weight = idf(doc_freq, num_docs);
+                Ok(TokenWeight {
+                    token: token.clone(),
+                    doc_freq,
+                    weight: if stop { weight * STOP_TOKEN_WEIGHT } else { weight },
+                    stop,
+                })
+            })
+            .collect()
+    }
+
+    /// Build the Tantivy query (OR of all tokens, compound variants, stems
+    /// and label words), restricted to the request's TLDs, language, categories and `filter`
+    ///
+    /// The TLD clause is a required, zero-scored facet match, so documents
+    /// in other TLDs are skipped by the engine and BM25 is unaffected.
+    fn build_query(
+        &self,
+        tokens: &[TokenWeight],
+        variants: &[Variant],
+        request: &SearchRequest,
+    ) -> BooleanQuery {
+        let tlds = request.tlds();
+        let filter = &request.filter;
+        let lang = request.lang.as_deref().map(str::to_lowercase);
+        let categories = request.categories();
+        let compound_tokens: BTreeSet<&str> = variants
+            .iter()
+            .filter(|v| v.compound)
+            .flat_map(|v| v.tokens.iter().map(String::as_str))
+            .filter(|t| !tokens.iter().any(|qt| qt.token == *t))
+            .collect();
+        let compound_queries = compound_tokens.into_iter().map(|token| {
+            let term = Term::from_field_text(self.schema.tokens, token);
+            let term_query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+            (Occur::Should, Box::new(term_query) as Box<dyn Query>)
+        });
+        let stems: BTreeSet<&str> = variants
+            .iter()
+            .filter(|v| v.stemmed)
+            .flat_map(|v| v.tokens.iter().map(String::as_str))
+            .collect();
+        let stem_queries = stems.into_iter().map(|stem| {
+            let term = Term::from_field_text(self.schema.stems, stem);
+            let term_query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+            (Occur::Should, Box::new(term_query) as Box<dyn Query>)
+        });
+        let label_words: BTreeSet<&str> = variants
+            .iter()
+            .filter(|v| v.label)
+            .flat_map(|v| v.tokens.iter().map(String::as_str))
+            .collect();
+        // Zero-scored, so label words don't add to the BM25 of segmented
+        // documents; the collector only counts them for documents without
+        // tokens
+        let label_queries = label_words.into_iter().map(|word| {
+            let term = Term::from_field_text(self.schema.label, word);
+            let term_query = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+            let label_query = ConstScoreQuery::new(term_query, 0.0);
+            (Occur::Should, Box::new(label_query) as Box<dyn Query>)
+        });
+
+        let token_queries: Vec<(Occur, Box<dyn Query>)> = tokens
+            .iter()
+            .map(|token| {
+                let term = Term::from_field_text(self.schema.tokens, &token.token);
+                let term_query: Box<dyn Query> =
+                    Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+                if token.stop {
+                    let boosted = BoostQuery::new(term_query, STOP_TOKEN_WEIGHT);
+                    (Occur::Should, Box::new(boosted) as Box<dyn Query>)
+                } else {
+                    (Occur::Should, term_query)
+                }
+            })
+            .chain(compound_queries)
+            .chain(stem_queries)
+            .chain(label_queries)
+            .collect();
+
+        if tlds.is_empty() && lang.is_none() && categories.is_empty() && filter.is_none() {
+            return BooleanQuery::new(token_queries);
+        }
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> =
+            vec![(Occur::Must, Box::new(BooleanQuery::new(token_queries)))];
+
+        if !tlds.is_empty() {
+            let tld_queries: Vec<(Occur, Box<dyn Query>)> = tlds
+                .iter()
+                .map(|tld| {
+                    let term = Term::from_facet(self.schema.tld, &Facet::from_path([tld]));
+                    let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+                    (Occur::Should, Box::new(term_query) as Box<dyn Query>)
+                })
+                .collect();
+            let tld_filter = ConstScoreQuery::new(Box::new(BooleanQuery::new(tld_queries)), 0.0);
+            clauses.push((Occur::Must, Box::new(tld_filter)));
+        }
+
+        if let Some(lang) = lang {
+            let term = Term::from_field_text(self.schema.lang, &lang);
+            let lang_filter = TermQuery::new(term, IndexRecordOption::Basic);
+            let lang_filter = ConstScoreQuery::new(Box::new(lang_filter), 0.0);
+            clauses.push((Occur::Must, Box::new(lang_filter)));
+        }
+
+        if !categories.is_empty() {
+            let category_terms = categories
+                .iter()
+                .map(|category| Facet::from_path([category]))
+                .map(|facet| Term::from_facet(self.schema.category, &facet))
+                .collect();
+            let category_filter = BooleanQuery::new_multiterms_query(category_terms);
+            let category_filter = ConstScoreQuery::new(Box::new(category_filter), 0.0);
+            clauses.push((Occur::Must, Box::new(category_filter)));
+        }
+
+        if let Some(filter) = filter {
+            let filter = ConstScoreQuery::new(filter.box_clone(), 0.0);
+            clauses.push((Occur::Must, Box::new(filter)));
+        }
+
+        BooleanQuery::new(clauses)
+    }
+}
+
+/// Keep the better of two same-label results, recording the other's TLD
+fn merge_duplicate(
+    existing: &mut RankedResult,
+    mut candidate: RankedResult,
+    profile: &RankingProfile,
+) {
+    if profile.compare_results(&candidate, existing).is_lt() {
+        candidate.also_in = std::mem::take(&mut existing.also_in);
+        candidate.also_in.push(existing.domain.tld.clone());
+        *existing = candidate;
+    } else {
+        existing.also_in.push(candidate.domain.tld);
+    }
+}
+
+/// Matching documents in the whole index, extrapolated from the share of it
+/// scanned when collection stopped early
+///
+/// Matches are assumed evenly spread over document ids; with nothing scanned
+/// there is nothing to extrapolate from.
+fn estimate_total(matched: usize, scanned_share: Option<f64>) -> u64 {
+    match scanned_share {
+        Some(share) if share > 0.0 && share < 1.0 => {
+            (matched as f64 / share).round().max(matched as f64) as u64
+        }
+        _ => matched as u64,
+    }
+}
+
+/// BM25 inverse document frequency; rare tokens weigh more
+fn idf(doc_freq: u64, num_docs: u64) -> f32 {
+    let doc_freq = doc_freq as f32;
+    let num_docs = num_docs as f32;
+    (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln()
+}
+
+/// Sort by the profile's keys and merge into the final list
+fn rank(mut ranked_results: Vec<RankedResult>, request: &SearchRequest) -> Vec<SearchResult> {
+    let profile = &request.profile;
+
+    match profile.interleave {
+        Interleave::Hyphen => interleave(ranked_results, request),
+        Interleave::None => {
+            ranked_results.sort_by(|a, b| profile.compare_results(a, b));
+            ranked_results.truncate(request.limit);
+            ranked_results
+                .into_iter()
+                .map(|r| r.into_result(&profile.boosts, Bucket::All, request.explain))
+                .collect()
+        }
+    }
+}
+
+/// Sort and interleave hyphenated and non-hyphenated results
+///
+/// Every prefix of the list is as near the profile's `hyphen_ratio` of
+/// hyphenated results as whole results allow, hyphenated first; when one
+/// group runs out the other fills the list. A ratio of 0 leaves hyphenated
+/// domains out.
+fn interleave(ranked_results: Vec<RankedResult>, request: &SearchRequest) -> Vec<SearchResult> {
+    let profile = &request.profile;
+    let limit = request.limit;
+    let ratio = profile.hyphen_ratio.clamp(0.0, 1.0);
+    let finish = |r: RankedResult, bucket| r.into_result(&profile.boosts, bucket, request.explain);
+
+    // Separate hyphenated and non-hyphenated domains
+    let (mut hyphenated, mut non_hyphenated): (Vec<_>, Vec<_>) = ranked_results
+        .into_iter()
+        .partition(|r| r.domain.has_hyphen);
+    if ratio == 0.0 {
+        hyphenated.clear();
+    }
+
+    // Sort each group by the profile (default: match_count DESC, length ASC, bm25 DESC)
+    let sort_fn = |a: &RankedResult, b: &RankedResult| profile.compare_results(a, b);
+    hyphenated.sort_by(sort_fn);
+    non_hyphenated.sort_by(sort_fn);
+
+    let mut results: Vec<SearchResult> =
+        Vec::with_capacity(limit.min(hyphenated.len() + non_hyphenated.len()));
+
+    let mut hyp_iter = hyphenated.into_iter();
+    let mut non_hyp_iter = non_hyphenated.into_iter();
+    let mut hyphen_count = 0;
+
+    while results.len() < limit {
+        // Hyphenated while they're short of their share of the next slot
+        let hyphen_turn = (hyphen_count as f64) < ratio * (results.len() + 1) as f64;
+        let hyphen = |r| (r, Bucket::Hyphenated);
+        let plain = |r| (r, Bucket::Plain);
+        let next = if hyphen_turn {
+            hyp_iter.next().map(hyphen).or_else(|| non_hyp_iter.next().map(plain))
+        } else {
+            non_hyp_iter.next().map(plain).or_else(|| hyp_iter.next().map(hyphen))
+        };
+        let Some((r, bucket)) = next else {
+            break;
+        };
+        if bucket == Bucket::Hyphenated {
+            hyphen_count += 1;
+        }
+        results.push(finish(r, bucket));
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::{Domain, Taxonomy};
+
+    fn test_searcher(domains: &[(&str, &[&str])]) -> Searcher {
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+
+        for (raw, tokens) in domains {
+            let normalized = Domain::new(*raw)
+                .normalize()
+                .unwrap()
+                .with_tokens(tokens.iter().map(|t| t.to_string()).collect());
+            writer.add_document(schema.to_document(&normalized)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        Searcher::new(&index, schema).unwrap()
+    }
+
+    #[test]
+    fn test_search_ranks_by_match_count() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("bestcoffee.com", &["best", "coffee"]),
+            ("bestshop.net", &["best", "shop"]),
+        ]);
+
+        let results = searcher.search(&SearchRequest::new("best coffee", 10)).unwrap();
+
+        assert_eq!(results.total_candidates, 3);
+        assert_eq!(results.results[0].domain.domain, "bestcoffee.com");
+        assert_eq!(results.results[0].match_count, 2);
+    }
+
+    #[test]
+    fn test_search_tld_filter_and_min_match() {
+        let searcher = test_searcher(&[
+            ("bestcoffee.com", &["best", "coffee"]),
+            ("bestcoffee.net", &["best", "coffee"]),
+            ("coffee.net", &["coffee"]),
+        ]);
+
+        let mut request = SearchRequest::new("best coffee", 10);
+        request.tld = Some("NET".to_string());
+        request.min_match = Some(2);
+
+        let results = searcher.search(&request).unwrap();
+
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].domain.domain, "bestcoffee.net");
+    }
+
+    #[test]
+    fn test_search_tld_filter_ranks_every_match() {
+        let mut domains: Vec<(String, &[&str])> = (0..50)
+            .map(|i| (format!("coffee{}.com", i), &["coffee"] as &[&str]))
+            .collect();
+        domains.push(("coffeecoffeecoffee.io".to_string(), &["coffee", "coffee", "coffee"]));
+        domains.push(("coffeebeans.io".to_string(), &["coffee", "beans"]));
+        let domains: Vec<(&str, &[&str])> = domains.iter().map(|(d, t)| (d.as_str(), *t)).collect();
+        let searcher = test_searcher(&domains);
+
+        let mut request = SearchRequest::new("coffee", 1);
+        request.tld = Some("io".to_string());
+        let results = searcher.search(&request).unwrap();
+
+        assert_eq!(results.total_candidates, 2);
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].domain.domain, "coffeebeans.io");
+    }
+
+    #[test]
+    fn test_search_multiple_tlds() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("coffee.net", &["coffee"]),
+            ("coffee.org", &["coffee"]),
+        ]);
+
+        let mut request = SearchRequest::new("coffee", 10);
+        request.tld = Some("net, .ORG".to_string());
+        let results = searcher.search(&request).unwrap();
+
+        let mut tlds: Vec<&str> = results.results.iter().map(|r| r.domain.tld.as_str()).collect();
+        tlds.sort();
+        assert_eq!(tlds, vec!["net", "org"]);
+        assert_eq!(results.total_candidates, 2);
+    }
+
+    #[test]
+    fn test_search_filter_restricts_candidates() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("coffeeshop.com", &["coffee", "shop"]),
+        ]);
+
+        let term = Term::from_field_text(searcher.schema().domain_exact, "coffeeshop.com");
+        let mut request = SearchRequest::new("coffee", 10);
+        request.filter = Some(Arc::new(TermQuery::new(term, IndexRecordOption::Basic)));
+        let results = searcher.search(&request).unwrap();
+
+        assert_eq!(results.total_candidates, 1);
+        assert_eq!(results.results[0].domain.domain, "coffeeshop.com");
+    }
+
+    #[test]
+    fn test_search_lang_filter() {
+        let searcher = test_searcher(&[
+            ("cheapflights.com", &["cheap", "flights"]),
+            ("billigeflugreisen.de", &["billige", "flug", "reisen"]),
+        ]);
+
+        let mut request = SearchRequest::new("cheap billige", 10);
+        request.lang = Some("DE".to_string());
+        let results = searcher.search(&request).unwrap();
+
+        assert_eq!(results.total_candidates, 1);
+        assert_eq!(results.results[0].domain.domain, "billigeflugreisen.de");
+        assert_eq!(results.results[0].domain.lang.as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn test_search_category_filter_and_counts() {
+        let taxonomy = Taxonomy::new(HashMap::from([
+            ("finance".to_string(), vec!["loan".to_string(), "bank".to_string()]),
+            ("crypto".to_string(), vec!["coin".to_string(), "bank".to_string()]),
+        ]))
+        .unwrap();
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+        for (raw, tokens) in [
+            ("fastloan.com", ["fast", "loan"]),
+            ("coinbank.com", ["coin", "bank"]),
+            ("fastcar.com", ["fast", "car"]),
+        ] {
+            let tokens: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+            let normalized = Domain::new(raw).normalize().unwrap();
+            let categories = taxonomy.tag(&normalized.label, &tokens);
+            let normalized = normalized.with_tokens(tokens).with_categories(categories);
+            writer.add_document(schema.to_document(&normalized)).unwrap();
+        }
+        writer.commit().unwrap();
+        let searcher = Searcher::new(&index, schema).unwrap();
+
+        let mut request = SearchRequest::new("fast bank", 10);
+        request.category_counts = true;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.total_candidates, 3);
+        let counts = results.category_counts.unwrap();
+        assert_eq!(counts.get("finance"), Some(&2));
+        assert_eq!(counts.get("crypto"), Some(&1));
+
+        request.category = Some("Crypto".to_string());
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.total_candidates, 1);
+        assert_eq!(results.results[0].domain.categories, vec!["crypto", "finance"]);
+
+        assert_eq!(searcher.categories().unwrap().get("finance"), Some(&2));
+    }
+
+    #[test]
+    fn test_search_interleaves_hyphenated() {
+        let searcher = test_searcher(&[
+            ("coffeeshop.com", &["coffee", "shop"]),
+            ("coffee-shop.com", &["coffee", "shop"]),
+            ("coffeeshops.com", &["coffee", "shops"]),
+        ]);
+
+        let results = searcher.search(&SearchRequest::new("coffee", 10)).unwrap();
+
+        assert!(results.results[0].domain.has_hyphen);
+        assert!(!results.results[1].domain.has_hyphen);
+    }
+
+    #[test]
+    fn test_hyphen_ratio() {
+        let searcher = test_searcher(&[
+            ("coffee-shop.com", &["coffee", "shop"]),
+            ("coffee-bar.com", &["coffee", "bar"]),
+            ("coffee-cup.com", &["coffee", "cup"]),
+            ("coffeeshop.com", &["coffee", "shop"]),
+            ("coffeebar.com", &["coffee", "bar"]),
+            ("coffeecup.com", &["coffee", "cup"]),
+            ("coffeepot.com", &["coffee", "pot"]),
+        ]);
+        let hyphens = |ratio: f64, limit: usize| -> Vec<bool> {
+            let mut request = SearchRequest::new("coffee", limit);
+            request.profile.hyphen_ratio = ratio;
+            let results = searcher.search(&request).unwrap().results;
+            results.iter().map(|r| r.domain.has_hyphen).collect()
+        };
+
+        assert_eq!(hyphens(0.5, 4), vec![true, false, true, false]);
+        assert_eq!(hyphens(0.25, 4), vec![true, false, false, false]);
+        assert_eq!(hyphens(0.0, 10), vec![false; 4]);
+        // Plain domains fill in once the hyphenated run out
+        assert_eq!(hyphens(1.0, 5), vec![true, true, true, false, false]);
+    }
+
+    #[test]
+    fn test_short_results_escalate_candidates() {
+        // Label deduplication folds the 20 candidates kept for 2 results
+        // into one, leaving the longer label behind
+        let tlds: Vec<String> = (b'a'..=b'y').map(|c| format!("{}z", c as char)).collect();
+        let mut domains: Vec<(String, &[&str])> =
+            tlds.iter().map(|tld| (format!("coffee.{}", tld), &["coffee"][..])).collect();
+        domains.push(("coffeeshop.com".to_string(), &["coffee", "shop"]));
+        let domains: Vec<(&str, &[&str])> =
+            domains.iter().map(|(domain, tokens)| (domain.as_str(), *tokens)).collect();
+        let searcher = test_searcher(&domains);
+
+        let mut request = SearchRequest::new("coffee", 2);
+        request.dedupe = Dedupe::Label;
+        request.profile.interleave = Interleave::None;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.results.len(), 2);
+        assert_eq!(results.results[1].domain.domain, "coffeeshop.com");
+        assert_eq!(results.candidates_scanned, 2 * domains.len());
+
+        // Without the scan budget for a second pass the list stays short
+        request.max_scanned = domains.len();
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.candidates_scanned, domains.len());
+    }
+
+    #[test]
+    fn test_search_prefers_rare_tokens() {
+        let searcher = test_searcher(&[
+            ("thebest.com", &["the", "best"]),
+            ("theshop.com", &["the", "shop"]),
+            ("theblog.com", &["the", "blog"]),
+            ("blockchain.com", &["blockchain"]),
+        ]);
+
+        let results = searcher.search(&SearchRequest::new("the blockchain", 10)).unwrap();
+
+        assert_eq!(results.results[0].domain.domain, "blockchain.com");
+        let weights = &results.token_weights;
+        assert_eq!(weights[0].doc_freq, 3);
+        assert_eq!(weights[1].doc_freq, 1);
+        assert!(weights[1].weight > weights[0].weight);
+    }
+
+    #[test]
+    fn test_search_stop_tokens() {
+        let searcher = test_searcher(&[
+            ("thecoffee.com", &["the", "coffee"]),
+            ("theshop.com", &["the", "shop"]),
+            ("bestcoffee.com", &["best", "coffee"]),
+        ]);
+
+        let mut request = SearchRequest::new("the best coffee", 10);
+        request.stop_tokens = vec!["the".to_string()];
+        let results = searcher.search(&request).unwrap();
+
+        let domains: Vec<&str> = results.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, vec!["bestcoffee.com", "thecoffee.com"]);
+        assert_eq!(results.results[1].match_count, 1);
+        assert!(results.token_weights[0].stop);
+
+        // Without stop tokens "the" is a full keyword
+        request.stop_tokens.clear();
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.total_candidates, 3);
+
+        // A query of nothing but stop tokens still matches
+        let mut request = SearchRequest::new("the", 10);
+        request.stop_tokens = vec!["the".to_string()];
+        assert_eq!(searcher.search(&request).unwrap().total_candidates, 2);
+    }
+
+    #[test]
+    fn test_search_compound_matches() {
+        let searcher = test_searcher(&[
+            ("petcare.com", &["petcare"]),
+            ("petcarepro.com", &["pet", "care", "pro"]),
+            ("petshop.com", &["pet", "shop"]),
+        ]);
+
+        let mut request = SearchRequest::new("pet care", 10);
+        request.explain = true;
+        let results = searcher.search(&request).unwrap();
+        let joined = results.results.iter().find(|r| r.domain.domain == "petcare.com").unwrap();
+        assert_eq!(joined.match_count, 2);
+        assert!(joined.compound_match);
+        let first = results.results[0].explanation.as_ref().unwrap();
+        assert_eq!(first.matched_tokens, vec!["pet", "care"]);
+        let plain = results.results.iter().find(|r| r.domain.domain == "petcarepro.com").unwrap();
+        assert!(!plain.compound_match);
+
+        let results = searcher.search(&SearchRequest::new("petcare", 10)).unwrap();
+        let split = results.results.iter().find(|r| r.domain.domain == "petcarepro.com").unwrap();
+        assert_eq!(split.match_count, 1);
+        assert!(split.compound_match);
+        assert_eq!(results.total_candidates, 2);
+    }
+
+    #[test]
+    fn test_search_stems() {
+        let searcher = test_searcher(&[
+            ("shoe.com", &["shoe"]),
+            ("runningshoes.com", &["running", "shoes"]),
+            ("shoestring.com", &["shoestring"]),
+        ]);
+
+        // The searched form ranks above other inflections
+        let results = searcher.search(&SearchRequest::new("shoes", 10)).unwrap();
+        let domains: Vec<&str> = results.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, vec!["runningshoes.com", "shoe.com"]);
+
+        let results = searcher.search(&SearchRequest::new("run shoe", 10)).unwrap();
+        assert_eq!(results.results[0].domain.domain, "runningshoes.com");
+        assert_eq!(results.results[0].match_count, 2);
+
+        let mut request = SearchRequest::new("shoes", 10);
+        request.stem = false;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].domain.domain, "runningshoes.com");
+    }
+
+    #[test]
+    fn test_search_falls_back_to_label() {
+        let searcher = test_searcher(&[
+            ("pet-shop.com", &[]),
+            ("petshop.net", &[]),
+            ("pet-supply.com", &["pet", "supply"]),
+        ]);
+
+        // Unsegmented domains match by the words of their label, below
+        // domains matching by token
+        let results = searcher.search(&SearchRequest::new("pet", 10)).unwrap();
+        let domains: Vec<&str> = results.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, vec!["pet-supply.com", "pet-shop.com"]);
+        assert!(results.results[0].score > results.results[1].score);
+
+        let results = searcher.search(&SearchRequest::new("petshop", 10)).unwrap();
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].domain.domain, "petshop.net");
+        assert_eq!(results.results[0].match_count, 1);
+    }
+
+    #[test]
+    fn test_label_fallback_skips_segmented_domains() {
+        let searcher = test_searcher(&[
+            ("pet-shop.com", &["pet", "shop"]),
+            ("petshop.net", &["pet", "shop"]),
+            ("pet-care.org", &["petcare"]),
+        ]);
+
+        // The label word adds nothing to a domain matched by its tokens, and
+        // doesn't match one whose tokens don't hold it
+        let results = searcher.search(&SearchRequest::new("pet", 10)).unwrap();
+        let domains: BTreeSet<&str> =
+            results.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, BTreeSet::from(["pet-shop.com", "petshop.net"]));
+        assert_eq!(results.results[0].score, results.results[1].score);
+    }
+
+    #[test]
+    fn test_search_index_without_dga_score() {
+        // An index built before the score was added, with the fields before it
+        let schema = DomainSchema::new();
+        let mut builder = tantivy::schema::Schema::builder();
+        for (field, entry) in schema.schema.fields() {
+            if field.field_id() < schema.dga_score.field_id() {
+                builder.add_field(entry.clone());
+            }
+        }
+        let index = Index::create_in_ram(builder.build());
+        let mut writer = index.writer(15_000_000).unwrap();
+        let normalized = Domain::new("bestcoffee.com")
+            .normalize()
+            .unwrap()
+            .with_tokens(vec!["best".to_string(), "coffee".to_string()]);
+        let mut doc = tantivy::TantivyDocument::new();
+        for field_value in schema.to_document(&normalized).field_values() {
+            if field_value.field().field_id() < schema.dga_score.field_id() {
+                doc.add_field_value(field_value.field(), field_value.value().clone());
+            }
+        }
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+        let searcher = Searcher::new(&index, schema).unwrap();
+
+        let mut request = SearchRequest::new("coffee", 10);
+        request.profile.filters.max_dga_score = Some(0.5);
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.results[0].domain.dga_score, 0.0);
+    }
+
+    #[test]
+    fn test_search_did_you_mean() {
+        let labels: Vec<String> = (0..12).map(|i| format!("coffee{i}.com")).collect();
+        let mut domains: Vec<(&str, &[&str])> =
+            labels.iter().map(|label| (label.as_str(), &["coffee"][..])).collect();
+        domains.push(("cofeeshop.com", &["cofee", "shop"]));
+        let searcher = test_searcher(&domains);
+
+        let results = searcher.search(&SearchRequest::new("cofee", 5)).unwrap();
+        assert_eq!(results.results.len(), 1);
+        assert_eq!(results.did_you_mean.len(), 1);
+        assert_eq!(results.did_you_mean[0].token, "cofee");
+        assert_eq!(results.did_you_mean[0].suggestion, "coffee");
+        assert!(results.autocorrected.is_none());
+
+        let mut request = SearchRequest::new("cofee", 5);
+        request.autocorrect = true;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.autocorrected.as_deref(), Some("coffee"));
+        assert_eq!(results.results.len(), 5);
+        assert_eq!(results.did_you_mean[0].suggestion, "coffee");
+
+        // Frequent tokens aren't corrected
+        let results = searcher.search(&SearchRequest::new("coffee", 5)).unwrap();
+        assert!(results.did_you_mean.is_empty());
+    }
+
+    #[test]
+    fn test_search_highlight() {
+        let searcher = test_searcher(&[
+            ("bestcoffeeshop.com", &["best", "coffee", "shop"]),
+            ("petcare.com", &["petcare"]),
+        ]);
+
+        let mut request = SearchRequest::new("coffee", 10);
+        assert!(searcher.search(&request).unwrap().results[0].highlighted.is_none());
+
+        request.highlight = true;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.results[0].highlighted.as_deref(), Some("best[coffee]shop"));
+        assert!(results.results[0].explanation.is_none());
+
+        let mut request = SearchRequest::new("pet care", 10);
+        request.highlight = true;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.results[0].highlighted.as_deref(), Some("[petcare]"));
+    }
+
+    #[test]
+    fn test_search_domain_hacks() {
+        let searcher = test_searcher(&[
+            ("delicio.us", &["delicio"]),
+            ("delicious.com", &["delicious"]),
+        ]);
+
+        let mut request = SearchRequest::new("delicious", 10);
+        let results = searcher.search(&request).unwrap();
+        assert!(results.hacks.is_empty());
+
+        request.hacks = true;
+        let results = searcher.search(&request).unwrap();
+        assert_eq!(results.hacks.len(), 1);
+        assert_eq!(results.hacks[0].domain, "delicio.us");
+        assert!(results.hacks[0].in_zone);
+
+        let mut request = SearchRequest::new("photous", 10);
+        request.hacks = true;
+        let hack = &searcher.search(&request).unwrap().hacks[0];
+        assert_eq!(hack.domain, "photo.us");
+        assert!(!hack.in_zone);
+    }
+
+    #[test]
+    fn test_search_explain() {
+        let searcher = test_searcher(&[
+            ("coffee-shop.com", &["coffee", "shop"]),
+            ("coffeeshop.com", &["coffee", "shop"]),
+        ]);
+
+        let mut request = SearchRequest::new("coffee shop", 10);
+        let results = searcher.search(&request).unwrap();
+        assert!(results.query.is_none());
+        assert!(results.results[0].explanation.is_none());
+
+        request.explain = true;
+        let results = searcher.search(&request).unwrap();
+        assert!(results.query.unwrap().contains("BooleanQuery"));
+
+        let first = results.results[0].explanation.as_ref().unwrap();
+        assert_eq!(first.bucket, Bucket::Hyphenated);
+        assert_eq!(first.matched_tokens, vec!["coffee", "shop"]);
+        assert_eq!(first.match_count, 2);
+
+        let second = results.results[1].explanation.as_ref().unwrap();
+        assert_eq!(second.bucket, Bucket::Plain);
+    }
+
+    #[test]
+    fn test_search_dedupe_by_label() {
+        let searcher = test_searcher(&[
+            ("bestcoffee.com", &["best", "coffee"]),
+            ("bestcoffee.net", &["best", "coffee"]),
+            ("bestcoffee.org", &["best", "coffee"]),
+            ("coffeeshop.com", &["coffee", "shop"]),
+        ]);
+
+        let mut request = SearchRequest::new("best coffee", 10);
+        request.dedupe = Dedupe::Label;
+        let results = searcher.search(&request).unwrap();
+
+        assert_eq!(results.results.len(), 2);
+        let top = &results.results[0];
+        assert_eq!(top.domain.label, "bestcoffee");
+        let mut tlds = top.also_in.clone();
+        tlds.push(top.domain.tld.clone());
+        tlds.sort();
+        assert_eq!(tlds, vec!["com", "net", "org"]);
+        assert!(results.results[1].also_in.is_empty());
+    }
+
+    #[test]
+    fn test_search_profile_filters_and_order() {
+        let searcher = test_searcher(&[
+            ("coffee-shop.com", &["coffee", "shop"]),
+            ("bestcoffeeshop.com", &["best", "coffee", "shop"]),
+            ("coffee.com", &["coffee"]),
+        ]);
+
+        let profiles = crate::RankingProfiles::builtin();
+        let mut request = SearchRequest::new("coffee shop", 10);
+
+        request.profile = profiles.get(Some("brandable")).unwrap().1.clone();
+        let results = searcher.search(&request).unwrap();
+        let domains: Vec<&str> = results.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, vec!["coffee.com"]);
+
+        request.profile = profiles.get(Some("exact-match")).unwrap().1.clone();
+        let results = searcher.search(&request).unwrap();
+        let domains: Vec<&str> = results.results.iter().map(|r| r.domain.domain.as_str()).collect();
+        assert_eq!(domains, vec!["coffee-shop.com", "bestcoffeeshop.com"]);
+    }
+
+    #[test]
+    fn test_search_progressive_reports_batches() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("bestcoffee.com", &["best", "coffee"]),
+            ("coffeeshop.net", &["coffee", "shop"]),
+        ]);
+        let request = SearchRequest::new("coffee", 10);
+
+        let mut scanned = Vec::new();
+        let results = searcher
+            .search_progressive(&request, 1, |progress| {
+                scanned.push(progress.candidates_scanned);
+                true
+            })
+            .unwrap();
+
+        assert_eq!(scanned, vec![1, 2]);
+        assert_eq!(results.total_candidates, searcher.search(&request).unwrap().total_candidates);
+    }
+
+    #[test]
+    fn test_search_progressive_stops_when_asked() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("bestcoffee.com", &["best", "coffee"]),
+            ("coffeeshop.net", &["coffee", "shop"]),
+        ]);
+
+        let results = searcher
+            .search_progressive(&SearchRequest::new("coffee", 10), 1, |_| false)
+            .unwrap();
+
+        assert_eq!(results.total_candidates, 1);
+    }
+
+    #[test]
+    fn test_search_timeout_returns_partial_results() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("bestcoffee.com", &["best", "coffee"]),
+        ]);
+
+        let mut request = SearchRequest::new("coffee", 10);
+        request.timeout = Some(Duration::ZERO);
+        let results = searcher.search(&request).unwrap();
+        assert!(results.partial);
+        assert!(results.results.is_empty());
+
+        request.timeout = Some(Duration::from_secs(60));
+        let results = searcher.search(&request).unwrap();
+        assert!(!results.partial);
+        assert_eq!(results.results.len(), 2);
+        assert_eq!(results.total_hits, 2);
+        assert!(!results.total_hits_estimated);
+    }
+
+    #[test]
+    fn test_estimate_total() {
+        assert_eq!(estimate_total(40, None), 40);
+        assert_eq!(estimate_total(40, Some(0.25)), 160);
+        assert_eq!(estimate_total(40, Some(0.0)), 40);
+        assert_eq!(estimate_total(0, Some(0.5)), 0);
+    }
+
+    #[test]
+    fn test_appraise_uses_index_evidence() {
+        let searcher = test_searcher(&[
+            ("coffee.com", &["coffee"]),
+            ("coffeeshop.net", &["coffee", "shop"]),
+            ("coffeeshop.io", &["coffee", "shop"]),
+            ("shop.com", &["shop"]),
+        ]);
+        let weights = AppraisalWeights::default();
+
+        let domain = Domain::new("coffeeshop.com").normalize().unwrap();
+        let appraisal = searcher.appraise(&domain, &weights).unwrap();
+        assert!(!appraisal.registered);
+        assert_eq!(appraisal.comparables, vec!["io", "net"]);
+        let words: Vec<_> = appraisal.words.iter().map(|w| (w.word.as_str(), w.doc_freq)).collect();
+        assert_eq!(words, vec![("coffee", 3), ("shop", 3)]);
+
+        let unknown = Domain::new("xqzvkw.com").normalize().unwrap();
+        let unknown = searcher.appraise(&unknown, &weights).unwrap();
+        assert!(unknown.comparables.is_empty());
+        assert!(appraisal.score > unknown.score);
+    }
+
+    #[test]
+    fn test_search_empty_query() {
+        let searcher = test_searcher(&[]);
+        let err = searcher.search(&SearchRequest::new("   ", 10)).unwrap_err();
+
+        assert!(err.is_client_error());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::error::Result;
+use domain_core::stem::stem;
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tantivy::schema::Field;
+use tantivy::TantivyError;
+use tantivy_fst::Automaton;
+
+/// Shortest token checked; shorter ones are one edit from too many words
+const MIN_TOKEN_LEN: usize = 4;
+
+/// Shortest token that may be two edits from its correction
+const TWO_EDIT_LEN: usize = 7;
+
+/// Share of the indexed domains a token may appear in and still be checked;
+/// tokens in a single domain are checked in any index
+const RARE_TOKEN_SHARE: f64 = 1e-5;
+
+/// How many times more domains a correction must appear in than the token
+const MIN_FREQ_RATIO: u64 = 10;
+
+/// A likely intended form of a rare query token
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Suggestion {
+    /// The query token as searched
+    pub token: String,
+    /// Indexed token one or two edits away
+    pub suggestion: String,
+    /// Number of indexed domains containing the suggestion
+    pub doc_freq: u64,
+}
+
+/// Whether a token is rare enough in the index to look for a correction
+pub(crate) fn is_rare(token: &str, doc_freq: u64, num_docs: u64) -> bool {
+    let max_doc_freq = (num_docs as f64 * RARE_TOKEN_SHARE).max(1.0);
+    token.chars().count() >= MIN_TOKEN_LEN && doc_freq as f64 <= max_doc_freq
+}
+
+/// The indexed token closest to `token` (one edit, or two for long tokens),
+/// the most frequent among equally close ones, when it appears in at least
+/// [`MIN_FREQ_RATIO`] times as many domains
+///
+/// Inflections of the token (`shoe` for `shoes`) aren't suggested, since
+/// stemming matches them already.
+pub(crate) fn suggest(
+    searcher: &tantivy::Searcher,
+    field: Field,
+    token: &str,
+    doc_freq: u64,
+) -> Result<Option<Suggestion>> {
+    let max_distance = if token.chars().count() >= TWO_EDIT_LEN { 2 } else { 1 };
+    let dfa = Dfa(builder(max_distance).build_dfa(token));
+
+    let mut found: HashMap<String, u64> = HashMap::new();
+    for segment in searcher.segment_readers() {
+        let inverted_index = segment.inverted_index(field)?;
+        let mut terms = inverted_index
+            .terms()
+            .search(&dfa)
+            .into_stream()
+            .map_err(TantivyError::from)?;
+        while terms.advance() {
+            let Ok(term) = std::str::from_utf8(terms.key()) else {
+                continue;
+            };
+            *found.entry(term.to_string()).or_default() += terms.value().doc_freq as u64;
+        }
+    }
+
+    let min_doc_freq = doc_freq.max(1) * MIN_FREQ_RATIO;
+    let token_stem = stem(token);
+    let best = found
+        .into_iter()
+        .filter(|(term, freq)| *freq >= min_doc_freq && stem(term) != token_stem)
+        .filter_map(|(term, freq)| match dfa.0.eval(&term) {
+            Distance::Exact(distance) => Some((distance, freq, term)),
+            Distance::AtLeast(_) => None,
+        })
+        // Closest first, then most frequent, then alphabetical for stable output
+        .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+    Ok(best.map(|(_, doc_freq, suggestion)| Suggestion {
+        token: token.to_string(),
+        suggestion,
+        doc_freq,
+    }))
+}
+
+/// Levenshtein automaton builders, with transpositions counting as one edit;
+/// building the tables is slow, so they are built once
+fn builder(max_distance: u8) -> &'static LevenshteinAutomatonBuilder {
+    static BUILDERS: [OnceLock<LevenshteinAutomatonBuilder>; 2] =
+        [OnceLock::new(), OnceLock::new()];
+    BUILDERS[max_distance as usize - 1]
+        .get_or_init(|| LevenshteinAutomatonBuilder::new(max_distance, true))
+}
+
+/// A Levenshtein DFA walking the term dictionary
+struct Dfa(DFA);
+
+impl Automaton for Dfa {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != levenshtein_automata::SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::{Domain, DomainSchema};
+    use tantivy::Index;
+
+    #[test]
+    fn test_suggest_frequent_neighbour() {
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+        let add = |label: String, tokens: &[&str]| {
+            let domain = Domain::new(format!("{label}.com"))
+                .normalize()
+                .unwrap()
+                .with_tokens(tokens.iter().map(|t| t.to_string()).collect());
+            writer.add_document(schema.to_document(&domain)).unwrap();
+        };
+        for i in 0..12 {
+            add(format!("coffee{i}"), &["coffee"]);
+            add(format!("shoe{i}"), &["shoe"]);
+        }
+        add("cofee".to_string(), &["cofee"]);
+        add("shoes".to_string(), &["shoes"]);
+        writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        let suggestion = suggest(&searcher, schema.tokens, "cofee", 1).unwrap().unwrap();
+        assert_eq!(suggestion.suggestion, "coffee");
+        assert_eq!(suggestion.doc_freq, 12);
+        // Transposed letters are one edit
+        let suggestion = suggest(&searcher, schema.tokens, "cofefe", 0).unwrap().unwrap();
+        assert_eq!(suggestion.suggestion, "coffee");
+
+        // Not frequent enough compared to the token, or an inflection
+        assert!(suggest(&searcher, schema.tokens, "cofee", 2).unwrap().is_none());
+        assert!(suggest(&searcher, schema.tokens, "shoes", 1).unwrap().is_none());
+        assert!(suggest(&searcher, schema.tokens, "tea", 0).unwrap().is_none());
+
+        assert!(is_rare("cofee", 1, 1000));
+        assert!(!is_rare("cof", 0, 1000));
+        assert!(!is_rare("coffee", 12, 1000));
+        assert!(is_rare("coffee", 12, 10_000_000));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::{Error, Result};
+use domain_core::{Config, DomainSchema};
+use domain_search::{Dedupe, SearchRequest, Searcher};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tantivy::Index;
+use tracing::{info, warn};
+
+/// Candidates rescored between two progress snapshots in `stream` mode, as
+/// `/search/stream` does
+const STREAM_BATCH_SIZE: usize = 250;
+
+/// How a recorded query is replayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// As recorded
+    Search,
+    /// Collapsing labels across TLDs
+    Dedupe,
+    /// Progressive search with snapshots, as `/search/stream`
+    Stream,
+    /// With domain hack suggestions
+    Hacks,
+    /// With per-category counts
+    Facets,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 5] =
+        [Mode::Search, Mode::Dedupe, Mode::Stream, Mode::Hacks, Mode::Facets];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Search => "search",
+            Mode::Dedupe => "dedupe",
+            Mode::Stream => "stream",
+            Mode::Hacks => "hacks",
+            Mode::Facets => "facets",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.as_str() == name)
+    }
+}
+
+/// Parse a comma-separated list of modes
+pub fn parse_modes(modes: &str) -> Result<Vec<Mode>> {
+    modes
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            Mode::parse(name).ok_or_else(|| {
+                let known: Vec<&str> = Mode::ALL.iter().map(Mode::as_str).collect();
+                Error::Config(format!("Unknown mode '{}' (one of {})", name, known.join(", ")))
+            })
+        })
+        .collect()
+}
+
+/// Latency summary of one mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub queries: usize,
+    pub errors: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub mean_ms: f64,
+    /// Queries per second, one at a time
+    pub throughput: f64,
+}
+
+impl Report {
+    fn new(mut latencies: Vec<Duration>, errors: usize, elapsed: Duration) -> Self {
+        latencies.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let total: Duration = latencies.iter().sum();
+        let queries = latencies.len();
+        Self {
+            queries,
+            errors,
+            p50_ms: percentile(&latencies, 50.0).map_or(0.0, ms),
+            p95_ms: percentile(&latencies, 95.0).map_or(0.0, ms),
+            p99_ms: percentile(&latencies, 99.0).map_or(0.0, ms),
+            mean_ms: if queries > 0 { ms(total) / queries as f64 } else { 0.0 },
+            throughput: if elapsed.is_zero() {
+                0.0
+            } else {
+                queries as f64 / elapsed.as_secs_f64()
+            },
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Parse a query log: one query per line, either the keywords themselves or
+/// a logged request (`/search?q=coffee+shop&tld=com&dedupe=label`) whose
+/// `q`, `tld`, `lang`, `category`, `limit`, `min_match` and `dedupe` are
+/// kept. Blank lines and lines starting with `#` are skipped.
+pub fn parse_queries(log: &str, limit: usize) -> Vec<SearchRequest> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| parse_query(line, limit))
+        .collect()
+}
+
+fn parse_query(line: &str, limit: usize) -> Option<SearchRequest> {
+    let params = match line.split_once('?') {
+        Some((_, params)) => params,
+        None if line.starts_with("q=") => line,
+        None => return Some(SearchRequest::new(line, limit)),
+    };
+
+    let mut request = SearchRequest::new("", limit);
+    for (key, value) in form_urlencoded::parse(params.as_bytes()) {
+        let value = value.into_owned();
+        match key.as_ref() {
+            "q" => request.query = value,
+            "tld" => request.tld = Some(value),
+            "lang" => request.lang = Some(value),
+            "category" => request.category = Some(value),
+            "limit" => request.limit = value.parse().unwrap_or(limit),
+            "min_match" => request.min_match = value.parse().ok(),
+            "dedupe" if value == "label" => request.dedupe = Dedupe::Label,
+            _ => {}
+        }
+    }
+    (!request.query.trim().is_empty()).then_some(request)
+}
+
+/// Replay a query log against an index once per mode and report latencies
+pub fn run(
+    config: &Config,
+    index_path: &Path,
+    queries_path: &Path,
+    modes: &[Mode],
+    limit: usize,
+    rounds: usize,
+) -> Result<()> {
+    let log = std::fs::read_to_string(queries_path)?;
+    let mut queries = parse_queries(&log, limit);
+    if queries.is_empty() {
+        return Err(Error::Parse(format!("No queries in {}", queries_path.display())));
+    }
+    for query in &mut queries {
+        query.stop_tokens = config.stop_tokens.clone();
+    }
+
+    let index = Index::open_in_dir(index_path)?;
+    let searcher = Searcher::new(&index, DomainSchema::new())?;
+    info!(
+        index = ?index_path,
+        documents = index.reader()?.searcher().num_docs(),
+        queries = queries.len(),
+        rounds,
+        "Replaying query log"
+    );
+
+    // One untimed pass loads the index files into the page cache
+    for query in &queries {
+        let _ = searcher.search(query);
+    }
+
+    for &mode in modes {
+        let report = replay(&searcher, &queries, mode, rounds.max(1));
+        info!(
+            mode = mode.as_str(),
+            queries = report.queries,
+            errors = report.errors,
+            p50_ms = format!("{:.2}", report.p50_ms),
+            p95_ms = format!("{:.2}", report.p95_ms),
+            p99_ms = format!("{:.2}", report.p99_ms),
+            mean_ms = format!("{:.2}", report.mean_ms),
+            qps = format!("{:.1}", report.throughput),
+            "Benchmark"
+        );
+    }
+    Ok(())
+}
+
+/// Run every query `rounds` times in one mode
+fn replay(searcher: &Searcher, queries: &[SearchRequest], mode: Mode, rounds: usize) -> Report {
+    let queries: Vec<SearchRequest> = queries
+        .iter()
+        .cloned()
+        .map(|mut request| {
+            match mode {
+                Mode::Search | Mode::Stream => {}
+                Mode::Dedupe => request.dedupe = Dedupe::Label,
+                Mode::Hacks => request.hacks = true,
+                Mode::Facets => request.category_counts = true,
+            }
+            request
+        })
+        .collect();
+
+    let mut latencies = Vec::with_capacity(queries.len() * rounds);
+    let mut errors = 0;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for request in &queries {
+            let query_start = Instant::now();
+            let result = match mode {
+                Mode::Stream => searcher.search_progressive(request, STREAM_BATCH_SIZE, |_| true),
+                _ => searcher.search(request),
+            };
+            match result {
+                Ok(_) => latencies.push(query_start.elapsed()),
+                Err(e) => {
+                    warn!(query = %request.query, error = %e, "Query failed");
+                    errors += 1;
+                }
+            }
+        }
+    }
+    Report::new(latencies, errors, start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_queries() {
+        let log = "# recorded 2024-06-01\n\
+            coffee shop\n\
+            \n\
+            /search?q=cloud+hosting&tld=com,net&dedupe=label&limit=10\n\
+            q=reisen&lang=de&min_match=1\n\
+            /search?tld=com\n";
+        let queries = parse_queries(log, 50);
+
+        assert_eq!(queries.len(), 3);
+        assert_eq!(queries[0].query, "coffee shop");
+        assert_eq!(queries[0].limit, 50);
+        assert_eq!(queries[1].query, "cloud hosting");
+        assert_eq!(queries[1].tld.as_deref(), Some("com,net"));
+        assert_eq!(queries[1].dedupe, Dedupe::Label);
+        assert_eq!(queries[1].limit, 10);
+        assert_eq!(queries[2].lang.as_deref(), Some("de"));
+        assert_eq!(queries[2].min_match, Some(1));
+    }
+
+    #[test]
+    fn test_report_percentiles() {
+        let latencies: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let report = Report::new(latencies, 2, Duration::from_secs(2));
+
+        assert_eq!(report.queries, 100);
+        assert_eq!(report.errors, 2);
+        assert_eq!(report.p50_ms, 50.0);
+        assert_eq!(report.p95_ms, 95.0);
+        assert_eq!(report.p99_ms, 99.0);
+        assert_eq!(report.mean_ms, 50.5);
+        assert_eq!(report.throughput, 50.0);
+        assert_eq!(Report::new(vec![], 0, Duration::ZERO).p99_ms, 0.0);
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(parse_modes("search, stream").unwrap(), vec![Mode::Search, Mode::Stream]);
+        assert!(parse_modes("turbo").is_err());
+    }
+}
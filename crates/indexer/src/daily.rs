@@ -1,20 +1,40 @@
+use crate::dated::{self, DatedIndexes};
+use crate::full::{load_taxonomy, segmenter, tld_filter};
+use crate::history::HistoryIndex;
 use crate::progress::IndexProgress;
-use anyhow::Result;
-use domain_core::{domain::should_filter_domain, Config, Domain, DomainSchema};
+use crate::throttle;
+use crate::watch::WatchRun;
+use crate::error::Result;
+use domain_core::tld_stats::{self, TldHistory};
+use domain_core::{
+    domain::{should_filter_domain, TldFilter},
+    writer_lock, Config, Domain, DomainSchema, LabelValidation, Taxonomy,
+};
 use futures::StreamExt;
 use std::path::Path;
-use tantivy::{Index, Term};
+use tantivy::Term;
 use tracing::{debug, info, warn};
-use word_client::WordClient;
+use watchlist::DomainEvent;
+use word_client::Segmenter;
 use zonefile_client::{parser::batch_stream, DomainStream, ZonefileDownloader, ZonefileType};
 
+/// What a daily sync changed in the main index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncCounts {
+    pub added: u64,
+    pub deleted: u64,
+    /// Documents in the index after the sync
+    pub documents: u64,
+}
+
 /// Run daily sync with download from API
-pub async fn run_with_download(config: &Config, index_path: &Path) -> Result<()> {
-    let downloader = ZonefileDownloader::new(
-        &config.zonefile_api_url,
-        &config.zonefile_token,
-        std::env::temp_dir().join("zonefile-indexer"),
-    )?;
+pub async fn run_with_download(
+    config: &Config,
+    index_path: &Path,
+    work_dir: &Path,
+) -> Result<SyncCounts> {
+    let downloader =
+        ZonefileDownloader::new(&config.zonefile_api_url, &config.zonefile_token, work_dir)?;
 
     // Download both files
     info!("Downloading daily update file...");
@@ -26,32 +46,60 @@ pub async fn run_with_download(config: &Config, index_path: &Path) -> Result<()>
     run(config, Some(adds_path), Some(removes_path), index_path).await
 }
 
+/// Where a sync records domains besides the main index, the taxonomy
+/// tagging every domain it writes and the TLDs and labels it covers
+struct SyncOutputs {
+    taxonomy: Taxonomy,
+    tlds: TldFilter,
+    validation: LabelValidation,
+    /// Segment hostname labels separately (`INDEX_SUBDOMAIN_LABELS`)
+    subdomains: bool,
+    /// Stored with the tokens the sync segments (`SEGMENTATION_VERSION`)
+    segmentation_version: u64,
+    dated: DatedIndexes,
+    history: Option<HistoryIndex>,
+    watch: Option<WatchRun>,
+}
+
 /// Run daily sync from local files
 pub async fn run(
     config: &Config,
     adds_path: Option<impl AsRef<Path>>,
     removes_path: Option<impl AsRef<Path>>,
     index_path: &Path,
-) -> Result<()> {
+) -> Result<SyncCounts> {
     info!("Starting daily sync");
 
     // Open existing index
     let schema = DomainSchema::new();
-    let index = Index::open_in_dir(index_path)?;
+    let index = throttle::open_index(config, index_path)?;
     let reader = index.reader()?;
     let initial_count = reader.searcher().num_docs();
 
     info!(documents = initial_count, "Current index size");
 
-    let mut writer = index.writer(500 * 1024 * 1024)?; // 500MB heap for daily updates
-
-    let word_client = WordClient::new(
-        &config.word_splitter_url,
-        &config.word_splitter_user,
-        &config.word_splitter_pass,
-        Some(config.word_batch_size),
-        Some(4), // 4 parallel API requests
-    )?;
+    // 500MB heap for daily updates
+    let mut writer = writer_lock::open_writer(&index, index_path, 500 * 1024 * 1024)?;
+    throttle::set_merge_policy(config, &writer);
+
+    // Dropped and newly registered domains, their history and watch rules,
+    // when configured
+    let mut outputs = SyncOutputs {
+        taxonomy: load_taxonomy(config)?,
+        tlds: tld_filter(config),
+        validation: config.label_validation,
+        subdomains: config.index_subdomain_labels,
+        segmentation_version: config.segmentation_version,
+        dated: DatedIndexes::open(config, 100 * 1024 * 1024)?,
+        history: config
+            .history_index_path
+            .as_deref()
+            .map(|path| HistoryIndex::open_or_create(path, 100 * 1024 * 1024, dated::now()))
+            .transpose()?,
+        watch: WatchRun::load(config).await,
+    };
+
+    let splitter = segmenter(config)?;
 
     let mut total_deleted: u64 = 0;
     let mut total_added: u64 = 0;
@@ -61,7 +109,15 @@ pub async fn run(
         let removes_path = removes_path.as_ref();
         if removes_path.exists() {
             info!(path = ?removes_path, "Processing removals...");
-            total_deleted = process_removals(&schema, &mut writer, removes_path).await?;
+            total_deleted = process_removals(
+                &schema,
+                &reader.searcher(),
+                &splitter,
+                &mut writer,
+                &mut outputs,
+                removes_path,
+            )
+            .await?;
             info!(deleted = total_deleted, "Removals complete");
         }
     }
@@ -71,7 +127,16 @@ pub async fn run(
         let adds_path = adds_path.as_ref();
         if adds_path.exists() {
             info!(path = ?adds_path, "Processing additions...");
-            total_added = process_additions(config, &schema, &word_client, &mut writer, adds_path).await?;
+            total_added = process_additions(
+                config,
+                &schema,
+                &reader.searcher(),
+                &splitter,
+                &mut writer,
+                &mut outputs,
+                adds_path,
+            )
+            .await?;
             info!(added = total_added, "Additions complete");
         }
     }
@@ -79,11 +144,27 @@ pub async fn run(
     // Commit changes
     info!("Committing changes...");
     writer.commit()?;
+    let (dropped, new_domains) = outputs.dated.commit()?;
+    if config.dropped_index_path.is_some() || config.new_domains_index_path.is_some() {
+        info!(dropped, new_domains, "Dated domains recorded");
+    }
+    if let Some(history) = outputs.history {
+        info!(events = history.commit()?, "Domain history recorded");
+    }
+    if let Some(watch) = outputs.watch {
+        watch.finish().await;
+    }
 
     // Reload reader to get updated count
     let reader = index.reader()?;
     let final_count = reader.searcher().num_docs();
 
+    if let Some(path) = &config.tld_stats_path {
+        if let Err(e) = record_tld_stats(path, &reader.searcher(), &schema) {
+            warn!(path = ?path, error = %e, "Failed to record TLD statistics");
+        }
+    }
+
     info!(
         initial = initial_count,
         deleted = total_deleted,
@@ -93,12 +174,40 @@ pub async fn run(
         "Daily sync complete"
     );
 
+    Ok(SyncCounts {
+        added: total_added,
+        deleted: total_deleted,
+        documents: final_count,
+    })
+}
+
+/// Append today's per-TLD document counts to the `TLD_STATS_PATH` file
+fn record_tld_stats(
+    path: &Path,
+    searcher: &tantivy::Searcher,
+    schema: &DomainSchema,
+) -> Result<()> {
+    let counts = tld_stats::count_tlds(searcher, schema)?;
+    let tlds = counts.len();
+
+    let mut history = TldHistory::load(path)?;
+    history.record(&dated::today().into_utc().date().to_string(), counts);
+    history.save(path)?;
+
+    info!(tlds, days = history.days.len(), "TLD statistics recorded");
     Ok(())
 }
 
+/// Delete removed domains, recording them in the dropped-domains index
+///
+/// Dropped domains keep the tokens stored in the main index; the few that
+/// aren't there (e.g. dropped the day they were added) are segmented again.
 async fn process_removals(
     schema: &DomainSchema,
+    searcher: &tantivy::Searcher,
+    splitter: &Segmenter,
     writer: &mut tantivy::IndexWriter,
+    outputs: &mut SyncOutputs,
     removes_path: &Path,
 ) -> Result<u64> {
     let domain_stream = DomainStream::from_file(removes_path);
@@ -108,19 +217,50 @@ async fn process_removals(
 
     let mut progress = IndexProgress::spinner();
     let mut deleted: u64 = 0;
+    let mut rejected: u64 = 0;
 
     while let Some(batch_result) = batched.next().await {
         let batch: Vec<String> = batch_result?;
+        let mut unsegmented: Vec<domain_core::NormalizedDomain> = Vec::new();
 
         for raw_domain in batch {
             let domain = Domain::new(&raw_domain);
 
-            match domain.normalize() {
-                Ok(normalized) => {
+            match domain.normalize_with(outputs.validation) {
+                // Never indexed, so neither dropped
+                Ok(normalized) if !outputs.tlds.allows(&normalized.tld) => {}
+                Ok(mut normalized) => {
                     // Delete by domain_exact term
                     let term = Term::from_field_text(schema.domain_exact, &normalized.domain_exact);
                     writer.delete_term(term);
                     deleted += 1;
+
+                    if let Some(history) = outputs.history.as_mut() {
+                        history.record_dropped(&normalized)?;
+                    }
+                    if let Some(watch) = outputs.watch.as_mut() {
+                        watch.check(&normalized, DomainEvent::Dropped);
+                    }
+                    let Some(dropped) = outputs.dated.dropped.as_mut() else {
+                        continue;
+                    };
+                    match dated::stored_tokens(searcher, schema, &normalized.domain_exact)? {
+                        Some((tokens, version)) => {
+                            normalized.categories =
+                                outputs.taxonomy.tag(&normalized.label, &tokens);
+                            normalized.tokens = tokens;
+                            normalized.seg_version = version;
+                            dropped.record(&normalized)?;
+                        }
+                        None if !should_filter_domain(&normalized.label) => {
+                            unsegmented.push(normalized);
+                        }
+                        None => {}
+                    }
+                }
+                Err(e @ domain_core::Error::InvalidCharacters(_)) => {
+                    debug!(domain = raw_domain, error = %e, "Rejected domain for deletion");
+                    rejected += 1;
                 }
                 Err(e) => {
                     debug!(domain = raw_domain, error = %e, "Failed to normalize for deletion");
@@ -128,18 +268,48 @@ async fn process_removals(
             }
         }
 
+        // Only filled when recording dropped domains
+        if !unsegmented.is_empty() {
+            let labels = unsegmented.iter().map(|d| d.segment_source(outputs.subdomains)).collect();
+            match splitter.segment_batch(labels).await {
+                Ok(segments) => {
+                    for (normalized, (_, tokens)) in unsegmented.iter_mut().zip(segments) {
+                        normalized.tokens = tokens;
+                        normalized.seg_version = outputs.segmentation_version;
+                    }
+                }
+                Err(e) => {
+                    warn!(error = %e, "Word segmentation failed, using empty tokens");
+                }
+            }
+            if let Some(dropped) = outputs.dated.dropped.as_mut() {
+                for normalized in &mut unsegmented {
+                    normalized.categories =
+                        outputs.taxonomy.tag(&normalized.label, &normalized.tokens);
+                    dropped.record(normalized)?;
+                }
+            }
+        }
+
         progress.inc(deleted - progress.count());
     }
 
     progress.finish();
+    if rejected > 0 {
+        info!(rejected, "Domains rejected during removal");
+    }
     Ok(deleted)
 }
 
+/// Add new domains, recording the ones not already indexed as newly
+/// registered and forgetting them as dropped
 async fn process_additions(
     config: &Config,
     schema: &DomainSchema,
-    word_client: &WordClient,
+    searcher: &tantivy::Searcher,
+    splitter: &Segmenter,
     writer: &mut tantivy::IndexWriter,
+    outputs: &mut SyncOutputs,
     adds_path: &Path,
 ) -> Result<u64> {
     let domain_stream = DomainStream::from_file(adds_path);
@@ -150,6 +320,7 @@ async fn process_additions(
     let mut progress = IndexProgress::spinner();
     let mut added: u64 = 0;
     let mut filtered: u64 = 0;
+    let mut rejected: u64 = 0;
 
     while let Some(batch_result) = batched.next().await {
         let batch: Vec<String> = batch_result?;
@@ -162,16 +333,29 @@ async fn process_additions(
         for raw_domain in &batch {
             let domain = Domain::new(raw_domain);
 
-            match domain.normalize() {
+            match domain.normalize_with(outputs.validation) {
+                Ok(normalized) if !outputs.tlds.allows(&normalized.tld) => {
+                    filtered += 1;
+                }
                 Ok(normalized) => {
+                    if let Some(history) = outputs.history.as_mut() {
+                        history.record_added(&normalized, searcher, schema)?;
+                    }
+                    if let Some(watch) = outputs.watch.as_mut() {
+                        watch.check(&normalized, DomainEvent::Added);
+                    }
                     if should_filter_domain(&normalized.label) {
                         filtered += 1;
                         continue;
                     }
 
-                    labels_to_segment.push(normalized.label.clone());
+                    labels_to_segment.push(normalized.segment_source(outputs.subdomains));
                     valid_domains.push(normalized);
                 }
+                Err(e @ domain_core::Error::InvalidCharacters(_)) => {
+                    debug!(domain = raw_domain, error = %e, "Rejected domain");
+                    rejected += 1;
+                }
                 Err(e) => {
                     debug!(domain = raw_domain, error = %e, "Failed to normalize");
                 }
@@ -180,10 +364,11 @@ async fn process_additions(
 
         // Segment labels
         if !labels_to_segment.is_empty() {
-            match word_client.segment_batch(labels_to_segment).await {
+            match splitter.segment_batch(labels_to_segment).await {
                 Ok(segments) => {
                     for (normalized, (_, tokens)) in valid_domains.iter_mut().zip(segments.iter()) {
                         normalized.tokens = tokens.clone();
+                        normalized.seg_version = outputs.segmentation_version;
                     }
                 }
                 Err(e) => {
@@ -193,10 +378,19 @@ async fn process_additions(
         }
 
         // Add to index
-        for normalized in &valid_domains {
+        for normalized in &mut valid_domains {
+            normalized.categories = outputs.taxonomy.tag(&normalized.label, &normalized.tokens);
             // Delete existing document first (in case it's a re-add)
             let term = Term::from_field_text(schema.domain_exact, &normalized.domain_exact);
             writer.delete_term(term);
+            if let Some(dropped) = outputs.dated.dropped.as_mut() {
+                dropped.forget(&normalized.domain_exact);
+            }
+            if let Some(new_domains) = outputs.dated.new_domains.as_mut() {
+                if !dated::is_indexed(searcher, schema, &normalized.domain_exact)? {
+                    new_domains.record(normalized)?;
+                }
+            }
 
             // Add new document
             let doc = schema.to_document(normalized);
@@ -212,6 +406,9 @@ async fn process_additions(
     if filtered > 0 {
         info!(filtered = filtered, "Domains filtered during addition");
     }
+    if rejected > 0 {
+        info!(rejected, "Domains rejected during addition");
+    }
 
     Ok(added)
 }
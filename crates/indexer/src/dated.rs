@@ -0,0 +1,166 @@
+use crate::error::Result;
+use domain_core::{writer_lock, Config, DatedSchema, DomainSchema, NormalizedDomain};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::TermQuery;
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::{DateTime, Index, IndexWriter, TantivyDocument, Term};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Writer for an index of dated domain events: the dropped-domains index
+/// (`DROPPED_INDEX_PATH`) or the new-domains index (`NEW_DOMAINS_INDEX_PATH`)
+///
+/// Each domain is stored with the day of the sync that recorded it; a domain
+/// recorded again replaces its older entry.
+pub struct DatedIndex {
+    schema: DatedSchema,
+    writer: IndexWriter,
+    date: DateTime,
+    recorded: u64,
+}
+
+impl DatedIndex {
+    /// Open the index, creating it on first use
+    pub fn open_or_create(path: &Path, schema: DatedSchema, heap_size: usize) -> Result<Self> {
+        std::fs::create_dir_all(path)?;
+        let directory = MmapDirectory::open(path)?;
+        let index = Index::open_or_create(directory, schema.domain.schema.clone())?;
+        let writer = writer_lock::open_writer(&index, path, heap_size)?;
+
+        Ok(Self {
+            schema,
+            writer,
+            date: today(),
+            recorded: 0,
+        })
+    }
+
+    /// Record a domain under today's date
+    pub fn record(&mut self, domain: &NormalizedDomain) -> Result<()> {
+        self.forget(&domain.domain_exact);
+        let doc = self.schema.to_document(domain, self.date);
+        self.writer.add_document(doc)?;
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Remove a domain, e.g. a dropped one that has been registered again
+    pub fn forget(&mut self, domain_exact: &str) {
+        let term = Term::from_field_text(self.schema.domain.domain_exact, domain_exact);
+        self.writer.delete_term(term);
+    }
+
+    /// Commit, returning how many domains were recorded
+    pub fn commit(mut self) -> Result<u64> {
+        self.writer.commit()?;
+        Ok(self.recorded)
+    }
+}
+
+/// The dated indices a daily sync records into, each optional
+#[derive(Default)]
+pub struct DatedIndexes {
+    /// Removed domains, from `DROPPED_INDEX_PATH`
+    pub dropped: Option<DatedIndex>,
+    /// Added domains not already in the main index, from `NEW_DOMAINS_INDEX_PATH`
+    pub new_domains: Option<DatedIndex>,
+}
+
+impl DatedIndexes {
+    pub fn open(config: &Config, heap_size: usize) -> Result<Self> {
+        let open = |path: &Option<PathBuf>, schema: DatedSchema| {
+            path.as_deref()
+                .map(|path| DatedIndex::open_or_create(path, schema, heap_size))
+                .transpose()
+        };
+        Ok(Self {
+            dropped: open(&config.dropped_index_path, DatedSchema::dropped())?,
+            new_domains: open(&config.new_domains_index_path, DatedSchema::registered())?,
+        })
+    }
+
+    /// Commit both indices, returning how many domains each recorded
+    pub fn commit(self) -> Result<(u64, u64)> {
+        let commit = |index: Option<DatedIndex>| index.map_or(Ok(0), DatedIndex::commit);
+        Ok((commit(self.dropped)?, commit(self.new_domains)?))
+    }
+}
+
+/// Whether a domain is in the main index
+pub fn is_indexed(
+    searcher: &tantivy::Searcher,
+    schema: &DomainSchema,
+    domain_exact: &str,
+) -> Result<bool> {
+    let term = Term::from_field_text(schema.domain_exact, domain_exact);
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    Ok(searcher.search(&query, &Count)? > 0)
+}
+
+/// Stored tokens of a domain in the main index with the segmentation
+/// version that produced them, if it is there
+pub fn stored_tokens(
+    searcher: &tantivy::Searcher,
+    schema: &DomainSchema,
+    domain_exact: &str,
+) -> Result<Option<(Vec<String>, u64)>> {
+    let term = Term::from_field_text(schema.domain_exact, domain_exact);
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.pop() else {
+        return Ok(None);
+    };
+
+    let doc: TantivyDocument = searcher.doc(address)?;
+    let tokens = doc
+        .get_first(schema.tokens)
+        .and_then(|value| value.as_str())
+        .map(|tokens| tokens.split_whitespace().map(String::from).collect())
+        .unwrap_or_default();
+    // Without a version the tokens count as stale, as `retokenize` treats
+    // them; indexes built before versions were stored need a rebuild
+    let version = doc
+        .get_first(schema.seg_version)
+        .and_then(|value| value.as_u64())
+        .unwrap_or(0);
+    Ok(Some((tokens, version)))
+}
+
+/// The current time, to the second
+pub fn now() -> DateTime {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    DateTime::from_timestamp_secs(now)
+}
+
+/// Midnight UTC of the current day
+pub fn today() -> DateTime {
+    let now = now().into_timestamp_secs();
+    DateTime::from_timestamp_secs(now - now % SECONDS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::Domain;
+
+    #[test]
+    fn test_recorded_domains_replace_older_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let normalized = Domain::new("coffee.com").normalize().unwrap();
+
+        for _ in 0..2 {
+            let mut dropped =
+                DatedIndex::open_or_create(dir.path(), DatedSchema::dropped(), 15_000_000).unwrap();
+            dropped.record(&normalized).unwrap();
+            assert_eq!(dropped.commit().unwrap(), 1);
+        }
+
+        let index = Index::open_in_dir(dir.path()).unwrap();
+        assert_eq!(index.reader().unwrap().searcher().num_docs(), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::Result;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tracing::info;
+use zonefile_client::SortedDomains;
+
+/// Domains in one zonefile but not the other
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeltaStats {
+    pub old: u64,
+    pub new: u64,
+    pub adds: u64,
+    pub removes: u64,
+}
+
+/// Write the domains added and removed between two full zonefiles, as the
+/// daily endpoints would
+///
+/// Both files are sorted externally in chunks of `memory_bytes` spilled to
+/// `temp_dir` (see [`SortedDomains`]), so files far larger than memory can
+/// be compared. The outputs are sorted.
+pub fn run(
+    old: &Path,
+    new: &Path,
+    adds: &Path,
+    removes: &Path,
+    memory_bytes: usize,
+    temp_dir: &Path,
+) -> Result<DeltaStats> {
+    info!(path = ?old, "Sorting old zonefile");
+    let old = SortedDomains::open(old, memory_bytes, temp_dir)?;
+    info!(path = ?new, "Sorting new zonefile");
+    let new = SortedDomains::open(new, memory_bytes, temp_dir)?;
+
+    info!("Comparing zonefiles");
+    let mut adds = BufWriter::new(File::create(adds)?);
+    let mut removes = BufWriter::new(File::create(removes)?);
+    let stats = compare(old, new, &mut adds, &mut removes)?;
+    adds.flush()?;
+    removes.flush()?;
+
+    info!(
+        old = stats.old,
+        new = stats.new,
+        adds = stats.adds,
+        removes = stats.removes,
+        "Delta written"
+    );
+    Ok(stats)
+}
+
+/// Walk two sorted, deduplicated streams and write what only one has
+fn compare(
+    old: impl Iterator<Item = zonefile_client::Result<String>>,
+    new: impl Iterator<Item = zonefile_client::Result<String>>,
+    adds: &mut impl Write,
+    removes: &mut impl Write,
+) -> Result<DeltaStats> {
+    let mut stats = DeltaStats::default();
+    let mut old = old.peekable();
+    let mut new = new.peekable();
+
+    loop {
+        let order = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(Err(_)), _) => return Err(old.next().unwrap().unwrap_err().into()),
+            (_, Some(Err(_))) => return Err(new.next().unwrap().unwrap_err().into()),
+            (Some(Ok(o)), Some(Ok(n))) => o.cmp(n),
+        };
+        match order {
+            Ordering::Less => {
+                writeln!(removes, "{}", old.next().unwrap()?)?;
+                stats.old += 1;
+                stats.removes += 1;
+            }
+            Ordering::Greater => {
+                writeln!(adds, "{}", new.next().unwrap()?)?;
+                stats.new += 1;
+                stats.adds += 1;
+            }
+            Ordering::Equal => {
+                old.next();
+                new.next();
+                stats.old += 1;
+                stats.new += 1;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_delta() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.txt");
+        let new = dir.path().join("new.txt");
+        std::fs::write(&old, "coffee.com\npet.io\nshop.net\nPET.io\n\ngone.org\n").unwrap();
+        std::fs::write(&new, "shop.net\n  coffee.com \nnew.ai\ncoffee.com\npet.io\n").unwrap();
+        let (adds, removes) = (dir.path().join("adds.txt"), dir.path().join("removes.txt"));
+
+        // A few bytes per run: every domain spills its own run
+        let stats = run(&old, &new, &adds, &removes, 1, dir.path()).unwrap();
+
+        assert_eq!(read(&adds), vec!["new.ai"]);
+        assert_eq!(read(&removes), vec!["gone.org"]);
+        assert_eq!(
+            stats,
+            DeltaStats {
+                old: 4,
+                new: 4,
+                adds: 1,
+                removes: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.txt");
+        let new = dir.path().join("new.txt");
+        std::fs::write(&old, "").unwrap();
+        std::fs::write(&new, "a.com\n").unwrap();
+        let (adds, removes) = (dir.path().join("adds.txt"), dir.path().join("removes.txt"));
+
+        let stats = run(&old, &new, &adds, &removes, 1 << 20, dir.path()).unwrap();
+        assert_eq!(read(&adds), vec!["a.com"]);
+        assert!(read(&removes).is_empty());
+        assert_eq!(stats.old, 0);
+    }
+}
@@ -0,0 +1,162 @@
+use tantivy::directory::error::OpenDirectoryError;
+use tantivy::TantivyError;
+use thiserror::Error;
+
+/// Why an indexer run failed
+///
+/// Each class exits with its own code (see [`Error::exit_code`]), so
+/// scripts running the indexer can tell a download worth retrying from an
+/// index that needs a human.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// Invalid configuration or command-line options
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    /// Fetching files from the zonefile provider
+    #[error("Download failed: {0}")]
+    Download(zonefile_client::Error),
+
+    /// Malformed input: a zonefile, domain list or query log
+    #[error("Invalid input: {0}")]
+    Parse(String),
+
+    /// The word splitter or word list
+    #[error("Segmentation failed: {0}")]
+    Segmentation(#[from] word_client::Error),
+
+    /// An index built with a different schema than this indexer writes
+    #[error("Schema mismatch: {0}")]
+    Schema(String),
+
+    /// Reading or writing the index
+    #[error("Index error: {0}")]
+    Tantivy(TantivyError),
+
+    /// Another writer holds the index
+    #[error("{0}")]
+    Locked(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Watch rules: {0}")]
+    Watchlist(#[from] watchlist::Error),
+
+    #[error("Search error: {0}")]
+    Search(#[from] domain_search::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    #[error("Webhook failed: {0}")]
+    Webhook(#[from] reqwest::Error),
+}
+
+impl Error {
+    /// Process exit code of the failure class
+    ///
+    /// | Code | Class |
+    /// |------|-------|
+    /// | 1 | Anything else (watch rules, exports, webhooks) |
+    /// | 2 | Configuration or options |
+    /// | 3 | Download, worth retrying |
+    /// | 4 | Malformed input |
+    /// | 5 | Segmentation |
+    /// | 6 | Schema mismatch, the index needs a rebuild |
+    /// | 7 | Index read or write, possibly corruption |
+    /// | 8 | Index locked by another writer |
+    /// | 9 | Filesystem |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Config(_) => 2,
+            Error::Download(_) => 3,
+            Error::Parse(_) => 4,
+            Error::Segmentation(_) => 5,
+            Error::Schema(_) => 6,
+            Error::Tantivy(_) => 7,
+            Error::Locked(_) => 8,
+            Error::Io(_) => 9,
+            Error::Watchlist(_)
+            | Error::Search(_)
+            | Error::Json(_)
+            | Error::Parquet(_)
+            | Error::Webhook(_) => 1,
+        }
+    }
+}
+
+impl From<TantivyError> for Error {
+    fn from(e: TantivyError) -> Self {
+        match e {
+            TantivyError::SchemaError(message) => Error::Schema(message),
+            e => Error::Tantivy(e),
+        }
+    }
+}
+
+impl From<OpenDirectoryError> for Error {
+    fn from(e: OpenDirectoryError) -> Self {
+        TantivyError::from(e).into()
+    }
+}
+
+impl From<zonefile_client::Error> for Error {
+    fn from(e: zonefile_client::Error) -> Self {
+        match e {
+            zonefile_client::Error::Io(e) => Error::Io(e),
+            zonefile_client::Error::InvalidZonefile(message) => Error::Parse(message),
+            e => Error::Download(e),
+        }
+    }
+}
+
+impl From<domain_core::Error> for Error {
+    fn from(e: domain_core::Error) -> Self {
+        match e {
+            domain_core::Error::InvalidDomain(_)
+            | domain_core::Error::InvalidCharacters(_)
+            | domain_core::Error::Json(_) => {
+                Error::Parse(e.to_string())
+            }
+            domain_core::Error::Config(message) => Error::Config(message),
+            domain_core::Error::Io(e) => Error::Io(e),
+            domain_core::Error::Tantivy(e) => e.into(),
+            e @ domain_core::Error::WriterLocked { .. } => Error::Locked(e.to_string()),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::error::DataCorruption;
+
+    #[test]
+    fn test_failure_classes_map_to_exit_codes() {
+        let download = zonefile_client::Error::DownloadFailed {
+            status: 503,
+            message: "unavailable".to_string(),
+        };
+        assert_eq!(Error::from(download).exit_code(), 3);
+        let invalid = zonefile_client::Error::InvalidZonefile("empty".to_string());
+        assert_eq!(Error::from(invalid).exit_code(), 4);
+
+        let schema = TantivyError::SchemaError("fields differ".to_string());
+        assert_eq!(Error::from(domain_core::Error::Tantivy(schema)).exit_code(), 6);
+        let corrupt = TantivyError::DataCorruption(DataCorruption::comment_only("bad checksum"));
+        assert_eq!(Error::from(corrupt).exit_code(), 7);
+
+        let locked = domain_core::Error::WriterLocked {
+            path: "index".into(),
+            holder: None,
+        };
+        assert_eq!(Error::from(locked).exit_code(), 8);
+        assert_eq!(Error::from(domain_core::Error::Config("bad".into())).exit_code(), 2);
+    }
+}
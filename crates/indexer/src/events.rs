@@ -0,0 +1,183 @@
+use crate::error::{Error, Result};
+use crate::maintenance::IndexCounts;
+use domain_core::Config;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// How a pipeline run ended, posted to `PIPELINE_WEBHOOK_URLS`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PipelineEvent {
+    /// `full` built an index
+    FullBuildFinished {
+        index: PathBuf,
+        documents: u64,
+        seconds: f64,
+    },
+    /// `daily` synced an index
+    DailySyncFinished {
+        index: PathBuf,
+        added: u64,
+        deleted: u64,
+        /// Documents in the index after the sync
+        documents: u64,
+        seconds: f64,
+    },
+    /// A daily sync merged the index's segments in the maintenance window
+    IndexOptimized {
+        index: PathBuf,
+        before: IndexCounts,
+        after: IndexCounts,
+        seconds: f64,
+    },
+    /// Fetching zonefiles from the provider failed
+    DownloadFailed { command: String, error: String },
+    /// The index has another schema or couldn't be read or written
+    IndexVerificationFailed {
+        command: String,
+        index: PathBuf,
+        error: String,
+    },
+}
+
+impl PipelineEvent {
+    /// The event of a run that failed with `error`, if operators are told
+    /// about its class
+    pub fn failure(command: &str, index: &Path, error: &Error) -> Option<Self> {
+        match error {
+            Error::Download(_) => Some(PipelineEvent::DownloadFailed {
+                command: command.to_string(),
+                error: error.to_string(),
+            }),
+            Error::Schema(_) | Error::Tantivy(_) => Some(PipelineEvent::IndexVerificationFailed {
+                command: command.to_string(),
+                index: index.to_path_buf(),
+                error: error.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// An event with the time it was sent
+#[derive(Serialize)]
+struct Notification<'a> {
+    #[serde(flatten)]
+    event: &'a PipelineEvent,
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+}
+
+/// Post the event of a `command` run on `index` to every pipeline webhook,
+/// passing its result on
+///
+/// `finished` describes a successful run. Failed deliveries are logged, not
+/// returned, so a webhook being down never fails the run itself.
+pub async fn report<T>(
+    config: &Config,
+    command: &str,
+    index: &Path,
+    result: Result<T>,
+    finished: impl FnOnce(&T) -> PipelineEvent,
+) -> Result<T> {
+    if config.pipeline_webhook_urls.is_empty() {
+        return result;
+    }
+    let event = match &result {
+        Ok(value) => Some(finished(value)),
+        Err(e) => PipelineEvent::failure(command, index, e),
+    };
+    if let Some(event) = event {
+        notify(config, &event).await;
+    }
+    result
+}
+
+/// Post an event to every pipeline webhook, logging failed deliveries
+pub async fn notify(config: &Config, event: &PipelineEvent) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let notification = Notification { event, timestamp };
+    for url in &config.pipeline_webhook_urls {
+        if let Err(e) = post(url, &notification).await {
+            warn!(url, error = %e, "Failed to deliver pipeline notification");
+        }
+    }
+}
+
+async fn post(url: &str, notification: &Notification<'_>) -> Result<()> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?
+        .post(url)
+        .json(notification)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_report_posts_outcomes() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/events"))
+            .and(body_partial_json(serde_json::json!({
+                "event": "daily_sync_finished",
+                "index": "/data/index",
+                "added": 12,
+                "deleted": 3,
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/events"))
+            .and(body_partial_json(serde_json::json!({
+                "event": "index_verification_failed",
+                "command": "daily",
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut config = Config::test();
+        // The unreachable webhook is skipped over
+        config.pipeline_webhook_urls = vec![
+            "http://127.0.0.1:9/events".to_string(),
+            format!("{}/events", server.uri()),
+        ];
+        let index = Path::new("/data/index");
+        let synced = |seconds: &f64| PipelineEvent::DailySyncFinished {
+            index: index.to_path_buf(),
+            added: 12,
+            deleted: 3,
+            documents: 100,
+            seconds: *seconds,
+        };
+
+        let ok = report(&config, "daily", index, Ok(1.5), synced).await;
+        assert_eq!(ok.unwrap(), 1.5);
+        let schema = Error::Schema("rebuild it".to_string());
+        assert!(report(&config, "daily", index, Err(schema), synced)
+            .await
+            .is_err());
+        // Not an event operators are told about
+        let config_error = Error::Config("bad".to_string());
+        assert!(report(&config, "daily", index, Err(config_error), synced)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::error::{Error, Result};
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tantivy::schema::{Facet, FieldType, IndexRecordOption};
+use tantivy::{DocSet, Index, SegmentReader, Term, TERMINATED};
+use tracing::info;
+
+/// Rows per Parquet row group
+const ROW_GROUP_SIZE: usize = 1_000_000;
+
+/// Output format of `export-terms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Csv,
+    Parquet,
+}
+
+/// A term and the number of documents containing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermCount {
+    pub term: String,
+    pub doc_freq: u64,
+}
+
+/// Write the terms of an indexed field with their document frequencies,
+/// most frequent first
+pub fn run(
+    index_path: &Path,
+    field: &str,
+    tld: Option<&str>,
+    min_df: u64,
+    limit: Option<usize>,
+    format: Format,
+    output: &Path,
+) -> Result<()> {
+    let index = Index::open_in_dir(index_path)?;
+    let mut counts = term_counts(&index, field, tld, min_df)?;
+    if let Some(limit) = limit {
+        counts.truncate(limit);
+    }
+
+    match format {
+        Format::Csv => write_csv(&counts, output)?,
+        Format::Parquet => write_parquet(&counts, output)?,
+    }
+    info!(field, terms = counts.len(), output = ?output, "Terms exported");
+    Ok(())
+}
+
+/// Terms of `field` in at least `min_df` documents, most frequent first
+///
+/// Frequencies come straight from the term dictionaries, which still count
+/// deleted documents until their segments are merged (`optimize`). With
+/// `tld`, only documents under that TLD are counted, by walking every
+/// term's postings; deleted documents are skipped then.
+pub fn term_counts(
+    index: &Index,
+    field: &str,
+    tld: Option<&str>,
+    min_df: u64,
+) -> Result<Vec<TermCount>> {
+    let schema = index.schema();
+    let Ok(field) = schema.get_field(field) else {
+        return Err(Error::Config(format!("Unknown field '{}'", field)));
+    };
+    let entry = schema.get_field_entry(field);
+    if !entry.is_indexed() {
+        return Err(Error::Config(format!("Field '{}' is not indexed", entry.name())));
+    }
+    let is_facet = matches!(entry.field_type(), FieldType::Facet(_));
+    let tld = tld
+        .map(|tld| -> Result<Term> {
+            let tld_field = schema.get_field("tld")?;
+            Ok(Term::from_facet(tld_field, &Facet::from_path([tld.trim_start_matches('.')])))
+        })
+        .transpose()?;
+
+    let searcher = index.reader()?.searcher();
+    let mut frequencies: HashMap<Vec<u8>, u64> = HashMap::new();
+    for segment in searcher.segment_readers() {
+        let inverted_index = segment.inverted_index(field)?;
+        let in_tld = match &tld {
+            Some(term) => Some(documents_with(segment, term)?),
+            None => None,
+        };
+
+        let mut terms = inverted_index.terms().stream()?;
+        while terms.advance() {
+            let doc_freq = match &in_tld {
+                None => u64::from(terms.value().doc_freq),
+                Some(in_tld) => {
+                    let mut postings = inverted_index
+                        .read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic)?;
+                    let mut count = 0;
+                    while postings.doc() != TERMINATED {
+                        count += u64::from(in_tld[postings.doc() as usize]);
+                        postings.advance();
+                    }
+                    count
+                }
+            };
+            if doc_freq > 0 {
+                *frequencies.entry(terms.key().to_vec()).or_default() += doc_freq;
+            }
+        }
+    }
+
+    let mut counts: Vec<TermCount> = frequencies
+        .into_iter()
+        .filter(|(_, doc_freq)| *doc_freq >= min_df)
+        .map(|(key, doc_freq)| {
+            let term = if is_facet {
+                Facet::from_encoded(key)
+                    .map(|facet| facet.to_path().join("/"))
+                    .unwrap_or_default()
+            } else {
+                String::from_utf8_lossy(&key).into_owned()
+            };
+            TermCount { term, doc_freq }
+        })
+        // Facet dictionaries also hold the root facet
+        .filter(|count| !count.term.is_empty())
+        .collect();
+    counts.sort_by(|a, b| b.doc_freq.cmp(&a.doc_freq).then_with(|| a.term.cmp(&b.term)));
+    Ok(counts)
+}
+
+/// Live documents of a segment containing `term`, by doc id
+fn documents_with(segment: &SegmentReader, term: &Term) -> Result<Vec<bool>> {
+    let mut documents = vec![false; segment.max_doc() as usize];
+    let alive = segment.alive_bitset();
+    let inverted_index = segment.inverted_index(term.field())?;
+    if let Some(mut postings) = inverted_index.read_postings(term, IndexRecordOption::Basic)? {
+        while postings.doc() != TERMINATED {
+            let doc = postings.doc();
+            documents[doc as usize] = alive.is_none_or(|alive| alive.is_alive(doc));
+            postings.advance();
+        }
+    }
+    Ok(documents)
+}
+
+fn write_csv(counts: &[TermCount], output: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(output)?);
+    writeln!(writer, "term,doc_freq")?;
+    for count in counts {
+        writeln!(writer, "{},{}", csv_field(&count.term), count.doc_freq)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_parquet(counts: &[TermCount], output: &Path) -> Result<()> {
+    let schema = Arc::new(parse_message_type(
+        "message terms { required binary term (UTF8); required int64 doc_freq; }",
+    )?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(File::create(output)?, schema, properties)?;
+
+    for rows in counts.chunks(ROW_GROUP_SIZE) {
+        let mut row_group = writer.next_row_group()?;
+
+        let terms: Vec<ByteArray> = rows.iter().map(|c| ByteArray::from(c.term.as_str())).collect();
+        let mut column = row_group.next_column()?.expect("term column");
+        column.typed::<ByteArrayType>().write_batch(&terms, None, None)?;
+        column.close()?;
+
+        let doc_freqs: Vec<i64> = rows.iter().map(|c| c.doc_freq as i64).collect();
+        let mut column = row_group.next_column()?.expect("doc_freq column");
+        column.typed::<Int64Type>().write_batch(&doc_freqs, None, None)?;
+        column.close()?;
+
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::{Domain, DomainSchema, Taxonomy};
+
+    fn index() -> Index {
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+        let taxonomy =
+            Taxonomy::new(HashMap::from([("food".to_string(), vec!["coffee".to_string()])]))
+                .unwrap();
+        for (domain, tokens) in [
+            ("coffeeshop.com", vec!["coffee", "shop"]),
+            ("coffee.com", vec!["coffee"]),
+            ("coffee.io", vec!["coffee"]),
+            ("petshop.com", vec!["pet", "shop"]),
+            ("gone.com", vec!["gone", "shop"]),
+        ] {
+            let mut normalized = Domain::new(domain)
+                .normalize()
+                .unwrap()
+                .with_tokens(tokens.iter().map(|t| t.to_string()).collect());
+            normalized.categories = taxonomy.tag(&normalized.label, &normalized.tokens);
+            writer.add_document(schema.to_document(&normalized)).unwrap();
+        }
+        writer.commit().unwrap();
+        writer.delete_term(Term::from_field_text(schema.domain_exact, "gone.com"));
+        writer.commit().unwrap();
+        index
+    }
+
+    fn counts(counts: &[TermCount]) -> Vec<(&str, u64)> {
+        counts.iter().map(|c| (c.term.as_str(), c.doc_freq)).collect()
+    }
+
+    #[test]
+    fn test_term_counts() {
+        let index = index();
+
+        // Deleted documents still count until segments merge
+        let tokens = term_counts(&index, "tokens", None, 2).unwrap();
+        assert_eq!(counts(&tokens), vec![("coffee", 3), ("shop", 3)]);
+
+        let tlds = term_counts(&index, "tld", None, 1).unwrap();
+        assert_eq!(counts(&tlds), vec![("com", 4), ("io", 1)]);
+
+        let categories = term_counts(&index, "category", None, 1).unwrap();
+        assert_eq!(counts(&categories), vec![("food", 3)]);
+
+        assert!(term_counts(&index, "nope", None, 1).is_err());
+    }
+
+    #[test]
+    fn test_term_counts_in_tld() {
+        let tokens = term_counts(&index(), "tokens", Some(".com"), 1).unwrap();
+        assert_eq!(counts(&tokens), vec![("coffee", 2), ("shop", 2), ("pet", 1)]);
+    }
+
+    #[test]
+    fn test_write_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let rows = vec![
+            TermCount { term: "coffee".to_string(), doc_freq: 3 },
+            TermCount { term: "a,b".to_string(), doc_freq: 1 },
+        ];
+
+        let csv = dir.path().join("terms.csv");
+        write_csv(&rows, &csv).unwrap();
+        assert_eq!(std::fs::read_to_string(&csv).unwrap(), "term,doc_freq\ncoffee,3\n\"a,b\",1\n");
+
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        let parquet = dir.path().join("terms.parquet");
+        write_parquet(&rows, &parquet).unwrap();
+        let reader = SerializedFileReader::new(File::open(&parquet).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+    }
+}
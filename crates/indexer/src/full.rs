@@ -1,116 +1,220 @@
+use crate::dated;
+use crate::error::{Error, Result};
 use crate::progress::IndexProgress;
-use anyhow::Result;
-use domain_core::{domain::should_filter_domain, Config, Domain, DomainSchema};
-use futures::StreamExt;
+use crate::state::{self, Checkpoint, StateStore, Status};
+use domain_core::{
+    domain::{should_filter_domain, TldFilter},
+    writer_lock, Config, Domain, DomainSchema, LabelValidation, NormalizedDomain, Taxonomy,
+};
+use futures::{Stream, StreamExt};
+use rayon::prelude::*;
 use std::path::Path;
 use tantivy::Index;
 use tracing::{debug, info, warn};
-use word_client::WordClient;
+use word_client::{LocalSegmenter, Segmenter, WordClient};
 use zonefile_client::{parser::batch_stream, DomainStream, ZonefileDownloader, ZonefileType};
 
 /// Run full indexing with download from API
+///
+/// Domains are indexed while the zonefile downloads: the archive is unzipped
+/// off the response and never written to disk, and the download waits
+/// whenever segmentation and writing fall behind.
 pub async fn run_with_download(
     config: &Config,
     output_path: &Path,
     heap_size: usize,
     commit_interval: usize,
-) -> Result<()> {
-    // Download the zonefile
-    let downloader = ZonefileDownloader::new(
-        &config.zonefile_api_url,
-        &config.zonefile_token,
-        std::env::temp_dir().join("zonefile-indexer"),
-    )?;
+    previous: Option<&Path>,
+    work_dir: &Path,
+) -> Result<u64> {
+    let downloader =
+        ZonefileDownloader::new(&config.zonefile_api_url, &config.zonefile_token, work_dir)?;
+    let domains = downloader.stream_domains(ZonefileType::Full).await?;
+
+    info!("Starting full index build from the download");
+    info!(output = ?output_path);
+    build(config, domains, None, output_path, heap_size, commit_interval, previous).await
+}
 
-    let input_path = downloader.download(ZonefileType::Full).await?;
+/// The taxonomy named by `TAXONOMY_FILE`, or an empty one tagging nothing
+pub fn load_taxonomy(config: &Config) -> Result<Taxonomy> {
+    let Some(path) = &config.taxonomy_file else {
+        return Ok(Taxonomy::default());
+    };
+    let taxonomy = Taxonomy::load(path)?;
+    info!(path = %path.display(), "Taxonomy loaded");
+    Ok(taxonomy)
+}
 
-    run(config, &input_path, output_path, heap_size, commit_interval).await
+/// The TLDs `full` and `daily` index, logged when not all are
+pub fn tld_filter(config: &Config) -> TldFilter {
+    let tlds = TldFilter::from_config(config);
+    if !tlds.is_empty() {
+        info!(include = ?config.include_tlds, exclude = ?config.exclude_tlds, "Filtering TLDs");
+    }
+    tlds
+}
+
+/// Split labels with the word list named by `WORD_LIST_FILE`, or else the
+/// word splitter API
+pub fn segmenter(config: &Config) -> Result<Segmenter> {
+    if let Some(path) = &config.word_list_file {
+        let segmenter = LocalSegmenter::load(path)?;
+        info!(path = %path.display(), words = segmenter.len(), "Segmenting with local word list");
+        return Ok(Segmenter::Local(segmenter.into()));
+    }
+    Ok(Segmenter::Remote(WordClient::new(
+        &config.word_splitter_url,
+        &config.word_splitter_user,
+        &config.word_splitter_pass,
+        Some(config.word_batch_size),
+        Some(4), // 4 parallel API requests
+    )?))
 }
 
 /// Run full indexing from a local file
+///
+/// With a `previous` index, domains it has keep their stored tokens and only
+/// the others are segmented. Returns how many domains were indexed.
 pub async fn run(
     config: &Config,
     input_path: &Path,
     output_path: &Path,
     heap_size: usize,
     commit_interval: usize,
-) -> Result<()> {
+    previous: Option<&Path>,
+) -> Result<u64> {
     info!("Starting full index build");
     info!(input = ?input_path, output = ?output_path);
-    info!(heap_mb = heap_size / 1024 / 1024, commit_interval = commit_interval);
 
     // Count total domains for progress
     info!("Counting domains in file...");
     let total_count = DomainStream::count_file(input_path).await?;
     info!(total = total_count, "Total domains to index");
 
-    // Create Tantivy index
-    std::fs::create_dir_all(output_path)?;
+    let domains = DomainStream::from_file(input_path);
+    let total = Some(total_count);
+    build(config, domains, total, output_path, heap_size, commit_interval, previous).await
+}
+
+/// Index a stream of domains into a new index, with progress against
+/// `total_count` when it is known, reusing the tokens `previous` stores
+///
+/// With `STATE_DIR`, every commit is checkpointed and a checkpoint kept by
+/// `state::prepare` is resumed from: the index is reopened and the domains
+/// read before it are skipped.
+async fn build(
+    config: &Config,
+    domains: impl Stream<Item = zonefile_client::Result<String>>,
+    total_count: Option<u64>,
+    output_path: &Path,
+    heap_size: usize,
+    commit_interval: usize,
+    previous: Option<&Path>,
+) -> Result<u64> {
+    info!(heap_mb = heap_size / 1024 / 1024, commit_interval = commit_interval);
+
+    // Open the previous index before creating the new one, which may replace it
+    let previous = match previous {
+        Some(path) if path == output_path => {
+            let message = "--reuse-tokens must name an index other than the output";
+            return Err(Error::Config(message.to_string()));
+        }
+        Some(path) => {
+            let searcher = Index::open_in_dir(path)?.reader()?.searcher();
+            let documents = searcher.num_docs();
+            info!(path = ?path, documents, "Reusing tokens of previous index");
+            Some(searcher)
+        }
+        None => None,
+    };
+
+    let taxonomy = load_taxonomy(config)?;
+    let tlds = tld_filter(config);
+
+    let state = state::from_config(config, "full")?;
+    let resumed = match &state {
+        Some(state) => state.checkpoint()?,
+        None => None,
+    };
+
+    // Create Tantivy index, or reopen the one a checkpoint was taken of
     let schema = DomainSchema::new();
-    let index = Index::create_in_dir(output_path, schema.schema.clone())?;
-    let mut writer = index.writer(heap_size)?;
+    let index = match &resumed {
+        Some(checkpoint) => {
+            info!(read = checkpoint.read, indexed = checkpoint.indexed, "Resuming from checkpoint");
+            Index::open_in_dir(output_path)?
+        }
+        None => {
+            std::fs::create_dir_all(output_path)?;
+            Index::create_in_dir(output_path, schema.schema.clone())?
+        }
+    };
+    let mut writer = writer_lock::open_writer(&index, output_path, heap_size)?;
 
     // Create word client with parallel requests
-    let word_client = WordClient::new(
-        &config.word_splitter_url,
-        &config.word_splitter_user,
-        &config.word_splitter_pass,
-        Some(config.word_batch_size),
-        Some(4), // 4 parallel API requests
-    )?;
+    let splitter = segmenter(config)?;
+
+    let mut checkpoint = resumed.unwrap_or_else(|| Checkpoint {
+        index: output_path.to_path_buf(),
+        read: 0,
+        indexed: 0,
+    });
 
     // Set up progress tracking
-    let mut progress = IndexProgress::new(total_count);
+    let mut progress = match total_count {
+        Some(total) => IndexProgress::new(total.saturating_sub(checkpoint.read)),
+        None => IndexProgress::spinner(),
+    };
 
-    // Process domains in batches
-    let domain_stream = DomainStream::from_file(input_path);
-    let batched_stream = batch_stream(domain_stream, config.word_batch_size);
+    // Process domains in batches, past those read before the checkpoint
+    let domains = domains.skip(checkpoint.read as usize);
+    let batched_stream = batch_stream(domains, config.word_batch_size);
 
     futures::pin_mut!(batched_stream);
 
-    let mut indexed_count: u64 = 0;
+    let mut indexed_count: u64 = checkpoint.indexed;
+    let mut reused_count: u64 = 0;
     let mut filtered_count: u64 = 0;
+    let mut rejected_count: u64 = 0;
     let mut error_count: u64 = 0;
-    let mut last_commit: u64 = 0;
+    let mut last_commit: u64 = indexed_count;
+    let mut rejects: Vec<String> = Vec::new();
 
     while let Some(batch_result) = batched_stream.next().await {
         let batch: Vec<String> = batch_result?;
         let batch_size = batch.len();
+        checkpoint.read += batch_size as u64;
 
-        // Normalize and filter domains
-        let mut valid_domains: Vec<(String, domain_core::NormalizedDomain)> = Vec::new();
-        let mut labels_to_segment: Vec<String> = Vec::new();
-
-        for raw_domain in &batch {
-            let domain = Domain::new(raw_domain);
-
-            match domain.normalize() {
-                Ok(normalized) => {
-                    // Apply filtering rules
-                    if should_filter_domain(&normalized.label) {
-                        filtered_count += 1;
-                        continue;
-                    }
-
-                    labels_to_segment.push(normalized.label.clone());
-                    valid_domains.push((raw_domain.clone(), normalized));
-                }
-                Err(e) => {
-                    debug!(domain = raw_domain, error = %e, "Failed to normalize domain");
-                    error_count += 1;
-                }
-            }
+        // Normalize and filter domains on the rayon pool, keeping file order
+        let normalized = normalize_batch(&batch, &tlds, config.label_validation);
+        filtered_count += normalized.filtered;
+        rejected_count += normalized.rejected.len() as u64;
+        error_count += normalized.invalid.len() as u64;
+        rejects.extend(normalized.rejected);
+        rejects.extend(normalized.invalid);
+        let mut valid_domains = normalized.kept;
+        if let Some(previous) = &previous {
+            let version = config.segmentation_version;
+            reused_count += reuse_tokens(previous, &schema, &mut valid_domains, version)?;
         }
+        let unsegmented: Vec<usize> = (0..valid_domains.len())
+            .filter(|&i| valid_domains[i].tokens.is_empty())
+            .collect();
+        let subdomains = config.index_subdomain_labels;
+        let labels_to_segment: Vec<String> = unsegmented
+            .iter()
+            .map(|&i| valid_domains[i].segment_source(subdomains))
+            .collect();
 
         // Segment labels in batch
         if !labels_to_segment.is_empty() {
-            match word_client.segment_batch(labels_to_segment).await {
+            match splitter.segment_batch(labels_to_segment).await {
                 Ok(segments) => {
                     // Match segments with domains by index
-                    for (i, (_, tokens)) in segments.iter().enumerate() {
-                        if i < valid_domains.len() {
-                            valid_domains[i].1.tokens = tokens.clone();
-                        }
+                    for (&i, (_, tokens)) in unsegmented.iter().zip(segments) {
+                        valid_domains[i].tokens = tokens;
+                        valid_domains[i].seg_version = config.segmentation_version;
                     }
                 }
                 Err(e) => {
@@ -121,7 +225,8 @@ pub async fn run(
         }
 
         // Add documents to index
-        for (_, normalized) in &valid_domains {
+        for normalized in &mut valid_domains {
+            normalized.categories = taxonomy.tag(&normalized.label, &normalized.tokens);
             let doc = schema.to_document(normalized);
             writer.add_document(doc)?;
             indexed_count += 1;
@@ -130,8 +235,13 @@ pub async fn run(
         // Commit periodically
         if indexed_count - last_commit >= commit_interval as u64 {
             info!(indexed = indexed_count, "Committing checkpoint...");
+            progress.set_message("(committing)");
             writer.commit()?;
             last_commit = indexed_count;
+            if let Some(state) = &state {
+                checkpoint.indexed = indexed_count;
+                record_commit(state.as_ref(), &checkpoint, total_count, &mut rejects)?;
+            }
         }
 
         progress.inc(batch_size as u64);
@@ -140,13 +250,22 @@ pub async fn run(
     // Final commit
     info!("Final commit...");
     writer.commit()?;
+    if let Some(state) = &state {
+        state.append_rejects(&rejects)?;
+        state.clear_checkpoint()?;
+        checkpoint.indexed = indexed_count;
+        state.save_status(&Status::new(&checkpoint, total_count, true))?;
+    }
 
     progress.finish();
 
     info!(
         indexed = indexed_count,
+        reused = reused_count,
         filtered = filtered_count,
+        rejected = rejected_count,
         errors = error_count,
+        elapsed_secs = progress.elapsed().as_secs(),
         "Indexing complete"
     );
 
@@ -160,5 +279,416 @@ pub async fn run(
     }
     info!(size_gb = total_size as f64 / 1024.0 / 1024.0 / 1024.0, "Index size");
 
-    Ok(())
+    Ok(indexed_count)
+}
+
+/// Record a commit in the state: the domains rejected since the last one,
+/// then the checkpoint and status
+fn record_commit(
+    state: &dyn StateStore,
+    checkpoint: &Checkpoint,
+    total: Option<u64>,
+    rejects: &mut Vec<String>,
+) -> Result<()> {
+    state.append_rejects(rejects)?;
+    rejects.clear();
+    state.save_checkpoint(checkpoint)?;
+    state.save_status(&Status::new(checkpoint, total, false))
+}
+
+/// Give domains the tokens stored for them in the previous index, returning
+/// how many had some
+///
+/// Domains stored without tokens (e.g. their segmentation failed) or with
+/// tokens of a segmentation version before `version` are left to be
+/// segmented again.
+fn reuse_tokens(
+    previous: &tantivy::Searcher,
+    schema: &DomainSchema,
+    domains: &mut [NormalizedDomain],
+    version: u64,
+) -> Result<u64> {
+    domains
+        .par_iter_mut()
+        .map(|domain| {
+            let stored = dated::stored_tokens(previous, schema, &domain.domain_exact)?;
+            match stored.filter(|(tokens, stored)| !tokens.is_empty() && *stored >= version) {
+                Some((tokens, stored)) => {
+                    domain.tokens = tokens;
+                    domain.seg_version = stored;
+                    Ok(1)
+                }
+                None => Ok(0),
+            }
+        })
+        .sum()
+}
+
+/// The domains of a batch kept for indexing, in batch order
+struct NormalizedBatch {
+    kept: Vec<NormalizedDomain>,
+    /// Dropped by the TLD filter or `should_filter_domain`
+    filtered: u64,
+    /// Labels with characters DNS doesn't allow (`LABEL_VALIDATION`), as read
+    rejected: Vec<String>,
+    /// Failed to normalize otherwise, as read
+    invalid: Vec<String>,
+}
+
+/// What normalization made of one raw domain
+enum Normalized {
+    Kept(NormalizedDomain),
+    Filtered,
+    Rejected(String),
+    Invalid(String),
+}
+
+/// Normalize and filter a batch across the rayon pool (`--cpu-threads`)
+fn normalize_batch(
+    batch: &[String],
+    tlds: &TldFilter,
+    validation: LabelValidation,
+) -> NormalizedBatch {
+    let normalized: Vec<Normalized> = batch
+        .par_iter()
+        .map(|raw_domain| match Domain::new(raw_domain).normalize_with(validation) {
+            // Apply filtering rules
+            Ok(normalized)
+                if !tlds.allows(&normalized.tld) || should_filter_domain(&normalized.label) =>
+            {
+                Normalized::Filtered
+            }
+            Ok(normalized) => Normalized::Kept(normalized),
+            Err(e @ domain_core::Error::InvalidCharacters(_)) => {
+                debug!(domain = raw_domain, error = %e, "Rejected domain");
+                Normalized::Rejected(raw_domain.clone())
+            }
+            Err(e) => {
+                debug!(domain = raw_domain, error = %e, "Failed to normalize domain");
+                Normalized::Invalid(raw_domain.clone())
+            }
+        })
+        .collect();
+
+    let mut batch = NormalizedBatch {
+        kept: Vec::with_capacity(normalized.len()),
+        filtered: 0,
+        rejected: Vec::new(),
+        invalid: Vec::new(),
+    };
+    for domain in normalized {
+        match domain {
+            Normalized::Kept(domain) => batch.kept.push(domain),
+            Normalized::Filtered => batch.filtered += 1,
+            Normalized::Rejected(domain) => batch.rejected.push(domain),
+            Normalized::Invalid(domain) => batch.invalid.push(domain),
+        }
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_search::{SearchRequest, Searcher};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    const DOMAINS: &str = "coffeeshop.com\nCoffee-Shop.io\npetcare.com\nbanklo.an\n\
+        1234567.com\nnodot\nshop.net\n";
+
+    /// Splits labels into known words, like the word splitter's `/segment/bulk`
+    struct Segmenter;
+
+    impl Respond for Segmenter {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            const WORDS: &[&str] = &["coffee", "shop", "pet", "care", "bank"];
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let results: Vec<serde_json::Value> = body["labels"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|label| {
+                    let label = label.as_str().unwrap();
+                    let words: Vec<&str> = label
+                        .split('-')
+                        .flat_map(|part| {
+                            let mut rest = part;
+                            let mut words = Vec::new();
+                            while let Some(word) = WORDS.iter().find(|w| rest.starts_with(*w)) {
+                                words.push(*word);
+                                rest = &rest[word.len()..];
+                            }
+                            words
+                        })
+                        .collect();
+                    serde_json::json!({ "label": label, "segmentation": words })
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "results": results }))
+        }
+    }
+
+    /// Index `DOMAINS` against a word splitter answering with `splitter`
+    async fn index_domains(splitter: impl Respond + 'static) -> (tempfile::TempDir, Searcher) {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/segment/bulk"))
+            .respond_with(splitter)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("domains.txt");
+        std::fs::write(&input, DOMAINS).unwrap();
+        let taxonomy = dir.path().join("taxonomy.json");
+        std::fs::write(&taxonomy, r#"{ "pets": ["pet"], "food": ["coffee"] }"#).unwrap();
+
+        let mut config = Config::test();
+        config.word_splitter_url = server.uri();
+        config.taxonomy_file = Some(taxonomy);
+        config.word_batch_size = 3;
+        let output = dir.path().join("index");
+        run(&config, &input, &output, 50_000_000, 2, None).await.unwrap();
+
+        let index = Index::open_in_dir(&output).unwrap();
+        let searcher = Searcher::new(&index, DomainSchema::new()).unwrap();
+        (dir, searcher)
+    }
+
+    fn domains(searcher: &Searcher, request: &SearchRequest) -> Vec<String> {
+        let mut domains: Vec<String> = searcher
+            .search(request)
+            .unwrap()
+            .results
+            .into_iter()
+            .map(|r| r.domain.domain)
+            .collect();
+        domains.sort();
+        domains
+    }
+
+    #[tokio::test]
+    async fn test_full_index_is_searchable() {
+        let (_dir, searcher) = index_domains(Segmenter).await;
+
+        assert_eq!(searcher.reader().searcher().num_docs(), 5);
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("coffee shop", 10)),
+            vec!["coffee-shop.io", "coffeeshop.com", "shop.net"]
+        );
+
+        let mut request = SearchRequest::new("coffee", 10);
+        request.tld = Some("io".to_string());
+        assert_eq!(domains(&searcher, &request), vec!["coffee-shop.io"]);
+
+        let mut request = SearchRequest::new("care", 10);
+        request.category = Some("pets".to_string());
+        assert_eq!(domains(&searcher, &request), vec!["petcare.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_full_index_streams_download() {
+        use async_zip::base::write::ZipFileWriter;
+        use async_zip::{Compression, ZipEntryBuilder};
+
+        let mut archive = ZipFileWriter::new(Vec::new());
+        let entry = ZipEntryBuilder::new("domains.txt".into(), Compression::Deflate);
+        archive.write_entry_whole(entry, DOMAINS.as_bytes()).await.unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/token/get/full/list/zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.close().await.unwrap()))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let words = dir.path().join("words.txt");
+        std::fs::write(&words, "coffee\nshop\npet\ncare\nbank\n").unwrap();
+        let mut config = Config::test();
+        config.zonefile_api_url = server.uri();
+        config.zonefile_token = "token".to_string();
+        config.word_list_file = Some(words);
+        let output = dir.path().join("index");
+        let work = dir.path().join("work");
+        run_with_download(&config, &output, 50_000_000, 2, None, &work).await.unwrap();
+
+        let index = Index::open_in_dir(&output).unwrap();
+        let searcher = Searcher::new(&index, DomainSchema::new()).unwrap();
+        assert_eq!(searcher.reader().searcher().num_docs(), 5);
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("pet care", 10)),
+            vec!["petcare.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_full_rebuild_reuses_tokens() {
+        let (dir, _) = index_domains(Segmenter).await;
+        let previous = dir.path().join("index");
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/segment/bulk"))
+            .respond_with(Segmenter)
+            .mount(&server)
+            .await;
+        let input = dir.path().join("rebuild.txt");
+        std::fs::write(&input, format!("{}petshop.org\n", DOMAINS)).unwrap();
+        let mut config = Config::test();
+        config.word_splitter_url = server.uri();
+        config.word_batch_size = 100;
+        let output = dir.path().join("rebuilt");
+        run(&config, &input, &output, 50_000_000, 1000, Some(&previous)).await.unwrap();
+
+        // Only the label the previous index lacks was sent to the splitter
+        let requests = server.received_requests().await.unwrap();
+        let labels: Vec<serde_json::Value> =
+            requests.iter().map(|r| serde_json::from_slice(&r.body).unwrap()).collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0]["labels"], serde_json::json!(["petshop"]));
+
+        let index = Index::open_in_dir(&output).unwrap();
+        let searcher = Searcher::new(&index, DomainSchema::new()).unwrap();
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("pet", 10)),
+            vec!["petcare.com", "petshop.org"]
+        );
+
+        let same = run(&config, &input, &previous, 50_000_000, 1000, Some(&previous)).await;
+        assert!(same.is_err());
+
+        // Tokens of an older segmentation version are segmented again
+        config.segmentation_version = 2;
+        let output = dir.path().join("resegmented");
+        run(&config, &input, &output, 50_000_000, 1000, Some(&previous)).await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let labels = serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap();
+        assert_eq!(labels["labels"].as_array().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_normalize_batch_keeps_order() {
+        let batch: Vec<String> = DOMAINS.lines().map(str::to_string).collect();
+        let tlds = TldFilter::new(["com", "io", "net"], []);
+        let normalized = normalize_batch(&batch, &tlds, LabelValidation::Strict);
+
+        let kept: Vec<&str> = normalized.kept.iter().map(|d| d.domain_exact.as_str()).collect();
+        assert_eq!(kept, vec!["coffeeshop.com", "coffee-shop.io", "petcare.com", "shop.net"]);
+        // banklo.an by TLD and 1234567.com as a number; nodot has no TLD
+        assert_eq!(normalized.filtered, 2);
+        assert!(normalized.rejected.is_empty());
+        assert_eq!(normalized.invalid, vec!["nodot"]);
+
+        let batch = vec!["coffee_shop.com".to_string(), "coffee.com".to_string()];
+        let strict = normalize_batch(&batch, &tlds, LabelValidation::Strict);
+        assert_eq!(strict.kept.len(), 1);
+        assert_eq!(strict.rejected, vec!["coffee_shop.com"]);
+        let lenient = normalize_batch(&batch, &tlds, LabelValidation::Lenient);
+        assert_eq!((lenient.kept.len(), lenient.rejected.len()), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_full_index_survives_rate_limiting() {
+        let (_dir, searcher) = index_domains(ResponseTemplate::new(429)).await;
+
+        // Every domain is kept without tokens, matching keywords by the
+        // words of its label only
+        assert_eq!(searcher.reader().searcher().num_docs(), 5);
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("coffee", 10)),
+            vec!["coffee-shop.io"]
+        );
+        assert!(domains(&searcher, &SearchRequest::new("care", 10)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_full_index_with_word_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("domains.txt");
+        std::fs::write(&input, DOMAINS).unwrap();
+        let words = dir.path().join("words.txt");
+        std::fs::write(&words, "coffee\nshop\npet\ncare\nbank\n").unwrap();
+
+        let mut config = Config::test();
+        // Nothing listens there; segmentation must not need it
+        config.word_splitter_url = "http://127.0.0.1:9".to_string();
+        config.word_list_file = Some(words);
+        let output = dir.path().join("index");
+        run(&config, &input, &output, 50_000_000, 1000, None).await.unwrap();
+
+        let index = Index::open_in_dir(&output).unwrap();
+        let searcher = Searcher::new(&index, DomainSchema::new()).unwrap();
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("pet care", 10)),
+            vec!["petcare.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_full_index_resumes_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let words = dir.path().join("words.txt");
+        std::fs::write(&words, "coffee\nshop\npet\ncare\nbank\n").unwrap();
+        let mut config = Config::test();
+        config.word_list_file = Some(words);
+        config.word_batch_size = 2;
+        config.state_dir = Some(dir.path().join("state"));
+        let output = dir.path().join("index");
+        assert!(state::prepare(&config, "full", &output, true).is_err());
+
+        // A build that stopped once the first three domains were committed
+        let head = dir.path().join("head.txt");
+        std::fs::write(&head, DOMAINS.lines().take(3).collect::<Vec<_>>().join("\n")).unwrap();
+        state::prepare(&config, "full", &output, false).unwrap();
+        run(&config, &head, &output, 50_000_000, 1000, None).await.unwrap();
+        let store = state::LocalStore::open(dir.path().join("state/full")).unwrap();
+        let checkpoint = Checkpoint {
+            index: output.clone(),
+            read: 3,
+            indexed: 3,
+        };
+        store.save_checkpoint(&checkpoint).unwrap();
+
+        // With an empty label the parser passes and normalization rejects
+        let input = dir.path().join("domains.txt");
+        std::fs::write(&input, format!("{DOMAINS}.com\n")).unwrap();
+        state::prepare(&config, "full", &output, true).unwrap();
+        run(&config, &input, &output, 50_000_000, 1, None).await.unwrap();
+
+        let index = Index::open_in_dir(&output).unwrap();
+        let searcher = Searcher::new(&index, DomainSchema::new()).unwrap();
+        assert_eq!(searcher.reader().searcher().num_docs(), 5);
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("coffee shop", 10)),
+            vec!["coffee-shop.io", "coffeeshop.com", "shop.net"]
+        );
+        assert_eq!(store.checkpoint().unwrap(), None);
+        let rejects = std::fs::read_to_string(dir.path().join("state/full/rejects.txt")).unwrap();
+        assert_eq!(rejects, ".com\n");
+    }
+
+    #[tokio::test]
+    async fn test_full_index_filters_tlds() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("domains.txt");
+        std::fs::write(&input, DOMAINS).unwrap();
+        let words = dir.path().join("words.txt");
+        std::fs::write(&words, "coffee\nshop\npet\ncare\nbank\n").unwrap();
+
+        let mut config = Config::test();
+        config.word_list_file = Some(words);
+        config.include_tlds = vec!["com".to_string(), "net".to_string()];
+        config.exclude_tlds = vec!["net".to_string()];
+        let output = dir.path().join("index");
+        run(&config, &input, &output, 50_000_000, 1000, None).await.unwrap();
+
+        let index = Index::open_in_dir(&output).unwrap();
+        let searcher = Searcher::new(&index, DomainSchema::new()).unwrap();
+        assert_eq!(searcher.reader().searcher().num_docs(), 2);
+        assert_eq!(
+            domains(&searcher, &SearchRequest::new("coffee shop", 10)),
+            vec!["coffeeshop.com"]
+        );
+    }
 }
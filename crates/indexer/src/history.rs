@@ -0,0 +1,159 @@
+use crate::dated;
+use crate::error::Result;
+use domain_core::{writer_lock, DomainSchema, HistorySchema, LifecycleEvent, NormalizedDomain};
+use std::collections::HashSet;
+use std::path::Path;
+use tantivy::collector::Count;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::TermQuery;
+use tantivy::schema::IndexRecordOption;
+use tantivy::{DateTime, Index, IndexWriter, Term};
+
+/// Writer for the domain history index (`HISTORY_INDEX_PATH`)
+///
+/// Unlike the dated indices, events are only ever appended: each drop and
+/// (re-)registration is one document dated with the time of the sync.
+pub struct HistoryIndex {
+    schema: HistorySchema,
+    /// Events recorded by earlier syncs
+    searcher: tantivy::Searcher,
+    writer: IndexWriter,
+    date: DateTime,
+    /// Domains dropped by this sync, re-registered if added again
+    dropped: HashSet<String>,
+    recorded: u64,
+}
+
+impl HistoryIndex {
+    /// Open the index, creating it on first use, to record events at `date`
+    pub fn open_or_create(path: &Path, heap_size: usize, date: DateTime) -> Result<Self> {
+        let schema = HistorySchema::new();
+        std::fs::create_dir_all(path)?;
+        let directory = MmapDirectory::open(path)?;
+        let index = Index::open_or_create(directory, schema.domain.schema.clone())?;
+
+        Ok(Self {
+            schema,
+            searcher: index.reader()?.searcher(),
+            writer: writer_lock::open_writer(&index, path, heap_size)?,
+            date,
+            dropped: HashSet::new(),
+            recorded: 0,
+        })
+    }
+
+    /// Record a domain removed from its zone
+    pub fn record_dropped(&mut self, domain: &NormalizedDomain) -> Result<()> {
+        self.dropped.insert(domain.domain_exact.clone());
+        self.record(domain, LifecycleEvent::Dropped)
+    }
+
+    /// Record an added domain as first seen or re-registered; domains that
+    /// were already in the main index (and not dropped today) are skipped
+    pub fn record_added(
+        &mut self,
+        domain: &NormalizedDomain,
+        main: &tantivy::Searcher,
+        main_schema: &DomainSchema,
+    ) -> Result<()> {
+        let event = if self.dropped.contains(&domain.domain_exact) {
+            LifecycleEvent::ReRegistered
+        } else if dated::is_indexed(main, main_schema, &domain.domain_exact)? {
+            return Ok(());
+        } else if self.has_history(&domain.domain_exact)? {
+            LifecycleEvent::ReRegistered
+        } else {
+            LifecycleEvent::FirstSeen
+        };
+        self.record(domain, event)
+    }
+
+    fn has_history(&self, domain_exact: &str) -> Result<bool> {
+        let term = Term::from_field_text(self.schema.domain.domain_exact, domain_exact);
+        let query = TermQuery::new(term, IndexRecordOption::Basic);
+        Ok(self.searcher.search(&query, &Count)? > 0)
+    }
+
+    fn record(&mut self, domain: &NormalizedDomain, event: LifecycleEvent) -> Result<()> {
+        let doc = self.schema.to_document(domain, event, self.date);
+        self.writer.add_document(doc)?;
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Commit, returning how many events were recorded
+    pub fn commit(mut self) -> Result<u64> {
+        self.writer.commit()?;
+        Ok(self.recorded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::Domain;
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::Value;
+    use tantivy::TantivyDocument;
+
+    fn events(path: &Path, domain_exact: &str) -> Vec<String> {
+        let schema = HistorySchema::new();
+        let searcher = Index::open_in_dir(path).unwrap().reader().unwrap().searcher();
+        let term = Term::from_field_text(schema.domain.domain_exact, domain_exact);
+        let query = TermQuery::new(term, IndexRecordOption::Basic);
+        let found = searcher.search(&query, &TopDocs::with_limit(10)).unwrap();
+        let mut events: Vec<(DateTime, LifecycleEvent)> = found
+            .into_iter()
+            .map(|(_, address)| {
+                let doc: TantivyDocument = searcher.doc(address).unwrap();
+                let event = doc.get_first(schema.event).unwrap().as_str().unwrap();
+                let date = doc.get_first(schema.date).unwrap().as_datetime().unwrap();
+                (date, LifecycleEvent::parse(event).unwrap())
+            })
+            .collect();
+        events.sort_by_key(|(date, event)| (*date, event.sync_order()));
+        events.into_iter().map(|(_, event)| event.as_str().to_string()).collect()
+    }
+
+    fn open(path: &Path, day: i64) -> HistoryIndex {
+        let date = DateTime::from_timestamp_secs(day * 86_400);
+        HistoryIndex::open_or_create(path, 15_000_000, date).unwrap()
+    }
+
+    #[test]
+    fn test_lifecycle_events() {
+        let main_dir = tempfile::tempdir().unwrap();
+        let schema = DomainSchema::new();
+        let main = Index::create_in_dir(main_dir.path(), schema.schema.clone()).unwrap();
+        let mut writer: IndexWriter = main.writer(15_000_000).unwrap();
+        let live = Domain::new("live.com").normalize().unwrap();
+        writer.add_document(schema.to_document(&live)).unwrap();
+        writer.commit().unwrap();
+        let main = main.reader().unwrap().searcher();
+
+        let dir = tempfile::tempdir().unwrap();
+        let coffee = Domain::new("coffee.com").normalize().unwrap();
+
+        // First sync: coffee.com registered, live.com dropped and re-added
+        let mut history = open(dir.path(), 1);
+        history.record_added(&coffee, &main, &schema).unwrap();
+        history.record_dropped(&live).unwrap();
+        history.record_added(&live, &main, &schema).unwrap();
+        assert_eq!(history.commit().unwrap(), 3);
+
+        // Second sync: coffee.com dropped, then registered again
+        let mut history = open(dir.path(), 2);
+        history.record_dropped(&coffee).unwrap();
+        history.commit().unwrap();
+        let mut history = open(dir.path(), 3);
+        history.record_added(&live, &main, &schema).unwrap();
+        history.record_added(&coffee, &main, &schema).unwrap();
+        assert_eq!(history.commit().unwrap(), 1);
+
+        assert_eq!(events(dir.path(), "live.com"), vec!["dropped", "re_registered"]);
+        assert_eq!(
+            events(dir.path(), "coffee.com"),
+            vec!["first_seen", "dropped", "re_registered"]
+        );
+    }
+}
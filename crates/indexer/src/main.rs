@@ -1,18 +1,41 @@
-use anyhow::Result;
+use crate::error::{Error, Result};
+use crate::events::PipelineEvent;
 use clap::{Parser, Subcommand};
-use domain_core::Config;
-use std::path::PathBuf;
-use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use domain_core::{synthetic, telemetry, Config, MergePressure, Taxonomy};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+use tracing::{info, warn};
 
+mod bench;
 mod daily;
+mod dated;
+mod delta;
+mod error;
+mod events;
+mod export;
 mod full;
+mod history;
+mod maintenance;
+mod offline;
 mod progress;
+mod report;
+mod retokenize;
+mod state;
+mod throttle;
+mod unlock;
+mod watch;
+mod workdir;
 
 #[derive(Parser)]
 #[command(name = "domain-indexer")]
 #[command(about = "Domain search indexer for Tantivy", version)]
 struct Cli {
+    /// Run without network access: local input files, segmentation from
+    /// WORD_LIST_FILE, and no option that needs a remote service
+    #[arg(long, global = true)]
+    offline: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -40,6 +63,28 @@ enum Commands {
         /// Commit interval (number of documents)
         #[arg(long, default_value = "1000000")]
         commit_interval: usize,
+
+        /// Threads normalizing and filtering domains (default: one per core)
+        #[arg(long)]
+        cpu_threads: Option<usize>,
+
+        /// Previous index whose stored tokens are reused, so only domains it
+        /// doesn't have are segmented
+        #[arg(long)]
+        reuse_tokens: Option<PathBuf>,
+
+        /// Index only these TLDs, comma-separated (default: INCLUDE_TLDS)
+        #[arg(long, value_delimiter = ',')]
+        include_tlds: Option<Vec<String>>,
+
+        /// Skip these TLDs, comma-separated (default: EXCLUDE_TLDS)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tlds: Option<Vec<String>>,
+
+        /// Continue the build that stopped at the checkpoint in STATE_DIR,
+        /// from the same input
+        #[arg(long)]
+        resume: bool,
     },
 
     /// Apply daily incremental updates (adds and deletes)
@@ -59,6 +104,131 @@ enum Commands {
         /// Path to the existing index directory
         #[arg(short, long)]
         index: Option<PathBuf>,
+
+        /// Keep the downloaded files in their directory under WORK_DIR
+        #[arg(long)]
+        keep_temp: bool,
+
+        /// Index only these TLDs, comma-separated (default: INCLUDE_TLDS)
+        #[arg(long, value_delimiter = ',')]
+        include_tlds: Option<Vec<String>>,
+
+        /// Skip these TLDs, comma-separated (default: EXCLUDE_TLDS)
+        #[arg(long, value_delimiter = ',')]
+        exclude_tlds: Option<Vec<String>>,
+    },
+
+    /// Compare two full zonefiles and write the added and removed domains,
+    /// as input for `daily` when the provider has no daily files
+    Delta {
+        /// Earlier full zonefile
+        #[arg(long)]
+        old: PathBuf,
+
+        /// Later full zonefile
+        #[arg(long)]
+        new: PathBuf,
+
+        /// Output file for domains only in the later zonefile
+        #[arg(long)]
+        out_adds: PathBuf,
+
+        /// Output file for domains only in the earlier zonefile
+        #[arg(long)]
+        out_removes: PathBuf,
+
+        /// Memory for sorting, in MB; larger files are sorted in chunks on disk
+        #[arg(long, default_value = "1024")]
+        memory_mb: usize,
+
+        /// Directory for the sorted chunks (needs about the size of both files)
+        #[arg(long)]
+        temp_dir: Option<PathBuf>,
+    },
+
+    /// Sort and deduplicate a domain list larger than memory
+    Sort {
+        /// Domain list, one per line
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output file for the sorted, distinct, lowercased domains
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Memory for sorting, in MB; larger files are sorted in chunks on disk
+        #[arg(long, default_value = "1024")]
+        memory_mb: usize,
+
+        /// Directory for the sorted chunks (needs about the size of the input)
+        #[arg(long)]
+        temp_dir: Option<PathBuf>,
+    },
+
+    /// Export the terms of an indexed field with their document frequencies
+    ExportTerms {
+        /// Field to export (e.g., tokens, tld, lang, category)
+        #[arg(long, default_value = "tokens")]
+        field: String,
+
+        /// Skip terms in fewer documents
+        #[arg(long, default_value = "1")]
+        min_df: u64,
+
+        /// Count only documents under this TLD (slower: reads every posting)
+        #[arg(long)]
+        tld: Option<String>,
+
+        /// Keep only the most frequent terms
+        #[arg(long)]
+        limit: Option<usize>,
+
+        #[arg(long, value_enum, default_value = "csv")]
+        format: export::Format,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Path to the index directory
+        #[arg(short, long)]
+        index: Option<PathBuf>,
+    },
+
+    /// Report label length, digit, hyphen, bigram and TLD distributions
+    Report {
+        /// Bigrams and TLDs to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: report::Format,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Path to the index directory
+        #[arg(short, long)]
+        index: Option<PathBuf>,
+    },
+
+    /// Release the writer lock of an index left behind by a crashed run
+    Unlock {
+        /// Remove a held lock whose holder can't be found running
+        #[arg(long)]
+        force: bool,
+
+        /// Path to the index directory
+        #[arg(short, long)]
+        index: Option<PathBuf>,
+    },
+
+    /// Segment again the labels of domains stored without tokens
+    Retokenize {
+        /// Path to the index directory
+        #[arg(short, long)]
+        index: Option<PathBuf>,
     },
 
     /// Show index statistics
@@ -68,6 +238,50 @@ enum Commands {
         index: Option<PathBuf>,
     },
 
+    /// Replay a query log against an index and report latency per search mode
+    Bench {
+        /// Query log: one query per line, as keywords or a logged
+        /// `/search?q=...` request
+        #[arg(short, long)]
+        queries: PathBuf,
+
+        /// Path to the index directory
+        #[arg(short, long)]
+        index: Option<PathBuf>,
+
+        /// Comma-separated modes: search, dedupe, stream, hacks, facets
+        #[arg(long, default_value = "search,dedupe,stream,hacks,facets")]
+        modes: String,
+
+        /// Results per query, unless the logged request sets `limit`
+        #[arg(long, default_value = "50")]
+        limit: usize,
+
+        /// Times the whole log is replayed per mode
+        #[arg(long, default_value = "1")]
+        rounds: usize,
+    },
+
+    /// Build a deterministic index of generated domains, for tests and
+    /// benchmarks without a zonefile or the word splitter
+    GenTestIndex {
+        /// Domains to generate (e.g., 50000, 200k, 1M)
+        #[arg(long, default_value = "100k", value_parser = parse_count)]
+        docs: usize,
+
+        /// Path to the output index directory (must not hold an index)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Generator seed; the same seed builds the same index
+        #[arg(long, default_value = "42")]
+        seed: u64,
+
+        /// Taxonomy file to tag the domains with (see TAXONOMY_FILE)
+        #[arg(long)]
+        taxonomy: Option<PathBuf>,
+    },
+
     /// Optimize/merge index segments
     Optimize {
         /// Path to the index directory
@@ -77,17 +291,80 @@ enum Commands {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
+async fn main() -> ExitCode {
     let cli = Cli::parse();
-    let config = Config::from_env()?;
+
+    // Initialize logging and trace export; spans are flushed on exit
+    let telemetry = match telemetry::init("domain-indexer", "info") {
+        Ok(telemetry) => telemetry,
+        Err(e) => return fail(e.into()),
+    };
+    let result = run(cli).await;
+    drop(telemetry);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => fail(e),
+    }
+}
+
+/// Report a failed run, exiting with the code of its class
+fn fail(e: Error) -> ExitCode {
+    eprintln!("Error: {}", e);
+    ExitCode::from(e.exit_code())
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    // Need no configuration, so they run without the word splitter set up
+    match &cli.command {
+        Commands::GenTestIndex { docs, output, seed, taxonomy } => {
+            let taxonomy = match taxonomy {
+                Some(path) => Taxonomy::load(path)?,
+                None => Taxonomy::default(),
+            };
+            info!(docs, seed, output = ?output, "Generating test index");
+            let index = synthetic::build_index(Some(output), *docs, *seed, &taxonomy)?;
+            let documents = index.reader()?.searcher().num_docs();
+            info!(documents, "Test index built");
+            return Ok(());
+        }
+        Commands::Delta {
+            old,
+            new,
+            out_adds,
+            out_removes,
+            memory_mb,
+            temp_dir,
+        } => {
+            let temp_dir = temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+            let memory = memory_mb * 1024 * 1024;
+            delta::run(old, new, out_adds, out_removes, memory, &temp_dir)?;
+            return Ok(());
+        }
+        Commands::Sort {
+            input,
+            output,
+            memory_mb,
+            temp_dir,
+        } => {
+            let temp_dir = temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+            info!(input = ?input, output = ?output, "Sorting domains");
+            let memory = memory_mb * 1024 * 1024;
+            let count = zonefile_client::sort_file(input, output, memory, &temp_dir)?;
+            info!(domains = count, "Sorted");
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let mut config = if cli.offline {
+        let config = Config::from_env_offline()?;
+        let otlp_endpoint = std::env::var(telemetry::OTLP_ENDPOINT_ENV).ok();
+        offline::check(&config, &cli.command, otlp_endpoint.as_deref().filter(|e| !e.is_empty()))?;
+        info!("Running offline");
+        config
+    } else {
+        Config::from_env()?
+    };
 
     match cli.command {
         Commands::Full {
@@ -96,20 +373,53 @@ async fn main() -> Result<()> {
             output,
             heap_gb,
             commit_interval,
+            cpu_threads,
+            reuse_tokens,
+            include_tlds,
+            exclude_tlds,
+            resume,
         } => {
+            override_tlds(&mut config, include_tlds, exclude_tlds);
+            if let Some(threads) = cpu_threads {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build_global()
+                    .map_err(|e| Error::Config(format!("--cpu-threads: {}", e)))?;
+                info!(threads, "Normalizing on a fixed number of threads");
+            }
             let output_path = output.unwrap_or_else(|| config.index_path.clone());
             let heap_size = heap_gb * 1024 * 1024 * 1024;
+            state::prepare(&config, "full", &output_path, resume)?;
 
-            if download {
+            let started = Instant::now();
+            let result = if download {
                 info!("Downloading full zonefile from API...");
-                full::run_with_download(&config, &output_path, heap_size, commit_interval).await?;
+                let previous = reuse_tokens.as_deref();
+                let work_dir = workdir::RunDir::create(&config, "full", false)?;
+                full::run_with_download(
+                    &config,
+                    &output_path,
+                    heap_size,
+                    commit_interval,
+                    previous,
+                    work_dir.path(),
+                )
+                .await
             } else {
                 let input_path = input.ok_or_else(|| {
-                    anyhow::anyhow!("--input is required when not using --download")
+                    Error::Config("--input is required when not using --download".to_string())
                 })?;
                 info!(input = ?input_path, output = ?output_path, "Building full index");
-                full::run(&config, &input_path, &output_path, heap_size, commit_interval).await?;
-            }
+                let previous = reuse_tokens.as_deref();
+                full::run(&config, &input_path, &output_path, heap_size, commit_interval, previous)
+                    .await
+            };
+            let finished = |&documents: &u64| PipelineEvent::FullBuildFinished {
+                index: output_path.clone(),
+                documents,
+                seconds: started.elapsed().as_secs_f64(),
+            };
+            events::report(&config, "full", &output_path, result, finished).await?;
         }
 
         Commands::Daily {
@@ -117,16 +427,67 @@ async fn main() -> Result<()> {
             removes,
             download,
             index,
+            keep_temp,
+            include_tlds,
+            exclude_tlds,
         } => {
+            override_tlds(&mut config, include_tlds, exclude_tlds);
             let index_path = index.unwrap_or_else(|| config.index_path.clone());
+            // Reject a malformed OPTIMIZE_WINDOW before syncing
+            maintenance::MaintenanceWindow::from_config(&config)?;
 
-            if download {
+            let started = Instant::now();
+            let result = if download {
                 info!("Downloading daily updates from API...");
-                daily::run_with_download(&config, &index_path).await?;
+                let work_dir = workdir::RunDir::create(&config, "daily", keep_temp)?;
+                daily::run_with_download(&config, &index_path, work_dir.path()).await
             } else {
                 info!(index = ?index_path, "Applying daily updates");
-                daily::run(&config, adds, removes, &index_path).await?;
-            }
+                daily::run(&config, adds, removes, &index_path).await
+            };
+            let finished = |counts: &daily::SyncCounts| PipelineEvent::DailySyncFinished {
+                index: index_path.clone(),
+                added: counts.added,
+                deleted: counts.deleted,
+                documents: counts.documents,
+                seconds: started.elapsed().as_secs_f64(),
+            };
+            events::report(&config, "daily", &index_path, result, finished).await?;
+            maintenance::auto_optimize(&config, &index_path).await?;
+        }
+
+        Commands::ExportTerms {
+            field,
+            min_df,
+            tld,
+            limit,
+            format,
+            output,
+            index,
+        } => {
+            let index_path = index.unwrap_or_else(|| config.index_path.clone());
+            export::run(&index_path, &field, tld.as_deref(), min_df, limit, format, &output)?;
+        }
+
+        Commands::Report {
+            top,
+            format,
+            output,
+            index,
+        } => {
+            let index_path = index.unwrap_or_else(|| config.index_path.clone());
+            report::run(&index_path, top, format, output.as_deref())?;
+        }
+
+        Commands::Unlock { force, index } => {
+            let index_path = index.unwrap_or_else(|| config.index_path.clone());
+            unlock::run(&index_path, force)?;
+        }
+
+        Commands::Retokenize { index } => {
+            let index_path = index.unwrap_or_else(|| config.index_path.clone());
+            info!(index = ?index_path, "Retokenizing documents without tokens");
+            retokenize::run(&config, &index_path).await?;
         }
 
         Commands::Stats { index } => {
@@ -134,6 +495,22 @@ async fn main() -> Result<()> {
             show_stats(&index_path)?;
         }
 
+        Commands::Bench {
+            queries,
+            index,
+            modes,
+            limit,
+            rounds,
+        } => {
+            let index_path = index.unwrap_or_else(|| config.index_path.clone());
+            let modes = bench::parse_modes(&modes)?;
+            bench::run(&config, &index_path, &queries, &modes, limit, rounds)?;
+        }
+
+        Commands::GenTestIndex { .. } | Commands::Delta { .. } | Commands::Sort { .. } => {
+            unreachable!("handled before loading the config")
+        }
+
         Commands::Optimize { index } => {
             let index_path = index.unwrap_or_else(|| config.index_path.clone());
             optimize_index(&index_path)?;
@@ -143,19 +520,64 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Replace `INCLUDE_TLDS` and `EXCLUDE_TLDS` with the lists given on the
+/// command line
+fn override_tlds(config: &mut Config, include: Option<Vec<String>>, exclude: Option<Vec<String>>) {
+    if let Some(include) = include {
+        config.include_tlds = include;
+    }
+    if let Some(exclude) = exclude {
+        config.exclude_tlds = exclude;
+    }
+}
+
+/// Parse a count with an optional `k` or `M` suffix
+fn parse_count(count: &str) -> Result<usize, String> {
+    let (digits, scale) = match count.strip_suffix(['k', 'K']) {
+        Some(digits) => (digits, 1_000),
+        None => match count.strip_suffix('M') {
+            Some(digits) => (digits, 1_000_000),
+            None => (count, 1),
+        },
+    };
+    digits
+        .parse::<usize>()
+        .map(|n| n * scale)
+        .map_err(|_| format!("invalid count '{}' (e.g. 50000, 200k, 1M)", count))
+}
+
 fn show_stats(index_path: &PathBuf) -> Result<()> {
-    use domain_core::DomainSchema;
     use tantivy::Index;
 
-    let schema = DomainSchema::new();
     let index = Index::open_in_dir(index_path)?;
     let reader = index.reader()?;
     let searcher = reader.searcher();
 
     let num_docs = searcher.num_docs();
     let num_segments = searcher.segment_readers().len();
+    let pressure = MergePressure::from_searcher(&searcher);
 
-    info!(documents = num_docs, segments = num_segments, "Index statistics");
+    info!(
+        documents = num_docs,
+        segments = num_segments,
+        deleted = pressure.deleted_documents(),
+        "Index statistics"
+    );
+    for segment in &pressure.segments {
+        info!(
+            segment = %segment.id,
+            documents = segment.documents,
+            deleted = segment.deleted_documents,
+            deleted_ratio = format!("{:.3}", segment.deleted_ratio()),
+            "Segment"
+        );
+    }
+    if pressure.optimize_recommended() {
+        warn!(
+            deleted_ratio = format!("{:.3}", pressure.deleted_ratio()),
+            "Deleted documents slow searches down; run `optimize` to merge them away"
+        );
+    }
 
     // Show space usage
     let mut total_size: u64 = 0;
@@ -174,16 +596,10 @@ fn show_stats(index_path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn optimize_index(index_path: &PathBuf) -> Result<()> {
-    use tantivy::{Index, TantivyDocument};
-
+fn optimize_index(index_path: &Path) -> Result<()> {
     info!("Optimizing index...");
 
-    let index = Index::open_in_dir(index_path)?;
-    let mut writer = index.writer::<TantivyDocument>(500 * 1024 * 1024)?; // 500MB heap
-
-    // Commit to finalize any pending merges
-    writer.commit()?;
+    maintenance::merge_segments(index_path)?;
 
     info!("Index optimization complete");
 
@@ -0,0 +1,247 @@
+use crate::error::{Error, Result};
+use crate::events::{self, PipelineEvent};
+use domain_core::{writer_lock, Config, MergePressure};
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tantivy::{Index, IndexWriter};
+use tracing::info;
+
+/// Heap of the writer merging segments
+const MERGE_HEAP: usize = 500 * 1024 * 1024;
+
+/// Minutes of the day (UTC) in which merges may run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    start: u32,
+    end: u32,
+}
+
+impl MaintenanceWindow {
+    /// Parse `HH:MM-HH:MM`; a window ending before it starts spans midnight
+    pub fn parse(window: &str) -> Result<Self> {
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| {
+                Error::Config(format!("maintenance window {window:?} is not HH:MM-HH:MM"))
+            })?;
+        Ok(Self {
+            start: minute_of_day(start.trim())?,
+            end: minute_of_day(end.trim())?,
+        })
+    }
+
+    /// `OPTIMIZE_WINDOW`, None when unset
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        config
+            .optimize_window
+            .as_deref()
+            .map(Self::parse)
+            .transpose()
+    }
+
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+fn minute_of_day(time: &str) -> Result<u32> {
+    let parsed = time.split_once(':').and_then(|(hours, minutes)| {
+        Some((hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?))
+    });
+    match parsed {
+        Some((hours, minutes)) if hours < 24 && minutes < 60 => Ok(hours * 60 + minutes),
+        _ => Err(Error::Config(format!("{time:?} is not a time of day (HH:MM)"))),
+    }
+}
+
+fn now_minute() -> u32 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    (secs % 86_400 / 60) as u32
+}
+
+/// Document and segment counts around a merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IndexCounts {
+    pub documents: u64,
+    pub deleted_documents: u64,
+    pub segments: usize,
+}
+
+impl IndexCounts {
+    fn of(pressure: &MergePressure) -> Self {
+        Self {
+            documents: pressure.documents(),
+            deleted_documents: pressure.deleted_documents(),
+            segments: pressure.segments.len(),
+        }
+    }
+}
+
+/// Merge every segment of an index into one, purging deleted documents
+pub fn merge_segments(index_path: &Path) -> Result<()> {
+    let index = Index::open_in_dir(index_path)?;
+    let mut writer: IndexWriter = writer_lock::open_writer(&index, index_path, MERGE_HEAP)?;
+
+    let segments = index.searchable_segment_ids()?;
+    if segments.len() > 1 || has_deletes(&index)? {
+        info!(segments = segments.len(), "Merging segments...");
+        writer.merge(&segments).wait()?;
+    }
+    writer.wait_merging_threads()?;
+    Ok(())
+}
+
+fn has_deletes(index: &Index) -> Result<bool> {
+    let searcher = index.reader()?.searcher();
+    Ok(MergePressure::from_searcher(&searcher).deleted_documents() > 0)
+}
+
+/// Merge the index after a daily sync when it crosses `OPTIMIZE_MAX_SEGMENTS`
+/// or `OPTIMIZE_DELETED_RATIO` inside `OPTIMIZE_WINDOW`
+///
+/// Returns whether a merge ran. A failed webhook is logged, not returned.
+pub async fn auto_optimize(config: &Config, index_path: &Path) -> Result<bool> {
+    let Some(window) = MaintenanceWindow::from_config(config)? else {
+        return Ok(false);
+    };
+    run_if_due(config, window, now_minute(), index_path).await
+}
+
+async fn run_if_due(
+    config: &Config,
+    window: MaintenanceWindow,
+    minute: u32,
+    index_path: &Path,
+) -> Result<bool> {
+    let before = pressure(index_path)?;
+    if !before.crosses(config.optimize_max_segments, config.optimize_deleted_ratio) {
+        return Ok(false);
+    }
+    if !window.contains(minute) {
+        info!(
+            segments = before.segments.len(),
+            deleted_ratio = format!("{:.3}", before.deleted_ratio()),
+            "Index needs optimizing, waiting for the maintenance window"
+        );
+        return Ok(false);
+    }
+
+    let before = IndexCounts::of(&before);
+    info!(
+        documents = before.documents,
+        deleted = before.deleted_documents,
+        segments = before.segments,
+        "Optimizing index in the maintenance window"
+    );
+    let started = Instant::now();
+    merge_segments(index_path)?;
+    let seconds = started.elapsed().as_secs_f64();
+
+    let after = IndexCounts::of(&pressure(index_path)?);
+    info!(
+        documents = after.documents,
+        deleted = after.deleted_documents,
+        segments = after.segments,
+        seconds,
+        "Index optimized"
+    );
+
+    let optimized = PipelineEvent::IndexOptimized {
+        index: index_path.to_path_buf(),
+        before,
+        after,
+        seconds,
+    };
+    events::notify(config, &optimized).await;
+    Ok(true)
+}
+
+fn pressure(index_path: &Path) -> Result<MergePressure> {
+    let index = Index::open_in_dir(index_path)?;
+    let searcher = index.reader()?.searcher();
+    Ok(MergePressure::from_searcher(&searcher))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::{Domain, DomainSchema};
+    use tantivy::Term;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_maintenance_window() {
+        let night = MaintenanceWindow::parse("23:30-02:00").unwrap();
+        assert!(night.contains(23 * 60 + 45));
+        assert!(night.contains(60));
+        assert!(!night.contains(2 * 60));
+        assert!(!night.contains(12 * 60));
+
+        let morning = MaintenanceWindow::parse("03:00 - 05:00").unwrap();
+        assert!(morning.contains(4 * 60));
+        assert!(!morning.contains(5 * 60));
+
+        assert!(MaintenanceWindow::parse("03:00").is_err());
+        assert!(MaintenanceWindow::parse("25:00-03:00").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_auto_optimize_merges_in_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = DomainSchema::new();
+        let index = Index::create_in_dir(dir.path(), schema.schema.clone()).unwrap();
+        let mut writer: IndexWriter = index.writer(15_000_000).unwrap();
+        writer.set_merge_policy(Box::new(tantivy::indexer::NoMergePolicy));
+        // Three segments, the first of them with a deleted document
+        for names in [&["a", "b"][..], &["c"], &["d"]] {
+            for name in names {
+                let domain = Domain::new(format!("{name}.com")).normalize().unwrap();
+                writer.add_document(schema.to_document(&domain)).unwrap();
+            }
+            writer.commit().unwrap();
+        }
+        writer.delete_term(Term::from_field_text(schema.domain_exact, "a.com"));
+        writer.commit().unwrap();
+        drop(writer);
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/events"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut config = Config::test();
+        config.optimize_max_segments = 2;
+        config.pipeline_webhook_urls = vec![format!("{}/events", server.uri())];
+        let window = MaintenanceWindow::parse("02:00-04:00").unwrap();
+
+        assert!(!run_if_due(&config, window, 12 * 60, dir.path())
+            .await
+            .unwrap());
+        assert_eq!(pressure(dir.path()).unwrap().segments.len(), 3);
+
+        assert!(run_if_due(&config, window, 3 * 60, dir.path())
+            .await
+            .unwrap());
+        let after = pressure(dir.path()).unwrap();
+        assert_eq!(after.segments.len(), 1);
+        assert_eq!(after.documents(), 3);
+        assert_eq!(after.deleted_documents(), 0);
+
+        // Below both thresholds now
+        assert!(!run_if_due(&config, window, 3 * 60, dir.path())
+            .await
+            .unwrap());
+    }
+}
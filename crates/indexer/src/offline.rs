@@ -0,0 +1,113 @@
+use crate::Commands;
+use crate::error::{Error, Result};
+use domain_core::Config;
+
+/// Refuse a command that would need the network in `--offline` mode
+///
+/// Everything a command would reach over the network is reported at once,
+/// before any work starts: zonefile downloads, the word splitter API (a
+/// `WORD_LIST_FILE` replaces it), watch rules in Redis with their webhooks,
+/// the pipeline webhooks and trace export to `otlp_endpoint`.
+pub fn check(config: &Config, command: &Commands, otlp_endpoint: Option<&str>) -> Result<()> {
+    let mut problems = Vec::new();
+
+    let (download, segments, watches) = match command {
+        Commands::Full { download, .. } => (*download, true, false),
+        Commands::Daily { download, .. } => (*download, true, true),
+        Commands::Retokenize { .. } => (false, true, false),
+        _ => (false, false, false),
+    };
+    if download {
+        problems.push("--download fetches zonefiles from the API; pass local files instead");
+    }
+    if segments && config.word_list_file.is_none() {
+        problems.push("WORD_LIST_FILE is needed to segment labels without the word splitter API");
+    }
+    if watches && config.redis_url.is_some() && config.watchlist_key.is_some() {
+        problems.push("watch rules are read from Redis; unset WATCHLIST_KEY");
+    }
+    if watches && config.watchlist_webhook_url.is_some() {
+        problems.push("watch alerts are posted to WATCHLIST_WEBHOOK_URL; unset it");
+    }
+    let events = matches!(command, Commands::Full { .. } | Commands::Daily { .. });
+    if events && !config.pipeline_webhook_urls.is_empty() {
+        problems.push("run outcomes are posted to PIPELINE_WEBHOOK_URLS; unset it");
+    }
+    if otlp_endpoint.is_some() {
+        problems.push("spans are exported to OTEL_EXPORTER_OTLP_ENDPOINT; unset it");
+    }
+
+    if !problems.is_empty() {
+        let problems = problems.join("\n  - ");
+        return Err(Error::Config(format!("Cannot run offline:\n  - {}", problems)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn full(download: bool) -> Commands {
+        Commands::Full {
+            input: Some(PathBuf::from("domains.txt")),
+            download,
+            output: None,
+            heap_gb: 1,
+            commit_interval: 1000,
+            cpu_threads: None,
+            reuse_tokens: None,
+            include_tlds: None,
+            exclude_tlds: None,
+            resume: false,
+        }
+    }
+
+    fn daily() -> Commands {
+        Commands::Daily {
+            adds: Some(PathBuf::from("adds.txt")),
+            removes: None,
+            download: false,
+            index: None,
+            keep_temp: false,
+            include_tlds: None,
+            exclude_tlds: None,
+        }
+    }
+
+    #[test]
+    fn test_local_files_and_word_list_run_offline() {
+        let mut config = Config::test();
+        config.word_list_file = Some(PathBuf::from("words.txt"));
+        config.redis_url = Some("redis://localhost".to_string());
+
+        assert!(check(&config, &full(false), None).is_ok());
+        assert!(check(&config, &daily(), None).is_ok());
+        assert!(check(&Config::test(), &Commands::Stats { index: None }, None).is_ok());
+    }
+
+    #[test]
+    fn test_network_options_are_refused() {
+        let error = check(&Config::test(), &full(true), Some("http://collector:4317"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("--download"));
+        assert!(error.contains("WORD_LIST_FILE"));
+        assert!(error.contains("OTEL_EXPORTER_OTLP_ENDPOINT"));
+        let retokenize = Commands::Retokenize { index: None };
+        assert!(check(&Config::test(), &retokenize, None).is_err());
+
+        let mut config = Config::test();
+        config.word_list_file = Some(PathBuf::from("words.txt"));
+        config.redis_url = Some("redis://localhost".to_string());
+        config.watchlist_key = Some("watch".to_string());
+        let error = check(&config, &daily(), None).unwrap_err().to_string();
+        assert!(error.contains("WATCHLIST_KEY"));
+        assert!(check(&config, &full(false), None).is_ok());
+
+        config.pipeline_webhook_urls = vec!["http://localhost/events".to_string()];
+        let error = check(&config, &full(false), None).unwrap_err().to_string();
+        assert!(error.contains("PIPELINE_WEBHOOK_URLS"));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+use tantivy::schema::{Facet, IndexRecordOption};
+use tantivy::{DocSet, Index, SegmentReader, TERMINATED};
+
+/// Output format of `report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Markdown,
+}
+
+/// Label statistics of an index, for checking a rebuild
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Report {
+    pub documents: u64,
+    /// Domains per label length
+    pub label_length: BTreeMap<u64, u64>,
+    pub mean_length: f64,
+    pub hyphenated: u64,
+    pub with_digits: u64,
+    /// Domains stored without tokens
+    pub unsegmented: u64,
+    /// Domains per `seg_version`, empty for indexes built before it was
+    /// stored
+    pub seg_versions: BTreeMap<u64, u64>,
+    /// Most common character pairs within labels
+    pub top_bigrams: Vec<Bigram>,
+    /// Largest TLDs first
+    pub tlds: Vec<TldReport>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Bigram {
+    pub bigram: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct TldReport {
+    pub tld: String,
+    pub documents: u64,
+    pub mean_length: f64,
+    pub hyphenated: u64,
+    pub with_digits: u64,
+}
+
+/// Counters of one TLD while scanning
+#[derive(Default)]
+struct TldCounts {
+    documents: u64,
+    length: u64,
+    hyphenated: u64,
+    with_digits: u64,
+}
+
+/// Build the report of an index, keeping the `top` bigrams and TLDs
+///
+/// Lengths, hyphens, TLDs and segmentation versions come from the fast
+/// fields, and unsegmented domains from the field norms of `tokens`. Digits
+/// and bigrams need the labels themselves, which are read from the
+/// `domain_exact` term dictionary rather than the document store.
+pub fn build(index: &Index, top: usize) -> Result<Report> {
+    let searcher = index.reader()?.searcher();
+    let domain_exact = index.schema().get_field("domain_exact")?;
+    let tokens = index.schema().get_field("tokens")?;
+
+    let mut lengths: BTreeMap<u64, u64> = BTreeMap::new();
+    let mut seg_versions: BTreeMap<u64, u64> = BTreeMap::new();
+    let mut unsegmented = 0;
+    let mut tlds: HashMap<String, TldCounts> = HashMap::new();
+    let mut bigrams: HashMap<[char; 2], u64> = HashMap::new();
+    let mut facet = Facet::root();
+
+    for segment in searcher.segment_readers() {
+        let len = segment.fast_fields().u64("len")?;
+        let has_hyphen = segment.fast_fields().u64("has_hyphen")?;
+        let tld = segment.facet_reader("tld")?;
+        let seg_version = segment.fast_fields().u64("seg_version").ok();
+        let token_counts = segment.get_fieldnorms_reader(tokens)?;
+
+        for doc in segment.doc_ids_alive() {
+            let length = len.first(doc).unwrap_or(0);
+            *lengths.entry(length).or_default() += 1;
+            if let Some(version) = seg_version.as_ref().and_then(|column| column.first(doc)) {
+                *seg_versions.entry(version).or_default() += 1;
+            }
+            unsegmented += u64::from(token_counts.fieldnorm(doc) == 0);
+
+            let name = match tld.facet_ords(doc).next() {
+                Some(ord) => {
+                    tld.facet_from_ord(ord, &mut facet)?;
+                    facet.to_path().join("/")
+                }
+                None => String::new(),
+            };
+            let counts = tlds.entry(name).or_default();
+            counts.documents += 1;
+            counts.length += length;
+            counts.hyphenated += u64::from(has_hyphen.first(doc).unwrap_or(0) > 0);
+        }
+
+        let inverted_index = segment.inverted_index(domain_exact)?;
+        let mut terms = inverted_index.terms().stream()?;
+        while terms.advance() {
+            if segment.has_deletes() && !is_alive(segment, &inverted_index, terms.value())? {
+                continue;
+            }
+            let Ok(domain) = std::str::from_utf8(terms.key()) else {
+                continue;
+            };
+            let Some((label, tld)) = domain.rsplit_once('.') else {
+                continue;
+            };
+            if label.chars().any(|c| c.is_ascii_digit()) {
+                if let Some(counts) = tlds.get_mut(tld) {
+                    counts.with_digits += 1;
+                }
+            }
+            let chars: Vec<char> = label.chars().collect();
+            for pair in chars.windows(2).filter(|pair| !pair.contains(&'-')) {
+                *bigrams.entry([pair[0], pair[1]]).or_default() += 1;
+            }
+        }
+    }
+
+    let mut report = Report {
+        label_length: lengths,
+        unsegmented,
+        seg_versions,
+        ..Report::default()
+    };
+    let mut total_length = 0;
+    for counts in tlds.values() {
+        report.documents += counts.documents;
+        report.hyphenated += counts.hyphenated;
+        report.with_digits += counts.with_digits;
+        total_length += counts.length;
+    }
+    report.mean_length = mean(total_length, report.documents);
+
+    let mut bigrams: Vec<Bigram> = bigrams
+        .into_iter()
+        .map(|(pair, count)| Bigram {
+            bigram: pair.iter().collect(),
+            count,
+        })
+        .collect();
+    bigrams.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.bigram.cmp(&b.bigram)));
+    bigrams.truncate(top);
+    report.top_bigrams = bigrams;
+
+    let mut tlds: Vec<TldReport> = tlds
+        .into_iter()
+        .map(|(tld, counts)| TldReport {
+            tld,
+            documents: counts.documents,
+            mean_length: mean(counts.length, counts.documents),
+            hyphenated: counts.hyphenated,
+            with_digits: counts.with_digits,
+        })
+        .collect();
+    tlds.sort_by(|a, b| b.documents.cmp(&a.documents).then_with(|| a.tld.cmp(&b.tld)));
+    tlds.truncate(top);
+    report.tlds = tlds;
+
+    Ok(report)
+}
+
+/// Whether the one document holding a `domain_exact` term is still alive
+fn is_alive(
+    segment: &SegmentReader,
+    inverted_index: &tantivy::InvertedIndexReader,
+    term_info: &tantivy::postings::TermInfo,
+) -> Result<bool> {
+    let mut postings =
+        inverted_index.read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+    while postings.doc() != TERMINATED {
+        if !segment.is_deleted(postings.doc()) {
+            return Ok(true);
+        }
+        postings.advance();
+    }
+    Ok(false)
+}
+
+fn mean(total: u64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total as f64 / count as f64
+    }
+}
+
+/// Share of `count` in `total`, as a percentage for display
+fn percent(count: u64, total: u64) -> f64 {
+    mean(count * 100, total)
+}
+
+/// Render the report as Markdown tables
+pub fn markdown(report: &Report) -> String {
+    let mut out = String::new();
+    let total = report.documents;
+    let _ = writeln!(out, "# Index Report\n");
+    let _ = writeln!(out, "| Domains | Mean length | Hyphenated | With digits |");
+    let _ = writeln!(out, "|---------|-------------|------------|-------------|");
+    let _ = writeln!(
+        out,
+        "| {} | {:.1} | {} ({:.1}%) | {} ({:.1}%) |\n",
+        total,
+        report.mean_length,
+        report.hyphenated,
+        percent(report.hyphenated, total),
+        report.with_digits,
+        percent(report.with_digits, total),
+    );
+
+    let _ = writeln!(out, "## Label Length\n");
+    let _ = writeln!(out, "| Length | Domains | Share |");
+    let _ = writeln!(out, "|--------|---------|-------|");
+    for (length, count) in &report.label_length {
+        let _ = writeln!(out, "| {} | {} | {:.1}% |", length, count, percent(*count, total));
+    }
+
+    if !report.seg_versions.is_empty() {
+        let _ = writeln!(out, "\n## Segmentation\n");
+        let unsegmented = report.unsegmented;
+        let share = percent(unsegmented, total);
+        let _ = writeln!(out, "Without tokens: {} ({:.1}%)\n", unsegmented, share);
+        let _ = writeln!(out, "| Version | Domains | Share |");
+        let _ = writeln!(out, "|---------|---------|-------|");
+        for (version, count) in &report.seg_versions {
+            let _ = writeln!(out, "| {} | {} | {:.1}% |", version, count, percent(*count, total));
+        }
+    }
+
+    let _ = writeln!(out, "\n## Top Bigrams\n");
+    let _ = writeln!(out, "| Bigram | Count |");
+    let _ = writeln!(out, "|--------|-------|");
+    for bigram in &report.top_bigrams {
+        let _ = writeln!(out, "| `{}` | {} |", bigram.bigram, bigram.count);
+    }
+
+    let _ = writeln!(out, "\n## TLDs\n");
+    let _ = writeln!(out, "| TLD | Domains | Mean length | Hyphenated | With digits |");
+    let _ = writeln!(out, "|-----|---------|-------------|------------|-------------|");
+    for tld in &report.tlds {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {:.1} | {:.1}% | {:.1}% |",
+            tld.tld,
+            tld.documents,
+            tld.mean_length,
+            percent(tld.hyphenated, tld.documents),
+            percent(tld.with_digits, tld.documents),
+        );
+    }
+    out
+}
+
+/// Write the report of the index at `index_path` to `output`, or stdout
+pub fn run(index_path: &Path, top: usize, format: Format, output: Option<&Path>) -> Result<()> {
+    let index = Index::open_in_dir(index_path)?;
+    let report = build(&index, top)?;
+    let rendered = match format {
+        Format::Json => serde_json::to_string_pretty(&report)? + "\n",
+        Format::Markdown => markdown(&report),
+    };
+    match output {
+        Some(path) => std::fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::{Domain, DomainSchema};
+    use tantivy::Term;
+
+    fn index() -> Index {
+        let schema = DomainSchema::new();
+        let index = Index::create_in_ram(schema.schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+        for domain in ["shop.com", "shop24.com", "pet-shop.com", "shop.io", "gone.com"] {
+            let mut normalized = Domain::new(domain).normalize().unwrap();
+            if domain != "shop.io" {
+                normalized.tokens = vec!["shop".to_string()];
+                normalized.seg_version = 1;
+            }
+            writer.add_document(schema.to_document(&normalized)).unwrap();
+        }
+        writer.commit().unwrap();
+        writer.delete_term(Term::from_field_text(schema.domain_exact, "gone.com"));
+        writer.commit().unwrap();
+        index
+    }
+
+    #[test]
+    fn test_report() {
+        let report = build(&index(), 3).unwrap();
+
+        assert_eq!(report.documents, 4);
+        assert_eq!(report.label_length, BTreeMap::from([(4, 2), (6, 1), (8, 1)]));
+        assert_eq!(report.mean_length, 5.5);
+        assert_eq!(report.hyphenated, 1);
+        assert_eq!(report.with_digits, 1);
+        assert_eq!(report.unsegmented, 1);
+        assert_eq!(report.seg_versions, BTreeMap::from([(0, 1), (1, 3)]));
+        // "sh", "ho" and "op" in every label
+        let bigrams: Vec<(&str, u64)> =
+            report.top_bigrams.iter().map(|b| (b.bigram.as_str(), b.count)).collect();
+        assert_eq!(bigrams, vec![("ho", 4), ("op", 4), ("sh", 4)]);
+        assert_eq!(report.tlds[0].tld, "com");
+        assert_eq!(report.tlds[0].documents, 3);
+        assert_eq!(report.tlds[0].with_digits, 1);
+        assert_eq!(report.tlds[1].tld, "io");
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = markdown(&build(&index(), 10).unwrap());
+        assert!(markdown.contains("| 4 | 5.5 | 1 (25.0%) | 1 (25.0%) |"));
+        assert!(markdown.contains("| com | 3 | 6.0 | 33.3% | 33.3% |"));
+        assert!(markdown.contains("Without tokens: 1 (25.0%)"));
+        assert!(markdown.contains("| 1 | 3 | 75.0% |"));
+    }
+}
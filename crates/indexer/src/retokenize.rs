@@ -0,0 +1,193 @@
+use crate::error::Result;
+use crate::progress::IndexProgress;
+use crate::{full, throttle};
+use domain_core::{writer_lock, Config, Domain, DomainSchema, LabelValidation, NormalizedDomain};
+use std::path::Path;
+use tantivy::schema::Value;
+use tantivy::{DocAddress, IndexWriter, Searcher, TantivyDocument, Term};
+use tracing::{debug, info};
+
+/// Heap of the writer replacing documents
+const WRITER_HEAP: usize = 500 * 1024 * 1024;
+
+/// Documents replaced between two commits
+const COMMIT_INTERVAL: u64 = 100_000;
+
+/// How a `retokenize` run went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    /// Documents stored without tokens or with an older segmentation version
+    pub found: u64,
+    /// Of those, documents written again with the tokens they got
+    pub retokenized: u64,
+}
+
+/// Segment again the labels of documents stored without tokens, such as
+/// those whose segmentation failed during `full` or `daily`, or with tokens
+/// of a segmentation version before `SEGMENTATION_VERSION`
+///
+/// Documents are segmented in batches of `WORD_BATCH_SIZE`, and those that
+/// get tokens are replaced, with their categories tagged again; the others
+/// are left as they are. A segmentation error stops the run, keeping the
+/// batches committed before it.
+pub async fn run(config: &Config, index_path: &Path) -> Result<Outcome> {
+    let index = throttle::open_index(config, index_path)?;
+    let schema = DomainSchema::new();
+    let mut writer: IndexWriter = writer_lock::open_writer(&index, index_path, WRITER_HEAP)?;
+    throttle::set_merge_policy(config, &writer);
+
+    let version = config.segmentation_version;
+    let searcher = index.reader()?.searcher();
+    let stale = stale_documents(&searcher, &schema, version)?;
+    let mut outcome = Outcome {
+        found: stale.len() as u64,
+        retokenized: 0,
+    };
+    info!(documents = outcome.found, version, "Found documents to segment again");
+    if stale.is_empty() {
+        return Ok(outcome);
+    }
+
+    let taxonomy = full::load_taxonomy(config)?;
+    let splitter = full::segmenter(config)?;
+    let subdomains = config.index_subdomain_labels;
+    let mut progress = IndexProgress::new(outcome.found);
+    let mut last_commit = 0;
+    for batch in stale.chunks(config.word_batch_size.max(1)) {
+        let domains = stored_domains(&searcher, &schema, batch)?;
+        let labels = domains.iter().map(|domain| domain.segment_source(subdomains)).collect();
+        let segments = splitter.segment_batch(labels).await?;
+        for (mut domain, (_, tokens)) in domains.into_iter().zip(segments) {
+            if tokens.is_empty() {
+                continue;
+            }
+            domain.categories = taxonomy.tag(&domain.label, &tokens);
+            domain.tokens = tokens;
+            domain.seg_version = version;
+            writer.delete_term(Term::from_field_text(schema.domain_exact, &domain.domain_exact));
+            writer.add_document(schema.to_document(&domain))?;
+            outcome.retokenized += 1;
+        }
+
+        if outcome.retokenized - last_commit >= COMMIT_INTERVAL {
+            info!(retokenized = outcome.retokenized, "Committing checkpoint...");
+            writer.commit()?;
+            last_commit = outcome.retokenized;
+        }
+        progress.inc(batch.len() as u64);
+    }
+
+    writer.commit()?;
+    writer.wait_merging_threads()?;
+    progress.finish();
+    info!(
+        found = outcome.found,
+        retokenized = outcome.retokenized,
+        "Retokenizing complete"
+    );
+    Ok(outcome)
+}
+
+/// Live documents with an empty `tokens` field or a segmentation version
+/// before `version`, found by field norms and fast fields so no stored
+/// document is loaded
+fn stale_documents(
+    searcher: &Searcher,
+    schema: &DomainSchema,
+    version: u64,
+) -> Result<Vec<DocAddress>> {
+    let version_field = schema.schema.get_field_name(schema.seg_version);
+    let mut stale = Vec::new();
+    for (segment_ord, segment) in searcher.segment_readers().iter().enumerate() {
+        let fieldnorms = segment.get_fieldnorms_reader(schema.tokens)?;
+        let versions = segment.fast_fields().u64(version_field)?;
+        for doc_id in segment.doc_ids_alive() {
+            let outdated = versions.first(doc_id).unwrap_or(0) < version;
+            if fieldnorms.fieldnorm(doc_id) == 0 || outdated {
+                stale.push(DocAddress::new(segment_ord as u32, doc_id));
+            }
+        }
+    }
+    Ok(stale)
+}
+
+/// The domains of stored documents, ready to be segmented again
+fn stored_domains(
+    searcher: &Searcher,
+    schema: &DomainSchema,
+    addresses: &[DocAddress],
+) -> Result<Vec<NormalizedDomain>> {
+    let mut domains = Vec::with_capacity(addresses.len());
+    for &address in addresses {
+        let doc: TantivyDocument = searcher.doc(address)?;
+        let Some(domain_exact) = doc.get_first(schema.domain_exact).and_then(|v| v.as_str())
+        else {
+            continue;
+        };
+        // Already accepted once, so only a changed normalization fails
+        match Domain::new(domain_exact).normalize_with(LabelValidation::Lenient) {
+            Ok(domain) => domains.push(domain),
+            Err(e) => debug!(domain = domain_exact, error = %e, "Skipped stored domain"),
+        }
+    }
+    Ok(domains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_search::{SearchRequest, Searcher as DomainSearcher};
+    use tantivy::Index;
+
+    #[tokio::test]
+    async fn test_retokenize_segments_stale_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        std::fs::create_dir_all(&path).unwrap();
+        let schema = DomainSchema::new();
+        let index = Index::create_in_dir(&path, schema.schema.clone()).unwrap();
+        let mut writer: IndexWriter = index.writer(15_000_000).unwrap();
+        let stored: [(&str, &[&str], u64); 3] = [
+            ("petshop.com", &[], 0),
+            ("coffee.com", &["coffee"], 1),
+            ("coffeeshop.com", &["coffeeshop"], 1),
+        ];
+        for (raw, tokens, version) in stored {
+            let mut domain = Domain::new(raw).normalize().unwrap();
+            domain.tokens = tokens.iter().map(|t| t.to_string()).collect();
+            domain.seg_version = version;
+            writer.add_document(schema.to_document(&domain)).unwrap();
+        }
+        writer.commit().unwrap();
+        drop(writer);
+
+        let words = dir.path().join("words.txt");
+        std::fs::write(&words, "pet\nshop\ncoffee\n").unwrap();
+        let mut config = Config::test();
+        config.word_list_file = Some(words);
+        config.word_batch_size = 2;
+        let outcome = run(&config, &path).await.unwrap();
+
+        assert_eq!(outcome, Outcome { found: 1, retokenized: 1 });
+        let searcher = DomainSearcher::new(&index, DomainSchema::new()).unwrap();
+        let search = |query: &str| -> Vec<String> {
+            searcher.reader().reload().unwrap();
+            let results = searcher.search(&SearchRequest::new(query, 10)).unwrap().results;
+            let mut domains: Vec<String> = results.into_iter().map(|r| r.domain.domain).collect();
+            domains.sort();
+            domains
+        };
+        assert_eq!(search("pet"), vec!["petshop.com"]);
+        assert_eq!(search("shop"), vec!["petshop.com"]);
+
+        // A new segmentation version reaches every document
+        config.segmentation_version = 2;
+        let outcome = run(&config, &path).await.unwrap();
+        assert_eq!(outcome, Outcome { found: 3, retokenized: 3 });
+        assert_eq!(search("shop"), vec!["coffeeshop.com", "petshop.com"]);
+        assert_eq!(searcher.reader().searcher().num_docs(), 3);
+
+        let again = run(&config, &path).await.unwrap();
+        assert_eq!(again, Outcome { found: 0, retokenized: 0 });
+    }
+}
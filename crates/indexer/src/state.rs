@@ -0,0 +1,231 @@
+use crate::error::{Error, Result};
+use domain_core::Config;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where a build can pick up after a restart
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Index the build writes
+    pub index: PathBuf,
+    /// Input domains read up to the last commit
+    pub read: u64,
+    /// Documents committed
+    pub indexed: u64,
+}
+
+/// How far a build got, rewritten at every commit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub index: PathBuf,
+    pub read: u64,
+    pub indexed: u64,
+    /// Domains in the input, when known up front
+    pub total: Option<u64>,
+    pub finished: bool,
+    /// Seconds since the Unix epoch
+    pub updated: u64,
+}
+
+impl Status {
+    pub fn new(checkpoint: &Checkpoint, total: Option<u64>, finished: bool) -> Self {
+        let updated = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Self {
+            index: checkpoint.index.clone(),
+            read: checkpoint.read,
+            indexed: checkpoint.indexed,
+            total,
+            finished,
+            updated,
+        }
+    }
+}
+
+/// Storage for the sidecar state of a build: its checkpoint, progress status
+/// and reject log
+///
+/// Local files are the only backend; a shared store (Redis, S3) slots in
+/// behind the same calls for indexers on hosts that don't keep their disks.
+pub trait StateStore: Send + Sync {
+    /// The checkpoint of a build that didn't finish
+    fn checkpoint(&self) -> Result<Option<Checkpoint>>;
+
+    fn save_checkpoint(&self, checkpoint: &Checkpoint) -> Result<()>;
+
+    /// Forget the checkpoint once the build is done
+    fn clear_checkpoint(&self) -> Result<()>;
+
+    fn save_status(&self, status: &Status) -> Result<()>;
+
+    /// Append domains that failed to normalize to the reject log
+    fn append_rejects(&self, domains: &[String]) -> Result<()>;
+
+    /// Empty the reject log before a new build
+    fn clear_rejects(&self) -> Result<()>;
+}
+
+/// The store of `command` under `STATE_DIR`, None when it is unset
+pub fn from_config(config: &Config, command: &str) -> Result<Option<Box<dyn StateStore>>> {
+    let Some(dir) = &config.state_dir else {
+        return Ok(None);
+    };
+    let store = LocalStore::open(dir.join(command))?;
+    Ok(Some(Box::new(store)))
+}
+
+/// Get the state of `command` ready for a run writing `index`
+///
+/// With `resume`, the checkpoint left by a run on the same index is kept for
+/// it to continue from; otherwise checkpoint and reject log start over.
+pub fn prepare(config: &Config, command: &str, index: &Path, resume: bool) -> Result<()> {
+    let Some(store) = from_config(config, command)? else {
+        if resume {
+            return Err(Error::Config(
+                "--resume needs STATE_DIR, where the checkpoint is kept".to_string(),
+            ));
+        }
+        return Ok(());
+    };
+    if !resume {
+        store.clear_checkpoint()?;
+        return store.clear_rejects();
+    }
+    match store.checkpoint()? {
+        Some(checkpoint) if checkpoint.index == index => Ok(()),
+        Some(checkpoint) => Err(Error::Config(format!(
+            "The checkpoint in STATE_DIR is for {}, not {}",
+            checkpoint.index.display(),
+            index.display()
+        ))),
+        None => Err(Error::Config(
+            "No checkpoint in STATE_DIR to resume from".to_string(),
+        )),
+    }
+}
+
+/// State kept as files in a directory: `checkpoint.json`, `status.json` and
+/// `rejects.txt`
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| {
+            let message = format!("creating state directory {}: {}", dir.display(), e);
+            std::io::Error::new(e.kind(), message)
+        })?;
+        Ok(Self { dir })
+    }
+
+    fn checkpoint_path(&self) -> PathBuf {
+        self.dir.join("checkpoint.json")
+    }
+
+    fn rejects_path(&self) -> PathBuf {
+        self.dir.join("rejects.txt")
+    }
+}
+
+/// Replace a file through a rename, so a crash never leaves half of it
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+impl StateStore for LocalStore {
+    fn checkpoint(&self) -> Result<Option<Checkpoint>> {
+        match fs::read(self.checkpoint_path()) {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save_checkpoint(&self, checkpoint: &Checkpoint) -> Result<()> {
+        write_atomic(&self.checkpoint_path(), &serde_json::to_vec(checkpoint)?)
+    }
+
+    fn clear_checkpoint(&self) -> Result<()> {
+        match fs::remove_file(self.checkpoint_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn save_status(&self, status: &Status) -> Result<()> {
+        write_atomic(&self.dir.join("status.json"), &serde_json::to_vec(status)?)
+    }
+
+    fn append_rejects(&self, domains: &[String]) -> Result<()> {
+        if domains.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.rejects_path())?;
+        for domain in domains {
+            writeln!(file, "{domain}")?;
+        }
+        Ok(())
+    }
+
+    fn clear_rejects(&self) -> Result<()> {
+        match fs::remove_file(self.rejects_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::open(dir.path().join("full")).unwrap();
+        assert_eq!(store.checkpoint().unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            index: PathBuf::from("/data/index"),
+            read: 2000,
+            indexed: 1800,
+        };
+        store.save_checkpoint(&checkpoint).unwrap();
+        store
+            .save_status(&Status::new(&checkpoint, Some(5000), false))
+            .unwrap();
+        store.append_rejects(&["nodot".to_string()]).unwrap();
+        store.append_rejects(&["-bad-.com".to_string()]).unwrap();
+
+        // A second store over the same files, as after a restart
+        let store = LocalStore::open(dir.path().join("full")).unwrap();
+        assert_eq!(store.checkpoint().unwrap(), Some(checkpoint));
+        let rejects = fs::read_to_string(dir.path().join("full/rejects.txt")).unwrap();
+        assert_eq!(rejects, "nodot\n-bad-.com\n");
+        let status: Status =
+            serde_json::from_slice(&fs::read(dir.path().join("full/status.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            (status.read, status.total, status.finished),
+            (2000, Some(5000), false)
+        );
+
+        store.clear_checkpoint().unwrap();
+        store.clear_rejects().unwrap();
+        assert_eq!(store.checkpoint().unwrap(), None);
+        assert!(!dir.path().join("full/rejects.txt").exists());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::{Error, Result};
+use domain_core::{Config, DomainSchema};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tantivy::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use tantivy::directory::{
+    AntiCallToken, Directory, DirectoryLock, FileHandle, Lock, MmapDirectory, TerminatingWrite,
+    WatchCallback, WatchHandle, WritePtr,
+};
+use tantivy::indexer::LogMergePolicy;
+use tantivy::{Index, IndexWriter};
+use tracing::info;
+
+/// Segments with more documents are left for `optimize` by the low-priority
+/// merge policy
+const LOW_PRIORITY_MAX_MERGE_DOCS: usize = 1_000_000;
+
+/// Segments the low-priority merge policy waits for before merging them
+const LOW_PRIORITY_MIN_MERGE_SEGMENTS: usize = 32;
+
+/// Open an index for a daily sync, throttling its writes to
+/// `DAILY_WRITE_LIMIT_MB` when set
+pub fn open_index(config: &Config, path: &Path) -> Result<Index> {
+    let directory = MmapDirectory::open(path)?;
+    let index = if config.daily_write_limit_mb == 0 {
+        Index::open(directory)?
+    } else {
+        info!(mb_per_second = config.daily_write_limit_mb, "Throttling index writes");
+        let bytes_per_second = config.daily_write_limit_mb * 1024 * 1024;
+        Index::open(ThrottledDirectory::new(directory, bytes_per_second))?
+    };
+    // Syncing writes documents of the current schema
+    if index.schema() != DomainSchema::new().schema {
+        return Err(Error::Schema(format!(
+            "index at {} was built with another schema; rebuild it with `full`",
+            path.display()
+        )));
+    }
+    Ok(index)
+}
+
+/// Apply `DAILY_LOW_PRIORITY_MERGES` to a daily sync's writer
+pub fn set_merge_policy(config: &Config, writer: &IndexWriter) {
+    if config.daily_low_priority_merges {
+        writer.set_merge_policy(Box::new(low_priority_merge_policy()));
+    }
+}
+
+/// Merge only small segments, and only once many have piled up, so a sync
+/// doesn't rewrite the large segments of the full build
+fn low_priority_merge_policy() -> LogMergePolicy {
+    let mut policy = LogMergePolicy::default();
+    policy.set_max_docs_before_merge(LOW_PRIORITY_MAX_MERGE_DOCS);
+    policy.set_min_num_segments(LOW_PRIORITY_MIN_MERGE_SEGMENTS);
+    policy
+}
+
+/// Shares a write rate between every file being written
+#[derive(Debug)]
+struct RateLimiter {
+    bytes_per_second: f64,
+    /// When the bytes written so far are paid for
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second.max(1) as f64,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Account for `bytes` about to be written, sleeping when ahead of the rate
+    fn acquire(&self, bytes: usize) {
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            // Idle time doesn't build up credit for a later burst
+            let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_second);
+            *next = (*next).max(now) + cost;
+            next.saturating_duration_since(now)
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// A directory whose writes (new segments, merges) are rate limited; reads,
+/// locks and `meta.json` updates go straight through
+#[derive(Debug)]
+struct ThrottledDirectory {
+    inner: Box<dyn Directory>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl ThrottledDirectory {
+    fn new(inner: impl Directory, bytes_per_second: u64) -> Self {
+        Self {
+            inner: Box::new(inner),
+            limiter: Arc::new(RateLimiter::new(bytes_per_second)),
+        }
+    }
+}
+
+impl Clone for ThrottledDirectory {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.box_clone(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+impl Directory for ThrottledDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        self.inner.get_file_handle(path)
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.inner.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.inner.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        let inner = self.inner.open_write(path)?;
+        Ok(BufWriter::new(Box::new(ThrottledWrite {
+            inner,
+            limiter: self.limiter.clone(),
+        })))
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        self.inner.atomic_read(path)
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.inner.atomic_write(path, data)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.inner.sync_directory()
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        self.inner.acquire_lock(lock)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> tantivy::Result<WatchHandle> {
+        self.inner.watch(watch_callback)
+    }
+}
+
+struct ThrottledWrite {
+    inner: WritePtr,
+    limiter: Arc<RateLimiter>,
+}
+
+impl Write for ThrottledWrite {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.limiter.acquire(buf.len());
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl TerminatingWrite for ThrottledWrite {
+    fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
+        self.inner.terminate_ref(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::{writer_lock, Domain, DomainSchema};
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(1000);
+        let start = Instant::now();
+        limiter.acquire(100);
+        limiter.acquire(100);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_throttled_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = DomainSchema::new();
+        Index::create_in_dir(dir.path(), schema.schema.clone()).unwrap();
+
+        let mut config = Config::test();
+        config.daily_write_limit_mb = 1024;
+        config.daily_low_priority_merges = true;
+        let index = open_index(&config, dir.path()).unwrap();
+        let mut writer: IndexWriter =
+            writer_lock::open_writer(&index, dir.path(), 15_000_000).unwrap();
+        set_merge_policy(&config, &writer);
+        // Locks still go through to the directory on disk
+        assert!(writer_lock::is_locked(dir.path()).unwrap());
+        for domain in ["coffee.com", "shop.io"] {
+            let normalized = Domain::new(domain).normalize().unwrap();
+            writer.add_document(schema.to_document(&normalized)).unwrap();
+        }
+        writer.commit().unwrap();
+        drop(writer);
+
+        let reopened = Index::open_in_dir(dir.path()).unwrap();
+        assert_eq!(reopened.reader().unwrap().searcher().num_docs(), 2);
+    }
+}
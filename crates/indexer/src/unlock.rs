@@ -0,0 +1,110 @@
+use crate::error::{Error, Result};
+use domain_core::writer_lock::{self, Holder, LOCK_FILE, PID_FILE};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// What `unlock` found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// No process holds the writer; leftover files were cleaned up
+    NotLocked,
+    /// A lock no running process could be found for was removed
+    Removed,
+}
+
+/// Recover an index whose writer lock outlived its run
+///
+/// A lock held by a process still running on this host is never removed.
+/// A held lock whose holder is gone or unknown, such as a stale lock on a
+/// network filesystem, is only removed with `force`.
+pub fn run(index_path: &Path, force: bool) -> Result<Outcome> {
+    let holder = writer_lock::holder(index_path);
+
+    if !writer_lock::is_locked(index_path)? {
+        remove(&index_path.join(PID_FILE))?;
+        info!(index = ?index_path, "Index is not locked");
+        return Ok(Outcome::NotLocked);
+    }
+
+    let described = holder
+        .as_ref()
+        .map_or("an unknown process".to_string(), Holder::to_string);
+    match holder.as_ref().and_then(Holder::is_running) {
+        Some(true) => {
+            let message = format!("Index is being written by {}; stop it first", described);
+            return Err(Error::Locked(message));
+        }
+        _ if !force => {
+            return Err(Error::Locked(format!(
+                "Index is locked by {}; if no other process writes it, run again with --force",
+                described
+            )))
+        }
+        _ => {}
+    }
+
+    remove(&index_path.join(LOCK_FILE))?;
+    remove(&index_path.join(PID_FILE))?;
+    warn!(index = ?index_path, holder = %described, "Writer lock removed");
+    Ok(Outcome::Removed)
+}
+
+/// Remove a file that may not exist
+fn remove(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::DomainSchema;
+    use tantivy::{Index, IndexWriter};
+
+    fn index(dir: &Path) -> Index {
+        Index::create_in_dir(dir, DomainSchema::new().schema).unwrap()
+    }
+
+    #[test]
+    fn test_not_locked_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = index(dir.path());
+        drop(writer_lock::open_writer::<tantivy::TantivyDocument>(&index, dir.path(), 15_000_000));
+        assert!(dir.path().join(PID_FILE).exists());
+
+        assert_eq!(run(dir.path(), false).unwrap(), Outcome::NotLocked);
+        assert!(!dir.path().join(PID_FILE).exists());
+    }
+
+    #[test]
+    fn test_refuses_running_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = index(dir.path());
+        let _writer: IndexWriter =
+            writer_lock::open_writer(&index, dir.path(), 15_000_000).unwrap();
+
+        let error = run(dir.path(), true).unwrap_err();
+        assert!(error.to_string().contains("stop it first"), "{}", error);
+        assert!(writer_lock::is_locked(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_force_removes_stale_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = index(dir.path());
+        let _writer: IndexWriter =
+            writer_lock::open_writer(&index, dir.path(), 15_000_000).unwrap();
+        // Pretend the holder is a process that no longer exists
+        let mut holder = writer_lock::holder(dir.path()).unwrap();
+        holder.pid = u32::MAX;
+        std::fs::write(dir.path().join(PID_FILE), serde_json::to_string(&holder).unwrap()).unwrap();
+
+        let error = run(dir.path(), false).unwrap_err();
+        assert!(error.to_string().contains("--force"), "{}", error);
+
+        assert_eq!(run(dir.path(), true).unwrap(), Outcome::Removed);
+        assert!(!writer_lock::is_locked(dir.path()).unwrap());
+    }
+}
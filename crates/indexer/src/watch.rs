@@ -0,0 +1,76 @@
+use domain_core::{Config, NormalizedDomain};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use watchlist::{Alert, DomainEvent, Notifier, WatchRule, WatchStore, Watchlist};
+
+/// Watch rules evaluated during one daily sync, with the alerts raised
+pub struct WatchRun {
+    store: WatchStore,
+    rules: Vec<WatchRule>,
+    watchlist: Watchlist,
+    notifier: Notifier,
+    alerts: Vec<Alert>,
+    timestamp: u64,
+}
+
+impl WatchRun {
+    /// Load the rules; None when the watchlist is disabled, has no rules or
+    /// can't be reached (the sync goes ahead without it)
+    pub async fn load(config: &Config) -> Option<Self> {
+        let (Some(redis_url), Some(key)) = (&config.redis_url, &config.watchlist_key) else {
+            return None;
+        };
+
+        let loaded = async {
+            let store = WatchStore::connect(redis_url, key, config.watchlist_max_alerts).await?;
+            let rules = store.rules().await?;
+            let notifier = Notifier::new(config.watchlist_webhook_url.clone())?;
+            Ok::<_, watchlist::Error>((store, rules, notifier))
+        };
+        let (store, rules, notifier) = match loaded.await {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                warn!(error = %e, "Watchlist unavailable, skipping watch rules");
+                return None;
+            }
+        };
+
+        let watchlist = Watchlist::new(rules.clone());
+        if watchlist.is_empty() {
+            return None;
+        }
+        info!(rules = watchlist.len(), "Evaluating watch rules");
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Some(Self {
+            store,
+            rules,
+            watchlist,
+            notifier,
+            alerts: Vec::new(),
+            timestamp,
+        })
+    }
+
+    pub fn check(&mut self, domain: &NormalizedDomain, event: DomainEvent) {
+        let alerts = self.watchlist.check(domain, event, self.timestamp);
+        self.alerts.extend(alerts);
+    }
+
+    /// Store the alerts and deliver them to their webhooks
+    pub async fn finish(self) {
+        if self.alerts.is_empty() {
+            info!("No watch rule matched");
+            return;
+        }
+
+        if let Err(e) = self.store.push_alerts(&self.alerts).await {
+            warn!(error = %e, "Failed to store alerts");
+        }
+        let failed = self.notifier.send(&self.rules, &self.alerts).await;
+        info!(alerts = self.alerts.len(), failed_webhooks = failed, "Watch alerts raised");
+    }
+}
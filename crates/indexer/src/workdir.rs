@@ -0,0 +1,72 @@
+use crate::error::Result;
+use domain_core::Config;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// A run's own directory under `WORK_DIR`, removed when dropped
+///
+/// Dropping covers failed runs too: an error returned past the owner still
+/// cleans up, unless `--keep-temp` asked to keep the files for inspection.
+pub struct RunDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl RunDir {
+    /// Create `WORK_DIR/<command>-<pid>-<unix seconds>`
+    pub fn create(config: &Config, command: &str, keep: bool) -> Result<Self> {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let path = config
+            .work_dir
+            .join(format!("{command}-{}-{started}", std::process::id()));
+        std::fs::create_dir_all(&path).map_err(|e| {
+            let message = format!("creating work directory {}: {}", path.display(), e);
+            std::io::Error::new(e.kind(), message)
+        })?;
+        Ok(Self { path, keep })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        if self.keep {
+            info!(path = %self.path.display(), "Keeping work directory");
+            return;
+        }
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!(path = %self.path.display(), error = %e, "Failed to remove work directory");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_dir_is_removed_unless_kept() {
+        let work = tempfile::tempdir().unwrap();
+        let mut config = Config::test();
+        config.work_dir = work.path().join("nested");
+
+        let run = RunDir::create(&config, "daily", false).unwrap();
+        let path = run.path().to_path_buf();
+        assert!(path.starts_with(&config.work_dir));
+        std::fs::write(path.join("dailyupdate.txt"), "example.com\n").unwrap();
+        drop(run);
+        assert!(!path.exists());
+
+        let run = RunDir::create(&config, "daily", true).unwrap();
+        let path = run.path().to_path_buf();
+        drop(run);
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::error::{Error, Result};
+use crate::model::RdapInfo;
+use crate::rate::RateLimiter;
+use futures::future::join_all;
+use moka::sync::Cache;
+use reqwest::{Client, StatusCode};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Longest a lookup waits for the rate limiter before it is skipped
+const MAX_RATE_WAIT: Duration = Duration::from_secs(5);
+
+/// Client for RDAP domain lookups, with a cache and a request rate limit
+///
+/// `base_url` is an RDAP service answering `{base_url}/domain/{domain}`: a
+/// registry's own service, or a redirector such as `https://rdap.org` that
+/// forwards to the right registry.
+#[derive(Clone)]
+pub struct RdapClient {
+    client: Client,
+    base_url: String,
+    /// Lookups by domain; `None` records domains the registry doesn't know
+    cache: Cache<String, Option<RdapInfo>>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RdapClient {
+    /// Create a new RdapClient
+    ///
+    /// # Arguments
+    /// * `base_url` - RDAP service URL
+    /// * `requests_per_second` - Lookups sent per second, across all callers
+    /// * `cache_entries` - Lookups kept in memory
+    /// * `cache_ttl` - How long a lookup is reused
+    pub fn new(
+        base_url: impl Into<String>,
+        requests_per_second: u32,
+        cache_entries: u64,
+        cache_ttl: Duration,
+    ) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::limited(5))
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            cache: Cache::builder()
+                .max_capacity(cache_entries)
+                .time_to_live(cache_ttl)
+                .build(),
+            limiter: Arc::new(RateLimiter::per_second(
+                requests_per_second,
+                MAX_RATE_WAIT,
+            )),
+        })
+    }
+
+    /// Registration data of a domain, or None when the registry doesn't
+    /// know it (e.g. it is not registered)
+    #[tracing::instrument(skip(self))]
+    pub async fn lookup(&self, domain: &str) -> Result<Option<RdapInfo>> {
+        let domain = domain.to_lowercase();
+        if let Some(cached) = self.cache.get(&domain) {
+            debug!("RDAP cache hit");
+            return Ok(cached);
+        }
+
+        self.limiter.acquire().await?;
+        let response = self
+            .client
+            .get(format!("{}/domain/{}", self.base_url, domain))
+            .header(reqwest::header::ACCEPT, "application/rdap+json")
+            .send()
+            .await?;
+
+        let info = match response.status() {
+            StatusCode::NOT_FOUND => None,
+            status if status.is_success() => Some(RdapInfo::from_json(&response.text().await?)?),
+            status => {
+                return Err(Error::Api {
+                    status: status.as_u16(),
+                    message: response.text().await.unwrap_or_default(),
+                })
+            }
+        };
+
+        self.cache.insert(domain, info.clone());
+        Ok(info)
+    }
+
+    /// Whether a lookup of the domain would be answered from the cache
+    pub fn is_cached(&self, domain: &str) -> bool {
+        self.cache.contains_key(&domain.to_lowercase())
+    }
+
+    /// Look up several domains concurrently, keeping the ones found
+    ///
+    /// Failed lookups are logged and left out, so one slow registry doesn't
+    /// fail the others.
+    pub async fn lookup_many<'a>(
+        &self,
+        domains: impl IntoIterator<Item = &'a str>,
+    ) -> BTreeMap<String, RdapInfo> {
+        let lookups = domains.into_iter().map(|domain| async move {
+            match self.lookup(domain).await {
+                Ok(info) => info.map(|info| (domain.to_string(), info)),
+                Err(e) => {
+                    warn!(domain, error = %e, "RDAP lookup failed");
+                    None
+                }
+            }
+        });
+        join_all(lookups).await.into_iter().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_lookup_is_cached() {
+        let server = MockServer::start().await;
+        let body = r#"{
+            "events": [{"eventAction": "registration", "eventDate": "2020-01-02T00:00:00Z"}]
+        }"#;
+        Mock::given(method("GET"))
+            .and(path("/domain/coffee.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/domain/unregistered.com"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/domain/broken.com"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let client = RdapClient::new(server.uri(), 100, 100, Duration::from_secs(60)).unwrap();
+        assert!(!client.is_cached("coffee.com"));
+        for _ in 0..2 {
+            let info = client.lookup("Coffee.com").await.unwrap().unwrap();
+            assert_eq!(info.created.as_deref(), Some("2020-01-02T00:00:00Z"));
+            assert!(client.lookup("unregistered.com").await.unwrap().is_none());
+        }
+        assert!(matches!(
+            client.lookup("broken.com").await,
+            Err(Error::Api { status: 500, .. })
+        ));
+        assert!(client.is_cached("COFFEE.com"));
+        assert!(!client.is_cached("broken.com"));
+
+        let found = client.lookup_many(["coffee.com", "unregistered.com", "broken.com"]).await;
+        assert_eq!(found.keys().collect::<Vec<_>>(), vec!["coffee.com"]);
+    }
+}
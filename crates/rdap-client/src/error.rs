@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("HTTP request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("RDAP error: {status} - {message}")]
+    Api { status: u16, message: String },
+
+    #[error("JSON parsing error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Rate limit reached, lookup skipped")]
+    RateLimited,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,9 @@
+mod client;
+mod error;
+mod model;
+mod rate;
+
+pub use client::RdapClient;
+pub use error::{Error, Result};
+pub use model::RdapInfo;
+pub use rate::RateLimiter;
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Registration data of a domain, from its registry's RDAP service
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RdapInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registrar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registrar_iana_id: Option<String>,
+    /// Registration date (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    /// EPP statuses, e.g. `client transfer prohibited`
+    #[serde(default)]
+    pub status: Vec<String>,
+    #[serde(default)]
+    pub nameservers: Vec<String>,
+}
+
+/// The parts of an RDAP domain object (RFC 9083) that are kept
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RdapDomain {
+    #[serde(default)]
+    status: Vec<String>,
+    #[serde(default)]
+    events: Vec<RdapEvent>,
+    #[serde(default)]
+    entities: Vec<RdapEntity>,
+    #[serde(default)]
+    nameservers: Vec<RdapNameserver>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RdapEvent {
+    event_action: String,
+    event_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RdapEntity {
+    #[serde(default)]
+    roles: Vec<String>,
+    vcard_array: Option<Value>,
+    #[serde(default)]
+    public_ids: Vec<PublicId>,
+}
+
+#[derive(Deserialize)]
+struct PublicId {
+    #[serde(rename = "type")]
+    kind: String,
+    identifier: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RdapNameserver {
+    ldh_name: Option<String>,
+}
+
+impl RdapInfo {
+    /// Extract the registration data from an RDAP domain response
+    pub fn from_json(body: &str) -> serde_json::Result<Self> {
+        let domain: RdapDomain = serde_json::from_str(body)?;
+
+        let event = |action: &str| {
+            domain
+                .events
+                .iter()
+                .find(|event| event.event_action == action)
+                .and_then(|event| event.event_date.clone())
+        };
+        let registrar = domain
+            .entities
+            .iter()
+            .find(|entity| entity.roles.iter().any(|role| role == "registrar"));
+
+        Ok(Self {
+            registrar: registrar.and_then(|entity| vcard_name(entity.vcard_array.as_ref()?)),
+            registrar_iana_id: registrar.and_then(|entity| {
+                entity
+                    .public_ids
+                    .iter()
+                    .find(|id| id.kind == "IANA Registrar ID")
+                    .map(|id| id.identifier.clone())
+            }),
+            created: event("registration"),
+            expires: event("expiration"),
+            updated: event("last changed"),
+            status: domain.status,
+            nameservers: domain
+                .nameservers
+                .into_iter()
+                .filter_map(|ns| ns.ldh_name)
+                .map(|name| name.to_lowercase())
+                .collect(),
+        })
+    }
+}
+
+/// The formatted name (`fn`) of a jCard: `["vcard", [["fn", {}, "text", "Name"], ...]]`
+fn vcard_name(vcard: &Value) -> Option<String> {
+    vcard
+        .get(1)?
+        .as_array()?
+        .iter()
+        .find(|property| property.get(0).and_then(Value::as_str) == Some("fn"))?
+        .get(3)?
+        .as_str()
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"{
+        "objectClassName": "domain",
+        "ldhName": "EXAMPLE.COM",
+        "status": ["client delete prohibited", "client transfer prohibited"],
+        "events": [
+            {"eventAction": "registration", "eventDate": "1995-08-14T04:00:00Z"},
+            {"eventAction": "expiration", "eventDate": "2025-08-13T04:00:00Z"},
+            {"eventAction": "last changed", "eventDate": "2024-08-14T07:01:34Z"}
+        ],
+        "entities": [{
+            "objectClassName": "entity",
+            "roles": ["registrar"],
+            "publicIds": [{"type": "IANA Registrar ID", "identifier": "376"}],
+            "vcardArray": ["vcard", [
+                ["version", {}, "text", "4.0"],
+                ["fn", {}, "text", "RESERVED-Internet Assigned Numbers Authority"]
+            ]]
+        }],
+        "nameservers": [{"ldhName": "A.IANA-SERVERS.NET"}, {"ldhName": "B.IANA-SERVERS.NET"}]
+    }"#;
+
+    #[test]
+    fn test_from_json() {
+        let info = RdapInfo::from_json(EXAMPLE).unwrap();
+
+        assert_eq!(
+            info.registrar.as_deref(),
+            Some("RESERVED-Internet Assigned Numbers Authority")
+        );
+        assert_eq!(info.registrar_iana_id.as_deref(), Some("376"));
+        assert_eq!(info.created.as_deref(), Some("1995-08-14T04:00:00Z"));
+        assert_eq!(info.expires.as_deref(), Some("2025-08-13T04:00:00Z"));
+        assert_eq!(info.status.len(), 2);
+        assert_eq!(info.nameservers, vec!["a.iana-servers.net", "b.iana-servers.net"]);
+    }
+
+    #[test]
+    fn test_sparse_response() {
+        let info = RdapInfo::from_json(r#"{"objectClassName": "domain"}"#).unwrap();
+        assert_eq!(info, RdapInfo::default());
+    }
+}
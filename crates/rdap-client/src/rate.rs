@@ -0,0 +1,67 @@
+use crate::error::{Error, Result};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Spaces requests evenly at a fixed rate, shared by all callers
+pub struct RateLimiter {
+    interval: Duration,
+    /// Longest a caller waits for its slot before giving up
+    max_wait: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn per_second(requests: u32, max_wait: Duration) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / requests.max(1),
+            max_wait,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for the next free slot, or fail when it is more than `max_wait`
+    /// away
+    pub async fn acquire(&self) -> Result<()> {
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(Instant::now());
+            if slot.duration_since(Instant::now()) > self.max_wait {
+                return Err(Error::RateLimited);
+            }
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_are_spaced() {
+        let limiter = RateLimiter::per_second(10, Duration::from_millis(250));
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limiter.acquire().await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_full_queue_is_rejected() {
+        let limiter = RateLimiter::per_second(1, Duration::from_millis(1500));
+
+        limiter.acquire().await.unwrap();
+        // Reserve the next two slots without waiting for them
+        let next = limiter.acquire();
+        let after = limiter.acquire();
+        let (first, second) = tokio::join!(next, after);
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(Error::RateLimited)));
+    }
+}
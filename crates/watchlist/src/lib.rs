@@ -0,0 +1,9 @@
+mod error;
+mod notify;
+mod rule;
+mod store;
+
+pub use error::{Error, Result};
+pub use notify::Notifier;
+pub use rule::{Alert, DomainEvent, RuleKind, WatchRule, Watchlist};
+pub use store::WatchStore;
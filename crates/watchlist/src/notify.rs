@@ -0,0 +1,118 @@
+use crate::error::Result;
+use crate::rule::{Alert, WatchRule};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::warn;
+
+/// Alerts per webhook request
+const ALERTS_PER_REQUEST: usize = 500;
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    alerts: &'a [Alert],
+}
+
+/// Delivers alerts to each rule's webhook, or the default one
+pub struct Notifier {
+    client: reqwest::Client,
+    default_url: Option<String>,
+}
+
+impl Notifier {
+    pub fn new(default_url: Option<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        Ok(Self { client, default_url })
+    }
+
+    /// POST `{"alerts": [...]}` to every destination, returning how many
+    /// requests failed
+    ///
+    /// Alerts of rules without a webhook go to the default URL, or nowhere
+    /// when there is none; they remain queryable from the store either way.
+    pub async fn send(&self, rules: &[WatchRule], alerts: &[Alert]) -> usize {
+        let mut by_url: BTreeMap<&str, Vec<Alert>> = BTreeMap::new();
+        for alert in alerts {
+            let url = rules
+                .iter()
+                .find(|rule| rule.id == alert.rule_id)
+                .and_then(|rule| rule.webhook.as_deref())
+                .or(self.default_url.as_deref());
+            if let Some(url) = url {
+                by_url.entry(url).or_default().push(alert.clone());
+            }
+        }
+
+        let mut failed = 0;
+        for (url, alerts) in by_url {
+            for chunk in alerts.chunks(ALERTS_PER_REQUEST) {
+                if let Err(e) = self.post(url, chunk).await {
+                    warn!(url, alerts = chunk.len(), error = %e, "Failed to deliver alerts");
+                    failed += 1;
+                }
+            }
+        }
+        failed
+    }
+
+    async fn post(&self, url: &str, alerts: &[Alert]) -> Result<()> {
+        self.client
+            .post(url)
+            .json(&WebhookPayload { alerts })
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::{DomainEvent, RuleKind};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn alert(rule_id: &str, domain: &str) -> Alert {
+        Alert {
+            rule_id: rule_id.to_string(),
+            rule_name: rule_id.to_string(),
+            domain: domain.to_string(),
+            event: DomainEvent::Added,
+            matched: "acme".to_string(),
+            timestamp: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alerts_go_to_rule_or_default_webhook() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/rule"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/default"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let rules = vec![WatchRule {
+            id: "r1".to_string(),
+            name: "acme".to_string(),
+            kind: RuleKind::Keyword,
+            pattern: "acme".to_string(),
+            max_distance: None,
+            webhook: Some(format!("{}/rule", server.uri())),
+        }];
+        let notifier = Notifier::new(Some(format!("{}/default", server.uri()))).unwrap();
+
+        let alerts = vec![alert("r1", "acme.com"), alert("r2", "acme.net")];
+        assert_eq!(notifier.send(&rules, &alerts).await, 1);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::{Error, Result};
+use domain_core::NormalizedDomain;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Edits allowed by fuzzy rules that don't set `max_distance`
+const DEFAULT_MAX_DISTANCE: usize = 1;
+
+/// Most edits a fuzzy rule may allow; beyond this nearly every label matches
+const MAX_DISTANCE_LIMIT: usize = 3;
+
+/// How a rule's pattern is matched against domains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum RuleKind {
+    /// The label (hyphens ignored) contains the pattern
+    Keyword,
+    /// The full domain matches the regular expression
+    Regex,
+    /// Some part of the label (hyphens ignored) is within `max_distance`
+    /// edits of the pattern, e.g. a brand name and its typos
+    Fuzzy,
+}
+
+/// A user-defined rule evaluated against every daily add and removal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WatchRule {
+    /// Assigned when the rule is stored
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub kind: RuleKind,
+    pub pattern: String,
+    /// Edits allowed by fuzzy rules (default 1, at most 3)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_distance: Option<usize>,
+    /// Webhook receiving this rule's alerts instead of the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+/// Whether a domain appeared in or dropped from its zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum DomainEvent {
+    Added,
+    Dropped,
+}
+
+/// A domain event matching a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Alert {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub domain: String,
+    pub event: DomainEvent,
+    /// The part of the domain that matched
+    pub matched: String,
+    /// When the sync that raised the alert ran (Unix seconds)
+    pub timestamp: u64,
+}
+
+impl WatchRule {
+    /// Check the pattern, normalizing keyword and fuzzy patterns to lowercase
+    pub fn validate(&mut self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(Error::InvalidRule("name cannot be empty".to_string()));
+        }
+        if self.pattern.trim().is_empty() {
+            return Err(Error::InvalidRule("pattern cannot be empty".to_string()));
+        }
+        match self.kind {
+            RuleKind::Regex => {
+                Regex::new(&self.pattern).map_err(|e| Error::InvalidRule(e.to_string()))?;
+            }
+            RuleKind::Keyword | RuleKind::Fuzzy => {
+                self.pattern = compact(&self.pattern);
+            }
+        }
+        if self.max_distance.is_some_and(|d| d > MAX_DISTANCE_LIMIT) {
+            return Err(Error::InvalidRule(format!(
+                "max_distance cannot exceed {}",
+                MAX_DISTANCE_LIMIT
+            )));
+        }
+        Ok(())
+    }
+}
+
+enum Matcher {
+    Keyword(String),
+    Regex(Regex),
+    Fuzzy { pattern: Vec<char>, max_distance: usize },
+}
+
+/// Compiled rules, checked against each added or dropped domain
+pub struct Watchlist {
+    rules: Vec<(WatchRule, Matcher)>,
+}
+
+impl Watchlist {
+    /// Compile the rules, skipping (and logging) invalid ones
+    pub fn new(rules: Vec<WatchRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter_map(|mut rule| {
+                if let Err(e) = rule.validate() {
+                    warn!(rule = rule.id, error = %e, "Skipping invalid watch rule");
+                    return None;
+                }
+                let matcher = match rule.kind {
+                    RuleKind::Keyword => Matcher::Keyword(rule.pattern.clone()),
+                    RuleKind::Regex => Matcher::Regex(Regex::new(&rule.pattern).ok()?),
+                    RuleKind::Fuzzy => Matcher::Fuzzy {
+                        pattern: rule.pattern.chars().collect(),
+                        max_distance: rule.max_distance.unwrap_or(DEFAULT_MAX_DISTANCE),
+                    },
+                };
+                Some((rule, matcher))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Alerts for every rule the domain matches
+    pub fn check(
+        &self,
+        domain: &NormalizedDomain,
+        event: DomainEvent,
+        timestamp: u64,
+    ) -> Vec<Alert> {
+        let label = compact(&domain.label);
+
+        self.rules
+            .iter()
+            .filter_map(|(rule, matcher)| {
+                let matched = match matcher {
+                    Matcher::Keyword(keyword) => label.contains(keyword).then(|| keyword.clone()),
+                    Matcher::Regex(regex) => regex
+                        .find(&domain.domain_exact)
+                        .map(|found| found.as_str().to_string()),
+                    Matcher::Fuzzy {
+                        pattern,
+                        max_distance,
+                    } => fuzzy_find(&label, pattern, *max_distance),
+                }?;
+                Some(Alert {
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    domain: domain.domain_exact.clone(),
+                    event,
+                    matched,
+                    timestamp,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lowercase without hyphens or whitespace, so `Acme-Corp` matches `acmecorp`
+fn compact(text: &str) -> String {
+    text.chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The first part of `label` within `max_distance` edits of `pattern`
+fn fuzzy_find(label: &str, pattern: &[char], max_distance: usize) -> Option<String> {
+    let label: Vec<char> = label.chars().collect();
+    let shortest = pattern.len().saturating_sub(max_distance).max(1);
+    let longest = (pattern.len() + max_distance).min(label.len());
+
+    (shortest..=longest).find_map(|len| {
+        label
+            .windows(len)
+            .find(|window| levenshtein(window, pattern) <= max_distance)
+            .map(|window| window.iter().collect())
+    })
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain_core::Domain;
+
+    fn rule(kind: RuleKind, pattern: &str) -> WatchRule {
+        WatchRule {
+            id: pattern.to_string(),
+            name: pattern.to_string(),
+            kind,
+            pattern: pattern.to_string(),
+            max_distance: None,
+            webhook: None,
+        }
+    }
+
+    fn matches(watchlist: &Watchlist, domain: &str) -> Vec<String> {
+        let domain = Domain::new(domain).normalize().unwrap();
+        watchlist
+            .check(&domain, DomainEvent::Added, 0)
+            .into_iter()
+            .map(|alert| alert.rule_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_kinds() {
+        let watchlist = Watchlist::new(vec![
+            rule(RuleKind::Keyword, "Acme Corp"),
+            rule(RuleKind::Regex, r"^login-.*\.com$"),
+            rule(RuleKind::Fuzzy, "paypal"),
+        ]);
+
+        assert_eq!(matches(&watchlist, "acme-corp-store.net"), vec!["Acme Corp"]);
+        assert_eq!(matches(&watchlist, "login-bank.com"), vec![r"^login-.*\.com$"]);
+        assert_eq!(matches(&watchlist, "secure-paypa1.com"), vec!["paypal"]);
+        assert_eq!(matches(&watchlist, "paypl-help.com"), vec!["paypal"]);
+        assert!(matches(&watchlist, "coffee.com").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        assert!(rule(RuleKind::Regex, "(unclosed").validate().is_err());
+        assert!(rule(RuleKind::Keyword, "  ").validate().is_err());
+
+        let mut too_fuzzy = rule(RuleKind::Fuzzy, "acme");
+        too_fuzzy.max_distance = Some(5);
+        assert!(too_fuzzy.validate().is_err());
+
+        let watchlist = Watchlist::new(vec![rule(RuleKind::Regex, "(unclosed")]);
+        assert!(watchlist.is_empty());
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(levenshtein(&chars("paypal"), &chars("paypal")), 0);
+        assert_eq!(levenshtein(&chars("paypa1"), &chars("paypal")), 1);
+        assert_eq!(levenshtein(&chars("pypl"), &chars("paypal")), 2);
+    }
+}
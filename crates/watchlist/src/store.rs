@@ -0,0 +1,112 @@
+use crate::error::Result;
+use crate::rule::{Alert, WatchRule};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+/// Redis persistence for watch rules and the alerts they raised
+///
+/// Rules live in the hash `{key}:rules` (id → JSON) and alerts in the list
+/// `{key}:alerts`, newest first and capped at `max_alerts`.
+#[derive(Clone)]
+pub struct WatchStore {
+    conn: ConnectionManager,
+    key: String,
+    max_alerts: usize,
+}
+
+impl WatchStore {
+    pub async fn connect(redis_url: &str, key: &str, max_alerts: usize) -> Result<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let conn = ConnectionManager::new(client).await?;
+        Ok(Self {
+            conn,
+            key: key.to_string(),
+            max_alerts: max_alerts.max(1),
+        })
+    }
+
+    fn rules_key(&self) -> String {
+        format!("{}:rules", self.key)
+    }
+
+    fn alerts_key(&self) -> String {
+        format!("{}:alerts", self.key)
+    }
+
+    /// Every stored rule, by name
+    pub async fn rules(&self) -> Result<Vec<WatchRule>> {
+        let mut conn = self.conn.clone();
+        let values: Vec<String> = conn.hvals(self.rules_key()).await?;
+
+        let mut rules = values
+            .iter()
+            .map(|json| serde_json::from_str(json))
+            .collect::<std::result::Result<Vec<WatchRule>, _>>()?;
+        rules.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        Ok(rules)
+    }
+
+    /// Validate and store a rule under a new id
+    pub async fn add_rule(&self, mut rule: WatchRule) -> Result<WatchRule> {
+        rule.validate()?;
+        rule.id = uuid::Uuid::new_v4().to_string();
+
+        let mut conn = self.conn.clone();
+        let _: () = conn
+            .hset(self.rules_key(), &rule.id, serde_json::to_string(&rule)?)
+            .await?;
+        Ok(rule)
+    }
+
+    /// Delete a rule, returning whether it existed
+    pub async fn remove_rule(&self, id: &str) -> Result<bool> {
+        let mut conn = self.conn.clone();
+        let removed: u64 = conn.hdel(self.rules_key(), id).await?;
+        Ok(removed > 0)
+    }
+
+    /// Prepend alerts, dropping the oldest beyond `max_alerts`
+    pub async fn push_alerts(&self, alerts: &[Alert]) -> Result<()> {
+        if alerts.is_empty() {
+            return Ok(());
+        }
+        let values = alerts
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut conn = self.conn.clone();
+        let _: () = redis::pipe()
+            .lpush(self.alerts_key(), values)
+            .ignore()
+            .ltrim(self.alerts_key(), 0, self.max_alerts as isize - 1)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Up to `limit` of the newest alerts, optionally for one rule
+    pub async fn alerts(&self, limit: usize, rule_id: Option<&str>) -> Result<Vec<Alert>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        // A rule's alerts may be anywhere in the list
+        let scan = if rule_id.is_some() { self.max_alerts } else { limit };
+
+        let mut conn = self.conn.clone();
+        let values: Vec<String> = conn.lrange(self.alerts_key(), 0, scan as isize - 1).await?;
+
+        let mut alerts = Vec::new();
+        for json in values {
+            let alert: Alert = serde_json::from_str(&json)?;
+            if rule_id.is_none_or(|id| alert.rule_id == id) {
+                alerts.push(alert);
+                if alerts.len() == limit {
+                    break;
+                }
+            }
+        }
+        Ok(alerts)
+    }
+}
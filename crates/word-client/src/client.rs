@@ -25,6 +25,7 @@ struct SegmentResult {
     segmentation: Vec<String>,
     /// Keywords extracted (includes compounds like "marketing" -> "market")
     #[serde(default)]
+    #[allow(dead_code)]
     keywords: Vec<String>,
 }
 
@@ -85,6 +86,7 @@ impl WordClient {
     /// Segment a batch of labels using parallel API calls
     ///
     /// Returns a Vec of (label, segments) pairs in the same order as input
+    #[tracing::instrument(skip_all, fields(labels = labels.len()))]
     pub async fn segment_batch(&self, labels: Vec<String>) -> Result<Vec<(String, Vec<String>)>> {
         if labels.is_empty() {
             return Ok(Vec::new());
@@ -253,14 +255,132 @@ mod base64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
 
     #[test]
     fn test_base64_encode() {
-        use base64::Engine;
         let encoded = base64::Engine::encode(
             &base64::engine::general_purpose::STANDARD,
             "user:pass",
         );
         assert_eq!(encoded, "dXNlcjpwYXNz");
     }
+
+    /// Splits labels into the known words they start with, like the word
+    /// splitter's `/segment/bulk`
+    struct Segmenter;
+
+    impl Respond for Segmenter {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            const WORDS: &[&str] = &["coffee", "shop", "pet", "care"];
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let results: Vec<serde_json::Value> = body["labels"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|label| {
+                    let label = label.as_str().unwrap();
+                    let mut rest = label;
+                    let mut words = Vec::new();
+                    while let Some(word) = WORDS.iter().find(|w| rest.starts_with(*w)) {
+                        words.push(*word);
+                        rest = &rest[word.len()..];
+                    }
+                    if !rest.is_empty() {
+                        words.push(rest);
+                    }
+                    serde_json::json!({ "label": label, "segmentation": words })
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "results": results }))
+        }
+    }
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_segment_batch_in_chunks() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/segment/bulk"))
+            .and(header("authorization", "Basic dXNlcjpwYXNz"))
+            .respond_with(Segmenter)
+            // Three chunks of the batch, then the single label
+            .expect(4)
+            .mount(&server)
+            .await;
+        let client = WordClient::new(server.uri(), "user", "pass", Some(2), Some(2)).unwrap();
+
+        let segments = client
+            .segment_batch(labels(&["coffeeshop", "petcare", "xyz", "shop", "petshop"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                ("coffeeshop".to_string(), labels(&["coffee", "shop"])),
+                ("petcare".to_string(), labels(&["pet", "care"])),
+                ("xyz".to_string(), labels(&["xyz"])),
+                ("shop".to_string(), labels(&["shop"])),
+                ("petshop".to_string(), labels(&["pet", "shop"])),
+            ]
+        );
+        assert_eq!(client.segment_single("petcare").await.unwrap(), labels(&["pet", "care"]));
+        assert!(client.segment_batch(Vec::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_segment_batch_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("splitter down"))
+            .mount(&server)
+            .await;
+        let client = WordClient::new(server.uri(), "user", "pass", None, None).unwrap();
+
+        match client.segment_batch(labels(&["coffeeshop"])).await {
+            Err(Error::Api { status, message }) => {
+                assert_eq!(status, 500);
+                assert_eq!(message, "splitter down");
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_chunk_fails_the_batch() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(Segmenter).mount(&server).await;
+        let client = WordClient::new(server.uri(), "user", "pass", Some(1), Some(1)).unwrap();
+
+        // Rate limiting isn't retried; callers index the batch without tokens
+        let result = client.segment_batch(labels(&["coffeeshop", "petcare"])).await;
+        assert!(matches!(result, Err(Error::Api { status: 429, .. })));
+
+        let segments = client.segment_batch(labels(&["coffeeshop"])).await.unwrap();
+        assert_eq!(segments[0].1, labels(&["coffee", "shop"]));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+        let client = WordClient::new(server.uri(), "user", "pass", None, None).unwrap();
+
+        assert!(matches!(
+            client.segment_batch(labels(&["coffeeshop"])).await,
+            Err(Error::Request(_))
+        ));
+    }
 }
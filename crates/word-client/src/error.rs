@@ -8,6 +8,9 @@ pub enum Error {
     #[error("API error: {status} - {message}")]
     Api { status: u16, message: String },
 
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
 
@@ -1,5 +1,9 @@
 mod client;
 mod error;
+mod local;
+mod segmenter;
 
 pub use client::WordClient;
 pub use error::{Error, Result};
+pub use local::LocalSegmenter;
+pub use segmenter::Segmenter;
@@ -0,0 +1,191 @@
+use crate::error::Result;
+use domain_core::query::Segment;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Splits labels into words from a local word list, without the word
+/// splitter API
+///
+/// The list has one word per line, most frequent first. Each label is split
+/// at hyphens and between letters and digits; letter runs are then
+/// segmented into the most probable word sequence, scoring words by their
+/// rank in the list (Zipf's law). Letters no word covers stay together as
+/// one token.
+#[derive(Debug, Clone)]
+pub struct LocalSegmenter {
+    /// Cost of each known word, lower for more frequent ones
+    costs: HashMap<String, f64>,
+    /// Cost per character not covered by a word
+    unknown_cost: f64,
+    /// Longest word in characters
+    max_len: usize,
+}
+
+impl LocalSegmenter {
+    /// Load a word list file; blank lines and lines starting with `#` are
+    /// skipped
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::new(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    /// Build a segmenter from words, most frequent first
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let words: Vec<String> = words
+            .into_iter()
+            .map(|word| word.as_ref().to_lowercase())
+            .collect();
+        let log_count = (words.len().max(2) as f64).ln();
+
+        let mut costs = HashMap::with_capacity(words.len());
+        for (rank, word) in words.into_iter().enumerate() {
+            let cost = ((rank + 1) as f64 * log_count).ln();
+            costs.entry(word).or_insert(cost);
+        }
+        let max_len = costs.keys().map(|w| w.chars().count()).max().unwrap_or(0);
+        // Dearer than the rarest word, so any word beats leaving letters
+        let unknown_cost = costs.values().cloned().fold(0.0, f64::max) + 1.0;
+
+        Self {
+            costs,
+            unknown_cost,
+            max_len,
+        }
+    }
+
+    /// Number of known words
+    pub fn len(&self) -> usize {
+        self.costs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.costs.is_empty()
+    }
+
+    /// Words of a label, in order
+    pub fn segment(&self, label: &str) -> Vec<String> {
+        let label = label.to_lowercase();
+        let mut tokens = Vec::new();
+        for part in label.split('-').filter(|part| !part.is_empty()) {
+            let mut run = String::new();
+            let mut digits = false;
+            for c in part.chars() {
+                if !run.is_empty() && c.is_ascii_digit() != digits {
+                    self.push_run(&run, digits, &mut tokens);
+                    run.clear();
+                }
+                digits = c.is_ascii_digit();
+                run.push(c);
+            }
+            self.push_run(&run, digits, &mut tokens);
+        }
+        tokens
+    }
+
+    fn push_run(&self, run: &str, digits: bool, tokens: &mut Vec<String>) {
+        if run.is_empty() {
+            return;
+        }
+        if digits {
+            tokens.push(run.to_string());
+        } else {
+            tokens.extend(self.segment_letters(run));
+        }
+    }
+
+    /// Cheapest split of a run of letters into known words and unknown runs
+    fn segment_letters(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let n = chars.len();
+
+        // best[i]: cheapest cost of chars[..i] and where its last piece starts
+        let mut best: Vec<(f64, usize)> = vec![(f64::INFINITY, 0); n + 1];
+        best[0] = (0.0, 0);
+        for end in 1..=n {
+            // A lone uncovered character
+            best[end] = (best[end - 1].0 + self.unknown_cost, end - 1);
+            for start in end.saturating_sub(self.max_len)..end {
+                let word: String = chars[start..end].iter().collect();
+                if let Some(cost) = self.costs.get(&word) {
+                    let total = best[start].0 + cost;
+                    if total < best[end].0 {
+                        best[end] = (total, start);
+                    }
+                }
+            }
+        }
+
+        // Walk back, joining consecutive uncovered characters
+        let mut pieces: Vec<(String, bool)> = Vec::new();
+        let mut end = n;
+        while end > 0 {
+            let start = best[end].1;
+            let piece: String = chars[start..end].iter().collect();
+            let known = self.costs.contains_key(&piece);
+            match pieces.last_mut() {
+                Some((last, false)) if !known => last.insert_str(0, &piece),
+                _ => pieces.push((piece, known)),
+            }
+            end = start;
+        }
+        pieces.into_iter().rev().map(|(piece, _)| piece).collect()
+    }
+}
+
+impl Segment for LocalSegmenter {
+    fn segment(&self, word: &str) -> Vec<String> {
+        LocalSegmenter::segment(self, word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segmenter() -> LocalSegmenter {
+        LocalSegmenter::new([
+            "the", "shop", "best", "coffee", "pet", "care", "car", "pets", "hop", "s", "e",
+        ])
+    }
+
+    #[test]
+    fn test_segment() {
+        let segmenter = segmenter();
+        assert_eq!(segmenter.segment("coffeeshop"), vec!["coffee", "shop"]);
+        assert_eq!(segmenter.segment("PetCare"), vec!["pet", "care"]);
+        assert_eq!(segmenter.segment("best-coffee"), vec!["best", "coffee"]);
+        assert_eq!(segmenter.segment("shop24"), vec!["shop", "24"]);
+        assert_eq!(segmenter.segment("xyzcoffee"), vec!["xyz", "coffee"]);
+        assert_eq!(segmenter.segment("qwrtz"), vec!["qwrtz"]);
+        assert!(segmenter.segment("--").is_empty());
+    }
+
+    #[test]
+    fn test_prefers_frequent_words() {
+        // "pets care" and "pet scare" both cover the label
+        let segmenter = LocalSegmenter::new(["pet", "care", "pets", "scare"]);
+        assert_eq!(segmenter.segment("petscare"), vec!["pet", "scare"]);
+        let segmenter = LocalSegmenter::new(["pets", "care", "pet", "scare"]);
+        assert_eq!(segmenter.segment("petscare"), vec!["pets", "care"]);
+    }
+
+    #[test]
+    fn test_load_skips_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.txt");
+        std::fs::write(&path, "# most frequent first\ncoffee\n\nshop\n").unwrap();
+
+        let segmenter = LocalSegmenter::load(&path).unwrap();
+        assert_eq!(segmenter.len(), 2);
+        assert_eq!(segmenter.segment("coffeeshop"), vec!["coffee", "shop"]);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::client::WordClient;
+use crate::error::Result;
+use crate::local::LocalSegmenter;
+use std::sync::Arc;
+
+/// Where labels are split into words: the word splitter API or a local
+/// word list
+#[derive(Clone)]
+pub enum Segmenter {
+    Remote(WordClient),
+    Local(Arc<LocalSegmenter>),
+}
+
+impl Segmenter {
+    /// Segment a batch of labels
+    ///
+    /// Returns a Vec of (label, segments) pairs in the same order as input
+    pub async fn segment_batch(&self, labels: Vec<String>) -> Result<Vec<(String, Vec<String>)>> {
+        match self {
+            Segmenter::Remote(client) => client.segment_batch(labels).await,
+            Segmenter::Local(segmenter) => Ok(labels
+                .into_iter()
+                .map(|label| {
+                    let segments = segmenter.segment(&label);
+                    (label, segments)
+                })
+                .collect()),
+        }
+    }
+
+    /// Whether segmentation needs the network
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Segmenter::Remote(_))
+    }
+}
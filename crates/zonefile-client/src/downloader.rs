@@ -1,13 +1,26 @@
 use crate::error::{Error, Result};
+use crate::parser::domain_line;
+use async_stream::try_stream;
 use bytes::Bytes;
-use futures::StreamExt;
-use reqwest::Client;
+use futures::{Stream, StreamExt};
+use reqwest::{Client, Response};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
 use tracing::{debug, info};
 
+/// Domains read off a streamed archive before they are handed over
+const STREAM_CHUNK: usize = 10_000;
+
+/// Chunks buffered between a streamed download and its consumer; once a slow
+/// consumer lets them fill up, the download waits
+const STREAM_BUFFERED_CHUNKS: usize = 16;
+
+/// Downloaded bytes between two progress logs
+const PROGRESS_LOG_BYTES: u64 = 100 * 1024 * 1024;
+
 /// Type of zonefile to download
 #[derive(Debug, Clone, Copy)]
 pub enum ZonefileType {
@@ -65,15 +78,37 @@ impl ZonefileDownloader {
         })
     }
 
+    /// URL of a zonefile's ZIP archive
+    fn url(&self, zonefile_type: ZonefileType) -> String {
+        format!(
+            "{}/{}/get/{}/list/zip",
+            self.base_url,
+            self.token,
+            zonefile_type.endpoint()
+        )
+    }
+
+    /// Send a GET request, failing on an error status
+    async fn get(&self, url: &str) -> Result<Response> {
+        let response = self.client.get(url).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::DownloadFailed {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(response)
+    }
+
     /// Download a zonefile and return the path to the extracted file
     ///
     /// Downloads a ZIP file from the API, extracts domains.txt, and returns its path.
+    #[tracing::instrument(skip(self))]
     pub async fn download(&self, zonefile_type: ZonefileType) -> Result<PathBuf> {
         let endpoint = zonefile_type.endpoint();
-        let url = format!(
-            "{}/{}/get/{}/list/zip",
-            self.base_url, self.token, endpoint
-        );
+        let url = self.url(zonefile_type);
 
         info!(endpoint = endpoint, "Downloading zonefile");
 
@@ -96,15 +131,7 @@ impl ZonefileDownloader {
 
     /// Download a file from URL to disk with progress tracking
     async fn download_file(&self, url: &str, path: &Path) -> Result<()> {
-        let response = self.client.get(url).send().await?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(Error::DownloadFailed {
-                status: status.as_u16(),
-                message: response.text().await.unwrap_or_default(),
-            });
-        }
+        let response = self.get(url).await?;
 
         let total_size = response.content_length().unwrap_or(0);
         info!(
@@ -123,7 +150,7 @@ impl ZonefileDownloader {
             downloaded += chunk.len() as u64;
 
             // Log progress every 100MB
-            if downloaded - last_log > 100 * 1024 * 1024 {
+            if downloaded - last_log > PROGRESS_LOG_BYTES {
                 let pct = if total_size > 0 {
                     (downloaded as f64 / total_size as f64 * 100.0) as u32
                 } else {
@@ -165,7 +192,7 @@ impl ZonefileDownloader {
                 .map_err(|e| Error::Zip(e.to_string()))?;
 
             // First priority: domains.txt
-            if filename == "domains.txt" || filename.ends_with("/domains.txt") {
+            if is_domains_txt(filename) {
                 domains_idx = Some(idx);
                 break;
             }
@@ -202,32 +229,119 @@ impl ZonefileDownloader {
 
     /// Download directly to memory (for smaller files like daily updates)
     pub async fn download_to_memory(&self, zonefile_type: ZonefileType) -> Result<Vec<u8>> {
-        let endpoint = zonefile_type.endpoint();
-        let url = format!(
-            "{}/{}/get/{}/list/zip",
-            self.base_url, self.token, endpoint
-        );
+        debug!(endpoint = zonefile_type.endpoint(), "Downloading zonefile to memory");
 
-        debug!(endpoint = endpoint, "Downloading zonefile to memory");
+        let response = self.get(&self.url(zonefile_type)).await?;
+        let bytes = response.bytes().await?;
+        Ok(bytes.to_vec())
+    }
 
-        let response = self.client.get(&url).send().await?;
+    /// Stream the domains of a zonefile's domains.txt while it downloads,
+    /// writing neither the archive nor the extracted file to disk
+    ///
+    /// The archive is unzipped off the response as it arrives and its domains
+    /// are handed over in chunks through a bounded channel: when the consumer
+    /// falls behind, reading and with it the download pause until it catches
+    /// up. Unlike [`download`](Self::download), the archive must hold a
+    /// domains.txt, since the entries after the one being read aren't known.
+    #[tracing::instrument(skip(self))]
+    pub async fn stream_domains(
+        &self,
+        zonefile_type: ZonefileType,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        info!(endpoint = zonefile_type.endpoint(), "Streaming zonefile");
+        let response = self.get(&self.url(zonefile_type)).await?;
+
+        let (tx, mut rx) = mpsc::channel(STREAM_BUFFERED_CHUNKS);
+        tokio::spawn(async move {
+            if let Err(e) = stream_archive(response, &tx).await {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
 
-        let status = response.status();
-        if !status.is_success() {
-            return Err(Error::DownloadFailed {
-                status: status.as_u16(),
-                message: response.text().await.unwrap_or_default(),
-            });
+        Ok(try_stream! {
+            while let Some(chunk) = rx.recv().await {
+                for domain in chunk? {
+                    yield domain;
+                }
+            }
+        })
+    }
+}
+
+/// Whether a ZIP entry is the zonefile's domain list
+fn is_domains_txt(filename: &str) -> bool {
+    filename == "domains.txt" || filename.ends_with("/domains.txt")
+}
+
+/// Read domains.txt off a downloading archive into `tx`, in chunks of
+/// `STREAM_CHUNK` domains
+async fn stream_archive(response: Response, tx: &mpsc::Sender<Result<Vec<String>>>) -> Result<()> {
+    use async_zip::base::read::stream::ZipFileReader;
+    use tokio_util::compat::FuturesAsyncReadCompatExt;
+    use tokio_util::io::StreamReader;
+
+    let mut downloaded: u64 = 0;
+    let mut last_log: u64 = 0;
+    let body = response.bytes_stream().map(move |chunk| {
+        let chunk: Bytes = chunk.map_err(std::io::Error::other)?;
+        downloaded += chunk.len() as u64;
+        if downloaded - last_log > PROGRESS_LOG_BYTES {
+            info!(downloaded_mb = downloaded / 1024 / 1024, "Download progress");
+            last_log = downloaded;
         }
+        Ok::<_, std::io::Error>(chunk)
+    });
 
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
+    let mut zip = ZipFileReader::with_tokio(StreamReader::new(body));
+    let mut entry = loop {
+        let entry = zip
+            .next_with_entry()
+            .await
+            .map_err(|e| Error::Zip(e.to_string()))?
+            .ok_or_else(|| Error::InvalidZonefile("No domains.txt in archive".to_string()))?;
+        let filename = entry
+            .reader()
+            .entry()
+            .filename()
+            .as_str()
+            .map_err(|e| Error::Zip(e.to_string()))?;
+        if is_domains_txt(filename) {
+            break entry;
+        }
+        zip = entry.skip().await.map_err(|e| Error::Zip(e.to_string()))?;
+    };
+
+    let mut lines = BufReader::new(entry.reader_mut().compat()).lines();
+    let mut chunk = Vec::with_capacity(STREAM_CHUNK);
+    let mut count: u64 = 0;
+    while let Some(line) = lines.next_line().await? {
+        let Some(domain) = domain_line(&line) else {
+            continue;
+        };
+        chunk.push(domain.to_string());
+        count += 1;
+        if chunk.len() == STREAM_CHUNK {
+            let full = std::mem::replace(&mut chunk, Vec::with_capacity(STREAM_CHUNK));
+            // The consumer stopped reading; nothing left to stream for
+            if tx.send(Ok(full)).await.is_err() {
+                return Ok(());
+            }
+        }
     }
+    if !chunk.is_empty() {
+        let _ = tx.send(Ok(chunk)).await;
+    }
+
+    info!(domains = count, "Zonefile streamed");
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
     fn test_zonefile_type_endpoint() {
@@ -235,4 +349,132 @@ mod tests {
         assert_eq!(ZonefileType::DailyUpdate.endpoint(), "dailyupdate");
         assert_eq!(ZonefileType::DailyRemove.endpoint(), "dailyremove");
     }
+
+    /// A ZIP archive holding `files`, as the zonefile API serves them
+    async fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        use async_zip::base::write::ZipFileWriter;
+        use async_zip::{Compression, ZipEntryBuilder};
+
+        let mut writer = ZipFileWriter::new(Vec::new());
+        for (name, content) in files {
+            let entry = ZipEntryBuilder::new((*name).into(), Compression::Deflate);
+            writer.write_entry_whole(entry, content.as_bytes()).await.unwrap();
+        }
+        writer.close().await.unwrap()
+    }
+
+    async fn serve(endpoint: &str, response: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/token/get/{}/list/zip", endpoint)))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_download_extracts_domains_txt() {
+        let files = [("readme.txt", "ignored"), ("domains.txt", "coffee.com\npet.io\n")];
+        let archive = zip(&files).await;
+        let server = serve("full", ResponseTemplate::new(200).set_body_bytes(archive)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        let extracted = downloader.download(ZonefileType::Full).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&extracted).unwrap(), "coffee.com\npet.io\n");
+        assert!(!dir.path().join("full.zip").exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_falls_back_to_any_txt() {
+        let archive = zip(&[("added-2024-06-01.txt", "new.com\n")]).await;
+        let server = serve("dailyupdate", ResponseTemplate::new(200).set_body_bytes(archive)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        let extracted = downloader.download(ZonefileType::DailyUpdate).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&extracted).unwrap(), "new.com\n");
+    }
+
+    #[tokio::test]
+    async fn test_download_without_txt_fails() {
+        let archive = zip(&[("domains.csv", "coffee.com\n")]).await;
+        let server = serve("full", ResponseTemplate::new(200).set_body_bytes(archive)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        let result = downloader.download(ZonefileType::Full).await;
+        assert!(matches!(result, Err(Error::InvalidZonefile(_))));
+    }
+
+    #[tokio::test]
+    async fn test_download_reports_api_errors() {
+        let response = ResponseTemplate::new(401).set_body_string("invalid token");
+        let server = serve("dailyremove", response).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        match downloader.download(ZonefileType::DailyRemove).await {
+            Err(Error::DownloadFailed { status, message }) => {
+                assert_eq!(status, 401);
+                assert_eq!(message, "invalid token");
+            }
+            other => panic!("expected DownloadFailed, got {:?}", other),
+        }
+        assert!(matches!(
+            downloader.download_to_memory(ZonefileType::DailyRemove).await,
+            Err(Error::DownloadFailed { status: 401, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_domains() {
+        let domains: String = (0..25_000).map(|i| format!("domain{}.com\n", i)).collect();
+        let files = [("readme.txt", "ignored"), ("domains.txt", domains.as_str())];
+        let archive = zip(&files).await;
+        let server = serve("full", ResponseTemplate::new(200).set_body_bytes(archive)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        let stream = downloader.stream_domains(ZonefileType::Full).await.unwrap();
+        let streamed: Vec<String> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(streamed.len(), 25_000);
+        assert_eq!(streamed[0], "domain0.com");
+        assert_eq!(streamed[24_999], "domain24999.com");
+        // Nothing was written to disk
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_domains_without_domains_txt_fails() {
+        let archive = zip(&[("added-2024-06-01.txt", "new.com\n")]).await;
+        let server = serve("full", ResponseTemplate::new(200).set_body_bytes(archive)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        let stream = downloader.stream_domains(ZonefileType::Full).await.unwrap();
+        let streamed: Vec<Result<String>> = stream.collect().await;
+        assert!(matches!(streamed.as_slice(), [Err(Error::InvalidZonefile(_))]));
+
+        let response = ResponseTemplate::new(401).set_body_string("invalid token");
+        let server = serve("full", response).await;
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+        assert!(matches!(
+            downloader.stream_domains(ZonefileType::Full).await.err(),
+            Some(Error::DownloadFailed { status: 401, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_download_to_memory() {
+        let archive = zip(&[("domains.txt", "coffee.com\n")]).await;
+        let response = ResponseTemplate::new(200).set_body_bytes(archive.clone());
+        let server = serve("dailyupdate", response).await;
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = ZonefileDownloader::new(server.uri(), "token", dir.path()).unwrap();
+
+        let bytes = downloader.download_to_memory(ZonefileType::DailyUpdate).await.unwrap();
+        assert_eq!(bytes, archive);
+    }
 }
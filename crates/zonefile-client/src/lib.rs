@@ -1,7 +1,9 @@
 mod downloader;
 mod error;
 pub mod parser;
+pub mod sort;
 
 pub use downloader::{ZonefileDownloader, ZonefileType};
 pub use error::{Error, Result};
 pub use parser::DomainStream;
+pub use sort::{sort_file, SortedDomains};
@@ -27,27 +27,14 @@ impl DomainStream {
             let mut count: u64 = 0;
 
             while let Some(line) = lines.next_line().await? {
-                let line = line.trim();
-
-                // Skip empty lines and comments
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
-
-                // Basic validation: must contain at least one dot
-                if !line.contains('.') {
-                    continue;
-                }
-
-                // Skip lines that are too long (DNS label limit is 253 total)
-                if line.len() > 253 {
+                let Some(line) = domain_line(&line) else {
                     continue;
-                }
+                };
 
                 count += 1;
 
                 // Log progress every 10M domains
-                if count % 10_000_000 == 0 {
+                if count.is_multiple_of(10_000_000) {
                     debug!(count = count / 1_000_000, "Parsed {}M domains", count / 1_000_000);
                 }
 
@@ -67,19 +54,9 @@ impl DomainStream {
             use std::io::BufRead;
             for line in reader.lines() {
                 let line = line?;
-                let line = line.trim();
-
-                // Skip empty lines and comments
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
-
-                // Basic validation
-                if !line.contains('.') || line.len() > 253 {
-                    continue;
+                if let Some(domain) = domain_line(&line) {
+                    yield domain.to_string();
                 }
-
-                yield line.to_string();
             }
         }
     }
@@ -92,8 +69,7 @@ impl DomainStream {
         let mut count: u64 = 0;
 
         while let Some(line) = lines.next_line().await? {
-            let line = line.trim();
-            if !line.is_empty() && !line.starts_with('#') && line.contains('.') {
+            if domain_line(&line).is_some() {
                 count += 1;
             }
         }
@@ -102,6 +78,31 @@ impl DomainStream {
     }
 }
 
+/// The domain on a zonefile line, None for lines to skip
+pub(crate) fn domain_line(line: &str) -> Option<&str> {
+    let line = line.trim();
+
+    // Skip empty lines and comments
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    // Basic validation: must contain a dot other than the trailing one, so a
+    // bare TLD (`com.`) is skipped
+    let name = line.strip_suffix('.').unwrap_or(line);
+    if !name.contains('.') {
+        return None;
+    }
+
+    // Skip names over the DNS limit of 253 characters, trailing dot aside;
+    // normalization checks each label once IDNA has converted the name
+    if name.len() > 253 {
+        return None;
+    }
+
+    Some(line)
+}
+
 /// Batch domains from a stream into chunks
 pub fn batch_stream<S>(
     stream: S,
@@ -137,11 +138,10 @@ where
 mod tests {
     use super::*;
     use futures::StreamExt;
-    use tokio::io::AsyncWriteExt;
 
     #[tokio::test]
     async fn test_from_bytes() {
-        let data = b"example.com\ntest.net\n\n# comment\ninvalid\n".to_vec();
+        let data = b"example.com\ntest.net\n\n# comment\ninvalid\ncom.\n".to_vec();
 
         let stream = DomainStream::from_bytes(data);
         futures::pin_mut!(stream);
@@ -0,0 +1,224 @@
+use crate::error::Result;
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tracing::debug;
+
+/// Runs merged at once; more are first merged into fewer, longer ones so the
+/// number of open files stays bounded
+const MAX_FAN_IN: usize = 128;
+
+/// Bookkeeping bytes counted per buffered line on top of its text
+const LINE_OVERHEAD: usize = std::mem::size_of::<String>();
+
+/// Sort and deduplicate a domain list larger than memory into `output`
+///
+/// Returns the number of distinct domains written. See [`SortedDomains`].
+pub fn sort_file(
+    input: &Path,
+    output: &Path,
+    memory_bytes: usize,
+    temp_dir: &Path,
+) -> Result<u64> {
+    let sorted = SortedDomains::open(input, memory_bytes, temp_dir)?;
+    let mut writer = BufWriter::new(File::create(output)?);
+    let mut count = 0;
+    for domain in sorted {
+        writeln!(writer, "{}", domain?)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Sorted, deduplicated stream of the domains in a file of any size
+///
+/// Domains are trimmed and lowercased; blank lines and `#` comments are
+/// skipped. Chunks of about `memory_bytes` are sorted in memory and spilled
+/// to a scratch directory in `temp_dir`, then merged as the stream is read.
+/// The scratch directory needs about the size of the input and is removed
+/// when the stream is dropped.
+pub struct SortedDomains {
+    merge: Merge,
+    _scratch: TempDir,
+}
+
+impl SortedDomains {
+    /// Sort a file into runs, ready to be read in order
+    pub fn open(input: &Path, memory_bytes: usize, temp_dir: &Path) -> Result<Self> {
+        let scratch = TempDir::new_in(temp_dir)?;
+        let runs = sort_runs(input, scratch.path(), memory_bytes, MAX_FAN_IN)?;
+        Ok(Self {
+            merge: Merge::open(&runs)?,
+            _scratch: scratch,
+        })
+    }
+}
+
+impl Iterator for SortedDomains {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        self.merge.next()
+    }
+}
+
+/// Split a file into sorted, deduplicated runs of about `memory_bytes` each,
+/// merged down to at most `fan_in` runs
+fn sort_runs(input: &Path, dir: &Path, memory_bytes: usize, fan_in: usize) -> Result<Vec<PathBuf>> {
+    let reader = BufReader::with_capacity(1024 * 1024, File::open(input)?);
+
+    let mut runs = Vec::new();
+    let mut chunk: Vec<String> = Vec::new();
+    let mut chunk_bytes = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        chunk_bytes += line.len() + LINE_OVERHEAD;
+        chunk.push(line.to_lowercase());
+        if chunk_bytes >= memory_bytes {
+            runs.push(write_run(dir, runs.len(), &mut chunk)?);
+            chunk_bytes = 0;
+        }
+    }
+    if !chunk.is_empty() || runs.is_empty() {
+        runs.push(write_run(dir, runs.len(), &mut chunk)?);
+    }
+    debug!(input = ?input, runs = runs.len(), "Sorted runs written");
+
+    // Merge passes until one final merge can read every run
+    let mut number = runs.len();
+    while runs.len() > fan_in {
+        let mut merged = Vec::new();
+        for group in runs.chunks(fan_in) {
+            let path = dir.join(format!("run-{}", number));
+            number += 1;
+            let mut writer = BufWriter::new(File::create(&path)?);
+            for domain in Merge::open(group)? {
+                writeln!(writer, "{}", domain?)?;
+            }
+            writer.flush()?;
+            for run in group {
+                std::fs::remove_file(run)?;
+            }
+            merged.push(path);
+        }
+        runs = merged;
+    }
+    Ok(runs)
+}
+
+/// Sort, deduplicate and spill one chunk, leaving it empty
+fn write_run(dir: &Path, number: usize, chunk: &mut Vec<String>) -> Result<PathBuf> {
+    chunk.sort_unstable();
+    chunk.dedup();
+    let path = dir.join(format!("run-{}", number));
+    let mut writer = BufWriter::new(File::create(&path)?);
+    for domain in chunk.drain(..) {
+        writeln!(writer, "{}", domain)?;
+    }
+    writer.flush()?;
+    Ok(path)
+}
+
+/// Sorted, deduplicated stream of the lines of several sorted runs
+struct Merge {
+    runs: Vec<Lines<BufReader<File>>>,
+    heap: BinaryHeap<Reverse<(String, usize)>>,
+    last: Option<String>,
+}
+
+impl Merge {
+    fn open(paths: &[PathBuf]) -> Result<Self> {
+        let mut merge = Self {
+            runs: Vec::with_capacity(paths.len()),
+            heap: BinaryHeap::with_capacity(paths.len()),
+            last: None,
+        };
+        for path in paths {
+            merge.runs.push(BufReader::new(File::open(path)?).lines());
+            merge.advance(merge.runs.len() - 1)?;
+        }
+        Ok(merge)
+    }
+
+    /// Queue the next line of a run
+    fn advance(&mut self, run: usize) -> Result<()> {
+        if let Some(line) = self.runs[run].next() {
+            self.heap.push(Reverse((line?, run)));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        while let Some(Reverse((line, run))) = self.heap.pop() {
+            if let Err(e) = self.advance(run) {
+                return Some(Err(e));
+            }
+            if self.last.as_ref() != Some(&line) {
+                self.last = Some(line.clone());
+                return Some(Ok(line));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("domains.txt");
+        let output = dir.path().join("sorted.txt");
+        std::fs::write(&input, "# zone\npet.io\nCoffee.com\n\n  shop.net \ncoffee.com\n").unwrap();
+
+        // A few bytes per run: every domain spills its own run
+        let count = sort_file(&input, &output, 1, dir.path()).unwrap();
+
+        assert_eq!(count, 3);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "coffee.com\npet.io\nshop.net\n");
+        // Only the input, the output and nothing left in scratch
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_merge_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("domains.txt");
+        let domains: Vec<String> = (0..100).rev().map(|i| format!("d{}.com", i % 40)).collect();
+        std::fs::write(&input, domains.join("\n")).unwrap();
+        let runs_dir = dir.path().join("runs");
+        std::fs::create_dir(&runs_dir).unwrap();
+
+        let runs = sort_runs(&input, &runs_dir, 1, 3).unwrap();
+        assert!(runs.len() <= 3);
+
+        let merged: Vec<String> = Merge::open(&runs).unwrap().map(Result::unwrap).collect();
+        let mut expected: Vec<String> = (0..40).map(|i| format!("d{}.com", i)).collect();
+        expected.sort();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("domains.txt");
+        std::fs::write(&input, "").unwrap();
+
+        let sorted = SortedDomains::open(&input, 1 << 20, dir.path()).unwrap();
+        assert_eq!(sorted.count(), 0);
+    }
+}